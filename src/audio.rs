@@ -0,0 +1,77 @@
+use rodio::source::{SineWave, Source};
+use rodio::{OutputStream, OutputStreamHandle};
+use std::time::Duration;
+
+use crate::config::AudioConfig;
+use crate::i18n::{say, t};
+use crate::observer::GenerationObserver;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cue {
+    Save,
+    Error,
+    Complete,
+}
+
+// short synthesised beeps, so no sound files need shipping
+pub struct Audio {
+    // the stream has to stay alive for the handle to keep playing
+    _stream: Option<OutputStream>,
+    handle: Option<OutputStreamHandle>,
+    pub muted: bool,
+    pub volume: f32,
+}
+
+impl Audio {
+    pub fn new(config: &AudioConfig) -> Self {
+        let (stream, handle) = match OutputStream::try_default() {
+            Ok((stream, handle)) => (Some(stream), Some(handle)),
+            Err(e) => {
+                eprintln!("{}", t!("No audio output, sound cues disabled: {e}", e = e));
+                (None, None)
+            }
+        };
+        Audio {
+            _stream: stream,
+            handle,
+            muted: config.muted,
+            volume: config.volume.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn toggle_mute(&mut self) -> bool {
+        self.muted = !self.muted;
+        say!(
+            "Sound {muted}",
+            muted = if self.muted { t!("muted") } else { t!("on") }
+        );
+        self.muted
+    }
+
+    pub fn play(&self, cue: Cue) {
+        if self.muted {
+            return;
+        }
+        let Some(handle) = &self.handle else {
+            return;
+        };
+        let (frequency, millis) = match cue {
+            Cue::Save => (880.0, 120),
+            Cue::Error => (110.0, 250),
+            Cue::Complete => (660.0, 180),
+        };
+        let source = SineWave::new(frequency)
+            .take_duration(Duration::from_millis(millis))
+            .amplify(self.volume);
+        if let Err(e) = handle.play_raw(source) {
+            eprintln!("{}", t!("Failed to play sound: {e}", e = e));
+        }
+    }
+}
+
+// the constraint backend finishes instantly, so only the outcome gets a sound
+impl GenerationObserver for Audio {
+    fn on_complete(&mut self, success: bool) {
+        self.play(if success { Cue::Complete } else { Cue::Error });
+    }
+}
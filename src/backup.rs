@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+use crate::grid::TileGrid;
+use crate::i18n::say;
+use crate::{TileSystem, TileType};
+
+// the map as it was before the last bulk operation, kept apart from undo so
+// a long run of edits afterwards can't push it out
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PreOperation {
+    pub operation: String,
+    pub tiles: TileGrid,
+}
+
+impl TileSystem {
+    fn has_content(&self) -> bool {
+        self.tile_types()
+            .iter()
+            .flatten()
+            .any(|tile| *tile != TileType::Empty)
+    }
+
+    // painted by hand since the last generation, or never generated at all
+    fn hand_edited(&self) -> bool {
+        match &self.last_generation {
+            Some(generation) => generation.thumbnail != self.tile_types(),
+            None => self.has_content(),
+        }
+    }
+
+    fn snapshot(&mut self, operation: &str) {
+        self.pre_operation = Some(PreOperation {
+            operation: operation.to_string(),
+            tiles: self.tiles.clone(),
+        });
+        say!(
+            "Backed up the map before {operation} (Ctrl+U restores it)",
+            operation = operation
+        );
+    }
+
+    // clears and config loads only bother when there is something to lose
+    pub fn backup_before(&mut self, operation: &str) {
+        if self.has_content() {
+            self.snapshot(operation);
+        }
+    }
+
+    pub fn backup_before_generation(&mut self) {
+        if self.hand_edited() {
+            self.snapshot("generation");
+        }
+    }
+
+    // swaps the backup in as its own undo step; restoring twice swaps back
+    pub fn restore_pre_operation(&mut self) -> bool {
+        let Some(backup) = self.pre_operation.take() else {
+            say!("No pre-operation backup to restore");
+            return false;
+        };
+        self.push_undo();
+        let current = std::mem::replace(&mut self.tiles, backup.tiles);
+        self.sync_grid_size();
+        say!(
+            "Restored the map from before {operation}",
+            operation = backup.operation
+        );
+        self.pre_operation = Some(PreOperation {
+            operation: format!("restoring {}", backup.operation),
+            tiles: current,
+        });
+        true
+    }
+}
@@ -0,0 +1,271 @@
+use clap::Args;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::cli::MapSize;
+use crate::config::AppConfig;
+use crate::csp::{CspSolver, SolverSettings};
+use crate::exit::{self, CliError, Failure};
+use crate::i18n::{say, t};
+use crate::observer::{Observers, StatsObserver};
+use crate::trace::TraceObserver;
+use crate::wfc::WfcSolver;
+use crate::worker::solver_model;
+use crate::{Backend, TileSystem, integrity, library, parse_backend, time_seed};
+
+#[derive(Args)]
+pub struct GenerateArgs {
+    /// Number of maps to generate
+    #[arg(long, default_value_t = 1)]
+    count: usize,
+    /// Seed of the first map, the rest follow on from it
+    #[arg(long, default_value_t = 0, conflicts_with = "random")]
+    seed: u64,
+    /// A random seed for every map instead of sequential ones
+    #[arg(long)]
+    random: bool,
+    /// Saved config to learn from, the enabled samples when left out
+    #[arg(long, default_value = "")]
+    model: String,
+    /// Generator: the constraint solver, or WFC with the retries from config.json
+    #[arg(long, default_value = "sat", value_parser = ["wfc", "sat"])]
+    backend: String,
+    /// Directory for the numbered map files and manifest.json
+    #[arg(long, default_value = "generated")]
+    out_dir: String,
+    /// Also write a JSONL solver trace per map (map_NNN.trace.jsonl)
+    #[arg(long)]
+    trace: bool,
+    #[command(flatten)]
+    size: MapSize,
+}
+
+// one line of manifest.json per generated map
+#[derive(Serialize)]
+struct ManifestEntry {
+    file: Option<String>,
+    // for WFC, the retry's seed that solved it
+    seed: u64,
+    success: bool,
+    millis: f64,
+    // null for WFC, which doesn't count them
+    decisions: Option<usize>,
+    backtracks: usize,
+    restarts: usize,
+    peak_bytes: usize,
+    contradictions: usize,
+}
+
+#[derive(Serialize)]
+struct Manifest {
+    model: String,
+    backend: Backend,
+    width: usize,
+    height: usize,
+    maps: Vec<ManifestEntry>,
+}
+
+// what one map's run came to, whichever solver made it
+struct Run {
+    result: Option<Vec<Vec<usize>>>,
+    seed: u64,
+    decisions: Option<usize>,
+    backtracks: usize,
+    restarts: usize,
+    peak_bytes: usize,
+}
+
+// `generate [--count N] [--seed S | --random] [--model name] [--backend wfc|sat] [--out-dir dir]`
+pub fn run_headless(options: GenerateArgs) -> i32 {
+    exit::report(generate_batch(options))
+}
+
+fn generate_batch(options: GenerateArgs) -> Result<(), CliError> {
+    let app_config = AppConfig::load();
+    let mut base = TileSystem::load_headless(&options.size)?;
+    let (configs, tags) = library::scan(&app_config.library_dir);
+    base.set_library(configs, &tags);
+
+    let rules = base
+        .learn_rules(&options.model)
+        .map_err(|e| CliError::new(Failure::NotFound, e))?;
+    fs::create_dir_all(&options.out_dir).map_err(|e| {
+        CliError::new(
+            Failure::Io,
+            format!("Failed to create {}: {}", options.out_dir, e),
+        )
+    })?;
+
+    // built the way the window's generation builds them, from the map as it was
+    // loaded; each run rewrites the map, and training regions read from it
+    let backend = parse_backend(&options.backend);
+    let states = base.state_table();
+    let model = solver_model(&base, &states, &rules);
+    let fixed = base.locked_state_ids(&states);
+    let preferences = states.expand_preferences(&base.bias_preferences());
+    let weights = states.expand_weights(&base.learned_weights(&options.model));
+    let memory_budget = app_config.solver_memory_budget();
+    let settings = SolverSettings {
+        memory_budget,
+        ..SolverSettings::default()
+    };
+    let digits = options.count.to_string().len();
+    let mut unwritten = 0;
+    let mut manifest = Manifest {
+        model: options.model.clone(),
+        backend,
+        width: base.grid_width,
+        height: base.grid_height,
+        maps: Vec::new(),
+    };
+    for i in 0..options.count {
+        let seed = if options.random {
+            time_seed()
+        } else {
+            options.seed.wrapping_add(i as u64)
+        };
+        let started = Instant::now();
+        let mut stats = StatsObserver::default();
+        let stem = format!("map_{:0width$}", i + 1, width = digits);
+        let mut trace = if options.trace {
+            let path = Path::new(&options.out_dir).join(format!("{}.trace.jsonl", stem));
+            let trace = TraceObserver::create(
+                &path.display().to_string(),
+                base.grid_width,
+                base.grid_height,
+                seed,
+                states.labels(),
+            )
+            .map_err(|e| CliError::new(Failure::Io, e))?;
+            Some(trace)
+        } else {
+            None
+        };
+        let mut observers = Observers(vec![&mut stats]);
+        if let Some(trace) = trace.as_mut() {
+            observers.0.push(trace);
+        }
+        let run = match backend {
+            Backend::Sat => {
+                let mut solver = CspSolver::new(
+                    &model.rules,
+                    states.len(),
+                    base.grid_width,
+                    base.grid_height,
+                    seed,
+                )
+                .with_settings(settings);
+                solver.set_weighted_preferences(&preferences);
+                solver.set_targets(model.targets.clone());
+                solver.set_transition_costs(model.transition_costs.clone());
+                let result = solver.solve(&fixed, &mut observers);
+                Run {
+                    result,
+                    seed,
+                    decisions: Some(solver.decisions),
+                    backtracks: solver.backtracks,
+                    restarts: solver.restarts,
+                    peak_bytes: solver.peak_memory,
+                }
+            }
+            Backend::Wfc => {
+                let mut solver = WfcSolver::new(
+                    &model.rules,
+                    states.len(),
+                    base.grid_width,
+                    base.grid_height,
+                    seed,
+                )
+                .with_options(app_config.wfc)
+                .with_memory_budget(memory_budget);
+                solver.set_weights(&weights);
+                solver.set_weighted_preferences(&preferences);
+                solver.set_targets(model.targets.clone());
+                solver.set_transition_costs(model.transition_costs.clone());
+                solver.fix(&fixed);
+                let result = solver.solve(&mut observers);
+                Run {
+                    result,
+                    seed: solver.seed,
+                    decisions: None,
+                    backtracks: solver.backtracks,
+                    // each retry moves on to the next seed
+                    restarts: solver.seed.wrapping_sub(seed) as usize,
+                    peak_bytes: solver.peak_memory,
+                }
+            }
+        };
+        drop(observers);
+        let millis = started.elapsed().as_secs_f64() * 1000.0;
+
+        let success = run.result.is_some();
+        let mut file = None;
+        if success {
+            // every cell is rewritten, so each run can reuse the same map
+            base.apply_generated_states(&options.model, run.seed, run.result, &states, backend);
+            base.pre_operation = None;
+            let path = Path::new(&options.out_dir)
+                .join(format!("{}.json", stem))
+                .display()
+                .to_string();
+            match integrity::write_checked(&path, &base) {
+                Ok(()) => file = Some(path),
+                Err(e) => {
+                    eprintln!("{}", t!("Failed to write {path}: {e}", path = path, e = e));
+                    unwritten += 1;
+                }
+            }
+        }
+        say!(
+            "{i}/{count} seed {seed}: {success} in {millis} ms",
+            i = i + 1,
+            count = options.count,
+            seed = run.seed,
+            success = if success { t!("ok") } else { t!("no solution") },
+            millis = format!("{:.1}", millis)
+        );
+        manifest.maps.push(ManifestEntry {
+            file,
+            seed: run.seed,
+            success,
+            millis,
+            decisions: run.decisions,
+            backtracks: run.backtracks,
+            restarts: run.restarts,
+            peak_bytes: run.peak_bytes,
+            contradictions: stats.contradictions,
+        });
+    }
+
+    let manifest_path = Path::new(&options.out_dir).join("manifest.json");
+    serde_json::to_string_pretty(&manifest)
+        .map_err(|e| e.to_string())
+        .and_then(|json| integrity::write_atomic(&manifest_path.display().to_string(), &json))
+        .map_err(|e| CliError::new(Failure::Io, format!("Failed to write the manifest: {}", e)))?;
+    let successes = manifest.maps.iter().filter(|entry| entry.success).count();
+    say!(
+        "Generated {successes} of {count} maps into {out_dir}",
+        successes = successes,
+        count = options.count,
+        out_dir = options.out_dir
+    );
+    if unwritten > 0 {
+        return Err(CliError::new(
+            Failure::Io,
+            format!("{} generated map(s) couldn't be written", unwritten),
+        ));
+    }
+    if successes < options.count {
+        return Err(CliError::new(
+            Failure::Unsolvable,
+            format!(
+                "{} of {} seeds found no solution",
+                options.count - successes,
+                options.count
+            ),
+        ));
+    }
+    Ok(())
+}
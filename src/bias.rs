@@ -0,0 +1,148 @@
+use piston_window::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::brushes::BrushStyle;
+use crate::i18n::say;
+use crate::{Tile, TileSystem, TileType, UndoStep, grid};
+
+// a painted pull towards one tile: with `strength` 1 the solver always tries it
+// first, with 0.5 on half of its picks, and otherwise picks as it would anyway
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BiasCell {
+    pub tile: TileType,
+    pub strength: f64,
+}
+
+impl TileSystem {
+    pub fn bias_at(&self, x: usize, y: usize) -> Option<&BiasCell> {
+        self.bias.get(y)?.get(x)?.as_ref()
+    }
+
+    pub fn set_bias(&mut self, x: usize, y: usize, cell: Option<BiasCell>) {
+        if x >= self.grid_width || y >= self.grid_height {
+            return;
+        }
+        if self.bias.len() != self.grid_height
+            || self.bias.iter().any(|row| row.len() != self.grid_width)
+        {
+            self.bias.resize(self.grid_height, Vec::new());
+            for row in &mut self.bias {
+                row.resize(self.grid_width, None);
+            }
+        }
+        self.bias[y][x] = cell;
+        self.dirty = true;
+    }
+
+    fn push_bias_undo(&mut self) {
+        self.push_undo_step(UndoStep::Bias(self.bias.clone()));
+    }
+
+    // the brush's footprint pulled towards `tile`, strength 0 erasing, as one undo step
+    pub fn paint_bias(
+        &mut self,
+        x: usize,
+        y: usize,
+        style: &BrushStyle,
+        tile: &TileType,
+        strength: f64,
+    ) {
+        self.push_bias_undo();
+        self.paint_bias_stroke(x, y, style, tile, strength);
+    }
+
+    pub fn paint_bias_line(
+        &mut self,
+        from: (usize, usize),
+        x: usize,
+        y: usize,
+        style: &BrushStyle,
+        tile: &TileType,
+        strength: f64,
+    ) {
+        self.push_bias_undo();
+        for (px, py) in grid::line_cells(from, (x, y)) {
+            self.paint_bias_stroke(px, py, style, tile, strength);
+        }
+    }
+
+    // more of a stroke begun with paint_bias, sharing its undo step
+    pub fn paint_bias_stroke(
+        &mut self,
+        x: usize,
+        y: usize,
+        style: &BrushStyle,
+        tile: &TileType,
+        strength: f64,
+    ) {
+        let cell = (strength > 0.0).then(|| BiasCell {
+            tile: tile.clone(),
+            strength: strength.min(1.0),
+        });
+        for (px, py) in self.brush_cells(x, y, style) {
+            self.set_bias(px, py, cell.clone());
+        }
+    }
+
+    pub fn clear_bias(&mut self) {
+        if self.bias.iter().flatten().any(Option::is_some) {
+            self.push_bias_undo();
+        }
+        self.bias.clear();
+        say!("Bias layer cleared");
+    }
+
+    // per-cell (tile id, strength) hints for the solver: painted bias where there is
+    // some, the Voronoi regions' tiles at full strength elsewhere
+    pub fn bias_preferences(&self) -> HashMap<(usize, usize), (usize, f64)> {
+        let mut preferences: HashMap<(usize, usize), (usize, f64)> = self
+            .biome_preferences()
+            .into_iter()
+            .map(|(cell, tile)| (cell, (tile, 1.0)))
+            .collect();
+        for (y, row) in self.bias.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                if let Some(cell) = cell {
+                    preferences.insert((x, y), (cell.tile.index(), cell.strength));
+                }
+            }
+        }
+        preferences
+    }
+
+    // chance the cell ends up as its bias tile before adjacency narrows anything:
+    // the bias's own share plus the tile's even share of the unbiased picks
+    pub fn expected_bias_share(&self, x: usize, y: usize) -> Option<(TileType, f64)> {
+        let cell = self.bias_at(x, y)?;
+        let tiles = TileType::ALL.len() - self.pruned_tiles.len();
+        let share = cell.strength + (1.0 - cell.strength) / tiles.max(1) as f64;
+        Some((cell.tile.clone(), share))
+    }
+
+    // the bias tile's colour over each painted cell, more opaque the likelier it is,
+    // with a bar along the bottom showing the share
+    pub fn render_bias_preview(&self, opacity: f32, c: Context, g: &mut G2d) {
+        if opacity <= 0.0 {
+            return;
+        }
+        let size = self.tile_size;
+        for y in 0..self.grid_height {
+            for x in 0..self.grid_width {
+                let Some((tile, share)) = self.expected_bias_share(x, y) else {
+                    continue;
+                };
+                let (world_x, world_y) = self.grid_to_world(x, y);
+                let mut colour = Tile::from_type(&tile).colour;
+                colour[3] = (0.15 + 0.6 * share as f32) * opacity;
+                rectangle(colour, [world_x, world_y, size, size], c.transform, g);
+                rectangle(
+                    [1.0, 1.0, 1.0, 0.8 * opacity],
+                    [world_x, world_y + size * 0.85, size * share, size * 0.15],
+                    c.transform,
+                    g,
+                );
+            }
+        }
+    }
+}
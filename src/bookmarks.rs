@@ -0,0 +1,210 @@
+use piston_window::*;
+use serde::{Deserialize, Serialize};
+
+use crate::bias::BiasCell;
+use crate::costs::TransitionCost;
+use crate::i18n::say;
+use crate::overrides::RuleOverride;
+use crate::targets::DistributionTargets;
+use crate::voronoi::BiomeSeed;
+use crate::{Backend, Tile, TileSystem, TileType};
+
+const THUMB_SIZE: f64 = 96.0;
+const THUMB_GAP: f64 = 12.0;
+const LABEL_HEIGHT: f64 = 16.0;
+
+// everything a generation used, so it can be run again to the same result
+// as long as the model's config hasn't been edited since
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub model: String,
+    pub seed: u64,
+    #[serde(default = "constraint_backend")]
+    pub backend: Backend,
+    pub locked: Vec<(usize, usize, TileType)>,
+    pub biome_seeds: Vec<BiomeSeed>,
+    pub thumbnail: Vec<Vec<TileType>>,
+    // None in bookmarks saved before these were kept
+    #[serde(default)]
+    pub settings: Option<GenerationSettings>,
+}
+
+// bookmarks from before the backend was kept all came from the constraint solver
+pub fn constraint_backend() -> Backend {
+    Backend::Sat
+}
+
+// the map's solver settings a generation ran with, besides its locks and sites
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GenerationSettings {
+    pub rule_overrides: Vec<RuleOverride>,
+    pub distribution_targets: Option<DistributionTargets>,
+    pub transition_costs: Vec<TransitionCost>,
+    pub bias: Vec<Vec<Option<BiasCell>>>,
+    pub rotatable_tiles: Vec<TileType>,
+    pub pruned_tiles: Vec<TileType>,
+}
+
+impl TileSystem {
+    // called after a successful generation, before anything else changes the map
+    pub fn remember_generation(&mut self, model: &str, seed: u64, backend: Backend) {
+        let mut locked: Vec<(usize, usize, TileType)> = self
+            .locked_cells
+            .iter()
+            .filter_map(|&(x, y)| self.get_tile(x, y).map(|t| (x, y, t.tile_type.clone())))
+            .collect();
+        locked.sort_by_key(|&(x, y, _)| (y, x));
+        self.last_generation = Some(Bookmark {
+            name: String::new(),
+            model: model.to_string(),
+            seed,
+            backend,
+            locked,
+            biome_seeds: self.biome_seeds.clone(),
+            thumbnail: self.tile_types(),
+            settings: Some(self.generation_settings()),
+        });
+    }
+
+    pub fn generation_settings(&self) -> GenerationSettings {
+        GenerationSettings {
+            rule_overrides: self.rule_overrides.clone(),
+            distribution_targets: self.distribution_targets.clone(),
+            transition_costs: self.transition_costs.clone(),
+            bias: self.bias.clone(),
+            rotatable_tiles: self.rotatable_tiles.clone(),
+            pruned_tiles: self.pruned_tiles.clone(),
+        }
+    }
+
+    pub fn set_generation_settings(&mut self, settings: GenerationSettings) {
+        self.rule_overrides = settings.rule_overrides;
+        self.distribution_targets = settings.distribution_targets;
+        self.transition_costs = settings.transition_costs;
+        self.bias = settings.bias;
+        self.rotatable_tiles = settings.rotatable_tiles;
+        self.pruned_tiles = settings.pruned_tiles;
+    }
+
+    pub fn bookmark_last_generation(&mut self, name: &str) -> bool {
+        let Some(mut bookmark) = self.last_generation.clone() else {
+            say!("Nothing generated yet to bookmark");
+            return false;
+        };
+        bookmark.name = if name.is_empty() {
+            format!("{} #{}", bookmark.model, bookmark.seed)
+        } else {
+            name.to_string()
+        };
+        say!(
+            "Bookmarked '{name}' (seed {seed})",
+            name = bookmark.name,
+            seed = bookmark.seed
+        );
+        self.bookmarks.push(bookmark);
+        self.dirty = true;
+        true
+    }
+
+    // puts the bookmark's locks, Voronoi sites and solver settings back so
+    // regenerating it matches; Ctrl+Z restores what they replaced
+    pub fn restore_bookmark_setup(&mut self, index: usize) -> Option<(String, u64, Backend)> {
+        let bookmark = self.bookmarks.get(index)?.clone();
+        self.push_setup_undo();
+        self.locked_cells.clear();
+        self.dungeon_cells.clear();
+        for (x, y, tile_type) in &bookmark.locked {
+            self.set_tile(*x, *y, Tile::from_type(tile_type));
+            self.locked_cells.insert((*x, *y));
+        }
+        self.biome_seeds = bookmark.biome_seeds;
+        match bookmark.settings {
+            Some(settings) => self.set_generation_settings(settings),
+            None => say!(
+                "Bookmark '{name}' doesn't record its rule overrides, targets, costs, bias, rotations or pruned tiles, using the current ones",
+                name = bookmark.name
+            ),
+        }
+        say!("Regenerating bookmark '{name}'", name = bookmark.name);
+        Some((bookmark.model, bookmark.seed, bookmark.backend))
+    }
+
+    fn gallery_slot(&self, index: usize) -> [f64; 4] {
+        let columns =
+            ((self.window_width - THUMB_GAP) / (THUMB_SIZE + THUMB_GAP)).max(1.0) as usize;
+        let (column, row) = (index % columns, index / columns);
+        [
+            THUMB_GAP + column as f64 * (THUMB_SIZE + THUMB_GAP),
+            THUMB_GAP + row as f64 * (THUMB_SIZE + THUMB_GAP + LABEL_HEIGHT),
+            THUMB_SIZE,
+            THUMB_SIZE,
+        ]
+    }
+
+    pub fn gallery_hit(&self, pos: [f64; 2]) -> Option<usize> {
+        (0..self.bookmarks.len()).find(|&i| {
+            let slot = self.gallery_slot(i);
+            pos[0] >= slot[0]
+                && pos[0] < slot[0] + slot[2]
+                && pos[1] >= slot[1]
+                && pos[1] < slot[1] + slot[3]
+        })
+    }
+
+    pub fn render_gallery(&self, glyphs: Option<&mut Glyphs>, c: Context, g: &mut G2d) {
+        rectangle(
+            [0.05, 0.05, 0.07, 0.95],
+            [0.0, 0.0, self.window_width, self.window_height],
+            c.transform,
+            g,
+        );
+        let mut glyphs = glyphs;
+        for (i, bookmark) in self.bookmarks.iter().enumerate() {
+            let slot = self.gallery_slot(i);
+            let rows = bookmark.thumbnail.len().max(1);
+            let columns = bookmark
+                .thumbnail
+                .iter()
+                .map(Vec::len)
+                .max()
+                .unwrap_or(1)
+                .max(1);
+            let cell = (slot[2] / columns as f64).min(slot[3] / rows as f64);
+            for (y, row) in bookmark.thumbnail.iter().enumerate() {
+                for (x, tile_type) in row.iter().enumerate() {
+                    rectangle(
+                        Tile::from_type(tile_type).colour,
+                        [
+                            slot[0] + x as f64 * cell,
+                            slot[1] + y as f64 * cell,
+                            cell,
+                            cell,
+                        ],
+                        c.transform,
+                        g,
+                    );
+                }
+            }
+            Rectangle::new_border([1.0, 1.0, 1.0, 0.6], 0.5).draw(
+                slot,
+                &c.draw_state,
+                c.transform,
+                g,
+            );
+
+            if let Some(glyphs) = glyphs.as_deref_mut() {
+                let transform = c.transform.trans(slot[0], slot[1] + slot[3] + 12.0);
+                text(
+                    [0.9, 0.9, 0.9, 1.0],
+                    10,
+                    &bookmark.name,
+                    glyphs,
+                    transform,
+                    g,
+                )
+                .ok();
+            }
+        }
+    }
+}
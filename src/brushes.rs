@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::{say, t};
+use crate::{TileSystem, TileType};
+
+// the bank holds this many brushes, picked with Shift+1-9
+pub const BANK_SIZE: usize = 9;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BrushShape {
+    #[default]
+    Square,
+    Circle,
+}
+
+// strokes repeated across the map's centre lines
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mirror {
+    #[default]
+    None,
+    // left-right
+    X,
+    // top-bottom
+    Y,
+    Both,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BrushStyle {
+    pub shape: BrushShape,
+    pub size: usize,
+    // chance each cell under the brush is painted, 1 for solid strokes
+    pub density: f64,
+    pub mirror: Mirror,
+}
+
+impl Default for BrushStyle {
+    fn default() -> Self {
+        BrushStyle {
+            shape: BrushShape::Square,
+            size: 1,
+            density: 1.0,
+            mirror: Mirror::None,
+        }
+    }
+}
+
+impl BrushStyle {
+    pub fn label(&self) -> String {
+        let mut label = format!("{:?} {}", self.shape, self.size).to_lowercase();
+        if self.density < 1.0 {
+            label += &format!(", {:.0}%", self.density * 100.0);
+        }
+        if self.mirror != Mirror::None {
+            label += &format!(", mirror {:?}", self.mirror).to_lowercase();
+        }
+        label
+    }
+
+    // "shape circle", "size 3", "density 0.4" or "mirror x" typed at the console
+    pub fn edit(&mut self, command: &str) -> Result<(), String> {
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        match parts.as_slice() {
+            ["shape", "square"] => self.shape = BrushShape::Square,
+            ["shape", "circle"] => self.shape = BrushShape::Circle,
+            ["size", size] => {
+                self.size = size
+                    .parse::<usize>()
+                    .map_err(|_| t!("'{size}' isn't a size", size = size))?
+                    .max(1);
+            }
+            ["density", density] => {
+                let density: f64 = density
+                    .parse()
+                    .map_err(|_| t!("'{density}' isn't a number", density = density))?;
+                if !(0.0..=1.0).contains(&density) || density == 0.0 {
+                    return Err(t!("Density goes from above 0 up to 1"));
+                }
+                self.density = density;
+            }
+            ["mirror", mirror] => {
+                self.mirror = match *mirror {
+                    "none" | "off" => Mirror::None,
+                    "x" => Mirror::X,
+                    "y" => Mirror::Y,
+                    "both" | "xy" => Mirror::Both,
+                    _ => {
+                        return Err(t!(
+                            "Unknown mirror '{mirror}', expected none, x, y or both",
+                            mirror = mirror
+                        ));
+                    }
+                };
+            }
+            _ => {
+                return Err(
+                    "Expected \"shape square|circle\", \"size <n>\", \"density <0-1>\" or \"mirror none|x|y|both\""
+                        .to_string(),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+// a brush saved under a name in the map's bank
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NamedBrush {
+    pub name: String,
+    pub tile: TileType,
+    pub style: BrushStyle,
+    // group or tile name the brush only replaces
+    #[serde(default)]
+    pub filter: Option<String>,
+}
+
+impl TileSystem {
+    // cells one dab of the brush covers at (x, y), mirrored copies included;
+    // density is left to the caller since it's rolled per stroke
+    pub fn brush_cells(&self, x: usize, y: usize, style: &BrushStyle) -> Vec<(usize, usize)> {
+        let size = style.size.max(1);
+        let half = size.saturating_sub(1) / 2;
+        let (left, top) = (x.saturating_sub(half), y.saturating_sub(half));
+        // centre of the footprint, which sits half a cell off for even sizes
+        let centre = (size as f64 - 1.0) / 2.0;
+        let radius = size as f64 / 2.0;
+        let mut cells = Vec::new();
+        for dy in 0..size {
+            for dx in 0..size {
+                let inside = match style.shape {
+                    BrushShape::Square => true,
+                    BrushShape::Circle => {
+                        let (ox, oy) = (dx as f64 - centre, dy as f64 - centre);
+                        ox * ox + oy * oy <= radius * radius
+                    }
+                };
+                let (px, py) = (left + dx, top + dy);
+                if inside && px < self.grid_width && py < self.grid_height {
+                    cells.push((px, py));
+                }
+            }
+        }
+        let (flip_x, flip_y) = match style.mirror {
+            Mirror::None => (false, false),
+            Mirror::X => (true, false),
+            Mirror::Y => (false, true),
+            Mirror::Both => (true, true),
+        };
+        let mirrored: Vec<(usize, usize)> = cells
+            .iter()
+            .flat_map(|&(px, py)| {
+                let (mx, my) = (self.grid_width - 1 - px, self.grid_height - 1 - py);
+                [
+                    flip_x.then_some((mx, py)),
+                    flip_y.then_some((px, my)),
+                    (flip_x && flip_y).then_some((mx, my)),
+                ]
+            })
+            .flatten()
+            .collect();
+        cells.extend(mirrored);
+        cells.sort_unstable();
+        cells.dedup();
+        cells
+    }
+
+    // saves a brush under `name`, replacing one of the same name; the bank is capped
+    pub fn save_brush(&mut self, brush: NamedBrush) -> Result<usize, String> {
+        if brush.name.is_empty() {
+            return Err(t!("Brush name can't be blank"));
+        }
+        let slot = match self.brushes.iter().position(|b| b.name == brush.name) {
+            Some(slot) => {
+                self.brushes[slot] = brush;
+                slot
+            }
+            None if self.brushes.len() >= BANK_SIZE => {
+                return Err(t!(
+                    "The brush bank is full ({size} brushes), delete one first",
+                    size = BANK_SIZE
+                ));
+            }
+            None => {
+                self.brushes.push(brush);
+                self.brushes.len() - 1
+            }
+        };
+        self.dirty = true;
+        say!(
+            "Saved brush '{brushes}' to slot {slot}",
+            brushes = self.brushes[slot].name,
+            slot = slot + 1
+        );
+        Ok(slot)
+    }
+
+    pub fn delete_brush(&mut self, name: &str) -> Result<(), String> {
+        let slot = self
+            .brushes
+            .iter()
+            .position(|b| b.name == name)
+            .ok_or_else(|| t!("No brush named '{name}'", name = name))?;
+        self.brushes.remove(slot);
+        self.dirty = true;
+        say!("Deleted brush '{name}'", name = name);
+        Ok(())
+    }
+
+    pub fn list_brushes(&self) {
+        if self.brushes.is_empty() {
+            say!("No saved brushes");
+            return;
+        }
+        say!("Brush bank:");
+        for (i, brush) in self.brushes.iter().enumerate() {
+            let filter = match &brush.filter {
+                Some(filter) => format!(", only {}", filter),
+                None => String::new(),
+            };
+            println!(
+                " {}. {}: {:?}, {}{}",
+                i + 1,
+                brush.name,
+                brush.tile,
+                brush.style.label(),
+                filter
+            );
+        }
+    }
+}
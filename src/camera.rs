@@ -0,0 +1,62 @@
+use piston_window::{Context, Transformed};
+
+const MIN_ZOOM: f64 = 1.0;
+const MAX_ZOOM: f64 = 8.0;
+
+// zoom and pan of the map itself; the panel and overlays are drawn without it
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+    pub zoom: f64,
+    pub offset: [f64; 2],
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            zoom: 1.0,
+            offset: [0.0, 0.0],
+        }
+    }
+}
+
+impl Camera {
+    pub fn transform(&self, c: Context) -> Context {
+        c.trans(self.offset[0], self.offset[1]).zoom(self.zoom)
+    }
+
+    // window position to map position, for looking up the cell under a pointer
+    pub fn window_to_map(&self, pos: [f64; 2]) -> [f64; 2] {
+        [
+            (pos[0] - self.offset[0]) / self.zoom,
+            (pos[1] - self.offset[1]) / self.zoom,
+        ]
+    }
+
+    // keeps the map point under `centre` where it is
+    pub fn zoom_about(&mut self, centre: [f64; 2], factor: f64) {
+        let zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        let ratio = zoom / self.zoom;
+        self.offset = [
+            centre[0] - (centre[0] - self.offset[0]) * ratio,
+            centre[1] - (centre[1] - self.offset[1]) * ratio,
+        ];
+        self.zoom = zoom;
+    }
+
+    // pans so the map point `point` sits at the middle of a `size` window
+    pub fn centre_on(&mut self, point: [f64; 2], size: [f64; 2]) {
+        self.offset = [
+            size[0] / 2.0 - point[0] * self.zoom,
+            size[1] / 2.0 - point[1] * self.zoom,
+        ];
+    }
+
+    pub fn pan(&mut self, delta: [f64; 2]) {
+        self.offset[0] += delta[0];
+        self.offset[1] += delta[1];
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.zoom == 1.0 && self.offset == [0.0, 0.0]
+    }
+}
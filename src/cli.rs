@@ -0,0 +1,99 @@
+use clap::{Args, Parser, Subcommand};
+
+use crate::{batch, convert, experiment, lint, selfcheck, serve};
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Paint tile maps and fill them in with wave function collapse"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+    /// Language for messages, loaded from lang/<code>.json (default English)
+    #[arg(long, global = true)]
+    pub lang: Option<String>,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Open the editor window (the default with no subcommand)
+    Edit(EditArgs),
+    /// Generate maps without opening a window
+    Generate(batch::GenerateArgs),
+    /// Convert a map between formats, picked by file extension
+    Convert(convert::ConvertArgs),
+    /// Serve the map and generation over HTTP
+    Serve(serve::ServeArgs),
+    /// Run a pipeline file
+    Pipeline {
+        #[command(subcommand)]
+        command: PipelineCommand,
+    },
+    /// Check the saved map for common problems
+    Lint(lint::LintArgs),
+    /// Time every cell order and contradiction strategy against saved configs
+    Experiment(experiment::ExperimentArgs),
+    /// Solve a bundled sample with fixed seeds and compare against golden hashes
+    Selfcheck(selfcheck::SelfcheckArgs),
+    /// Edit the map in the terminal (needs the `tui` feature)
+    Tui(MapSize),
+}
+
+#[derive(Subcommand)]
+pub enum PipelineCommand {
+    /// Run every stage of a pipeline TOML file in order
+    Run {
+        file: String,
+        #[command(flatten)]
+        size: MapSize,
+    },
+}
+
+// size of the map made when there is no save yet; the window follows the grid
+#[derive(Args, Clone, Copy, Debug)]
+pub struct MapSize {
+    /// Width of a new map in cells
+    #[arg(long, default_value_t = 16)]
+    pub width: usize,
+    /// Height of a new map in cells
+    #[arg(long, default_value_t = 16)]
+    pub height: usize,
+    /// Tile size of a new map in pixels
+    #[arg(long, default_value_t = 32.0)]
+    pub tile_size: f64,
+}
+
+impl Default for MapSize {
+    fn default() -> Self {
+        MapSize {
+            width: 16,
+            height: 16,
+            tile_size: 32.0,
+        }
+    }
+}
+
+#[derive(Args, Default)]
+pub struct EditArgs {
+    /// Generator used by W
+    #[arg(long, default_value = "wfc", value_parser = ["wfc", "sat"])]
+    pub backend: String,
+    /// Reload config.json and templates/ when they change on disk
+    #[arg(long)]
+    pub watch: bool,
+    /// Directory of shared configs, instead of library_dir from config.json
+    #[arg(long)]
+    pub library: Option<String>,
+    /// Write a JSONL solver trace of every generation into this directory
+    #[arg(long)]
+    pub trace: Option<String>,
+    /// Open the save without locking it, with saving turned off
+    #[arg(long)]
+    pub read_only: bool,
+    /// Window title, instead of window_title from config.json
+    #[arg(long)]
+    pub title: Option<String>,
+    #[command(flatten)]
+    pub size: MapSize,
+}
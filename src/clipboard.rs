@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::{say, t};
+use crate::{Region, Tile, TileSystem, TileType};
+
+// a rectangle of tiles as it travels through the OS clipboard
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Fragment {
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<Vec<TileType>>,
+}
+
+impl Fragment {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    // JSON as written by to_json, or CSV rows of tile names
+    pub fn parse(text: &str) -> Result<Fragment, String> {
+        if let Ok(fragment) = serde_json::from_str::<Fragment>(text) {
+            return Ok(fragment);
+        }
+        let mut tiles = Vec::new();
+        for (line_number, line) in text.lines().filter(|l| !l.trim().is_empty()).enumerate() {
+            let row = line
+                .split(',')
+                .map(|name| {
+                    TileType::parse(name.trim()).ok_or_else(|| {
+                        format!("Row {}: unknown tile '{}'", line_number + 1, name.trim())
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            tiles.push(row);
+        }
+        if tiles.is_empty() {
+            return Err(t!("Clipboard doesn't hold a map fragment"));
+        }
+        Ok(Fragment {
+            width: tiles.iter().map(Vec::len).max().unwrap_or(0),
+            height: tiles.len(),
+            tiles,
+        })
+    }
+}
+
+impl TileSystem {
+    pub fn copy_fragment(&self, region: Region) -> Fragment {
+        let tiles = (region.y..region.y + region.height)
+            .map(|y| {
+                (region.x..region.x + region.width)
+                    .map(|x| {
+                        self.get_tile(x, y)
+                            .map_or(TileType::Empty, |tile| tile.tile_type.clone())
+                    })
+                    .collect()
+            })
+            .collect();
+        Fragment {
+            width: region.width,
+            height: region.height,
+            tiles,
+        }
+    }
+
+    // top-left corner at (x, y), cropped to the map; locked cells are kept
+    pub fn paste_fragment(&mut self, fragment: &Fragment, x: usize, y: usize) {
+        self.push_undo();
+        for (dy, row) in fragment.tiles.iter().enumerate() {
+            for (dx, tile_type) in row.iter().enumerate() {
+                self.set_tile(x + dx, y + dy, Tile::from_type(tile_type));
+            }
+        }
+        say!(
+            "Pasted a {width}x{height} fragment at ({x}, {y})",
+            width = fragment.width,
+            height = fragment.height,
+            x = x,
+            y = y
+        );
+    }
+}
+
+// the OS clipboard; kept open for the whole session because on X11 copied text
+// only stays available while its owner is alive
+pub struct SystemClipboard(Option<arboard::Clipboard>);
+
+impl SystemClipboard {
+    pub fn new() -> Self {
+        match arboard::Clipboard::new() {
+            Ok(clipboard) => SystemClipboard(Some(clipboard)),
+            Err(e) => {
+                eprintln!("{}", t!("System clipboard unavailable: {e}", e = e));
+                SystemClipboard(None)
+            }
+        }
+    }
+
+    pub fn set(&mut self, text: String) -> Result<(), String> {
+        let clipboard = self
+            .0
+            .as_mut()
+            .ok_or_else(|| t!("System clipboard unavailable"))?;
+        clipboard.set_text(text).map_err(|e| e.to_string())
+    }
+
+    pub fn get(&mut self) -> Result<String, String> {
+        let clipboard = self
+            .0
+            .as_mut()
+            .ok_or_else(|| t!("System clipboard unavailable"))?;
+        clipboard.get_text().map_err(|e| e.to_string())
+    }
+}
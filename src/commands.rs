@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::AppConfig;
+use crate::i18n::say;
+use crate::maze::MazeAlgorithm;
+use crate::observer::StatsObserver;
+use crate::scatter::FeatureKind;
+use crate::{Backend, TileSystem, time_seed};
+
+// editor actions that run without prompting, so they can be recorded and replayed;
+// anything random draws a fresh seed each time it runs
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Command {
+    Clear,
+    Undo,
+    UnlockAll,
+    LoadConfig(String),
+    Erode,
+    RebuildElevation,
+    ScatterFeatures { kind: FeatureKind, spacing: f64 },
+    ClearFeatures,
+    // roads between the villages and houses
+    LayRoads,
+    Maze(MazeAlgorithm),
+    Dungeon,
+    SeedBiomes(usize),
+    ClearBiomes,
+    // runs a generator in place, a blank model uses the enabled samples
+    Generate(Generation),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "SavedGeneration")]
+pub struct Generation {
+    pub model: String,
+    pub backend: Backend,
+}
+
+// macros recorded before the backend was kept saved only the model, for the
+// constraint solver
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedGeneration {
+    Model(String),
+    Full { model: String, backend: Backend },
+}
+
+impl From<SavedGeneration> for Generation {
+    fn from(saved: SavedGeneration) -> Self {
+        match saved {
+            SavedGeneration::Model(model) => Generation {
+                model,
+                backend: Backend::Sat,
+            },
+            SavedGeneration::Full { model, backend } => Generation { model, backend },
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Macro {
+    pub name: String,
+    // function key number, F2-F9
+    pub key: Option<u8>,
+    pub commands: Vec<Command>,
+}
+
+impl TileSystem {
+    pub fn run_command(&mut self, command: &Command) -> bool {
+        match command {
+            Command::Clear => self.clear_map(),
+            Command::Undo => return self.undo(),
+            Command::UnlockAll => self.unlock_all(),
+            Command::LoadConfig(name) => return self.load_config(name),
+            Command::Erode => self.erode(time_seed()),
+            Command::RebuildElevation => self.reset_elevation(time_seed()),
+            Command::ScatterFeatures { kind, spacing } => {
+                self.scatter_features(*kind, *spacing, time_seed());
+            }
+            Command::ClearFeatures => self.clear_features(),
+            Command::LayRoads => {
+                self.lay_roads();
+            }
+            Command::Maze(algorithm) => self.generate_maze(*algorithm, time_seed()),
+            Command::Dungeon => {
+                self.generate_dungeon(time_seed());
+            }
+            Command::SeedBiomes(count) => self.seed_biomes(*count, time_seed()),
+            Command::ClearBiomes => self.clear_biomes(),
+            Command::Generate(Generation {
+                model,
+                backend: Backend::Sat,
+            }) => {
+                return self.generate_with_csp(model, time_seed(), &mut StatsObserver::default());
+            }
+            Command::Generate(Generation {
+                model,
+                backend: Backend::Wfc,
+            }) => {
+                let config = AppConfig::load();
+                let generated = self.generate_from_config(
+                    model,
+                    time_seed(),
+                    config.wfc,
+                    config.solver_memory_budget(),
+                    &mut StatsObserver::default(),
+                );
+                if let Err(e) = generated {
+                    println!("{}", e);
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+// runs commands for the key handlers, keeping a copy while a macro is being recorded
+#[derive(Default)]
+pub struct MacroRecorder {
+    recording: Option<Vec<Command>>,
+}
+
+impl MacroRecorder {
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    pub fn start(&mut self) {
+        self.recording = Some(Vec::new());
+        say!("Recording macro, Ctrl+R again to stop");
+    }
+
+    pub fn stop(&mut self) -> Vec<Command> {
+        let commands = self.recording.take().unwrap_or_default();
+        say!("Recorded {count} step(s)", count = commands.len());
+        commands
+    }
+
+    pub fn record(&mut self, command: Command) {
+        if let Some(recording) = &mut self.recording {
+            recording.push(command);
+        }
+    }
+
+    pub fn run(&mut self, tile_system: &mut TileSystem, command: Command) -> bool {
+        let success = tile_system.run_command(&command);
+        self.record(command);
+        success
+    }
+
+    // replays every step even if one fails, like pressing the keys by hand
+    pub fn play(&mut self, tile_system: &mut TileSystem, recorded: &Macro) {
+        say!("Playing macro '{name}'", name = recorded.name);
+        for command in &recorded.commands {
+            if !self.run(tile_system, command.clone()) {
+                say!(
+                    "Macro step {command} failed",
+                    command = format!("{:?}", command)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::commands::Macro;
+use crate::csp::DEFAULT_MEMORY_BUDGET;
+use crate::gamepad::GamepadConfig;
+use crate::i18n::t;
+use crate::integrity;
+use crate::palette::Palette;
+use crate::text_export::DEFAULT_GLYPHS;
+use crate::wfc::WfcOptions;
+
+// app-wide settings, kept apart from the map save in tile_system.json
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AppConfig {
+    #[serde(default)]
+    pub audio: AudioConfig,
+    // multiplier for the settings panel and other overlays, independent of display DPI
+    #[serde(default = "default_ui_scale")]
+    pub ui_scale: f64,
+    // most recently loaded config names first, for the Ctrl+O quick list
+    #[serde(default)]
+    pub recent_configs: Vec<String>,
+    #[serde(default)]
+    pub macros: Vec<Macro>,
+    // cap on the solver's decision stack; past it generation restarts instead of backtracking
+    #[serde(default = "default_solver_memory_mb")]
+    pub solver_memory_mb: usize,
+    // characters for the text export, one per tile type
+    #[serde(default = "default_text_glyphs")]
+    pub text_glyphs: String,
+    #[serde(default)]
+    pub gamepad: GamepadConfig,
+    // shared config files, scanned at startup and with Ctrl+P
+    #[serde(default = "default_library_dir")]
+    pub library_dir: String,
+    // pixels per tile for every open map, overriding what each was saved with
+    #[serde(default)]
+    pub tile_size: Option<f64>,
+    // start of the window title, followed by the project directory's name
+    #[serde(default = "default_window_title")]
+    pub window_title: String,
+    // cells a generation reveals per second while it runs, None shows them as fast as
+    // the solver finds them
+    #[serde(default)]
+    pub generation_speed: Option<f64>,
+    // tile colours over the built-in ones, see palette.rs
+    #[serde(default)]
+    pub palette: Palette,
+    // side of the square around the cursor that Ctrl+Shift+R rerolls
+    #[serde(default = "default_reroll_size")]
+    pub reroll_size: usize,
+    // retries and backtracking limits for `--backend wfc`
+    #[serde(default)]
+    pub wfc: WfcOptions,
+}
+
+fn default_ui_scale() -> f64 {
+    1.0
+}
+
+fn default_window_title() -> String {
+    "WaveFunctionCollapse".to_string()
+}
+
+fn default_reroll_size() -> usize {
+    5
+}
+
+fn default_library_dir() -> String {
+    "configs".to_string()
+}
+
+fn default_text_glyphs() -> String {
+    DEFAULT_GLYPHS.to_string()
+}
+
+fn default_solver_memory_mb() -> usize {
+    DEFAULT_MEMORY_BUDGET / (1024 * 1024)
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            audio: AudioConfig::default(),
+            ui_scale: default_ui_scale(),
+            recent_configs: Vec::new(),
+            macros: Vec::new(),
+            solver_memory_mb: default_solver_memory_mb(),
+            text_glyphs: default_text_glyphs(),
+            gamepad: GamepadConfig::default(),
+            library_dir: default_library_dir(),
+            tile_size: None,
+            window_title: default_window_title(),
+            generation_speed: None,
+            palette: Palette::new(),
+            reroll_size: default_reroll_size(),
+            wfc: WfcOptions::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AudioConfig {
+    pub muted: bool,
+    pub volume: f32,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        AudioConfig {
+            muted: true,
+            volume: 0.2,
+        }
+    }
+}
+
+impl AppConfig {
+    pub const CONFIG_FILE: &'static str = "config.json";
+    pub const RECENT_LIMIT: usize = 9;
+
+    pub fn load() -> Self {
+        match fs::read_to_string(Self::CONFIG_FILE) {
+            Ok(json_data) => serde_json::from_str(&json_data).unwrap_or_else(|e| {
+                eprintln!(
+                    "{}",
+                    t!(
+                        "Error parsing {file}: {e}, using defaults",
+                        file = Self::CONFIG_FILE,
+                        e = e
+                    )
+                );
+                AppConfig::default()
+            }),
+            Err(_) => AppConfig::default(),
+        }
+    }
+
+    pub fn solver_memory_budget(&self) -> usize {
+        self.solver_memory_mb.max(1) * 1024 * 1024
+    }
+
+    // `generation_speed` as the worker takes it: a speed that isn't above zero would
+    // never reveal a cell, so it counts as unset
+    pub fn generation_speed(&self) -> Option<f64> {
+        self.generation_speed
+            .filter(|speed| speed.is_finite() && *speed > 0.0)
+    }
+
+    pub fn remember_config(&mut self, name: &str) {
+        self.recent_configs.retain(|recent| recent != name);
+        self.recent_configs.insert(0, name.to_string());
+        self.recent_configs.truncate(Self::RECENT_LIMIT);
+        self.save();
+    }
+
+    pub fn rename_recent(&mut self, old: &str, new: &str) {
+        let mut changed = false;
+        for recent in &mut self.recent_configs {
+            if recent == old {
+                *recent = new.to_string();
+                changed = true;
+            }
+        }
+        if changed {
+            self.save();
+        }
+    }
+
+    // replaces a macro of the same name, and takes its key from any other macro
+    pub fn add_macro(&mut self, recorded: Macro) {
+        self.macros.retain(|m| m.name != recorded.name);
+        if recorded.key.is_some() {
+            for other in &mut self.macros {
+                if other.key == recorded.key {
+                    other.key = None;
+                }
+            }
+        }
+        self.macros.push(recorded);
+        self.save();
+    }
+
+    pub fn save(&self) {
+        match serde_json::to_string_pretty(self) {
+            Ok(json_data) => {
+                if let Err(e) = integrity::write_atomic(Self::CONFIG_FILE, &json_data) {
+                    eprintln!("{}", t!("Failed to save {e}", e = e));
+                }
+            }
+            Err(e) => eprintln!("{}", t!("Failed to serialize config: {e}", e = e)),
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::{say, t};
+use crate::resize::ResizePolicy;
+use crate::{TileSystem, TileType};
+
+// a saved config: the tile rows plus what the browser shows about them
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "StoredConfig")]
+pub struct SavedConfig {
+    pub tiles: Vec<Vec<TileType>>,
+    pub width: usize,
+    pub height: usize,
+    // seconds since the Unix epoch, 0 when unknown
+    pub created: u64,
+    pub modified: u64,
+    pub author: String,
+    pub description: String,
+}
+
+// saves from before metadata stored each config as bare tile rows
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredConfig {
+    Full {
+        tiles: Vec<Vec<TileType>>,
+        #[serde(default)]
+        created: u64,
+        #[serde(default)]
+        modified: u64,
+        #[serde(default)]
+        author: String,
+        #[serde(default)]
+        description: String,
+    },
+    Rows(Vec<Vec<TileType>>),
+}
+
+impl From<StoredConfig> for SavedConfig {
+    fn from(stored: StoredConfig) -> Self {
+        match stored {
+            StoredConfig::Full {
+                tiles,
+                created,
+                modified,
+                author,
+                description,
+            } => SavedConfig {
+                created,
+                modified,
+                author,
+                description,
+                ..SavedConfig::from_tiles(tiles, 0, String::new())
+            },
+            StoredConfig::Rows(tiles) => SavedConfig::from_tiles(tiles, 0, String::new()),
+        }
+    }
+}
+
+impl SavedConfig {
+    // dimensions are taken from the rows, so they always agree with them
+    pub fn from_tiles(tiles: Vec<Vec<TileType>>, now: u64, author: String) -> Self {
+        SavedConfig {
+            width: tiles.iter().map(Vec::len).max().unwrap_or(0),
+            height: tiles.len(),
+            tiles,
+            created: now,
+            modified: now,
+            author,
+            description: String::new(),
+        }
+    }
+
+    // new tiles under an existing name keep its author, description and creation time
+    pub fn replace_tiles(&mut self, tiles: Vec<Vec<TileType>>, now: u64) {
+        self.width = tiles.iter().map(Vec::len).max().unwrap_or(0);
+        self.height = tiles.len();
+        self.tiles = tiles;
+        self.modified = now;
+    }
+
+    // one line for the console listing, e.g. "16x16, by sam, modified 2026-10-16 09:30 UTC"
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!("{}x{}", self.width, self.height)];
+        if !self.author.is_empty() {
+            parts.push(format!("by {}", self.author));
+        }
+        if self.created > 0 {
+            parts.push(format!("created {}", format_timestamp(self.created)));
+        }
+        if self.modified > self.created {
+            parts.push(format!("modified {}", format_timestamp(self.modified)));
+        }
+        parts.join(", ")
+    }
+}
+
+pub fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+// who saved the config, from the environment; blank if it can't tell
+pub fn current_author() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default()
+}
+
+// "YYYY-MM-DD HH:MM UTC", days to a civil date as in Howard Hinnant's date algorithms
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let minutes = (secs % 86400) / 60;
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+impl TileSystem {
+    pub fn config_meta(&self, name: &str) -> Option<&SavedConfig> {
+        self.saved_configs.get(name)
+    }
+
+    pub fn describe_config(&mut self, name: &str, description: &str) -> Result<(), String> {
+        let config = self
+            .saved_configs
+            .get_mut(name)
+            .ok_or_else(|| t!("Configuration '{name}' not found", name = name))?;
+        config.description = description.to_string();
+        config.modified = now();
+        self.dirty = true;
+        say!("Described configuration '{name}'", name = name);
+        Ok(())
+    }
+
+    // says how a config saved from a map of another size is being fitted
+    pub fn check_config_size(&self, name: &str, policy: ResizePolicy) -> bool {
+        let Some((width, height)) = self.config_size(name) else {
+            return true;
+        };
+        if width == self.grid_width && height == self.grid_height {
+            return true;
+        }
+        say!(
+            "'{name}' is {width}x{height} but the map is {grid_width}x{grid_height}, loading with the {policy} policy",
+            name = name,
+            width = width,
+            height = height,
+            grid_width = self.grid_width,
+            grid_height = self.grid_height,
+            policy = format!("{:?}", policy)
+        );
+        false
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::{Direction, TileType};
+
+// the learned model plus output size, for external SAT / CP tools
+pub struct ConstraintModel<'a> {
+    pub rules: &'a HashMap<usize, HashSet<(Direction, usize)>>,
+    pub width: usize,
+    pub height: usize,
+    // pinned cells, e.g. locked tiles
+    pub fixed: &'a HashMap<(usize, usize), usize>,
+}
+
+impl ConstraintModel<'_> {
+    fn tile_count(&self) -> usize {
+        TileType::ALL.len()
+    }
+
+    fn allowed(&self, tile: usize, dir: Direction) -> BTreeSet<usize> {
+        self.rules
+            .get(&tile)
+            .map(|set| {
+                set.iter()
+                    .filter(|(d, _)| *d == dir)
+                    .map(|(_, n)| *n)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // x_{cell,tile}: DIMACS variables start at 1
+    fn var(&self, x: usize, y: usize, tile: usize) -> usize {
+        (y * self.width + x) * self.tile_count() + tile + 1
+    }
+
+    pub fn to_dimacs(&self) -> String {
+        let tiles = self.tile_count();
+        let mut clauses: Vec<Vec<i64>> = Vec::new();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                // exactly one tile per cell, tiles missing from the sample are ruled out
+                let known: Vec<usize> = (0..tiles).filter(|t| self.rules.contains_key(t)).collect();
+                clauses.push(known.iter().map(|t| self.var(x, y, *t) as i64).collect());
+                for a in 0..tiles {
+                    if !known.contains(&a) {
+                        clauses.push(vec![-(self.var(x, y, a) as i64)]);
+                    }
+                    for b in a + 1..tiles {
+                        clauses.push(vec![
+                            -(self.var(x, y, a) as i64),
+                            -(self.var(x, y, b) as i64),
+                        ]);
+                    }
+                }
+
+                // a tile here implies one of its allowed neighbours to the right / below
+                for (dir, nx, ny) in [(Direction::Right, x + 1, y), (Direction::Down, x, y + 1)] {
+                    if nx >= self.width || ny >= self.height {
+                        continue;
+                    }
+                    for a in &known {
+                        let mut clause = vec![-(self.var(x, y, *a) as i64)];
+                        clause.extend(
+                            self.allowed(*a, dir)
+                                .iter()
+                                .map(|b| self.var(nx, ny, *b) as i64),
+                        );
+                        clauses.push(clause);
+                    }
+                }
+            }
+        }
+
+        let mut fixed: Vec<_> = self.fixed.iter().collect();
+        fixed.sort();
+        for (&(x, y), &tile) in fixed {
+            if x < self.width && y < self.height {
+                clauses.push(vec![self.var(x, y, tile) as i64]);
+            }
+        }
+
+        let mut out = String::new();
+        writeln!(
+            out,
+            "c wf-c adjacency model, {}x{} grid",
+            self.width, self.height
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "c variable (y * {} + x) * {} + tile + 1",
+            self.width, tiles
+        )
+        .unwrap();
+        for (i, tile_type) in TileType::ALL.iter().enumerate() {
+            writeln!(out, "c tile {} = {:?}", i, tile_type).unwrap();
+        }
+        writeln!(
+            out,
+            "p cnf {} {}",
+            self.width * self.height * tiles,
+            clauses.len()
+        )
+        .unwrap();
+        for clause in clauses {
+            for literal in clause {
+                write!(out, "{} ", literal).unwrap();
+            }
+            writeln!(out, "0").unwrap();
+        }
+        out
+    }
+
+    pub fn to_minizinc(&self) -> String {
+        let pairs = |dir: Direction| -> String {
+            let mut rows = Vec::new();
+            for a in 0..self.tile_count() {
+                for b in self.allowed(a, dir) {
+                    rows.push(format!("{}, {}", a + 1, b + 1));
+                }
+            }
+            format!("[| {} |]", rows.join(" | "))
+        };
+
+        let mut out = String::new();
+        writeln!(out, "% wf-c adjacency model").unwrap();
+        for (i, tile_type) in TileType::ALL.iter().enumerate() {
+            writeln!(out, "% tile {} = {:?}", i + 1, tile_type).unwrap();
+        }
+        writeln!(out, "include \"table.mzn\";").unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "int: W = {};", self.width).unwrap();
+        writeln!(out, "int: H = {};", self.height).unwrap();
+        writeln!(out, "int: T = {};", self.tile_count()).unwrap();
+        writeln!(out, "array[1..H, 1..W] of var 1..T: grid;").unwrap();
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "array[int, 1..2] of int: right_pairs = {};",
+            pairs(Direction::Right)
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "array[int, 1..2] of int: down_pairs = {};",
+            pairs(Direction::Down)
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "constraint forall(y in 1..H, x in 1..W-1)(table([grid[y, x], grid[y, x+1]], right_pairs));"
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "constraint forall(y in 1..H-1, x in 1..W)(table([grid[y, x], grid[y+1, x]], down_pairs));"
+        )
+        .unwrap();
+
+        let mut fixed: Vec<_> = self.fixed.iter().collect();
+        fixed.sort();
+        for (&(x, y), &tile) in fixed {
+            if x < self.width && y < self.height {
+                writeln!(out, "constraint grid[{}, {}] = {};", y + 1, x + 1, tile + 1).unwrap();
+            }
+        }
+        writeln!(out).unwrap();
+        writeln!(out, "solve satisfy;").unwrap();
+        out
+    }
+}
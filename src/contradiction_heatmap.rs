@@ -0,0 +1,151 @@
+use piston_window::*;
+use std::collections::HashMap;
+
+use crate::config::AppConfig;
+use crate::i18n::say;
+use crate::observer::GenerationObserver;
+use crate::worker::GenerationJob;
+use crate::{Backend, TileSystem, time_seed};
+
+// runs when none are asked for
+pub const DEFAULT_RUNS: usize = 10;
+
+// how often each cell ran out of options over K generations from one model, queued one
+// after another on the generation worker; the results are thrown away, only the counts
+// are kept, and drawn over the map as a heat overlay
+pub struct ContradictionHeatmap {
+    pub map_index: usize,
+    label: String,
+    backend: Backend,
+    // the first run's seed, the rest follow on from it
+    seed: u64,
+    runs: usize,
+    finished: usize,
+    failed: usize,
+    counts: HashMap<(usize, usize), usize>,
+}
+
+impl GenerationObserver for ContradictionHeatmap {
+    fn on_contradiction(&mut self, cell: (usize, usize)) {
+        *self.counts.entry(cell).or_default() += 1;
+    }
+}
+
+impl ContradictionHeatmap {
+    // starts the first of `runs` generations from `label`, Err when its rules can't be learned
+    pub fn start(
+        tile_system: &TileSystem,
+        map_index: usize,
+        label: String,
+        runs: usize,
+        backend: Backend,
+        app_config: &AppConfig,
+    ) -> Result<(Self, GenerationJob), String> {
+        let mut heatmap = ContradictionHeatmap {
+            map_index,
+            label,
+            backend,
+            seed: time_seed(),
+            runs: runs.max(1),
+            finished: 0,
+            failed: 0,
+            counts: HashMap::new(),
+        };
+        let job = heatmap.spawn(tile_system, app_config)?;
+        Ok((heatmap, job))
+    }
+
+    fn spawn(
+        &mut self,
+        tile_system: &TileSystem,
+        app_config: &AppConfig,
+    ) -> Result<GenerationJob, String> {
+        say!(
+            "Contradiction heatmap run {run}/{runs}",
+            run = self.finished + 1,
+            runs = self.runs
+        );
+        GenerationJob::spawn(
+            tile_system,
+            self.map_index,
+            self.label.clone(),
+            self.seed.wrapping_add(self.finished as u64),
+            self.backend,
+            app_config,
+            None,
+        )
+    }
+
+    // still has runs to make; its job belongs to the heatmap until then
+    pub fn is_running(&self) -> bool {
+        self.finished < self.runs
+    }
+
+    // counts a finished run and starts the next, None once they're all done
+    pub fn next_run(
+        &mut self,
+        tile_system: &TileSystem,
+        solved: bool,
+        app_config: &AppConfig,
+    ) -> Option<GenerationJob> {
+        self.finished += 1;
+        if !solved {
+            self.failed += 1;
+        }
+        if self.is_running() {
+            match self.spawn(tile_system, app_config) {
+                Ok(job) => return Some(job),
+                Err(e) => {
+                    println!("{}", e);
+                    self.runs = self.finished;
+                }
+            }
+        }
+        self.report();
+        None
+    }
+
+    fn report(&self) {
+        let total: usize = self.counts.values().sum();
+        say!(
+            "Contradiction heatmap over {runs} run(s) of '{label}': {total} contradictions in {cells} cell(s), {failed} run(s) failed",
+            runs = self.finished,
+            label = self.label,
+            total = total,
+            cells = self.counts.len(),
+            failed = self.failed
+        );
+        let mut hottest: Vec<(&(usize, usize), &usize)> = self.counts.iter().collect();
+        hottest.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (cell, count) in hottest.into_iter().take(5) {
+            say!(
+                "  {cell}: {count}",
+                cell = format!("{:?}", cell),
+                count = count
+            );
+        }
+    }
+
+    // cells tinted from faint yellow for a single contradiction to solid red for the most
+    pub fn render(&self, tile_system: &TileSystem, c: Context, g: &mut G2d) {
+        let most = self.counts.values().copied().max().unwrap_or(0);
+        if most == 0 {
+            return;
+        }
+        for (&(x, y), &count) in &self.counts {
+            let heat = count as f32 / most as f32;
+            let (world_x, world_y) = tile_system.grid_to_world(x, y);
+            rectangle(
+                [1.0, 0.9 * (1.0 - heat), 0.0, 0.25 + 0.55 * heat],
+                [
+                    world_x,
+                    world_y,
+                    tile_system.tile_size,
+                    tile_system.tile_size,
+                ],
+                c.transform,
+                g,
+            );
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use clap::Args;
+use std::fs;
+use std::path::Path;
+
+use crate::clipboard::Fragment;
+use crate::exit::{self, CliError, Failure};
+use crate::i18n::say;
+use crate::svg_export::{SvgOptions, to_svg};
+use crate::text_export::{TextOptions, to_text};
+use crate::{TileSystem, integrity};
+
+// pixels per tile in converted PNGs
+const PNG_SCALE: usize = 8;
+
+#[derive(Args)]
+pub struct ConvertArgs {
+    /// Map JSON, or CSV rows of tile names
+    input: String,
+    /// .json, .csv, .txt, .svg or .png
+    output: String,
+    /// Tile size in pixels for maps made from CSV
+    #[arg(long, default_value_t = 32.0)]
+    tile_size: f64,
+}
+
+fn extension(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+fn read_text(path: &str) -> Result<String, CliError> {
+    fs::read_to_string(path)
+        .map_err(|e| CliError::new(Failure::from_io(&e), format!("{}: {}", path, e)))
+}
+
+fn read_map(path: &str, tile_size: f64) -> Result<TileSystem, CliError> {
+    let text = match extension(path).as_str() {
+        "json" => {
+            let text = read_text(path)?;
+            if let Ok(map) = integrity::read_checked::<TileSystem>(path) {
+                return Ok(map);
+            }
+            text
+        }
+        "csv" => read_text(path)?,
+        "png" | "tmx" => {
+            return Err(CliError::new(
+                Failure::Usage,
+                format!("Reading .{} isn't supported yet", extension(path)),
+            ));
+        }
+        other => {
+            return Err(CliError::new(
+                Failure::Usage,
+                format!("Don't know how to read '.{}' files", other),
+            ));
+        }
+    };
+    let fragment = Fragment::parse(&text)
+        .map_err(|e| CliError::new(Failure::Invalid, format!("{}: {}", path, e)))?;
+    from_fragment(&fragment, tile_size)
+}
+
+fn from_fragment(fragment: &Fragment, tile_size: f64) -> Result<TileSystem, CliError> {
+    if fragment.width == 0 {
+        return Err(CliError::new(Failure::Invalid, "No tiles to convert"));
+    }
+    let mut map = TileSystem::new(
+        fragment.width as f64 * tile_size,
+        fragment.height as f64 * tile_size,
+        tile_size,
+    );
+    map.paste_fragment(fragment, 0, 0);
+    Ok(map)
+}
+
+fn write_map(map: &TileSystem, path: &str) -> Result<(), CliError> {
+    let contents = match extension(path).as_str() {
+        "json" => {
+            return integrity::write_checked(path, map).map_err(|e| CliError::new(Failure::Io, e));
+        }
+        "csv" => map
+            .tile_types()
+            .iter()
+            .map(|row| {
+                let names: Vec<String> = row.iter().map(|tile| format!("{:?}", tile)).collect();
+                names.join(",") + "\n"
+            })
+            .collect(),
+        "txt" => to_text(map, &TextOptions::default()),
+        "svg" => to_svg(map, SvgOptions::default()),
+        "png" => {
+            return fs::write(path, map.to_png(PNG_SCALE)).map_err(|e| {
+                CliError::new(Failure::Io, format!("Failed to write {}: {}", path, e))
+            });
+        }
+        "tmx" => {
+            return Err(CliError::new(
+                Failure::Usage,
+                format!("Writing .{} isn't supported yet", extension(path)),
+            ));
+        }
+        other => {
+            return Err(CliError::new(
+                Failure::Usage,
+                format!("Don't know how to write '.{}' files", other),
+            ));
+        }
+    };
+    fs::write(path, contents)
+        .map_err(|e| CliError::new(Failure::Io, format!("Failed to write {}: {}", path, e)))
+}
+
+// `convert <input> <output>`, formats picked by extension
+pub fn run(args: ConvertArgs) -> i32 {
+    exit::report(
+        read_map(&args.input, args.tile_size)
+            .and_then(|map| write_map(&map, &args.output))
+            .map(|()| {
+                say!(
+                    "Converted {input} to {output}",
+                    input = args.input,
+                    output = args.output
+                )
+            }),
+    )
+}
@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::{say, t};
+use crate::{TileSystem, TileType};
+
+// how harsh it looks for two tiles to touch; the solver divides a tile's odds by e
+// for each unit of cost to its decided neighbours, so rules still decide what is
+// possible and costs only what is likely
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TransitionCost {
+    pub tile: TileType,
+    pub neighbour: TileType,
+    pub cost: f64,
+}
+
+impl TileSystem {
+    // cost between every pair of tile ids, both ways round; None when nothing is set
+    pub fn transition_cost_matrix(&self) -> Option<Vec<Vec<f64>>> {
+        if self.transition_costs.is_empty() {
+            return None;
+        }
+        let mut matrix = vec![vec![0.0; TileType::ALL.len()]; TileType::ALL.len()];
+        for entry in &self.transition_costs {
+            let (a, b) = (entry.tile.index(), entry.neighbour.index());
+            matrix[a][b] = entry.cost;
+            matrix[b][a] = entry.cost;
+        }
+        Some(matrix)
+    }
+
+    // "<tiles> <tiles> <cost>", tiles as names or groups, or "clear"; cost 0 removes a pair
+    pub fn edit_transition_cost(&mut self, command: &str) -> Result<(), String> {
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        if parts == ["clear"] {
+            self.transition_costs.clear();
+            self.dirty = true;
+            say!("Transition costs cleared");
+            return Ok(());
+        }
+        let [tiles, neighbours, cost] = parts.as_slice() else {
+            return Err(t!("Expected \"<tile> <tile> <cost>\" or \"clear\""));
+        };
+        let cost: f64 = cost
+            .parse()
+            .ok()
+            .filter(|cost: &f64| cost.is_finite() && *cost >= 0.0)
+            .ok_or_else(|| t!("'{cost}' isn't a cost of 0 or more", cost = cost))?;
+        let tiles = self.resolve_tiles(tiles)?;
+        let neighbours = self.resolve_tiles(neighbours)?;
+        for tile in &tiles {
+            for neighbour in &neighbours {
+                self.transition_costs.retain(|old| {
+                    !(old.tile == *tile && old.neighbour == *neighbour
+                        || old.tile == *neighbour && old.neighbour == *tile)
+                });
+                if cost > 0.0 {
+                    self.transition_costs.push(TransitionCost {
+                        tile: tile.clone(),
+                        neighbour: neighbour.clone(),
+                        cost,
+                    });
+                }
+            }
+        }
+        self.dirty = true;
+        self.list_transition_costs();
+        Ok(())
+    }
+
+    // the matrix with a row and column per tile, blank where there is no cost
+    pub fn list_transition_costs(&self) {
+        let Some(matrix) = self.transition_cost_matrix() else {
+            say!("No transition costs");
+            return;
+        };
+        say!("Transition costs:");
+        let names: Vec<String> = TileType::ALL.iter().map(|t| format!("{:?}", t)).collect();
+        let width = names.iter().map(String::len).max().unwrap_or(0);
+        let mut header = format!("{:width$}", "", width = width);
+        for name in &names {
+            header += &format!(" {:>width$}", name, width = width);
+        }
+        println!("{}", header);
+        for (name, row) in names.iter().zip(&matrix) {
+            let mut line = format!("{:width$}", name, width = width);
+            for cost in row {
+                let cell = if *cost > 0.0 {
+                    format!("{}", cost)
+                } else {
+                    String::new()
+                };
+                line += &format!(" {:>width$}", cell, width = width);
+            }
+            println!("{}", line);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::i18n::{say, t};
+use crate::samples::SampleSource;
+use crate::{Direction, TileSystem, TileType, build_adjacency_rules};
+
+// how many of a held-out sample's worst pairs get printed
+const LISTED_PAIRS: usize = 3;
+
+// neighbour pairs in a held-out sample that the rules learned without it don't allow
+#[derive(Debug, Default)]
+pub struct HoldoutReport {
+    pub pairs: usize,
+    pub violations: usize,
+    // (neighbour, side, tile) -> times seen, read as "Water right of Mountain"
+    pub broken: BTreeMap<(usize, &'static str, usize), usize>,
+}
+
+impl HoldoutReport {
+    pub fn rate(&self) -> f64 {
+        if self.pairs == 0 {
+            0.0
+        } else {
+            self.violations as f64 / self.pairs as f64
+        }
+    }
+}
+
+// each pair once: the cell to the right and the cell below
+fn check(
+    grid: &[Vec<TileType>],
+    rules: &HashMap<usize, HashSet<(Direction, usize)>>,
+) -> HoldoutReport {
+    let mut report = HoldoutReport::default();
+    for (y, row) in grid.iter().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            let neighbours = [
+                (Direction::Right, "right of", row.get(x + 1)),
+                (
+                    Direction::Down,
+                    "below",
+                    grid.get(y + 1).and_then(|below| below.get(x)),
+                ),
+            ];
+            for (dir, side, neighbour) in neighbours {
+                let Some(neighbour) = neighbour else {
+                    continue;
+                };
+                report.pairs += 1;
+                let allowed = rules
+                    .get(&tile.index())
+                    .is_some_and(|allowed| allowed.contains(&(dir, neighbour.index())));
+                if !allowed {
+                    report.violations += 1;
+                    *report
+                        .broken
+                        .entry((neighbour.index(), side, tile.index()))
+                        .or_default() += 1;
+                }
+            }
+        }
+    }
+    report
+}
+
+impl TileSystem {
+    // learns from the enabled samples minus the held-out ones and counts how often each
+    // held-out sample breaks those rules; blank holds out each enabled sample in turn,
+    // otherwise sample manager indices separated by spaces
+    pub fn cross_validate(&self, holdout: &str) -> Result<Vec<(String, HoldoutReport)>, String> {
+        let samples = self.samples();
+        let enabled: Vec<usize> = (0..samples.len())
+            .filter(|&i| samples[i].1.enabled && samples[i].1.weight > 0.0)
+            .collect();
+        let folds: Vec<Vec<usize>> = if holdout.trim().is_empty() {
+            enabled.iter().map(|&i| vec![i]).collect()
+        } else {
+            let picked = holdout
+                .split_whitespace()
+                .map(|i| {
+                    i.parse::<usize>()
+                        .ok()
+                        .filter(|&i| i < samples.len())
+                        .ok_or_else(|| t!("No sample {i}", i = i))
+                })
+                .collect::<Result<Vec<usize>, String>>()?;
+            vec![picked]
+        };
+
+        let mut reports = Vec::new();
+        for fold in folds {
+            let training: Vec<&SampleSource> = enabled
+                .iter()
+                .filter(|i| !fold.contains(i))
+                .map(|&i| &samples[i].0)
+                .collect();
+            if training.is_empty() {
+                return Err(
+                    "Cross-validation needs another enabled sample to learn from".to_string(),
+                );
+            }
+            let mut rules: HashMap<usize, HashSet<(Direction, usize)>> = HashMap::new();
+            for source in &training {
+                let learned =
+                    build_adjacency_rules(&self.sample_grid(source), &|t: &TileType| t.index());
+                for (tile, neighbours) in learned {
+                    rules.entry(tile).or_default().extend(neighbours);
+                }
+            }
+            self.apply_overrides(&mut rules);
+            for &i in &fold {
+                let source = &samples[i].0;
+                reports.push((source.id(), check(&self.sample_grid(source), &rules)));
+            }
+        }
+        Ok(reports)
+    }
+
+    pub fn print_cross_validation(&self, holdout: &str) -> Result<(), String> {
+        let mut reports = self.cross_validate(holdout)?;
+        if reports.is_empty() {
+            return Err(t!(
+                "No samples enabled, turn some on in the sample manager (J)"
+            ));
+        }
+        reports.sort_by(|a, b| b.1.rate().total_cmp(&a.1.rate()));
+        say!("Held-out samples against rules learned from the rest:");
+        for (id, report) in &reports {
+            println!(
+                " {:<28} {:>4} of {:>5} pairs broken ({:.1}%)",
+                id,
+                report.violations,
+                report.pairs,
+                report.rate() * 100.0
+            );
+            let mut broken: Vec<_> = report.broken.iter().collect();
+            broken.sort_by(|a, b| b.1.cmp(a.1));
+            for ((neighbour, side, tile), count) in broken.into_iter().take(LISTED_PAIRS) {
+                say!(
+                    "     {neighbour} {side} {tile} x{count}",
+                    neighbour = format!("{:?}", TileType::ALL[*neighbour]),
+                    side = side,
+                    tile = format!("{:?}", TileType::ALL[*tile]),
+                    count = count
+                );
+            }
+        }
+        if let Some((id, report)) = reports.first()
+            && report.violations > 0
+        {
+            say!("{id} agrees least with the others", id = id);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,795 @@
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::mpsc::Receiver;
+
+use crate::Direction;
+use crate::i18n::say;
+use crate::observer::{GenerationObserver, format_bytes};
+use crate::regions::Quiet;
+
+// give up on a model after this many branching decisions rather than hang the window
+const MAX_DECISIONS: usize = 200_000;
+
+pub const DEFAULT_MEMORY_BUDGET: usize = 256 * 1024 * 1024;
+
+// how hard the value order leans against a tile that is off its target share
+const TARGET_GAIN: f64 = 10.0;
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Down,
+    Direction::Left,
+    Direction::Right,
+];
+
+// which undecided cell to branch on next
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum CellOrder {
+    // fewest options left, ties by position
+    #[default]
+    MinimumRemaining,
+    // first undecided cell in reading order
+    Scanline,
+}
+
+// what to do when an assignment leads to a contradiction
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Strategy {
+    // undo the latest decision and try its next value
+    #[default]
+    Backtrack,
+    // throw the attempt away and start over with the rng where it is
+    Restart,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct SolverSettings {
+    pub cell_order: CellOrder,
+    pub strategy: Strategy,
+    pub max_restarts: usize,
+    // bytes the decision stack may hold before backtracking gives way to restarts
+    pub memory_budget: usize,
+}
+
+impl Default for SolverSettings {
+    fn default() -> Self {
+        SolverSettings {
+            cell_order: CellOrder::default(),
+            strategy: Strategy::default(),
+            max_restarts: 100,
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+        }
+    }
+}
+
+// desired share of the finished map per tile id, None for "whatever is left";
+// shares within `tolerance` of their target aren't corrected
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TargetShares {
+    pub shares: Vec<Option<f64>>,
+    pub tolerance: f64,
+}
+
+impl TargetShares {
+    // value-order weight per tile: its target share, scaled up while the decided cells
+    // have too little of it and down while they have too much
+    pub fn weights(&self, counts: &[usize]) -> Vec<f64> {
+        let decided: usize = counts.iter().sum();
+        let claimed: f64 = self.shares.iter().flatten().sum();
+        let open = self.shares.iter().filter(|share| share.is_none()).count();
+        let leftover = (1.0 - claimed).max(0.0) / open.max(1) as f64;
+        self.shares
+            .iter()
+            .zip(counts)
+            .map(|(share, &count)| {
+                let target = share.unwrap_or(leftover);
+                let actual = if decided == 0 {
+                    target
+                } else {
+                    count as f64 / decided as f64
+                };
+                let error = target - actual;
+                if error.abs() <= self.tolerance {
+                    target
+                } else {
+                    target * (TARGET_GAIN * error).exp()
+                }
+            })
+            .collect()
+    }
+}
+
+// what the solver generates from, swappable mid-run (see set_model_updates)
+#[derive(Clone, Debug, PartialEq)]
+pub struct SolverModel {
+    pub rules: HashMap<usize, HashSet<(Direction, usize)>>,
+    pub targets: Option<TargetShares>,
+    pub transition_costs: Option<Vec<Vec<f64>>>,
+}
+
+// domains before a decision, the cell decided, and values still to try
+type Frame = (Vec<u32>, usize, Vec<usize>);
+
+fn frame_bytes(cell_count: usize, capacity: usize) -> usize {
+    cell_count * size_of::<u32>() + capacity * size_of::<usize>()
+}
+
+// a search stopped between decisions by a suspend request, all it needs to go on
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Suspended {
+    fixed: Vec<((usize, usize), usize)>,
+    domains: Vec<u32>,
+    stack: Vec<Frame>,
+    weights: Vec<f64>,
+}
+
+// complete backtracking constraint solver over the learned adjacency rules: arc
+// consistency after every assignment, fewest-options-first cell order, seeded value order.
+// serializable whole, so a suspended search can be saved and resumed elsewhere
+#[derive(Serialize, Deserialize)]
+pub struct CspSolver {
+    width: usize,
+    height: usize,
+    // allowed[tile][direction] = bitmask of tiles that may sit in that direction
+    allowed: Vec<[u32; 4]>,
+    // per-cell tile tried first when branching, a soft hint the search may override,
+    // and the share of branchings it's honoured on
+    preferred: Vec<Option<(usize, f64)>>,
+    // per-cell bitmask of tiles ruled out before the search starts
+    excluded: Vec<u32>,
+    // cost between each pair of tile ids, see costs.rs
+    transition_costs: Option<Vec<Vec<f64>>>,
+    targets: Option<TargetShares>,
+    // replacement models sent while the search runs, picked up between decisions
+    #[serde(skip)]
+    model_updates: Option<Receiver<SolverModel>>,
+    // asks the search to stop before its next decision and keep where it got to
+    #[serde(skip)]
+    suspend_requests: Option<Receiver<()>>,
+    suspended: Option<Suspended>,
+    rng: ChaCha12Rng,
+    settings: SolverSettings,
+    pub decisions: usize,
+    // assignments that failed and had to be undone
+    pub backtracks: usize,
+    pub restarts: usize,
+    // most bytes held by saved domains on the decision stack at once
+    pub peak_memory: usize,
+}
+
+pub fn dir_index(dir: Direction) -> usize {
+    match dir {
+        Direction::Up => 0,
+        Direction::Down => 1,
+        Direction::Left => 2,
+        Direction::Right => 3,
+    }
+}
+
+// allowed[tile][direction] masks from the learned rules
+pub fn allowed_masks(
+    rules: &HashMap<usize, HashSet<(Direction, usize)>>,
+    tile_count: usize,
+) -> Vec<[u32; 4]> {
+    let mut allowed = vec![[0u32; 4]; tile_count];
+    for (tile, neighbours) in rules {
+        for (dir, neighbour) in neighbours {
+            allowed[*tile][dir_index(*dir)] |= 1 << neighbour;
+        }
+    }
+    allowed
+}
+
+impl CspSolver {
+    pub fn new(
+        rules: &HashMap<usize, HashSet<(Direction, usize)>>,
+        tile_count: usize,
+        width: usize,
+        height: usize,
+        seed: u64,
+    ) -> Self {
+        assert!(tile_count <= 32, "domains are u32 bitmasks");
+        CspSolver {
+            width,
+            height,
+            allowed: allowed_masks(rules, tile_count),
+            preferred: vec![None; width * height],
+            excluded: vec![0; width * height],
+            transition_costs: None,
+            targets: None,
+            model_updates: None,
+            suspend_requests: None,
+            suspended: None,
+            rng: ChaCha12Rng::seed_from_u64(seed),
+            settings: SolverSettings::default(),
+            decisions: 0,
+            backtracks: 0,
+            restarts: 0,
+            peak_memory: 0,
+        }
+    }
+
+    pub fn with_settings(mut self, settings: SolverSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn set_preferences(&mut self, preferred: &HashMap<(usize, usize), usize>) {
+        for (&(x, y), &tile) in preferred {
+            if x < self.width && y < self.height {
+                self.preferred[y * self.width + x] = Some((tile, 1.0));
+            }
+        }
+    }
+
+    // like set_preferences, each hint honoured on only `strength` of the branchings
+    pub fn set_weighted_preferences(&mut self, preferred: &HashMap<(usize, usize), (usize, f64)>) {
+        for (&(x, y), &(tile, strength)) in preferred {
+            if x < self.width && y < self.height && strength > 0.0 {
+                self.preferred[y * self.width + x] = Some((tile, strength.min(1.0)));
+            }
+        }
+    }
+
+    // hard limits unlike preferences: the cell can't take any of these tiles
+    pub fn exclude(&mut self, (x, y): (usize, usize), tile: usize) {
+        if x < self.width && y < self.height && tile < self.allowed.len() {
+            self.excluded[y * self.width + x] |= 1 << tile;
+        }
+    }
+
+    pub fn set_targets(&mut self, targets: Option<TargetShares>) {
+        self.targets = targets.filter(|targets| targets.shares.len() == self.allowed.len());
+    }
+
+    pub fn set_transition_costs(&mut self, costs: Option<Vec<Vec<f64>>>) {
+        self.transition_costs = costs.filter(|costs| costs.len() == self.allowed.len());
+    }
+
+    // the search swaps in the latest model from `updates` each time it is about to
+    // pick a cell, re-checking what it has decided so far against it
+    pub fn set_model_updates(&mut self, updates: Receiver<SolverModel>) {
+        self.model_updates = Some(updates);
+    }
+
+    // a request on `requests` stops the search before its next decision; `solve` then
+    // returns None without completing and `resume` goes on from there
+    pub fn set_suspend_requests(&mut self, requests: Receiver<()>) {
+        self.suspend_requests = Some(requests);
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspended.is_some()
+    }
+
+    // every cell's remaining tiles where a suspended search stopped
+    pub fn suspended_domains(&self) -> Option<&[u32]> {
+        self.suspended.as_ref().map(|s| s.domains.as_slice())
+    }
+
+    fn apply_model(&mut self, model: SolverModel) {
+        self.allowed = allowed_masks(&model.rules, self.allowed.len());
+        self.set_targets(model.targets);
+        self.set_transition_costs(model.transition_costs);
+    }
+
+    // tiles with at least one neighbour in the rules; the others can't be placed
+    fn known_tiles(&self) -> u32 {
+        (0..self.allowed.len())
+            .filter(|tile| self.allowed[*tile].iter().any(|mask| *mask != 0))
+            .fold(0u32, |mask, tile| mask | 1 << tile)
+    }
+
+    // the domains a search starts from: the known tiles less each cell's excluded ones,
+    // fixed cells pinned, before anything is propagated
+    fn root_domains(&self, fixed: &HashMap<(usize, usize), usize>) -> Vec<u32> {
+        let known = self.known_tiles();
+        let mut domains: Vec<u32> = self.excluded.iter().map(|ex| known & !ex).collect();
+        for (&(x, y), &tile) in fixed {
+            if x < self.width && y < self.height {
+                domains[y * self.width + x] &= 1 << tile;
+            }
+        }
+        domains
+    }
+
+    // replays the decisions on the stack from the root under the model as it is now,
+    // rebuilding each frame, so loosened rules give open cells back the tiles they now
+    // allow as tightened ones take them away. stops at the first decision that no
+    // longer fits and keeps its frame with the values still untried; false when that
+    // happened or the root itself fails
+    fn revalidate(
+        &self,
+        fixed: &HashMap<(usize, usize), usize>,
+        stack: &mut Vec<Frame>,
+        domains: &mut Vec<u32>,
+    ) -> bool {
+        let cell_count = domains.len();
+        // the tile each frame decided, which the next frame's saved domains hold and
+        // the current domains hold for the last one
+        let decided: Vec<u32> = stack
+            .iter()
+            .enumerate()
+            .map(|(i, (_, cell, _))| {
+                stack
+                    .get(i + 1)
+                    .map_or(domains[*cell], |(saved, _, _)| saved[*cell])
+            })
+            .collect();
+        let mut replay = self.root_domains(fixed);
+        if replay.contains(&0) || !self.propagate(&mut replay, 0..cell_count, &mut Quiet) {
+            stack.clear();
+            return false;
+        }
+        for (i, ((saved, cell, values), tile)) in stack.iter_mut().zip(decided).enumerate() {
+            saved.clone_from(&replay);
+            values.retain(|value| replay[*cell] & (1 << value) != 0);
+            replay[*cell] &= tile;
+            if replay[*cell] == 0 || !self.propagate(&mut replay, [*cell], &mut Quiet) {
+                stack.truncate(i + 1);
+                return false;
+            }
+        }
+        *domains = replay;
+        true
+    }
+
+    // scales each tile's weight down by e^-cost for every decided neighbour of `cell`
+    fn apply_transition_costs(&self, cell: usize, domains: &[u32], weights: &mut [f64]) {
+        let Some(costs) = &self.transition_costs else {
+            return;
+        };
+        let (x, y) = self.cell_xy(cell);
+        for dir in DIRECTIONS {
+            let Some((nx, ny)) = self.neighbour(x, y, dir) else {
+                continue;
+            };
+            let domain = domains[ny * self.width + nx];
+            if domain.count_ones() != 1 {
+                continue;
+            }
+            let neighbour = domain.trailing_zeros() as usize;
+            for (tile, weight) in weights.iter_mut().enumerate() {
+                *weight *= (-costs[tile][neighbour]).exp();
+            }
+        }
+    }
+
+    // cells already down to one tile, per tile
+    fn decided_counts(&self, domains: &[u32]) -> Vec<usize> {
+        let mut counts = vec![0; self.allowed.len()];
+        for domain in domains.iter().filter(|d| d.count_ones() == 1) {
+            counts[domain.trailing_zeros() as usize] += 1;
+        }
+        counts
+    }
+
+    // weighted shuffle (Efraimidis-Spirakis keys), heaviest last since values pop from the back
+    fn order_values(&mut self, values: &mut Vec<usize>, weights: &[f64]) {
+        let mut keyed: Vec<(f64, usize)> = values
+            .iter()
+            .map(|&tile| {
+                let u: f64 = self.rng.gen_range(f64::EPSILON..1.0);
+                let key = if weights[tile] > 0.0 {
+                    u.powf(1.0 / weights[tile])
+                } else {
+                    0.0
+                };
+                (key, tile)
+            })
+            .collect();
+        keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+        *values = keyed.into_iter().map(|(_, tile)| tile).collect();
+    }
+
+    fn neighbour(&self, x: usize, y: usize, dir: Direction) -> Option<(usize, usize)> {
+        match dir {
+            Direction::Up if y > 0 => Some((x, y - 1)),
+            Direction::Down if y + 1 < self.height => Some((x, y + 1)),
+            Direction::Left if x > 0 => Some((x - 1, y)),
+            Direction::Right if x + 1 < self.width => Some((x + 1, y)),
+            _ => None,
+        }
+    }
+
+    // AC-3 from the given cells; false when some domain empties
+    fn cell_xy(&self, cell: usize) -> (usize, usize) {
+        (cell % self.width, cell / self.width)
+    }
+
+    fn propagate(
+        &self,
+        domains: &mut [u32],
+        start: impl IntoIterator<Item = usize>,
+        observer: &mut dyn GenerationObserver,
+    ) -> bool {
+        let mut queue: VecDeque<usize> = start.into_iter().collect();
+        while let Some(cell) = queue.pop_front() {
+            let (x, y) = self.cell_xy(cell);
+            for dir in DIRECTIONS {
+                let Some((nx, ny)) = self.neighbour(x, y, dir) else {
+                    continue;
+                };
+                let mut support = 0;
+                for (tile, allowed) in self.allowed.iter().enumerate() {
+                    if domains[cell] & (1 << tile) != 0 {
+                        support |= allowed[dir_index(dir)];
+                    }
+                }
+                let n = ny * self.width + nx;
+                let narrowed = domains[n] & support;
+                if narrowed != domains[n] {
+                    let removed = domains[n] & !narrowed;
+                    for tile in (0..self.allowed.len()).filter(|t| removed & (1 << t) != 0) {
+                        observer.on_eliminate((nx, ny), tile);
+                    }
+                    if narrowed == 0 {
+                        observer.on_contradiction((nx, ny));
+                        return false;
+                    }
+                    if narrowed.count_ones() == 1 {
+                        observer.on_collapse((nx, ny), narrowed.trailing_zeros() as usize);
+                    }
+                    domains[n] = narrowed;
+                    queue.push_back(n);
+                }
+            }
+        }
+        true
+    }
+
+    // `fixed` pins cells (e.g. locked tiles); returns tile ids as rows, None if unsatisfiable
+    pub fn solve(
+        &mut self,
+        fixed: &HashMap<(usize, usize), usize>,
+        observer: &mut dyn GenerationObserver,
+    ) -> Option<Vec<Vec<usize>>> {
+        let result = self.search(fixed, observer);
+        self.finish(fixed, result, observer)
+    }
+
+    // goes on with a suspended search where it stopped, as if it never had
+    pub fn resume(&mut self, observer: &mut dyn GenerationObserver) -> Option<Vec<Vec<usize>>> {
+        let suspended = self.suspended.take()?;
+        let fixed: HashMap<(usize, usize), usize> = suspended.fixed.into_iter().collect();
+        let cell_count = self.width * self.height;
+        let stack_bytes = suspended
+            .stack
+            .iter()
+            .map(|(_, _, values)| frame_bytes(cell_count, values.capacity()))
+            .sum();
+        let result = self.decide(
+            &fixed,
+            suspended.domains,
+            suspended.stack,
+            stack_bytes,
+            suspended.weights,
+            observer,
+        );
+        self.finish(&fixed, result, observer)
+    }
+
+    // restarts a failed search while the strategy allows, then reports how it ended;
+    // a suspended search hasn't ended
+    fn finish(
+        &mut self,
+        fixed: &HashMap<(usize, usize), usize>,
+        mut result: Option<Vec<Vec<usize>>>,
+        observer: &mut dyn GenerationObserver,
+    ) -> Option<Vec<Vec<usize>>> {
+        while result.is_none()
+            && self.suspended.is_none()
+            && self.settings.strategy == Strategy::Restart
+            && self.restarts < self.settings.max_restarts
+            && self.decisions <= MAX_DECISIONS
+            && frame_bytes(self.width * self.height, self.allowed.len())
+                <= self.settings.memory_budget
+        {
+            self.restarts += 1;
+            observer.on_restart();
+            result = self.search(fixed, observer);
+        }
+        if self.suspended.is_none() {
+            observer.on_complete(result.is_some());
+        }
+        result
+    }
+
+    fn search(
+        &mut self,
+        fixed: &HashMap<(usize, usize), usize>,
+        observer: &mut dyn GenerationObserver,
+    ) -> Option<Vec<Vec<usize>>> {
+        let cell_count = self.width * self.height;
+        // tiles never seen in the sample have no neighbours and can't be placed
+        let mut domains = self.root_domains(fixed);
+        if domains.contains(&0) || !self.propagate(&mut domains, 0..cell_count, observer) {
+            return None;
+        }
+        self.decide(fixed, domains, Vec::new(), 0, Vec::new(), observer)
+    }
+
+    // the decision loop, from propagated `domains` and the decisions on `stack`
+    fn decide(
+        &mut self,
+        fixed: &HashMap<(usize, usize), usize>,
+        mut domains: Vec<u32>,
+        mut stack: Vec<Frame>,
+        mut stack_bytes: usize,
+        mut weights: Vec<f64>,
+        observer: &mut dyn GenerationObserver,
+    ) -> Option<Vec<Vec<usize>>> {
+        let cell_count = self.width * self.height;
+        // the running counts are refreshed every so often rather than every decision
+        let refresh = (cell_count / 64).max(1);
+        loop {
+            if let Some(targets) = &self.targets
+                && (weights.is_empty() || self.decisions.is_multiple_of(refresh))
+            {
+                weights = targets.weights(&self.decided_counts(&domains));
+            }
+            observer.on_step(&domains, &weights);
+            if self
+                .suspend_requests
+                .as_ref()
+                .is_some_and(|requests| requests.try_iter().count() > 0)
+            {
+                self.suspended = Some(Suspended {
+                    fixed: fixed.iter().map(|(&cell, &tile)| (cell, tile)).collect(),
+                    domains,
+                    stack,
+                    weights,
+                });
+                return None;
+            }
+            if let Some(model) = self
+                .model_updates
+                .as_ref()
+                .and_then(|updates| updates.try_iter().last())
+            {
+                self.apply_model(model);
+                weights.clear();
+                let holds = self.revalidate(fixed, &mut stack, &mut domains);
+                stack_bytes = stack
+                    .iter()
+                    .map(|(_, _, values)| frame_bytes(cell_count, values.capacity()))
+                    .sum();
+                if holds {
+                    say!("Model updated, reopened the undecided cells under it");
+                } else {
+                    say!("Model updated and the latest decisions no longer fit, backing up");
+                    self.advance(&mut stack, &mut domains, &mut stack_bytes, observer)?;
+                }
+                continue;
+            }
+            let mut undecided = (0..cell_count).filter(|cell| domains[*cell].count_ones() > 1);
+            let next = match self.settings.cell_order {
+                CellOrder::MinimumRemaining => {
+                    undecided.min_by_key(|cell| domains[*cell].count_ones())
+                }
+                CellOrder::Scanline => undecided.next(),
+            };
+            let Some(cell) = next else {
+                let rows = domains
+                    .chunks(self.width)
+                    .map(|row| row.iter().map(|d| d.trailing_zeros() as usize).collect())
+                    .collect();
+                return Some(rows);
+            };
+
+            let mut values: Vec<usize> = (0..self.allowed.len())
+                .filter(|tile| domains[cell] & (1 << tile) != 0)
+                .collect();
+            if self.targets.is_some() || self.transition_costs.is_some() {
+                let mut value_weights = if self.targets.is_some() {
+                    weights.clone()
+                } else {
+                    vec![1.0; self.allowed.len()]
+                };
+                self.apply_transition_costs(cell, &domains, &mut value_weights);
+                self.order_values(&mut values, &value_weights);
+            } else {
+                values.shuffle(&mut self.rng);
+            }
+            // values are popped from the back, so the preferred tile goes last
+            if let Some((preferred, strength)) = self.preferred[cell]
+                && (strength >= 1.0 || self.rng.gen_bool(strength))
+                && let Some(i) = values.iter().position(|&tile| tile == preferred)
+            {
+                let last = values.len() - 1;
+                values.swap(i, last);
+            }
+            // a restart never backs up, so only the decision being made keeps a frame;
+            // a model update then replays just that one and reopens the rest
+            if self.settings.strategy == Strategy::Restart {
+                stack.clear();
+                stack_bytes = 0;
+            }
+            stack_bytes += frame_bytes(cell_count, values.capacity());
+            stack.push((domains.clone(), cell, values));
+            if stack_bytes > self.peak_memory {
+                self.peak_memory = stack_bytes;
+                observer.on_memory(stack_bytes);
+            }
+            if stack_bytes > self.settings.memory_budget {
+                if self.settings.strategy == Strategy::Backtrack {
+                    say!(
+                        "Decision stack passed the {budget} memory budget, restarting instead of backtracking",
+                        budget = format_bytes(self.settings.memory_budget)
+                    );
+                    self.settings.strategy = Strategy::Restart;
+                } else {
+                    say!(
+                        "A single decision passes the {budget} memory budget, giving up",
+                        budget = format_bytes(self.settings.memory_budget)
+                    );
+                }
+                return None;
+            }
+
+            self.advance(&mut stack, &mut domains, &mut stack_bytes, observer)?;
+        }
+    }
+
+    // takes the next untried value, unwinding frames that ran out; None when there
+    // is nothing left to try or the search should give up
+    fn advance(
+        &mut self,
+        stack: &mut Vec<Frame>,
+        domains: &mut Vec<u32>,
+        stack_bytes: &mut usize,
+        observer: &mut dyn GenerationObserver,
+    ) -> Option<()> {
+        let cell_count = domains.len();
+        loop {
+            let (saved, cell, values) = stack.last_mut()?;
+            let Some(tile) = values.pop() else {
+                if let Some((_, _, values)) = stack.pop() {
+                    *stack_bytes -= frame_bytes(cell_count, values.capacity());
+                }
+                // the decision under it is undone too, for its next value
+                if let Some((_, cell, _)) = stack.last() {
+                    observer.on_backtrack(self.cell_xy(*cell));
+                }
+                continue;
+            };
+            self.decisions += 1;
+            if self.decisions > MAX_DECISIONS {
+                return None;
+            }
+            domains.clone_from(saved);
+            domains[*cell] = 1 << tile;
+            let cell = *cell;
+            observer.on_observe(self.cell_xy(cell), tile);
+            observer.on_collapse(self.cell_xy(cell), tile);
+            if self.propagate(domains, [cell], observer) {
+                return Some(());
+            }
+            self.backtracks += 1;
+            observer.on_backtrack(self.cell_xy(cell));
+            if self.settings.strategy == Strategy::Restart {
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::{self, Sender};
+
+    // may sit beside anything, so a column of it cuts the map in two
+    const FREE: usize = 3;
+
+    // tiles 0-2 swap 0 and 1 to their right and 1 and 2 below; the swaps don't
+    // commute, so no 2x2 block of them can be filled, though each cell on its own
+    // always has a neighbour it allows
+    fn swap_right(tile: usize) -> usize {
+        [1, 0, 2][tile]
+    }
+
+    fn swap_below(tile: usize) -> usize {
+        [0, 2, 1][tile]
+    }
+
+    fn rules(below: impl Fn(usize) -> Vec<usize>) -> HashMap<usize, HashSet<(Direction, usize)>> {
+        let mut rules: HashMap<usize, HashSet<(Direction, usize)>> = HashMap::new();
+        let mut allow = |tile, dir, neighbour, back| {
+            rules.entry(tile).or_default().insert((dir, neighbour));
+            rules.entry(neighbour).or_default().insert((back, tile));
+        };
+        for tile in 0..FREE {
+            allow(tile, Direction::Right, swap_right(tile), Direction::Left);
+            for neighbour in below(tile) {
+                allow(tile, Direction::Down, neighbour, Direction::Up);
+            }
+        }
+        for tile in 0..=FREE {
+            allow(FREE, Direction::Right, tile, Direction::Left);
+            allow(FREE, Direction::Left, tile, Direction::Right);
+            allow(FREE, Direction::Down, tile, Direction::Up);
+            allow(FREE, Direction::Up, tile, Direction::Down);
+        }
+        rules
+    }
+
+    fn model(below: impl Fn(usize) -> Vec<usize>) -> SolverModel {
+        SolverModel {
+            rules: rules(below),
+            targets: None,
+            transition_costs: None,
+        }
+    }
+
+    // a 4x2 map: an open column, a column pinned to FREE, then a 2x2 block. the open
+    // column comes first in the cell order, so the block contradicts with its
+    // decision on the stack
+    fn solver(rules: &HashMap<usize, HashSet<(Direction, usize)>>, seed: u64) -> CspSolver {
+        let mut solver = CspSolver::new(rules, 4, 4, 2, seed);
+        for y in 0..2 {
+            for x in [0, 2, 3] {
+                solver.exclude((x, y), FREE);
+            }
+        }
+        solver
+    }
+
+    fn fixed() -> HashMap<(usize, usize), usize> {
+        HashMap::from([((1, 0), FREE), ((1, 1), FREE)])
+    }
+
+    // sends the update the first time a cell runs out of options
+    struct LoosenOnContradiction {
+        update: Option<(Sender<SolverModel>, SolverModel)>,
+        contradicted: HashSet<(usize, usize)>,
+    }
+
+    impl GenerationObserver for LoosenOnContradiction {
+        fn on_contradiction(&mut self, cell: (usize, usize)) {
+            self.contradicted.insert(cell);
+            if let Some((sender, model)) = self.update.take() {
+                sender.send(model).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn loosened_rule_reopens_a_contradicted_cell() {
+        let strict = model(|tile| vec![swap_below(tile)]);
+        for seed in 0..5 {
+            assert!(
+                solver(&strict.rules, seed)
+                    .solve(&fixed(), &mut Quiet)
+                    .is_none(),
+                "seed {} filled the block under the strict rules",
+                seed
+            );
+
+            let mut solver = solver(&strict.rules, seed);
+            let (sender, updates) = mpsc::channel();
+            solver.set_model_updates(updates);
+            // anything may sit below tiles 0-2
+            let loose = model(|_| vec![0, 1, 2]);
+            let mut observer = LoosenOnContradiction {
+                update: Some((sender, loose)),
+                contradicted: HashSet::new(),
+            };
+            let rows = solver
+                .solve(&fixed(), &mut observer)
+                .unwrap_or_else(|| panic!("seed {} failed under the loosened rules", seed));
+            assert!(solver.backtracks > 0);
+            assert!(!observer.contradicted.is_empty());
+            assert!(observer.contradicted.iter().all(|&(x, _)| x >= 2));
+            for row in &rows {
+                assert_eq!(row[1], FREE);
+                assert_eq!(row[3], swap_right(row[2]));
+            }
+            // only the loosened rule lets the block be filled
+            assert!((2..4).any(|x| rows[1][x] != swap_below(rows[0][x])));
+        }
+    }
+}
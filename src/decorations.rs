@@ -0,0 +1,102 @@
+use crate::scatter::{Feature, FeatureKind};
+use crate::{TileSystem, UndoStep, grid};
+
+// what the brush does while painting decorations instead of terrain
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DecorationBrush {
+    Place(FeatureKind),
+    Erase,
+}
+
+impl DecorationBrush {
+    pub const ALL: [DecorationBrush; 5] = [
+        DecorationBrush::Place(FeatureKind::Tree),
+        DecorationBrush::Place(FeatureKind::Village),
+        DecorationBrush::Place(FeatureKind::Rock),
+        DecorationBrush::Place(FeatureKind::House),
+        DecorationBrush::Erase,
+    ];
+
+    // off -> each kind -> erase -> off, for Ctrl+Y
+    pub fn cycled(current: Option<Self>) -> Option<Self> {
+        match current {
+            None => Some(Self::ALL[0]),
+            Some(brush) => {
+                let i = Self::ALL.iter().position(|b| *b == brush).unwrap_or(0);
+                Self::ALL.get(i + 1).copied()
+            }
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            DecorationBrush::Place(kind) => format!("{:?}", kind),
+            DecorationBrush::Erase => "Erase".to_string(),
+        }
+    }
+}
+
+// decorations sit on top of the terrain, at most one per cell, and never change the tile under them
+impl TileSystem {
+    pub fn decoration_at(&self, x: usize, y: usize) -> Option<FeatureKind> {
+        self.features
+            .iter()
+            .find(|f| f.x == x && f.y == y)
+            .map(|f| f.kind)
+    }
+
+    pub fn set_decoration(&mut self, x: usize, y: usize, kind: Option<FeatureKind>) {
+        if x >= self.grid_width || y >= self.grid_height {
+            return;
+        }
+        self.features.retain(|f| f.x != x || f.y != y);
+        if let Some(kind) = kind {
+            self.features.push(Feature { x, y, kind });
+        }
+        self.dirty = true;
+    }
+
+    pub fn push_decoration_undo(&mut self) {
+        self.push_undo_step(UndoStep::Features(self.features.clone()));
+    }
+
+    // a size x size square of decorations centred on the cell, as one undo step
+    pub fn paint_decorations(&mut self, x: usize, y: usize, size: usize, brush: DecorationBrush) {
+        self.push_decoration_undo();
+        self.paint_decoration_stroke(x, y, size, brush);
+    }
+
+    pub fn paint_decoration_line(
+        &mut self,
+        from: (usize, usize),
+        x: usize,
+        y: usize,
+        size: usize,
+        brush: DecorationBrush,
+    ) {
+        self.push_decoration_undo();
+        for (px, py) in grid::line_cells(from, (x, y)) {
+            self.paint_decoration_stroke(px, py, size, brush);
+        }
+    }
+
+    // more of a stroke begun with paint_decorations, sharing its undo step
+    pub fn paint_decoration_stroke(
+        &mut self,
+        x: usize,
+        y: usize,
+        size: usize,
+        brush: DecorationBrush,
+    ) {
+        let kind = match brush {
+            DecorationBrush::Place(kind) => Some(kind),
+            DecorationBrush::Erase => None,
+        };
+        let half = size.saturating_sub(1) / 2;
+        for py in y.saturating_sub(half)..y.saturating_sub(half) + size {
+            for px in x.saturating_sub(half)..x.saturating_sub(half) + size {
+                self.set_decoration(px, py, kind);
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::i18n::say;
+use crate::maze::{FLOOR, WALL};
+use crate::{Region, Tile, TileSystem};
+
+// leaves are not split below this, rooms need a wall ring plus some floor
+const MIN_LEAF: usize = 7;
+const MIN_ROOM: usize = 3;
+
+pub struct DungeonLayout {
+    pub rooms: Vec<Region>,
+    pub corridors: Vec<(usize, usize)>,
+}
+
+// binary space partition of the grid, one room per leaf, siblings joined by L-shaped corridors
+pub fn layout(width: usize, height: usize, seed: u64) -> DungeonLayout {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut dungeon = DungeonLayout {
+        rooms: Vec::new(),
+        corridors: Vec::new(),
+    };
+    if width >= MIN_ROOM + 2 && height >= MIN_ROOM + 2 {
+        split(
+            Region {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            },
+            &mut rng,
+            &mut dungeon,
+        );
+    }
+    dungeon
+}
+
+// returns the centre of one room in this subtree for the parent to connect to
+fn split(leaf: Region, rng: &mut StdRng, dungeon: &mut DungeonLayout) -> (usize, usize) {
+    let can_split_x = leaf.width >= MIN_LEAF * 2;
+    let can_split_y = leaf.height >= MIN_LEAF * 2;
+    let split_x = match (can_split_x, can_split_y) {
+        (false, false) => return place_room(&leaf, rng, dungeon),
+        (true, false) => true,
+        (false, true) => false,
+        (true, true) => {
+            leaf.width > leaf.height || (leaf.width == leaf.height && rng.gen_bool(0.5))
+        }
+    };
+
+    let (a, b) = if split_x {
+        let at = rng.gen_range(MIN_LEAF..=leaf.width - MIN_LEAF);
+        (
+            Region { width: at, ..leaf },
+            Region {
+                x: leaf.x + at,
+                width: leaf.width - at,
+                ..leaf
+            },
+        )
+    } else {
+        let at = rng.gen_range(MIN_LEAF..=leaf.height - MIN_LEAF);
+        (
+            Region { height: at, ..leaf },
+            Region {
+                y: leaf.y + at,
+                height: leaf.height - at,
+                ..leaf
+            },
+        )
+    };
+    let from = split(a, rng, dungeon);
+    let to = split(b, rng, dungeon);
+    connect(from, to, rng.gen_bool(0.5), dungeon);
+    from
+}
+
+// room interior strictly inside the leaf so its wall ring fits too
+fn place_room(leaf: &Region, rng: &mut StdRng, dungeon: &mut DungeonLayout) -> (usize, usize) {
+    let max_width = leaf.width - 2;
+    let max_height = leaf.height - 2;
+    let width = rng.gen_range(MIN_ROOM.min(max_width)..=max_width);
+    let height = rng.gen_range(MIN_ROOM.min(max_height)..=max_height);
+    let x = leaf.x + 1 + rng.gen_range(0..=max_width - width);
+    let y = leaf.y + 1 + rng.gen_range(0..=max_height - height);
+    dungeon.rooms.push(Region {
+        x,
+        y,
+        width,
+        height,
+    });
+    (x + width / 2, y + height / 2)
+}
+
+fn connect(
+    from: (usize, usize),
+    to: (usize, usize),
+    horizontal_first: bool,
+    dungeon: &mut DungeonLayout,
+) {
+    let corner = if horizontal_first {
+        (to.0, from.1)
+    } else {
+        (from.0, to.1)
+    };
+    for (a, b) in [(from, corner), (corner, to)] {
+        for x in a.0.min(b.0)..=a.0.max(b.0) {
+            for y in a.1.min(b.1)..=a.1.max(b.1) {
+                dungeon.corridors.push((x, y));
+            }
+        }
+    }
+}
+
+impl TileSystem {
+    // lays out rooms and corridors and locks them, leaving everything else for the solver;
+    // the previous layout's locks go first, cells locked by hand stay locked
+    pub fn generate_dungeon(&mut self, seed: u64) -> usize {
+        let dungeon = layout(self.grid_width, self.grid_height, seed);
+        self.push_lock_undo();
+        for cell in std::mem::take(&mut self.dungeon_cells) {
+            self.locked_cells.remove(&cell);
+        }
+        let locked_before = self.locked_cells.clone();
+
+        for room in &dungeon.rooms {
+            // wall ring first so corridors can cut doorways through it
+            for y in room.y - 1..=room.y + room.height {
+                for x in room.x - 1..=room.x + room.width {
+                    let tile_type = if room.covers(x, y) { FLOOR } else { WALL };
+                    self.set_tile(x, y, Tile::from_type(&tile_type));
+                    self.locked_cells.insert((x, y));
+                }
+            }
+        }
+        for &(x, y) in &dungeon.corridors {
+            self.locked_cells.remove(&(x, y));
+            self.set_tile(x, y, Tile::from_type(&FLOOR));
+            self.locked_cells.insert((x, y));
+        }
+        self.dungeon_cells = self
+            .locked_cells
+            .difference(&locked_before)
+            .copied()
+            .collect();
+
+        say!(
+            "Laid out {count} room(s) with seed {seed}, generate (W) to fill the rest",
+            count = dungeon.rooms.len(),
+            seed = seed
+        );
+        dungeon.rooms.len()
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use crate::csp::CspSolver;
+use crate::i18n::{say, t};
+use crate::observer::StatsObserver;
+use crate::{Backend, Direction, TileSystem, TileType, integrity};
+
+// sides in the order signatures are printed
+const SIDES: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+fn opposite(side: Direction) -> Direction {
+    match side {
+        Direction::Up => Direction::Down,
+        Direction::Down => Direction::Up,
+        Direction::Left => Direction::Right,
+        Direction::Right => Direction::Left,
+    }
+}
+
+// the row or column of tiles along one side, read left to right or top to bottom
+fn edge(tiles: &[Vec<TileType>], side: Direction) -> Vec<TileType> {
+    match side {
+        Direction::Up => tiles.first().cloned().unwrap_or_default(),
+        Direction::Down => tiles.last().cloned().unwrap_or_default(),
+        Direction::Left => tiles
+            .iter()
+            .filter_map(|row| row.first().cloned())
+            .collect(),
+        Direction::Right => tiles.iter().filter_map(|row| row.last().cloned()).collect(),
+    }
+}
+
+// short stand-in for an edge profile; two chunks fit where one side's signature
+// equals the other's on the opposite side
+pub fn signature(edge: &[TileType]) -> String {
+    let bytes: Vec<u8> = edge.iter().map(|tile| tile.index() as u8).collect();
+    format!("{:08x}", integrity::fnv1a(&bytes) as u32)
+}
+
+// "right=forest left=coast model=islands" typed at the console
+pub struct ChunkRequest {
+    pub neighbours: Vec<(Direction, String)>,
+    pub model: String,
+}
+
+impl ChunkRequest {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut request = ChunkRequest {
+            neighbours: Vec::new(),
+            model: String::new(),
+        };
+        for part in input.split_whitespace() {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| t!("Expected side=config, got '{part}'", part = part))?;
+            let side = match key.to_lowercase().as_str() {
+                "model" => {
+                    request.model = value.to_string();
+                    continue;
+                }
+                "up" | "top" => Direction::Up,
+                "right" => Direction::Right,
+                "down" | "bottom" => Direction::Down,
+                "left" => Direction::Left,
+                _ => return Err(t!("Unknown side '{key}'", key = key)),
+            };
+            request.neighbours.push((side, value.to_string()));
+        }
+        Ok(request)
+    }
+}
+
+impl TileSystem {
+    // edge signatures of every config, and which configs can sit against each side
+    pub fn list_edge_matches(&self) {
+        let names = self.config_names(None);
+        if names.is_empty() {
+            say!("No saved configurations");
+            return;
+        }
+        let edges: HashMap<&String, [String; 4]> = names
+            .iter()
+            .filter_map(|name| {
+                let tiles = self.config(name)?;
+                Some((*name, SIDES.map(|side| signature(&edge(tiles, side)))))
+            })
+            .collect();
+        say!("Edge signatures (up, right, down, left):");
+        for name in &names {
+            let Some(signatures) = edges.get(name) else {
+                continue;
+            };
+            println!(" - {}: {}", name, signatures.join(" "));
+            for (i, side) in SIDES.iter().enumerate() {
+                let facing = SIDES
+                    .iter()
+                    .position(|s| *s == opposite(*side))
+                    .unwrap_or(0);
+                let fits: Vec<&str> = names
+                    .iter()
+                    .filter(|other| {
+                        edges
+                            .get(*other)
+                            .is_some_and(|theirs| theirs[facing] == signatures[i])
+                    })
+                    .map(|other| other.as_str())
+                    .collect();
+                if !fits.is_empty() {
+                    println!("     {:?}: {}", side, fits.join(", "));
+                }
+            }
+        }
+    }
+
+    // border cells of the map pinned to the facing edges of the chosen neighbours
+    pub fn edge_constraints(
+        &self,
+        neighbours: &[(Direction, String)],
+    ) -> Result<HashMap<(usize, usize), usize>, String> {
+        let (width, height) = (self.grid_width, self.grid_height);
+        let mut fixed = HashMap::new();
+        for (side, name) in neighbours {
+            let tiles = self
+                .config(name)
+                .ok_or_else(|| t!("Configuration '{name}' not found", name = name))?;
+            let profile = edge(tiles, opposite(*side));
+            let length = match side {
+                Direction::Up | Direction::Down => width,
+                Direction::Left | Direction::Right => height,
+            };
+            if profile.len() != length {
+                return Err(t!(
+                    "'{name}' has a {count}-tile {opposite} edge but the map needs {length}",
+                    name = name,
+                    count = profile.len(),
+                    opposite = format!("{:?}", opposite(*side)),
+                    length = length
+                ));
+            }
+            for (i, tile) in profile.iter().enumerate() {
+                let cell = match side {
+                    Direction::Up => (i, 0),
+                    Direction::Down => (i, height - 1),
+                    Direction::Left => (0, i),
+                    Direction::Right => (width - 1, i),
+                };
+                // corners shared by two neighbours have to agree
+                if fixed
+                    .insert(cell, tile.index())
+                    .is_some_and(|t| t != tile.index())
+                {
+                    return Err(t!(
+                        "The neighbours disagree on the corner at ({x}, {y})",
+                        x = cell.0,
+                        y = cell.1
+                    ));
+                }
+            }
+        }
+        Ok(fixed)
+    }
+
+    // a new chunk whose edges match the neighbours', locked cells kept
+    pub fn generate_chunk(&mut self, request: &ChunkRequest, seed: u64) -> Result<(), String> {
+        let rules = self.learn_rules(&request.model)?;
+        let mut fixed = self.edge_constraints(&request.neighbours)?;
+        for (cell, tile) in self.locked_tile_ids() {
+            if fixed.insert(cell, tile).is_some_and(|t| t != tile) {
+                return Err(t!(
+                    "Locked cell ({x}, {y}) doesn't match a neighbour's edge",
+                    x = cell.0,
+                    y = cell.1
+                ));
+            }
+        }
+        let mut solver = CspSolver::new(
+            &rules,
+            TileType::ALL.len(),
+            self.grid_width,
+            self.grid_height,
+            seed,
+        );
+        solver.set_preferences(&self.biome_preferences());
+        solver.set_targets(self.target_shares());
+        solver.set_transition_costs(self.transition_cost_matrix());
+        let result = solver.solve(&fixed, &mut StatsObserver::default());
+        if self.apply_generated(&request.model, seed, result, Backend::Sat) {
+            Ok(())
+        } else {
+            Err(t!("No chunk fits those neighbours with this model"))
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::TileType;
+use crate::grid::DoubleBuffer;
+
+pub struct ErosionSettings {
+    pub thermal_iterations: usize,
+    // steepest stable height difference between neighbours
+    pub talus: f64,
+    pub droplets: usize,
+    pub droplet_steps: usize,
+    pub capacity: f64,
+    pub erode_rate: f64,
+    pub deposit_rate: f64,
+    pub evaporation: f64,
+}
+
+impl Default for ErosionSettings {
+    fn default() -> Self {
+        ErosionSettings {
+            thermal_iterations: 20,
+            talus: 0.08,
+            droplets: 2000,
+            droplet_steps: 64,
+            capacity: 4.0,
+            erode_rate: 0.3,
+            deposit_rate: 0.3,
+            evaporation: 0.05,
+        }
+    }
+}
+
+// starting heights when a map has no elevation layer yet
+pub fn tile_height(tile_type: &TileType) -> f64 {
+    match tile_type {
+        TileType::Water => 0.15,
+        TileType::Coast => 0.34,
+        TileType::Empty | TileType::Land => 0.5,
+        TileType::Mountain => 0.85,
+    }
+}
+
+pub fn classify(height: f64) -> TileType {
+    if height < 0.3 {
+        TileType::Water
+    } else if height < 0.38 {
+        TileType::Coast
+    } else if height < 0.7 {
+        TileType::Land
+    } else {
+        TileType::Mountain
+    }
+}
+
+pub fn elevation_from_tiles(tiles: &[Vec<TileType>], seed: u64) -> Vec<Vec<f64>> {
+    // a little jitter so flat areas have somewhere to drain
+    let mut rng = StdRng::seed_from_u64(seed);
+    tiles
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|t| tile_height(t) + rng.gen_range(-0.04..0.04))
+                .collect()
+        })
+        .collect()
+}
+
+fn neighbours(
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    [
+        (x.wrapping_sub(1), y),
+        (x + 1, y),
+        (x, y.wrapping_sub(1)),
+        (x, y + 1),
+    ]
+    .into_iter()
+    .filter(move |&(nx, ny)| nx < width && ny < height)
+}
+
+// slumps slopes steeper than the talus angle, reading the previous pass so rows don't
+// see each other's partial updates
+pub fn thermal_erosion(heights: &mut [Vec<f64>], settings: &ErosionSettings) {
+    let height = heights.len();
+    let width = heights.first().map_or(0, |row| row.len());
+    let mut buffer = DoubleBuffer::new(heights.to_vec());
+    for _ in 0..settings.thermal_iterations {
+        for y in 0..height {
+            for x in 0..width {
+                let previous = buffer.front();
+                let here = previous[y][x];
+                let lowest = neighbours(x, y, width, height)
+                    .min_by(|a, b| previous[a.1][a.0].total_cmp(&previous[b.1][b.0]));
+                if let Some((nx, ny)) = lowest {
+                    let diff = here - previous[ny][nx];
+                    if diff > settings.talus {
+                        let moved = (diff - settings.talus) / 2.0 * 0.5;
+                        let next = buffer.back_mut();
+                        next[y][x] -= moved;
+                        next[ny][nx] += moved;
+                    }
+                }
+            }
+        }
+        buffer.swap();
+    }
+    heights.clone_from_slice(&buffer.into_front());
+}
+
+// rain droplets run downhill, picking up sediment on steep ground and dropping it where
+// the slope flattens, which cuts valleys and builds up plains
+pub fn hydraulic_erosion(heights: &mut [Vec<f64>], settings: &ErosionSettings, seed: u64) {
+    let height = heights.len();
+    let width = heights.first().map_or(0, |row| row.len());
+    if width == 0 {
+        return;
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    for _ in 0..settings.droplets {
+        let (mut x, mut y) = (rng.gen_range(0..width), rng.gen_range(0..height));
+        let mut sediment = 0.0;
+        let mut water = 1.0;
+
+        for _ in 0..settings.droplet_steps {
+            let here = heights[y][x];
+            let lowest = neighbours(x, y, width, height)
+                .min_by(|a, b| heights[a.1][a.0].total_cmp(&heights[b.1][b.0]));
+            let Some((nx, ny)) = lowest else {
+                break;
+            };
+            let slope = here - heights[ny][nx];
+            if slope <= 0.0 {
+                // stuck in a pit, fill it with what's carried
+                heights[y][x] += sediment;
+                sediment = 0.0;
+                break;
+            }
+
+            let capacity = slope * water * settings.capacity;
+            if sediment > capacity {
+                let deposit = (sediment - capacity) * settings.deposit_rate;
+                heights[y][x] += deposit;
+                sediment -= deposit;
+            } else {
+                let eroded = ((capacity - sediment) * settings.erode_rate).min(slope);
+                heights[y][x] -= eroded;
+                sediment += eroded;
+            }
+
+            x = nx;
+            y = ny;
+            water *= 1.0 - settings.evaporation;
+        }
+        heights[y][x] += sediment;
+    }
+}
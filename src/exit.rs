@@ -0,0 +1,99 @@
+use std::fmt;
+use std::io::ErrorKind;
+
+use crate::cli::MapSize;
+use crate::{TileSystem, integrity};
+
+// why a headless command failed; each kind has its own exit code so scripts
+// can react without parsing messages. clap exits with 2 on bad arguments itself
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Failure {
+    // an option combination or file format the command can't handle
+    Usage,
+    // a missing config, sample, template or input file
+    NotFound,
+    // the solver found no solution
+    Unsolvable,
+    // reading or writing a file failed
+    Io,
+    // a file exists but its contents don't parse
+    Invalid,
+}
+
+impl Failure {
+    pub fn code(self) -> i32 {
+        match self {
+            Failure::Usage => 2,
+            Failure::NotFound => 3,
+            Failure::Unsolvable => 4,
+            Failure::Io => 5,
+            Failure::Invalid => 6,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Failure::Usage => "usage",
+            Failure::NotFound => "not-found",
+            Failure::Unsolvable => "unsolvable",
+            Failure::Io => "io",
+            Failure::Invalid => "invalid",
+        }
+    }
+
+    // NotFound for a missing file, Io for anything else
+    pub fn from_io(error: &std::io::Error) -> Self {
+        if error.kind() == ErrorKind::NotFound {
+            Failure::NotFound
+        } else {
+            Failure::Io
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CliError {
+    pub kind: Failure,
+    pub message: String,
+}
+
+impl CliError {
+    pub fn new(kind: Failure, message: impl Into<String>) -> Self {
+        CliError {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+// prints `error[kind]: message` on stderr and returns the exit code
+pub fn report(result: Result<(), CliError>) -> i32 {
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("error[{}]: {}", e.kind.name(), e.message);
+            e.kind.code()
+        }
+    }
+}
+
+impl TileSystem {
+    // the saved map for headless commands: never prompts, and refuses to carry on
+    // from a fresh map when the save is damaged
+    pub fn load_headless(size: &MapSize) -> Result<Self, CliError> {
+        match integrity::read_with_backup(Self::SAVE_FILE) {
+            Some(Ok(tile_system)) => Ok(tile_system),
+            Some(Err(e)) => Err(CliError::new(
+                Failure::Invalid,
+                format!("{} (open the editor to recover the save)", e),
+            )),
+            None => Ok(Self::with_size(size)),
+        }
+    }
+}
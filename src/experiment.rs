@@ -0,0 +1,154 @@
+use clap::Args;
+use std::fmt::Write as _;
+use std::fs;
+use std::time::Instant;
+
+use crate::cli::MapSize;
+use crate::config::AppConfig;
+use crate::csp::{CellOrder, CspSolver, DEFAULT_MEMORY_BUDGET, SolverSettings, Strategy};
+use crate::exit::{self, CliError, Failure};
+use crate::i18n::say;
+use crate::library;
+use crate::observer::StatsObserver;
+use crate::{TileSystem, TileType};
+
+const CELL_ORDERS: [CellOrder; 2] = [CellOrder::MinimumRemaining, CellOrder::Scanline];
+const STRATEGIES: [Strategy; 2] = [Strategy::Backtrack, Strategy::Restart];
+
+#[derive(Args)]
+pub struct ExperimentArgs {
+    /// Seeds run for every combination
+    #[arg(long, default_value_t = 10)]
+    seeds: u64,
+    /// Decision stack budget per run, in MiB
+    #[arg(long = "memory-mb")]
+    memory_mb: Option<usize>,
+    /// CSV file with one row per run
+    #[arg(long, default_value = "experiment.csv")]
+    out: String,
+    /// Configs to compare, all saved configs when none are named
+    models: Vec<String>,
+    #[command(flatten)]
+    size: MapSize,
+}
+
+impl TileSystem {
+    // every model x cell order x strategy x seed, one CSV row per run
+    pub fn run_experiment(
+        &self,
+        models: &[String],
+        seeds: u64,
+        memory_budget: usize,
+    ) -> Result<String, String> {
+        let fixed = self.locked_tile_ids();
+        let mut csv = String::from(
+            "model,cell_order,strategy,seed,success,millis,decisions,backtracks,restarts,peak_bytes\n",
+        );
+        println!(
+            "{:<20} {:<17} {:<10} {:>8} {:>10} {:>11}",
+            "model", "cell order", "strategy", "success", "avg ms", "avg backtr."
+        );
+
+        for model in models {
+            let rules = self.learn_rules(model)?;
+            let label = if model.is_empty() { "(samples)" } else { model };
+            for cell_order in CELL_ORDERS {
+                for strategy in STRATEGIES {
+                    let settings = SolverSettings {
+                        cell_order,
+                        strategy,
+                        memory_budget,
+                        ..SolverSettings::default()
+                    };
+                    let (mut successes, mut total_millis, mut total_backtracks) = (0, 0.0, 0);
+                    for seed in 0..seeds {
+                        let started = Instant::now();
+                        let mut solver = CspSolver::new(
+                            &rules,
+                            TileType::ALL.len(),
+                            self.grid_width,
+                            self.grid_height,
+                            seed,
+                        )
+                        .with_settings(settings);
+                        let success = solver
+                            .solve(&fixed, &mut StatsObserver::default())
+                            .is_some();
+                        let millis = started.elapsed().as_secs_f64() * 1000.0;
+
+                        successes += success as u64;
+                        total_millis += millis;
+                        total_backtracks += solver.backtracks;
+                        writeln!(
+                            csv,
+                            "{},{:?},{:?},{},{},{:.3},{},{},{},{}",
+                            label,
+                            cell_order,
+                            strategy,
+                            seed,
+                            success,
+                            millis,
+                            solver.decisions,
+                            solver.backtracks,
+                            solver.restarts,
+                            solver.peak_memory
+                        )
+                        .ok();
+                    }
+                    let runs = seeds.max(1) as f64;
+                    println!(
+                        "{:<20} {:<17} {:<10} {:>7.0}% {:>10.2} {:>11.1}",
+                        label,
+                        format!("{:?}", cell_order),
+                        format!("{:?}", strategy),
+                        successes as f64 / runs * 100.0,
+                        total_millis / runs,
+                        total_backtracks as f64 / runs
+                    );
+                }
+            }
+        }
+        Ok(csv)
+    }
+}
+
+// `experiment [--seeds N] [--memory-mb N] [--out file.csv] [config ...]`
+pub fn run_headless(options: ExperimentArgs) -> i32 {
+    exit::report(experiment(options))
+}
+
+fn experiment(options: ExperimentArgs) -> Result<(), CliError> {
+    let memory_budget = options
+        .memory_mb
+        .map_or(DEFAULT_MEMORY_BUDGET, |mb| mb.max(1) * 1024 * 1024);
+    let mut tile_system = TileSystem::load_headless(&options.size)?;
+    let (library, tags) = library::scan(&AppConfig::load().library_dir);
+    tile_system.set_library(library, &tags);
+    let mut models = options.models;
+    if models.is_empty() {
+        models = tile_system
+            .config_names(None)
+            .into_iter()
+            .cloned()
+            .collect();
+    }
+    if models.is_empty() {
+        return Err(CliError::new(
+            Failure::NotFound,
+            "No saved configs to experiment on",
+        ));
+    }
+
+    // run_experiment only fails when a model can't be found
+    let csv = tile_system
+        .run_experiment(&models, options.seeds, memory_budget)
+        .map_err(|e| CliError::new(Failure::NotFound, e))?;
+    fs::write(&options.out, csv).map_err(|e| {
+        CliError::new(
+            Failure::Io,
+            format!("Failed to write {}: {}", options.out, e),
+        )
+    })?;
+    say!("Wrote {out}", out = options.out);
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use piston_window::{Button, ControllerButton, ControllerHat, HatState};
+use serde::{Deserialize, Serialize};
+
+use crate::Direction;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PadAction {
+    Move(Direction),
+    PreviousTile,
+    NextTile,
+    Place,
+    Fill,
+    // regenerate from the last model used, or the enabled samples
+    Generate,
+}
+
+// button numbers as the window backend reports them; the defaults follow the
+// usual XInput order (A, B, X, Y, LB, RB) and SDL's d-pad buttons
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadConfig {
+    pub place: u8,
+    pub fill: u8,
+    pub generate: u8,
+    pub previous_tile: u8,
+    pub next_tile: u8,
+    // up, down, left, right, for pads that report the d-pad as buttons rather than a hat
+    pub dpad: [u8; 4],
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        GamepadConfig {
+            place: 0,
+            fill: 1,
+            generate: 3,
+            previous_tile: 4,
+            next_tile: 5,
+            dpad: [11, 12, 13, 14],
+        }
+    }
+}
+
+impl GamepadConfig {
+    pub fn action(&self, button: &Button) -> Option<PadAction> {
+        match *button {
+            Button::Hat(ControllerHat { state, .. }) => match state {
+                HatState::Up => Some(PadAction::Move(Direction::Up)),
+                HatState::Down => Some(PadAction::Move(Direction::Down)),
+                HatState::Left => Some(PadAction::Move(Direction::Left)),
+                HatState::Right => Some(PadAction::Move(Direction::Right)),
+                _ => None,
+            },
+            Button::Controller(ControllerButton { button, .. }) => {
+                let directions = [
+                    Direction::Up,
+                    Direction::Down,
+                    Direction::Left,
+                    Direction::Right,
+                ];
+                if let Some(i) = self.dpad.iter().position(|&b| b == button) {
+                    Some(PadAction::Move(directions[i]))
+                } else if button == self.place {
+                    Some(PadAction::Place)
+                } else if button == self.fill {
+                    Some(PadAction::Fill)
+                } else if button == self.generate {
+                    Some(PadAction::Generate)
+                } else if button == self.previous_tile {
+                    Some(PadAction::PreviousTile)
+                } else if button == self.next_tile {
+                    Some(PadAction::NextTile)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
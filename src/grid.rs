@@ -0,0 +1,298 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::mem;
+
+use crate::{Tile, TileSystem};
+
+// tile storage for a map; dense keeps every Tile, sparse only the cells that differ from
+// a default tile so huge mostly-Empty canvases stay cheap
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TileGrid {
+    // plain rows, same JSON shape as the original `tiles` field
+    Dense(Vec<Vec<Tile>>),
+    Sparse {
+        width: usize,
+        height: usize,
+        default: Tile,
+        #[serde(with = "cell_list")]
+        cells: HashMap<(usize, usize), Tile>,
+    },
+}
+
+// maps with more cells than this start out sparse
+pub const SPARSE_ABOVE: usize = 512 * 512;
+
+// what makes two cells the same tile; colour follows from the type
+fn same_tile(a: &Tile, b: &Tile) -> bool {
+    a.tile_type == b.tile_type && a.rotation == b.rotation && a.visible == b.visible
+}
+
+impl TileGrid {
+    // a blank grid, sparse when it's big enough that most of it will likely stay blank
+    pub fn new(width: usize, height: usize, fill: Tile) -> Self {
+        if width * height > SPARSE_ABOVE {
+            TileGrid::sparse(width, height, fill)
+        } else {
+            TileGrid::dense(width, height, fill)
+        }
+    }
+
+    pub fn dense(width: usize, height: usize, fill: Tile) -> Self {
+        TileGrid::Dense(vec![vec![fill; width]; height])
+    }
+
+    pub fn sparse(width: usize, height: usize, default: Tile) -> Self {
+        TileGrid::Sparse {
+            width,
+            height,
+            default,
+            cells: HashMap::new(),
+        }
+    }
+
+    pub fn is_sparse(&self) -> bool {
+        matches!(self, TileGrid::Sparse { .. })
+    }
+
+    // the tile a sparse grid's unstored cells hold
+    pub fn default_tile(&self) -> Option<&Tile> {
+        match self {
+            TileGrid::Dense(_) => None,
+            TileGrid::Sparse { default, .. } => Some(default),
+        }
+    }
+
+    // a blank grid of another size, stored the same way
+    pub fn blank(&self, width: usize, height: usize) -> TileGrid {
+        match self.default_tile() {
+            Some(default) => TileGrid::sparse(width, height, default.clone()),
+            None => TileGrid::new(width, height, Tile::empty()),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        match self {
+            TileGrid::Dense(rows) => rows.first().map_or(0, |row| row.len()),
+            TileGrid::Sparse { width, .. } => *width,
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        match self {
+            TileGrid::Dense(rows) => rows.len(),
+            TileGrid::Sparse { height, .. } => *height,
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&Tile> {
+        match self {
+            TileGrid::Dense(rows) => rows.get(y).and_then(|row| row.get(x)),
+            TileGrid::Sparse {
+                width,
+                height,
+                default,
+                cells,
+            } => {
+                if x < *width && y < *height {
+                    Some(cells.get(&(x, y)).unwrap_or(default))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, tile: Tile) {
+        match self {
+            TileGrid::Dense(rows) => {
+                if let Some(cell) = rows.get_mut(y).and_then(|row| row.get_mut(x)) {
+                    *cell = tile;
+                }
+            }
+            TileGrid::Sparse {
+                width,
+                height,
+                default,
+                cells,
+            } => {
+                if x >= *width || y >= *height {
+                    return;
+                }
+                if same_tile(&tile, default) {
+                    cells.remove(&(x, y));
+                } else {
+                    cells.insert((x, y), tile);
+                }
+            }
+        }
+    }
+
+    // the stored cells: all of a dense grid in row order, only those that differ from
+    // the default in a sparse one
+    pub fn cells(&self) -> Box<dyn Iterator<Item = (usize, usize, &Tile)> + '_> {
+        match self {
+            TileGrid::Dense(rows) => Box::new(
+                rows.iter()
+                    .enumerate()
+                    .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, tile)| (x, y, tile))),
+            ),
+            TileGrid::Sparse { cells, .. } => {
+                Box::new(cells.iter().map(|(&(x, y), tile)| (x, y, tile)))
+            }
+        }
+    }
+
+    // every cell in row order, including default cells of a sparse grid
+    pub fn all_cells(&self) -> impl Iterator<Item = (usize, usize, &Tile)> + '_ {
+        let width = self.width();
+        (0..self.height())
+            .flat_map(move |y| (0..width).map(move |x| (x, y)))
+            .filter_map(|(x, y)| self.get(x, y).map(|tile| (x, y, tile)))
+    }
+
+    // cells of `now` that hold another tile than this grid does, with what they hold
+    // there; only stored cells are compared, so blank areas of sparse grids are skipped
+    pub fn edited_in(&self, now: &TileGrid) -> Vec<(usize, usize, Tile)> {
+        let mut edited: Vec<(usize, usize, Tile)> = now
+            .cells()
+            .chain(self.cells())
+            .filter_map(|(x, y, _)| {
+                let tile = now.get(x, y)?;
+                (!self.get(x, y).is_some_and(|before| same_tile(before, tile)))
+                    .then(|| (x, y, tile.clone()))
+            })
+            .collect();
+        edited.sort_by_key(|&(x, y, _)| (y, x));
+        edited.dedup_by_key(|(x, y, _)| (*x, *y));
+        edited
+    }
+
+    pub fn to_sparse(&self, default: Tile) -> TileGrid {
+        let mut sparse = TileGrid::sparse(self.width(), self.height(), default);
+        for (x, y, tile) in self.cells() {
+            sparse.set(x, y, tile.clone());
+        }
+        sparse
+    }
+
+    pub fn to_dense(&self) -> TileGrid {
+        let rows = (0..self.height())
+            .map(|y| {
+                (0..self.width())
+                    .filter_map(|x| self.get(x, y).cloned())
+                    .collect()
+            })
+            .collect();
+        TileGrid::Dense(rows)
+    }
+}
+
+// two copies of a grid for passes that work out every cell from the previous state:
+// read `front`, write `back`, then swap, so no cell sees a neighbour's partial update
+pub struct DoubleBuffer<T> {
+    front: T,
+    back: T,
+}
+
+impl<T: Clone> DoubleBuffer<T> {
+    pub fn new(grid: T) -> Self {
+        DoubleBuffer {
+            back: grid.clone(),
+            front: grid,
+        }
+    }
+
+    pub fn front(&self) -> &T {
+        &self.front
+    }
+
+    pub fn back_mut(&mut self) -> &mut T {
+        &mut self.back
+    }
+
+    // makes what was written the state the next pass reads; the back starts over as
+    // a copy of it, so cells a pass leaves alone keep their value
+    pub fn swap(&mut self) {
+        mem::swap(&mut self.front, &mut self.back);
+        self.back.clone_from(&self.front);
+    }
+
+    pub fn into_front(self) -> T {
+        self.front
+    }
+}
+
+impl TileSystem {
+    // runs a pass that reads the map as it was and writes a staged copy, then swaps
+    // the copy in whole as one undo step; locked cells keep their tiles
+    pub fn staged_update(&mut self, pass: impl FnOnce(&TileSystem, &mut TileGrid)) {
+        self.staged_update_with(TileSystem::push_undo, pass);
+    }
+
+    // the same, with `push_undo` taking the snapshot, for passes that go with changes
+    // to more than the tiles
+    pub fn staged_update_with(
+        &mut self,
+        push_undo: fn(&mut TileSystem),
+        pass: impl FnOnce(&TileSystem, &mut TileGrid),
+    ) {
+        let mut staged = self.tiles.clone();
+        pass(self, &mut staged);
+        for &(x, y) in &self.locked_cells {
+            if let Some(tile) = self.tiles.get(x, y) {
+                staged.set(x, y, tile.clone());
+            }
+        }
+        push_undo(self);
+        self.tiles = staged;
+        self.invalidate_index();
+        self.dirty = true;
+    }
+}
+
+// JSON object keys must be strings, so sparse cells are stored as a list of [[x, y], tile]
+mod cell_list {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+
+    use crate::Tile;
+
+    pub fn serialize<S: Serializer>(
+        cells: &HashMap<(usize, usize), Tile>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let list: Vec<(&(usize, usize), &Tile)> = cells.iter().collect();
+        list.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<(usize, usize), Tile>, D::Error> {
+        let list: Vec<((usize, usize), Tile)> = Vec::deserialize(deserializer)?;
+        Ok(list.into_iter().collect())
+    }
+}
+
+// cells on the straight line from `a` to `b`, both ends included (Bresenham)
+pub fn line_cells(a: (usize, usize), b: (usize, usize)) -> Vec<(usize, usize)> {
+    let (mut x, mut y) = (a.0 as isize, a.1 as isize);
+    let (x1, y1) = (b.0 as isize, b.1 as isize);
+    let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+    let (sx, sy) = ((x1 - x).signum(), (y1 - y).signum());
+    let mut error = dx + dy;
+    let mut cells = vec![(x as usize, y as usize)];
+    while (x, y) != (x1, y1) {
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += sx;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += sy;
+        }
+        cells.push((x as usize, y as usize));
+    }
+    cells
+}
@@ -0,0 +1,73 @@
+use std::collections::BTreeMap;
+
+use crate::i18n::{say, t};
+use crate::{TileSystem, TileType};
+
+// groups every new map starts with
+pub fn default_groups() -> BTreeMap<String, Vec<TileType>> {
+    BTreeMap::from([
+        ("wet".to_string(), vec![TileType::Water, TileType::Coast]),
+        (
+            "solid".to_string(),
+            vec![TileType::Land, TileType::Mountain],
+        ),
+    ])
+}
+
+// "Water+Coast" style lists
+pub fn parse_tiles(list: &str) -> Result<Vec<TileType>, String> {
+    list.split(['+', ','])
+        .map(|name| TileType::parse(name.trim()).ok_or(format!("Unknown tile '{}'", name.trim())))
+        .collect()
+}
+
+impl TileSystem {
+    // a group name or a single tile name, for anything that takes a set of tiles
+    pub fn resolve_tiles(&self, spec: &str) -> Result<Vec<TileType>, String> {
+        if let Some(tiles) = self.tile_groups.get(spec) {
+            return Ok(tiles.clone());
+        }
+        TileType::parse(spec)
+            .map(|tile| vec![tile])
+            .ok_or(format!("'{}' is neither a tile nor a group", spec))
+    }
+
+    // "name = Tile+Tile" defines or replaces a group, "name =" removes it
+    pub fn edit_group(&mut self, command: &str) -> Result<(), String> {
+        let (name, tiles) = command
+            .split_once('=')
+            .ok_or_else(|| t!("Expected \"<name> = <Tile>+<Tile>\""))?;
+        let name = name.trim();
+        if name.is_empty() || TileType::parse(name).is_some() {
+            return Err(t!("'{name}' can't be used as a group name", name = name));
+        }
+        if tiles.trim().is_empty() {
+            if self.tile_groups.remove(name).is_none() {
+                return Err(t!("No group '{name}'", name = name));
+            }
+            say!("Removed group '{name}'", name = name);
+        } else {
+            let tiles = parse_tiles(tiles)?;
+            say!(
+                "Group '{name}' = {tiles}",
+                name = name,
+                tiles = format!("{:?}", tiles)
+            );
+            self.tile_groups.insert(name.to_string(), tiles);
+        }
+        self.dirty = true;
+        Ok(())
+    }
+
+    pub fn list_groups(&self) {
+        if self.tile_groups.is_empty() {
+            say!("No tile groups");
+            return;
+        }
+        say!("Tile groups:");
+        for (name, tiles) in &self.tile_groups {
+            let names: Vec<String> = tiles.iter().map(|t| format!("{:?}", t)).collect();
+            println!(" - {}: {}", name, names.join("+"));
+        }
+    }
+}
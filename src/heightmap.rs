@@ -0,0 +1,545 @@
+use std::fs;
+
+use crate::TileSystem;
+use crate::i18n::{say, t};
+use crate::png_export::{self, SIGNATURE};
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// order the code length code lengths are stored in
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+// deflate reads bits least significant first
+struct Bits<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl Bits<'_> {
+    fn take(&mut self, count: u32) -> Result<u32, String> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = self
+                .bytes
+                .get(self.at / 8)
+                .ok_or_else(|| t!("The PNG is cut short"))?;
+            value |= ((byte >> (self.at % 8)) as u32 & 1) << i;
+            self.at += 1;
+        }
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        self.at = self.at.div_ceil(8) * 8;
+    }
+}
+
+// canonical Huffman code as codes per length and symbols in code order
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut symbols: Vec<u16> = (0..lengths.len() as u16)
+            .filter(|&symbol| lengths[symbol as usize] != 0)
+            .collect();
+        symbols.sort_by_key(|&symbol| lengths[symbol as usize]);
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Result<u16, String> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..16 {
+            code |= bits.take(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(t!("Bad compressed data in the PNG"))
+    }
+}
+
+fn dynamic_codes(bits: &mut Bits) -> Result<(Huffman, Huffman), String> {
+    let literals = bits.take(5)? as usize + 257;
+    let distances = bits.take(5)? as usize + 1;
+    let code_lengths = bits.take(4)? as usize + 4;
+    let mut lengths = [0u8; 19];
+    for &i in &CODE_LENGTH_ORDER[..code_lengths] {
+        lengths[i] = bits.take(3)? as u8;
+    }
+    let code = Huffman::new(&lengths);
+    let mut lengths = Vec::with_capacity(literals + distances);
+    while lengths.len() < literals + distances {
+        let (value, repeat) = match code.decode(bits)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths
+                    .last()
+                    .ok_or_else(|| t!("Bad compressed data in the PNG"))?;
+                (previous, 3 + bits.take(2)?)
+            }
+            17 => (0, 3 + bits.take(3)?),
+            _ => (0, 11 + bits.take(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() > literals + distances {
+        return Err(t!("Bad compressed data in the PNG"));
+    }
+    Ok((
+        Huffman::new(&lengths[..literals]),
+        Huffman::new(&lengths[literals..]),
+    ))
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [8u8; 288];
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+// a zlib stream's data, checked against the Adler-32 that ends it
+fn inflate(zlib: &[u8]) -> Result<Vec<u8>, String> {
+    let mut bits = Bits {
+        bytes: zlib.get(2..).ok_or_else(|| t!("The PNG is cut short"))?,
+        at: 0,
+    };
+    let mut out = Vec::new();
+    loop {
+        let last = bits.take(1)? == 1;
+        match bits.take(2)? {
+            0 => {
+                bits.align();
+                let start = bits.at / 8;
+                let header = bits
+                    .bytes
+                    .get(start..start + 4)
+                    .ok_or_else(|| t!("The PNG is cut short"))?;
+                let len = u16::from_le_bytes([header[0], header[1]]) as usize;
+                let block = bits
+                    .bytes
+                    .get(start + 4..start + 4 + len)
+                    .ok_or_else(|| t!("The PNG is cut short"))?;
+                out.extend(block);
+                bits.at = (start + 4 + len) * 8;
+            }
+            kind @ (1 | 2) => {
+                let (literals, distances) = if kind == 1 {
+                    fixed_codes()
+                } else {
+                    dynamic_codes(&mut bits)?
+                };
+                loop {
+                    let symbol = literals.decode(&mut bits)? as usize;
+                    if symbol < 256 {
+                        out.push(symbol as u8);
+                        continue;
+                    }
+                    if symbol == 256 {
+                        break;
+                    }
+                    let i = symbol - 257;
+                    if i >= LENGTH_BASE.len() {
+                        return Err(t!("Bad compressed data in the PNG"));
+                    }
+                    let length =
+                        LENGTH_BASE[i] as usize + bits.take(LENGTH_EXTRA[i] as u32)? as usize;
+                    let d = distances.decode(&mut bits)? as usize;
+                    if d >= DISTANCE_BASE.len() {
+                        return Err(t!("Bad compressed data in the PNG"));
+                    }
+                    let distance =
+                        DISTANCE_BASE[d] as usize + bits.take(DISTANCE_EXTRA[d] as u32)? as usize;
+                    if distance > out.len() {
+                        return Err(t!("Bad compressed data in the PNG"));
+                    }
+                    // copies may overlap what they write, so byte by byte
+                    let start = out.len() - distance;
+                    for i in 0..length {
+                        out.push(out[start + i]);
+                    }
+                }
+            }
+            _ => return Err(t!("Bad compressed data in the PNG")),
+        }
+        if last {
+            bits.align();
+            let start = bits.at / 8;
+            let checksum = bits
+                .bytes
+                .get(start..start + 4)
+                .ok_or_else(|| t!("The PNG is cut short"))?;
+            if u32::from_be_bytes(checksum.try_into().unwrap()) != png_export::adler32(&out) {
+                return Err(t!("The PNG's image data fails its checksum"));
+            }
+            return Ok(out);
+        }
+    }
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+// any non-interlaced PNG as brightness from 0 to 1 per pixel, row by row; colour
+// images are read as their luminance
+fn decode_grey(bytes: &[u8]) -> Result<(usize, usize, Vec<f64>), String> {
+    if !bytes.starts_with(&SIGNATURE) {
+        return Err(t!("Not a PNG file"));
+    }
+    let mut header = None;
+    let mut palette = Vec::new();
+    let mut data = Vec::new();
+    let mut at = SIGNATURE.len();
+    // a PNG without its IEND chunk lost its end somewhere
+    loop {
+        let start = bytes
+            .get(at..at + 8)
+            .ok_or_else(|| t!("The PNG is cut short"))?;
+        let len = u32::from_be_bytes(start[0..4].try_into().unwrap()) as usize;
+        let kind = &start[4..8];
+        let chunk = bytes
+            .get(at + 8..at + 8 + len)
+            .ok_or_else(|| t!("The PNG is cut short"))?;
+        let crc = bytes
+            .get(at + 8 + len..at + 12 + len)
+            .ok_or_else(|| t!("The PNG is cut short"))?;
+        if u32::from_be_bytes(crc.try_into().unwrap())
+            != png_export::crc32(&bytes[at + 4..at + 8 + len])
+        {
+            return Err(t!(
+                "The PNG's {kind} chunk fails its CRC",
+                kind = String::from_utf8_lossy(kind)
+            ));
+        }
+        match kind {
+            b"IHDR" if chunk.len() >= 13 => header = Some(chunk.to_vec()),
+            b"PLTE" => palette = chunk.to_vec(),
+            b"IDAT" => data.extend(chunk),
+            b"IEND" => break,
+            _ => {}
+        }
+        at += 12 + len;
+    }
+    let header = header.ok_or_else(|| t!("The PNG has no header"))?;
+    let width = u32::from_be_bytes(header[0..4].try_into().unwrap()) as usize;
+    let height = u32::from_be_bytes(header[4..8].try_into().unwrap()) as usize;
+    let (depth, colour_type, interlace) = (header[8] as usize, header[9], header[12]);
+    let channels = match colour_type {
+        0 | 3 => 1,
+        2 => 3,
+        4 => 2,
+        6 => 4,
+        _ => return Err(t!("Unsupported PNG colour type {kind}", kind = colour_type)),
+    };
+    if interlace != 0 {
+        return Err(t!("Interlaced PNGs aren't supported"));
+    }
+    if !matches!(depth, 1 | 2 | 4 | 8 | 16) || (channels > 1 && depth < 8) {
+        return Err(t!("Unsupported PNG bit depth {depth}", depth = depth));
+    }
+
+    let raw = inflate(&data)?;
+    let stride = (width * channels * depth).div_ceil(8);
+    // bytes per whole pixel, what the filters look back by
+    let step = (channels * depth / 8).max(1);
+    if raw.len() < (stride + 1) * height {
+        return Err(t!("The PNG is cut short"));
+    }
+    let mut pixels = vec![0u8; stride * height];
+    for y in 0..height {
+        let filter = raw[y * (stride + 1)];
+        let line = &raw[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+        for x in 0..stride {
+            let a = if x >= step {
+                pixels[y * stride + x - step]
+            } else {
+                0
+            };
+            let b = if y > 0 {
+                pixels[(y - 1) * stride + x]
+            } else {
+                0
+            };
+            let c = if x >= step && y > 0 {
+                pixels[(y - 1) * stride + x - step]
+            } else {
+                0
+            };
+            pixels[y * stride + x] = match filter {
+                0 => line[x],
+                1 => line[x].wrapping_add(a),
+                2 => line[x].wrapping_add(b),
+                3 => line[x].wrapping_add(((a as u16 + b as u16) / 2) as u8),
+                4 => line[x].wrapping_add(paeth(a, b, c)),
+                _ => return Err(t!("Bad PNG filter {filter}", filter = filter)),
+            };
+        }
+    }
+
+    let max = ((1u32 << depth) - 1) as f64;
+    let sample = |row: &[u8], i: usize| -> f64 {
+        let value = match depth {
+            16 => u16::from_be_bytes([row[i * 2], row[i * 2 + 1]]) as u32,
+            8 => row[i] as u32,
+            _ => {
+                let bit = i * depth;
+                (row[bit / 8] >> (8 - depth - bit % 8)) as u32 & ((1 << depth) - 1)
+            }
+        };
+        value as f64 / max
+    };
+    let luminance = |r: f64, g: f64, b: f64| 0.299 * r + 0.587 * g + 0.114 * b;
+    let mut grey = Vec::with_capacity(width * height);
+    for row in pixels.chunks(stride.max(1)).take(height) {
+        for x in 0..width {
+            grey.push(match colour_type {
+                0 | 4 => sample(row, x * channels),
+                3 => {
+                    let index = (sample(row, x) * max).round() as usize;
+                    let rgb = palette
+                        .get(index * 3..index * 3 + 3)
+                        .ok_or_else(|| t!("The PNG palette is too short"))?;
+                    luminance(rgb[0] as f64, rgb[1] as f64, rgb[2] as f64) / 255.0
+                }
+                _ => luminance(
+                    sample(row, x * channels),
+                    sample(row, x * channels + 1),
+                    sample(row, x * channels + 2),
+                ),
+            });
+        }
+    }
+    Ok((width, height, grey))
+}
+
+// bilinear sample of a width x height image at fractional pixel (x, y)
+fn sample_bilinear(grey: &[f64], width: usize, height: usize, x: f64, y: f64) -> f64 {
+    let x = x.clamp(0.0, (width - 1) as f64);
+    let y = y.clamp(0.0, (height - 1) as f64);
+    let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+    let at = |x: usize, y: usize| grey[y * width + x];
+    let top = at(x0, y0) * (1.0 - fx) + at(x1, y0) * fx;
+    let bottom = at(x0, y1) * (1.0 - fx) + at(x1, y1) * fx;
+    top * (1.0 - fy) + bottom * fy
+}
+
+impl TileSystem {
+    // a greyscale image as the elevation layer, black low and white high, stretched
+    // to the map's size; tiles are then re-derived from the heights as erosion does
+    pub fn import_heightmap(&mut self, path: &str) -> Result<(), String> {
+        let bytes = fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+        let (width, height, grey) = decode_grey(&bytes).map_err(|e| format!("{}: {}", path, e))?;
+        if width == 0 || height == 0 {
+            return Err(t!("{path} is empty", path = path));
+        }
+        // cell centres mapped onto pixel centres
+        let scale_x = width as f64 / self.grid_width.max(1) as f64;
+        let scale_y = height as f64 / self.grid_height.max(1) as f64;
+        let elevation: Vec<Vec<f64>> = (0..self.grid_height)
+            .map(|y| {
+                (0..self.grid_width)
+                    .map(|x| {
+                        sample_bilinear(
+                            &grey,
+                            width,
+                            height,
+                            (x as f64 + 0.5) * scale_x - 0.5,
+                            (y as f64 + 0.5) * scale_y - 0.5,
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        // one undo step puts back both the tiles and the elevation they came from
+        self.staged_update_with(TileSystem::push_elevation_undo, |map, staged| {
+            map.classify_elevation(&elevation, staged)
+        });
+        self.elevation = elevation;
+        say!(
+            "Imported a {width}x{height} heightmap from {path}",
+            width = width,
+            height = height,
+            path = path
+        );
+        Ok(())
+    }
+
+    // the elevation layer as a 16-bit greyscale PNG, one pixel per cell
+    pub fn export_heightmap(&self, path: &str) -> Result<(), String> {
+        let matches_grid = self.elevation.len() == self.grid_height
+            && self
+                .elevation
+                .iter()
+                .all(|row| row.len() == self.grid_width);
+        if !matches_grid {
+            return Err(t!(
+                "The map has no elevation layer yet, rebuild it with Ctrl+E first"
+            ));
+        }
+        let samples: Vec<u16> = self
+            .elevation
+            .iter()
+            .flatten()
+            .map(|height| (height.clamp(0.0, 1.0) * u16::MAX as f64).round() as u16)
+            .collect();
+        let png = png_export::encode_grey16(self.grid_width, self.grid_height, &samples);
+        fs::write(path, png).map_err(|e| t!("Failed to write {path}: {e}", path = path, e = e))?;
+        say!("Exported the elevation layer to {path}", path = path);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // written by zlib rather than png_export, so the decoder isn't only checked against its
+    // own encoder. The small image is the rows below with filters none/sub/up in a stored
+    // block and average/Paeth/sub in a fixed Huffman block
+    const SMALL: [[u8; 4]; 3] = [[0, 64, 128, 255], [10, 20, 30, 40], [255, 0, 255, 0]];
+
+    const STORED: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0x08, 0x00, 0x00, 0x00, 0x00, 0x91,
+        0x9f, 0xf1, 0x1a, 0x00, 0x00, 0x00, 0x1a, 0x49, 0x44, 0x41, 0x54, 0x78, 0x01, 0x01, 0x0f,
+        0x00, 0xf0, 0xff, 0x00, 0x00, 0x40, 0x80, 0xff, 0x01, 0x0a, 0x0a, 0x0a, 0x0a, 0x02, 0xf5,
+        0xec, 0xe1, 0xd8, 0x1e, 0xb6, 0x05, 0x85, 0xe5, 0x0a, 0x5e, 0x69, 0x00, 0x00, 0x00, 0x00,
+        0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+    const FIXED: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0x08, 0x00, 0x00, 0x00, 0x00, 0x91,
+        0x9f, 0xf1, 0x1a, 0x00, 0x00, 0x00, 0x16, 0x49, 0x44, 0x41, 0x54, 0x78, 0x01, 0x63, 0x66,
+        0x70, 0x48, 0xd8, 0xcf, 0xc2, 0x75, 0xe5, 0xde, 0x0a, 0xc6, 0xff, 0x40, 0x08, 0x00, 0x27,
+        0x58, 0x05, 0xcc, 0x2f, 0xd3, 0x31, 0x04, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44,
+        0xae, 0x42, 0x60, 0x82,
+    ];
+    const DYNAMIC: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x18, 0x00, 0x00, 0x00, 0x08, 0x08, 0x00, 0x00, 0x00, 0x00, 0xc6,
+        0xca, 0x60, 0xbf, 0x00, 0x00, 0x00, 0x44, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x6d, 0xc9,
+        0x41, 0x11, 0xc0, 0x20, 0x10, 0x43, 0xd1, 0x2c, 0xad, 0x8a, 0xda, 0x08, 0x9a, 0xd6, 0x07,
+        0x32, 0xa8, 0x26, 0xa2, 0xa3, 0x32, 0x96, 0xde, 0xc3, 0xcc, 0x9f, 0x77, 0xf9, 0x00, 0x92,
+        0x09, 0x37, 0xb8, 0x3a, 0x4e, 0x35, 0x75, 0x21, 0x96, 0x7b, 0x51, 0x78, 0xbe, 0xe1, 0xde,
+        0xaa, 0x00, 0xe8, 0x82, 0xac, 0x63, 0xff, 0x4c, 0xf1, 0x75, 0x1b, 0x4b, 0x98, 0x74, 0x37,
+        0x54, 0xa7, 0x47, 0x05, 0x61, 0x5f, 0x48, 0xe2, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e,
+        0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    // the 24x8 image zlib packs into a dynamic Huffman block, filters cycling through all five
+    fn dynamic_pixel(x: usize, y: usize) -> u8 {
+        [0, 0, 0, 51, 102, 255][(x * x + 3 * y + x * y) % 6]
+    }
+
+    fn levels(grey: &[f64]) -> Vec<u8> {
+        grey.iter().map(|v| (v * 255.0).round() as u8).collect()
+    }
+
+    // the IDAT chunk's data and where it starts; every fixture has exactly one
+    fn idat(png: &[u8]) -> (usize, usize) {
+        let at = png.windows(4).position(|w| w == b"IDAT").unwrap() + 4;
+        let len = u32::from_be_bytes(png[at - 8..at - 4].try_into().unwrap()) as usize;
+        (at, len)
+    }
+
+    #[test]
+    fn decodes_stored_and_fixed_huffman_blocks() {
+        for png in [STORED, FIXED] {
+            let (width, height, grey) = decode_grey(png).unwrap();
+            assert_eq!((width, height), (4, 3));
+            assert_eq!(levels(&grey), SMALL.concat());
+        }
+    }
+
+    #[test]
+    fn decodes_a_dynamic_huffman_block() {
+        let (width, height, grey) = decode_grey(DYNAMIC).unwrap();
+        assert_eq!((width, height), (24, 8));
+        let expected: Vec<u8> = (0..8)
+            .flat_map(|y| (0..24).map(move |x| dynamic_pixel(x, y)))
+            .collect();
+        assert_eq!(levels(&grey), expected);
+    }
+
+    #[test]
+    fn reads_back_the_heightmap_export() {
+        let samples: Vec<u16> = (0..30).map(|i| i * 2000).collect();
+        let (width, height, grey) =
+            decode_grey(&png_export::encode_grey16(6, 5, &samples)).unwrap();
+        assert_eq!((width, height), (6, 5));
+        for (value, sample) in grey.iter().zip(&samples) {
+            assert!((value * 65535.0 - *sample as f64).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn truncated_files_are_rejected() {
+        for png in [STORED, FIXED, DYNAMIC] {
+            for len in 0..png.len() {
+                assert!(decode_grey(&png[..len]).is_err(), "cut to {len} bytes");
+            }
+        }
+    }
+
+    #[test]
+    fn corrupt_chunks_fail_their_crc() {
+        let mut png = FIXED.to_vec();
+        let (at, _) = idat(&png);
+        png[at + 2] ^= 0x10;
+        assert!(decode_grey(&png).unwrap_err().contains("CRC"));
+    }
+
+    #[test]
+    fn corrupt_image_data_fails_its_checksum() {
+        // a flipped bit in a stored block's data still inflates, so only the Adler-32 catches
+        // it once the CRC is patched up to match
+        let mut png = STORED.to_vec();
+        let (at, len) = idat(&png);
+        png[at + 9] ^= 0x01;
+        let crc = png_export::crc32(&png[at - 4..at + len]);
+        png[at + len..at + len + 4].copy_from_slice(&crc.to_be_bytes());
+        assert!(decode_grey(&png).unwrap_err().contains("checksum"));
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::sync::OnceLock;
+
+// translations live in lang/<code>.json as one object from the English text, exactly
+// as written in the code with {name} placeholders, to the translated text, e.g.
+// { "Selected: {tile} tile": "Ausgewählt: {tile}" }; anything missing stays English
+pub const LANG_DIR: &str = "lang";
+
+static CATALOG: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+// loads the language once at startup; None or "en" keeps the English text
+pub fn init(lang: Option<&str>) {
+    let catalog = match lang.filter(|code| *code != "en") {
+        Some(code) => {
+            let path = format!("{}/{}.json", LANG_DIR, code);
+            match fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+            {
+                Ok(catalog) => catalog,
+                Err(e) => {
+                    eprintln!("Couldn't load {}: {}, using English", path, e);
+                    HashMap::new()
+                }
+            }
+        }
+        None => HashMap::new(),
+    };
+    CATALOG.set(catalog).ok();
+}
+
+// the translation of `text`, with each {name} replaced by its argument
+pub fn tr(text: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut out = CATALOG
+        .get()
+        .and_then(|catalog| catalog.get(text))
+        .map_or(text, String::as_str)
+        .to_string();
+    for (name, value) in args {
+        out = out.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    out
+}
+
+// t!("Saved {name}", name = value) gives the translated, filled-in String
+macro_rules! t {
+    ($text:expr) => {
+        $crate::i18n::tr($text, &[])
+    };
+    ($text:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::tr(
+            $text,
+            &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+],
+        )
+    };
+}
+
+// println! of a translated line
+macro_rules! say {
+    ($($arg:tt)+) => {
+        println!("{}", $crate::i18n::t!($($arg)+))
+    };
+}
+
+pub(crate) use {say, t};
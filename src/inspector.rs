@@ -0,0 +1,239 @@
+use piston_window::*;
+
+use crate::i18n::t;
+use crate::{Direction, Tile, TileSystem, TileType};
+
+const PADDING: f64 = 12.0;
+const ROW_HEIGHT: f64 = 28.0;
+const SWATCH: f64 = 14.0;
+const NAME_WIDTH: f64 = 150.0;
+const DIRECTION_WIDTH: f64 = 110.0;
+const BACKGROUND: [f32; 4] = [0.1, 0.1, 0.12, 0.95];
+const TEXT_COLOUR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+const HEADING_COLOUR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const OUTLINE: [f32; 4] = [0.5, 0.5, 0.55, 1.0];
+
+// neighbour columns in the order they're drawn
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InspectorSort {
+    Tile,
+    Weight,
+    Neighbours,
+}
+
+impl InspectorSort {
+    fn next(self) -> Self {
+        match self {
+            InspectorSort::Tile => InspectorSort::Weight,
+            InspectorSort::Weight => InspectorSort::Neighbours,
+            InspectorSort::Neighbours => InspectorSort::Tile,
+        }
+    }
+
+    fn label(self) -> String {
+        match self {
+            InspectorSort::Tile => t!("tile"),
+            InspectorSort::Weight => t!("weight"),
+            InspectorSort::Neighbours => t!("neighbours"),
+        }
+    }
+}
+
+// one tile of the learned model: its share of the training cells and what the
+// rules let sit next to it on each side
+#[derive(Clone, Debug)]
+pub struct InspectorRow {
+    pub tile: TileType,
+    pub weight: f64,
+    pub neighbours: [Vec<TileType>; 4],
+}
+
+// Shift+M overlay showing the rules a model learned; the rows are worked out when
+// something changes rather than every frame
+#[derive(Clone, Debug)]
+pub struct Inspector {
+    // a saved config, or the enabled samples when blank
+    pub model: String,
+    // only tiles whose name contains this are listed
+    pub filter: String,
+    pub sort: InspectorSort,
+    rows: Result<Vec<InspectorRow>, String>,
+}
+
+impl Inspector {
+    pub fn open(tile_system: &TileSystem, model: String) -> Self {
+        let mut inspector = Inspector {
+            model,
+            filter: String::new(),
+            sort: InspectorSort::Tile,
+            rows: Ok(Vec::new()),
+        };
+        inspector.refresh(tile_system);
+        inspector
+    }
+
+    pub fn cycle_sort(&mut self, tile_system: &TileSystem) {
+        self.sort = self.sort.next();
+        self.refresh(tile_system);
+    }
+
+    pub fn refresh(&mut self, tile_system: &TileSystem) {
+        self.rows = self.rows(tile_system);
+    }
+
+    fn rows(&self, tile_system: &TileSystem) -> Result<Vec<InspectorRow>, String> {
+        let filter = self.filter.to_lowercase();
+        let mut rows: Vec<InspectorRow> = tile_system
+            .inspect_model(&self.model)?
+            .into_iter()
+            .filter(|row| format!("{:?}", row.tile).to_lowercase().contains(&filter))
+            .collect();
+        match self.sort {
+            InspectorSort::Tile => rows.sort_by_key(|row| row.tile.index()),
+            InspectorSort::Weight => rows.sort_by(|a, b| b.weight.total_cmp(&a.weight)),
+            InspectorSort::Neighbours => rows.sort_by_key(|row| {
+                std::cmp::Reverse(row.neighbours.iter().map(Vec::len).sum::<usize>())
+            }),
+        }
+        Ok(rows)
+    }
+
+    pub fn render(
+        &self,
+        tile_system: &TileSystem,
+        glyphs: Option<&mut Glyphs>,
+        c: Context,
+        g: &mut G2d,
+    ) {
+        let (width, height) = (tile_system.window_width, tile_system.window_height);
+        rectangle(BACKGROUND, [0.0, 0.0, width, height], c.transform, g);
+        let Some(glyphs) = glyphs else {
+            return;
+        };
+        let mut label = |colour, size, line: &str, x: f64, y: f64| {
+            text(colour, size, line, glyphs, c.transform.trans(x, y), g).ok();
+        };
+
+        let model = if self.model.is_empty() {
+            t!("enabled samples")
+        } else {
+            self.model.clone()
+        };
+        let mut heading = t!(
+            "Model: {model}, sorted by {sort}",
+            model = model,
+            sort = self.sort.label()
+        );
+        if !self.filter.is_empty() {
+            heading += &t!(", matching '{filter}'", filter = self.filter);
+        }
+        label(HEADING_COLOUR, 13, &heading, PADDING, PADDING + 12.0);
+        label(
+            TEXT_COLOUR,
+            11,
+            &t!("S sort, / search, M model, Esc close"),
+            PADDING,
+            PADDING + 28.0,
+        );
+        let top = PADDING + 52.0;
+        let sides = [t!("Up"), t!("Right"), t!("Down"), t!("Left")];
+        for (i, side) in sides.iter().enumerate() {
+            label(
+                HEADING_COLOUR,
+                11,
+                side,
+                PADDING + NAME_WIDTH + i as f64 * DIRECTION_WIDTH,
+                top,
+            );
+        }
+
+        let rows = match &self.rows {
+            Ok(rows) => rows,
+            Err(e) => {
+                label(TEXT_COLOUR, 12, e, PADDING, top + ROW_HEIGHT);
+                return;
+            }
+        };
+        let mut swatches = Vec::new();
+        for (i, row) in rows.iter().enumerate() {
+            let y = top + 8.0 + i as f64 * ROW_HEIGHT;
+            swatches.push(([PADDING, y, SWATCH, SWATCH], row.tile.clone()));
+            label(
+                TEXT_COLOUR,
+                12,
+                &format!("{:?} {:.1}%", row.tile, row.weight * 100.0),
+                PADDING + SWATCH + 6.0,
+                y + SWATCH - 2.0,
+            );
+            for (d, neighbours) in row.neighbours.iter().enumerate() {
+                let x = PADDING + NAME_WIDTH + d as f64 * DIRECTION_WIDTH;
+                for (n, neighbour) in neighbours.iter().enumerate() {
+                    let area = [x + n as f64 * (SWATCH + 3.0), y, SWATCH, SWATCH];
+                    swatches.push((area, neighbour.clone()));
+                }
+            }
+        }
+        for (area, tile) in swatches {
+            rectangle(Tile::from_type(&tile).colour, area, c.transform, g);
+            Rectangle::new_border(OUTLINE, 0.5).draw(area, &c.draw_state, c.transform, g);
+        }
+    }
+}
+
+impl TileSystem {
+    // each tile's share of the cells the model learns from, weighted like the samples
+    pub fn learned_weights(&self, model: &str) -> [f64; TileType::ALL.len()] {
+        let mut counts = [0.0; TileType::ALL.len()];
+        if model.is_empty() {
+            for (source, setting) in self.samples() {
+                if !setting.enabled || setting.weight <= 0.0 {
+                    continue;
+                }
+                for tile in self.sample_grid(&source).iter().flatten() {
+                    counts[tile.index()] += setting.weight;
+                }
+            }
+        } else {
+            for tile in self.config(model).into_iter().flatten().flatten() {
+                counts[tile.index()] += 1.0;
+            }
+        }
+        let total: f64 = counts.iter().sum();
+        if total > 0.0 {
+            for count in &mut counts {
+                *count /= total;
+            }
+        }
+        counts
+    }
+
+    pub fn inspect_model(&self, model: &str) -> Result<Vec<InspectorRow>, String> {
+        let rules = self.learn_rules(model)?;
+        let weights = self.learned_weights(model);
+        Ok(TileType::ALL
+            .iter()
+            .map(|tile| {
+                let allowed = rules.get(&tile.index());
+                let neighbours = DIRECTIONS.map(|direction| {
+                    TileType::ALL
+                        .iter()
+                        .filter(|n| allowed.is_some_and(|a| a.contains(&(direction, n.index()))))
+                        .cloned()
+                        .collect()
+                });
+                InspectorRow {
+                    tile: tile.clone(),
+                    weight: weights[tile.index()],
+                    neighbours,
+                }
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,113 @@
+use crate::i18n::{say, t};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// held while an editor has a save open, so a second editor on the same directory
+// can't write over it; removed again when the editor exits
+pub struct InstanceLock {
+    path: PathBuf,
+}
+
+// whether a process with this pid exists: /proc on Linux, `kill -0` on other
+// Unixes and `tasklist` on Windows; counted as running when it can't be told
+fn is_running(pid: u32) -> bool {
+    if cfg!(target_os = "linux") {
+        return Path::new(&format!("/proc/{}", pid)).exists();
+    }
+    if cfg!(windows) {
+        let filter = format!("PID eq {}", pid);
+        return Command::new("tasklist")
+            .args(["/FI", &filter, "/NH", "/FO", "CSV"])
+            .output()
+            .map_or(true, |output| {
+                String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid))
+            });
+    }
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(Stdio::null())
+        .status()
+        .map_or(true, |status| status.success())
+}
+
+// the pid written in the lock file, if that process is still running
+fn live_holder(path: &Path) -> Option<u32> {
+    let pid: u32 = fs::read_to_string(path).ok()?.trim().parse().ok()?;
+    is_running(pid).then_some(pid)
+}
+
+impl InstanceLock {
+    pub fn path_for(save_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}.lock", save_path))
+    }
+
+    // Err names the process holding it; a lock left by a crashed editor is taken over
+    pub fn acquire(save_path: &str) -> Result<Self, String> {
+        let path = Self::path_for(save_path);
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    writeln!(file, "{}", std::process::id()).map_err(|e| {
+                        t!("Failed to write {path}: {e}", path = path.display(), e = e)
+                    })?;
+                    return Ok(InstanceLock { path });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    if let Some(pid) = live_holder(&path) {
+                        return Err(t!(
+                            "{save_path} is open in another editor (pid {pid})",
+                            save_path = save_path,
+                            pid = pid
+                        ));
+                    }
+                    say!("Removing stale lock {path}", path = path.display());
+                    fs::remove_file(&path).map_err(|e| {
+                        t!("Failed to remove {path}: {e}", path = path.display(), e = e)
+                    })?;
+                }
+                Err(e) => {
+                    return Err(t!(
+                        "Failed to create {path}: {e}",
+                        path = path.display(),
+                        e = e
+                    ));
+                }
+            }
+        }
+        Err(t!("Couldn't lock {save_path}", save_path = save_path))
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        fs::remove_file(&self.path).ok();
+    }
+}
+
+// the directory the editor was started in, which is where its saves live
+pub fn project_name() -> String {
+    std::env::current_dir()
+        .ok()
+        .and_then(|dir| {
+            dir.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "untitled".to_string())
+}
+
+// "<title> - <project>", with [read-only] and a trailing * for unsaved changes
+pub fn window_title(title: &str, read_only: bool, dirty: bool) -> String {
+    format!(
+        "{} - {}{}{}",
+        title,
+        project_name(),
+        if read_only {
+            t!(" [read-only]")
+        } else {
+            String::new()
+        },
+        if dirty { " *" } else { "" }
+    )
+}
@@ -0,0 +1,233 @@
+use crate::i18n::{say, t};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+// top-level key holding the checksum of the rest of the save
+const CHECKSUM_KEY: &str = "checksum";
+// the checksum is written as the save's first key, so the rest of the file is the
+// exact text it was taken over
+const CHECKSUM_LINE: &str = "{\n  \"checksum\": \"";
+const CHECKSUM_DIGITS: usize = 16;
+
+// FNV-1a, enough to notice a truncated or garbled file
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn checksum_of(text: &str) -> String {
+    format!("{:016x}", fnv1a(text.as_bytes()))
+}
+
+// how saves were checksummed before the checksum came first: over the compact form
+// of the parsed value, which floats don't always survive
+fn legacy_checksum_of(value: &Value) -> Result<String, String> {
+    serde_json::to_string(value)
+        .map(|compact| checksum_of(&compact))
+        .map_err(|e| e.to_string())
+}
+
+// the stored checksum and the JSON it covers, None when the file doesn't start
+// with one
+fn split_checksum(json_data: &str) -> Option<(&str, String)> {
+    let rest = json_data.strip_prefix(CHECKSUM_LINE)?;
+    let (stored, rest) = rest.split_at_checked(CHECKSUM_DIGITS)?;
+    let rest = rest.strip_prefix('"')?;
+    let payload = match rest.strip_prefix(',') {
+        Some(fields) => format!("{{{}", fields),
+        None => {
+            rest.trim().strip_prefix('}')?;
+            "{}".to_string()
+        }
+    };
+    Some((stored, payload))
+}
+
+pub fn backup_path(path: &str) -> String {
+    format!("{}.bak", path)
+}
+
+// writes through a temp file and a rename, copying whatever was there to `.bak` first
+pub fn write_atomic(path: &str, contents: &str) -> Result<(), String> {
+    let temp = format!("{}.tmp", path);
+    let mut file = fs::File::create(&temp).map_err(|e| format!("{}: {}", temp, e))?;
+    file.write_all(contents.as_bytes())
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("{}: {}", temp, e))?;
+    drop(file);
+
+    if Path::new(path).exists() {
+        fs::copy(path, backup_path(path))
+            .map_err(|e| t!("backing up {path}: {e}", path = path, e = e))?;
+    }
+    fs::rename(&temp, path).map_err(|e| t!("replacing {path}: {e}", path = path, e = e))
+}
+
+// pretty JSON with a checksum key added in front of the top-level object's fields,
+// taken over the exact text that follows it
+pub fn write_checked<T: Serialize>(path: &str, data: &T) -> Result<(), String> {
+    let payload = serde_json::to_string_pretty(data).map_err(|e| e.to_string())?;
+    let Some(fields) = payload.strip_prefix('{') else {
+        return Err(t!("save data is not a JSON object"));
+    };
+    let checksum = checksum_of(&payload);
+    let separator = if fields.starts_with('}') { "\n" } else { "," };
+    let json_data = format!("{}{}\"{}{}", CHECKSUM_LINE, checksum, separator, fields);
+    write_atomic(path, &json_data)
+}
+
+// Err if the file is unreadable, unparsable or fails its checksum; saves from
+// before checksums were added load with a warning
+pub fn read_checked<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    let json_data = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    if let Some((stored, payload)) = split_checksum(&json_data) {
+        let actual = checksum_of(&payload);
+        if stored != actual {
+            return Err(t!(
+                "{path}: checksum mismatch (stored {stored}, contents {actual})",
+                path = path,
+                stored = stored,
+                actual = actual
+            ));
+        }
+        return serde_json::from_str(&payload).map_err(|e| format!("{}: {}", path, e));
+    }
+    let mut value: Value =
+        serde_json::from_str(&json_data).map_err(|e| format!("{}: {}", path, e))?;
+    let stored = value
+        .as_object_mut()
+        .and_then(|object| object.remove(CHECKSUM_KEY));
+    match stored.as_ref().and_then(Value::as_str) {
+        Some(stored) => {
+            let actual = legacy_checksum_of(&value)?;
+            if stored != actual {
+                return Err(t!(
+                    "{path}: checksum mismatch (stored {stored}, contents {actual})",
+                    path = path,
+                    stored = stored,
+                    actual = actual
+                ));
+            }
+        }
+        None => say!(
+            "{path} has no checksum, it will get one on the next save",
+            path = path
+        ),
+    }
+    serde_json::from_value(value).map_err(|e| format!("{}: {}", path, e))
+}
+
+// the file itself, or its `.bak` when the file is damaged; None when neither exists
+pub fn read_with_backup<T: DeserializeOwned>(path: &str) -> Option<Result<T, String>> {
+    if !Path::new(path).exists() {
+        return None;
+    }
+    match read_checked(path) {
+        Ok(data) => Some(Ok(data)),
+        Err(e) => {
+            eprintln!("{}", t!("Error loading {e}", e = e));
+            let backup = backup_path(path);
+            if !Path::new(&backup).exists() {
+                return Some(Err(e));
+            }
+            let restored = read_checked(&backup);
+            if restored.is_ok() {
+                say!(
+                    "Loaded the previous save from {backup} instead",
+                    backup = backup
+                );
+            }
+            Some(restored)
+        }
+    }
+}
+
+// parses without checking the checksum, for recovering from a damaged save
+pub fn read_unchecked<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    let json_data = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut value: Value =
+        serde_json::from_str(&json_data).map_err(|e| format!("{}: {}", path, e))?;
+    if let Some(object) = value.as_object_mut() {
+        object.remove(CHECKSUM_KEY);
+    }
+    serde_json::from_value(value).map_err(|e| format!("{}: {}", path, e))
+}
+
+// renames a damaged file out of the way so the next save can't back it up over a good `.bak`
+pub fn quarantine(path: &str) -> Result<String, String> {
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let target = format!("{}.corrupt-{}", path, stamp);
+    fs::rename(path, &target).map_err(|e| t!("moving {path} aside: {e}", path = path, e = e))?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TileSystem;
+
+    // a file in the temp dir for one test, removed with its backup when dropped
+    struct Scratch(String);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "wfc-integrity-{}-{}.json",
+                name,
+                std::process::id()
+            ));
+            Scratch(path.display().to_string())
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+            let _ = fs::remove_file(backup_path(&self.0));
+        }
+    }
+
+    fn eroded_map() -> TileSystem {
+        let mut map = TileSystem::new(160.0, 120.0, 8.0);
+        map.erode(7);
+        map
+    }
+
+    #[test]
+    fn eroded_map_passes_its_own_checksum() {
+        let file = Scratch::new("eroded");
+        let map = eroded_map();
+        write_checked(&file.0, &map).unwrap();
+        let loaded: TileSystem = read_checked(&file.0).unwrap();
+        assert_eq!(loaded.elevation, map.elevation);
+        assert_eq!(loaded.tile_types(), map.tile_types());
+    }
+
+    #[test]
+    fn edited_save_fails_its_checksum() {
+        let file = Scratch::new("edited");
+        write_checked(&file.0, &eroded_map()).unwrap();
+        let json_data = fs::read_to_string(&file.0).unwrap();
+        fs::write(
+            &file.0,
+            json_data.replacen("\"grid_width\": 20", "\"grid_width\": 21", 1),
+        )
+        .unwrap();
+        assert!(read_checked::<TileSystem>(&file.0).is_err());
+    }
+
+    #[test]
+    fn empty_object_round_trips() {
+        let file = Scratch::new("empty");
+        write_checked(&file.0, &serde_json::Map::<String, Value>::new()).unwrap();
+        let loaded: serde_json::Map<String, Value> = read_checked(&file.0).unwrap();
+        assert!(loaded.is_empty());
+    }
+}
@@ -0,0 +1,226 @@
+use crate::i18n::t;
+
+// every key and mouse binding, the source for the F1 cheat sheet and the console listing
+// keep this in step with the input handling in main
+pub struct Binding {
+    pub keys: &'static str,
+    pub action: &'static str,
+    pub category: &'static str,
+}
+
+const fn bind(category: &'static str, keys: &'static str, action: &'static str) -> Binding {
+    Binding {
+        keys,
+        action,
+        category,
+    }
+}
+
+pub const KEYMAP: &[Binding] = &[
+    bind("Painting", "1-5", "Select tile type"),
+    bind("Painting", "Wheel", "Cycle tile type"),
+    bind("Painting", "Ctrl+wheel", "Brush size"),
+    bind("Painting", "Alt+click", "Pick the tile under the mouse"),
+    bind("Painting", "Left click", "Paint with the brush"),
+    bind("Painting", "Shift+click", "Line from the last painted cell"),
+    bind("Painting", "Right click", "Fill to border"),
+    bind(
+        "Painting",
+        "Shift+right drag",
+        "Preview a fill, release to commit",
+    ),
+    bind("Painting", "Arrows", "Move the keyboard cursor"),
+    bind("Painting", "Enter / Space", "Paint at the keyboard cursor"),
+    bind("Touch", "Tap / drag", "Paint"),
+    bind("Touch", "Pinch / two fingers", "Zoom/pan the map"),
+    bind("Touch", "Home", "Reset zoom and pan"),
+    bind("Gamepad", "D-pad", "Move the cursor"),
+    bind("Gamepad", "LB / RB", "Previous/next tile"),
+    bind("Gamepad", "A / B", "Paint/fill at the cursor"),
+    bind("Gamepad", "Y", "Generate from the last model"),
+    bind(
+        "Painting",
+        "Ctrl+Y",
+        "Cycle the decoration brush (trees, villages, rocks, houses, erase, off)",
+    ),
+    bind(
+        "Painting",
+        "Shift+Y",
+        "Paint the bias layer, with a preview",
+    ),
+    bind(
+        "Painting",
+        "Shift+B",
+        "Brush shape, density and mirroring; save to the brush bank",
+    ),
+    bind("Painting", "Shift+1-9", "Pick a brush from the bank"),
+    bind("Painting", "F", "Limit brush to a group/tile"),
+    bind("Painting", "Ctrl+F", "Edit tile groups"),
+    bind("Painting", "Middle click", "Lock/unlock a tile"),
+    bind("Painting", "U", "Unlock all"),
+    bind("Painting", "C", "Clear map (asks first)"),
+    bind("Painting", "Ctrl+Z", "Undo"),
+    bind("Painting", "Ctrl+A", "Select the island under the cursor"),
+    bind("Painting", "Delete", "Empty the selected island"),
+    bind(
+        "Painting",
+        "Ctrl+H",
+        "Replace, move or regenerate the selected island",
+    ),
+    bind(
+        "Painting",
+        "Ctrl+U",
+        "Restore the map from before the last clear, config load or generation",
+    ),
+    bind("Configs", "S / L", "Save/load a configuration"),
+    bind("Configs", "D", "Delete a configuration"),
+    bind(
+        "Configs",
+        "Ctrl+D / Ctrl+N",
+        "Duplicate/rename a configuration",
+    ),
+    bind("Configs", "P", "Print configurations, by tag"),
+    bind("Configs", "I", "Tag a configuration"),
+    bind("Configs", "Shift+I", "Describe a configuration"),
+    bind("Configs", "Ctrl+P", "Rescan the library directory"),
+    bind("Configs", "Ctrl+I", "Share a configuration to the library"),
+    bind("Configs", "R", "Distribution report"),
+    bind("Configs", "Ctrl+O", "Quick open recent"),
+    bind("Maps", "Tab", "Next map"),
+    bind("Maps", "+ / -", "Bigger/smaller tiles"),
+    bind("Maps", "Ctrl+1-9", "Switch to map"),
+    bind("Maps", "M", "New map from template"),
+    bind("Maps", "Ctrl+C / Ctrl+V", "Copy/paste map"),
+    bind("Maps", "O", "Mark selection corners (again to clear)"),
+    bind(
+        "Maps",
+        "Ctrl+Shift+C/V",
+        "Selection to/from the OS clipboard",
+    ),
+    bind("Maps", "B", "Dense/sparse storage"),
+    bind("Maps", "Ctrl+X", "Export SVG, text or PNG"),
+    bind("Maps", "Drop a .json", "Open a map file as a new map"),
+    bind("Generation", "W", "Generate"),
+    bind("Generation", "T / Ctrl+T", "Mark/clear training regions"),
+    bind("Generation", "Shift+T", "Rotate the cell under the cursor"),
+    bind("Generation", "Ctrl+Shift+T", "Choose rotatable tiles"),
+    bind(
+        "Generation",
+        "Ctrl+Shift+P",
+        "Constraint presets (island, river, mirror, landmass)",
+    ),
+    bind(
+        "Generation",
+        "Ctrl+Shift+R",
+        "Reroll the square around the cursor",
+    ),
+    bind("Generation", "J", "Sample manager"),
+    bind("Generation", "Ctrl+J", "Adjacency overrides"),
+    bind("Generation", "Shift+X", "Transition costs between tiles"),
+    bind("Generation", "Shift+J", "Cross-validate samples"),
+    bind(
+        "Generation",
+        "Shift+M",
+        "Model inspector (S sort, / search, M model)",
+    ),
+    bind("Generation", "Shift+U", "Tile usage audit and pruning"),
+    bind("Generation", "Ctrl+W", "Target tile percentages"),
+    bind(
+        "Generation",
+        "Shift+P",
+        "Pause/resume, hover for candidates; model edits apply on resume",
+    ),
+    bind(
+        "Generation",
+        "Shift+R",
+        "Minimum region size for chosen tiles",
+    ),
+    bind(
+        "Generation",
+        "Shift+E",
+        "Edge signatures; generate a chunk that tiles with chosen neighbours",
+    ),
+    bind(
+        "Generation",
+        "Shift+W",
+        "Show which cells each observation narrowed while generating",
+    ),
+    bind(
+        "Generation",
+        "Ctrl+Shift+W",
+        "Contradiction heatmap over several runs (again to hide)",
+    ),
+    bind(
+        "Generation",
+        "Ctrl+Shift+S",
+        "Save the running generation to a file, or resume a saved one",
+    ),
+    bind("Generation", "X", "Export constraints"),
+    bind("Generation", "Q", "Generate a maze"),
+    bind("Generation", "Ctrl+Q", "Lock in dungeon rooms"),
+    bind(
+        "Generation",
+        "Shift+G",
+        "Tile the map from a built-in Wang set",
+    ),
+    bind("Generation", "V", "Seed Voronoi regions"),
+    bind("Generation", "Ctrl+L", "Run a pipeline file"),
+    bind("Generation", "Ctrl+B", "Bookmark the last generation"),
+    bind(
+        "Generation",
+        "Ctrl+G",
+        "Bookmark gallery (click to regenerate)",
+    ),
+    bind("Terrain", "E", "Erode terrain"),
+    bind("Terrain", "Ctrl+E", "Rebuild elevation"),
+    bind(
+        "Terrain",
+        "Shift+H",
+        "Import/export elevation as a greyscale PNG heightmap",
+    ),
+    bind("Terrain", "K / Ctrl+K", "Scatter/clear features"),
+    bind(
+        "Terrain",
+        "Shift+K",
+        "Lay roads between villages and houses",
+    ),
+    bind("Layers", "Shift+L", "Pick the active layer"),
+    bind("Layers", "Shift+O", "Show/hide the active layer"),
+    bind("Layers", "[ / ]", "Active layer opacity down/up"),
+    bind("Maps", "Shift+V", "Lint the map for common problems"),
+    bind("Maps", "Shift+N", "Jump to the next lint finding"),
+    bind("Notes", "N", "Add note under cursor"),
+    bind("Notes", "A", "List notes"),
+    bind("App", "G", "Toggle settings panel"),
+    bind("App", "F1", "Toggle this cheat sheet"),
+    bind("App", "Ctrl+R", "Start/stop recording a macro"),
+    bind(
+        "App",
+        "Shift+C",
+        "Import/export the tile palette (.gpl, .json)",
+    ),
+    bind("App", "F2-F9", "Play a bound macro"),
+    bind("App", "Ctrl+M", "Mute/unmute sound cues"),
+    bind("App", "Ctrl+S", "Save all open maps"),
+    bind("App", "Esc", "Exit"),
+];
+
+// categories in the order they first appear in the table
+pub fn categories() -> Vec<&'static str> {
+    let mut categories = Vec::new();
+    for binding in KEYMAP {
+        if !categories.contains(&binding.category) {
+            categories.push(binding.category);
+        }
+    }
+    categories
+}
+
+pub fn print_keymap() {
+    for category in categories() {
+        println!("{}:", t!(category));
+        for binding in KEYMAP.iter().filter(|b| b.category == category) {
+            println!("  {:<16} {}", t!(binding.keys), t!(binding.action));
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::i18n::t;
+
+// how far Shift+-/= moves the active layer's opacity
+const OPACITY_STEP: f32 = 0.1;
+
+// what the map draws, bottom to top
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layer {
+    Terrain,
+    Decoration,
+    Weights,
+    Locks,
+    Annotations,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 5] = [
+        Layer::Terrain,
+        Layer::Decoration,
+        Layer::Weights,
+        Layer::Locks,
+        Layer::Annotations,
+    ];
+
+    pub fn label(&self) -> String {
+        match self {
+            Layer::Terrain => t!("Terrain"),
+            Layer::Decoration => t!("Decoration"),
+            Layer::Weights => t!("Weights"),
+            Layer::Locks => t!("Locks"),
+            Layer::Annotations => t!("Annotations"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LayerSetting {
+    pub visible: bool,
+    pub opacity: f32,
+}
+
+// per-layer visibility and opacity, plus the layer the keys act on
+#[derive(Clone, Debug)]
+pub struct Layers {
+    settings: [LayerSetting; Layer::ALL.len()],
+    pub active: Layer,
+}
+
+impl Default for Layers {
+    fn default() -> Self {
+        Layers {
+            settings: [LayerSetting {
+                visible: true,
+                opacity: 1.0,
+            }; Layer::ALL.len()],
+            active: Layer::Terrain,
+        }
+    }
+}
+
+impl Layers {
+    pub fn get(&self, layer: Layer) -> LayerSetting {
+        self.settings[layer as usize]
+    }
+
+    // the opacity to draw with, 0 when hidden
+    pub fn opacity(&self, layer: Layer) -> f32 {
+        let setting = self.get(layer);
+        if setting.visible {
+            setting.opacity
+        } else {
+            0.0
+        }
+    }
+
+    pub fn cycle_active(&mut self) -> Layer {
+        let i = Layer::ALL
+            .iter()
+            .position(|l| *l == self.active)
+            .unwrap_or(0);
+        self.active = Layer::ALL[(i + 1) % Layer::ALL.len()];
+        self.active
+    }
+
+    pub fn toggle(&mut self, layer: Layer) -> bool {
+        let setting = &mut self.settings[layer as usize];
+        setting.visible = !setting.visible;
+        setting.visible
+    }
+
+    // steps of OPACITY_STEP, shown again if it was hidden
+    pub fn adjust_opacity(&mut self, layer: Layer, steps: i32) -> f32 {
+        let setting = &mut self.settings[layer as usize];
+        let opacity = setting.opacity + steps as f32 * OPACITY_STEP;
+        setting.opacity = ((opacity / OPACITY_STEP).round() * OPACITY_STEP).clamp(0.0, 1.0);
+        setting.visible = true;
+        setting.opacity
+    }
+
+    // "Terrain 70%" or "Terrain hidden"
+    pub fn describe(&self, layer: Layer) -> String {
+        let setting = self.get(layer);
+        if setting.visible {
+            t!(
+                "{layer} {percent}%",
+                layer = layer.label(),
+                percent = format!("{:.0}", setting.opacity * 100.0)
+            )
+        } else {
+            t!("{layer} hidden", layer = layer.label())
+        }
+    }
+}
+
+// `colour` with its alpha scaled for a layer drawn at `opacity`
+pub fn faded(colour: [f32; 4], opacity: f32) -> [f32; 4] {
+    [colour[0], colour[1], colour[2], colour[3] * opacity]
+}
@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::i18n::{say, t};
+use crate::{TileSystem, TileType};
+
+// a config shared as a file in the library directory
+#[derive(Clone, Debug)]
+pub struct LibraryConfig {
+    pub path: String,
+    pub tiles: Vec<Vec<TileType>>,
+}
+
+// `{"tiles": [...], "tags": [...]}`; bare tile rows are accepted too
+#[derive(Serialize, Deserialize)]
+struct LibraryFile {
+    tiles: Vec<Vec<TileType>>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+fn parse(json: &str) -> Result<LibraryFile, String> {
+    serde_json::from_str::<LibraryFile>(json).or_else(|e| {
+        serde_json::from_str::<Vec<Vec<TileType>>>(json)
+            .map(|tiles| LibraryFile {
+                tiles,
+                tags: Vec::new(),
+            })
+            .map_err(|_| e.to_string())
+    })
+}
+
+// every *.json in `dir` by file stem; a missing directory is just an empty library
+pub fn scan(
+    dir: &str,
+) -> (
+    BTreeMap<String, LibraryConfig>,
+    BTreeMap<String, Vec<String>>,
+) {
+    let mut configs = BTreeMap::new();
+    let mut tags = BTreeMap::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return (configs, tags);
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()).map(String::from) else {
+            continue;
+        };
+        let parsed = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| parse(&json));
+        let path = path.display().to_string();
+        match parsed {
+            Ok(file) => {
+                if !file.tags.is_empty() {
+                    tags.insert(name.clone(), file.tags);
+                }
+                configs.insert(
+                    name,
+                    LibraryConfig {
+                        path,
+                        tiles: file.tiles,
+                    },
+                );
+            }
+            Err(e) => eprintln!(
+                "{}",
+                t!("Skipping library file {path}: {e}", path = path, e = e)
+            ),
+        }
+    }
+    (configs, tags)
+}
+
+impl TileSystem {
+    // a config by name, the map's own saved configs taking precedence over the library
+    pub fn config(&self, name: &str) -> Option<&Vec<Vec<TileType>>> {
+        self.saved_configs
+            .get(name)
+            .map(|config| &config.tiles)
+            .or_else(|| self.library.get(name).map(|config| &config.tiles))
+    }
+
+    pub fn set_library(
+        &mut self,
+        configs: BTreeMap<String, LibraryConfig>,
+        tags: &BTreeMap<String, Vec<String>>,
+    ) {
+        // tags from the files only fill in for configs not tagged in the app
+        for (name, file_tags) in tags {
+            if !self.config_tags.contains_key(name) {
+                self.config_tags.insert(
+                    name.clone(),
+                    file_tags.iter().map(|t| t.to_lowercase()).collect(),
+                );
+            }
+        }
+        self.library = configs;
+    }
+
+    // where a listed config comes from, None for the map's own
+    pub fn config_source(&self, name: &str) -> Option<&str> {
+        if self.saved_configs.contains_key(name) {
+            None
+        } else {
+            self.library.get(name).map(|config| config.path.as_str())
+        }
+    }
+
+    // writes a saved config to the library so other projects can pick it up
+    pub fn share_config(&self, name: &str, dir: &str) -> Result<String, String> {
+        let tiles = self
+            .saved_configs
+            .get(name)
+            .map(|config| &config.tiles)
+            .ok_or_else(|| t!("Configuration '{name}' not found", name = name))?;
+        let tags = self
+            .config_tags
+            .get(name)
+            .map(|tags| tags.iter().cloned().collect())
+            .unwrap_or_default();
+        let file = LibraryFile {
+            tiles: tiles.clone(),
+            tags,
+        };
+        fs::create_dir_all(dir).map_err(|e| t!("Failed to create {dir}: {e}", dir = dir, e = e))?;
+        let path = Path::new(dir).join(format!("{}.json", name));
+        let json = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+        fs::write(&path, json)
+            .map_err(|e| t!("Failed to write {path}: {e}", path = path.display(), e = e))?;
+        say!(
+            "Shared '{name}' to {path}",
+            name = name,
+            path = path.display()
+        );
+        Ok(path.display().to_string())
+    }
+}
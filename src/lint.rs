@@ -0,0 +1,265 @@
+use clap::Args;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::cli::MapSize;
+use crate::exit::{self, CliError, Failure};
+use crate::i18n::{say, t};
+use crate::samples::SampleSource;
+use crate::{Direction, TileSystem, TileType};
+
+#[derive(Args)]
+pub struct LintArgs {
+    /// Config whose rules the map is checked against, the enabled samples when blank
+    #[arg(long, default_value = "")]
+    model: String,
+    #[command(flatten)]
+    size: MapSize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LintKind {
+    // Empty cells walled in by tiles, out of reach from the map's edge
+    EmptyPocket,
+    // neighbours the model's rules don't allow
+    RuleViolation,
+    // a tile on the map's edge that no sample puts on its edge
+    Border,
+    // a tile with no neighbour of its own kind
+    Isolated,
+}
+
+impl LintKind {
+    pub fn label(&self) -> String {
+        match self {
+            LintKind::EmptyPocket => t!("empty pocket"),
+            LintKind::RuleViolation => t!("rule violation"),
+            LintKind::Border => t!("border"),
+            LintKind::Isolated => t!("isolated tile"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Finding {
+    pub kind: LintKind,
+    // the cell jumped to
+    pub cell: (usize, usize),
+    pub message: String,
+}
+
+// 4-connected runs of Empty cells that never touch the edge
+fn empty_pockets(tiles: &[Vec<TileType>]) -> Vec<Vec<(usize, usize)>> {
+    let height = tiles.len();
+    let width = tiles.first().map_or(0, Vec::len);
+    let mut seen = vec![vec![false; width]; height];
+    let mut pockets = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            if seen[y][x] || tiles[y][x] != TileType::Empty {
+                continue;
+            }
+            seen[y][x] = true;
+            let mut cells = Vec::new();
+            let mut touches_edge = false;
+            let mut stack = vec![(x, y)];
+            while let Some((cx, cy)) = stack.pop() {
+                cells.push((cx, cy));
+                touches_edge |= cx == 0 || cy == 0 || cx + 1 == width || cy + 1 == height;
+                let neighbours = [
+                    (cx.wrapping_sub(1), cy),
+                    (cx + 1, cy),
+                    (cx, cy.wrapping_sub(1)),
+                    (cx, cy + 1),
+                ];
+                for (nx, ny) in neighbours {
+                    if nx < width
+                        && ny < height
+                        && !seen[ny][nx]
+                        && tiles[ny][nx] == TileType::Empty
+                    {
+                        seen[ny][nx] = true;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+            if !touches_edge {
+                pockets.push(cells);
+            }
+        }
+    }
+    pockets
+}
+
+fn edge_cells(width: usize, height: usize) -> Vec<(usize, usize)> {
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|&(x, y)| x == 0 || y == 0 || x + 1 == width || y + 1 == height)
+        .collect()
+}
+
+impl TileSystem {
+    // ids of the tiles found along the edges of the model's samples
+    fn sample_edge_tiles(&self, model: &str) -> HashSet<usize> {
+        let grids: Vec<Vec<Vec<TileType>>> = if model.is_empty() {
+            self.samples()
+                .into_iter()
+                .filter(|(_, setting)| setting.enabled && setting.weight > 0.0)
+                .map(|(source, _)| self.sample_grid(&source))
+                .collect()
+        } else {
+            vec![self.sample_grid(&SampleSource::Config(model.to_string()))]
+        };
+        let mut tiles = HashSet::new();
+        for grid in grids {
+            let height = grid.len();
+            let width = grid.first().map_or(0, Vec::len);
+            for (x, y) in edge_cells(width, height) {
+                tiles.insert(grid[y][x].index());
+            }
+        }
+        tiles
+    }
+
+    // everything worth a second look on the current map, checked against the rules of
+    // `model` (a saved config, or the enabled samples when blank)
+    pub fn lint(&self, model: &str) -> Result<Vec<Finding>, String> {
+        let tiles = self.tile_types();
+        let rules = self.learn_rules(model)?;
+        let mut findings = Vec::new();
+
+        for pocket in empty_pockets(&tiles) {
+            findings.push(Finding {
+                kind: LintKind::EmptyPocket,
+                cell: pocket[0],
+                message: t!(
+                    "{count} Empty cell(s) walled in by other tiles",
+                    count = pocket.len()
+                ),
+            });
+        }
+
+        for (y, row) in tiles.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                if *tile == TileType::Empty {
+                    continue;
+                }
+                let neighbours = [
+                    (Direction::Right, row.get(x + 1)),
+                    (
+                        Direction::Down,
+                        tiles.get(y + 1).and_then(|below| below.get(x)),
+                    ),
+                ];
+                for (dir, neighbour) in neighbours {
+                    let Some(neighbour) = neighbour.filter(|n| **n != TileType::Empty) else {
+                        continue;
+                    };
+                    let allowed = rules
+                        .get(&tile.index())
+                        .is_some_and(|allowed| allowed.contains(&(dir, neighbour.index())));
+                    if !allowed {
+                        findings.push(Finding {
+                            kind: LintKind::RuleViolation,
+                            cell: (x, y),
+                            message: t!(
+                                "{neighbour} {side} {tile} isn't allowed by the model",
+                                neighbour = format!("{:?}", neighbour),
+                                side = if dir == Direction::Right {
+                                    t!("right of")
+                                } else {
+                                    t!("below")
+                                },
+                                tile = format!("{:?}", tile)
+                            ),
+                        });
+                    }
+                }
+            }
+        }
+
+        // grouped per tile, there's usually a whole stretch of edge
+        let edge_tiles = self.sample_edge_tiles(model);
+        let mut unbounded: BTreeMap<usize, Vec<(usize, usize)>> = BTreeMap::new();
+        for (x, y) in edge_cells(self.grid_width, self.grid_height) {
+            let tile = &tiles[y][x];
+            if *tile != TileType::Empty
+                && !edge_tiles.contains(&tile.index())
+                && !self.locked_cells.contains(&(x, y))
+            {
+                unbounded.entry(tile.index()).or_default().push((x, y));
+            }
+        }
+        for (tile, cells) in unbounded {
+            findings.push(Finding {
+                kind: LintKind::Border,
+                cell: cells[0],
+                message: t!(
+                    "{tile} on {count} map edge cell(s) but on no sample's edge, lock a border to constrain it",
+                    tile = format!("{:?}", TileType::ALL[tile]),
+                    count = cells.len()
+                ),
+            });
+        }
+
+        for (y, row) in tiles.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                if *tile == TileType::Empty {
+                    continue;
+                }
+                let neighbours = [
+                    x.checked_sub(1).and_then(|nx| row.get(nx)),
+                    row.get(x + 1),
+                    y.checked_sub(1).and_then(|ny| tiles.get(ny)).map(|r| &r[x]),
+                    tiles.get(y + 1).map(|r| &r[x]),
+                ];
+                if neighbours.iter().flatten().all(|n| *n != tile) {
+                    findings.push(Finding {
+                        kind: LintKind::Isolated,
+                        cell: (x, y),
+                        message: t!(
+                            "A lone {tile} with no {tile} beside it",
+                            tile = format!("{:?}", tile)
+                        ),
+                    });
+                }
+            }
+        }
+        Ok(findings)
+    }
+
+    pub fn print_lint(findings: &[Finding]) {
+        if findings.is_empty() {
+            say!("No problems found");
+            return;
+        }
+        for (i, finding) in findings.iter().enumerate() {
+            say!(
+                "{number}. ({x}, {y}) {kind}: {message}",
+                number = i + 1,
+                x = finding.cell.0,
+                y = finding.cell.1,
+                kind = finding.kind.label(),
+                message = finding.message
+            );
+        }
+        say!("{count} problem(s) found", count = findings.len());
+    }
+}
+
+// `lint`: lists the saved map's problems, exiting non-zero when there are any
+pub fn run_headless(args: LintArgs) -> i32 {
+    exit::report(lint(args))
+}
+
+fn lint(args: LintArgs) -> Result<(), CliError> {
+    let tile_system = TileSystem::load_headless(&args.size)?;
+    let findings = tile_system
+        .lint(&args.model)
+        .map_err(|e| CliError::new(Failure::NotFound, e))?;
+    TileSystem::print_lint(&findings);
+    if findings.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::new(Failure::Invalid, t!("Lint failed")))
+    }
+}
@@ -1,16 +1,136 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fs;
 use std::io::{self, Write};
 use std::{collections::HashMap, usize};
 
+use i18n::{say, t};
 use piston_window::*;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+mod audio;
+mod backup;
+mod batch;
+mod bias;
+mod bookmarks;
+mod brushes;
+mod camera;
+mod cli;
+mod clipboard;
+mod commands;
+mod config;
+mod config_meta;
+mod constraint_export;
+mod contradiction_heatmap;
+mod convert;
+mod costs;
+mod crossval;
+mod csp;
+mod decorations;
+mod dungeon;
+mod edges;
+mod erosion;
+mod exit;
+mod experiment;
+mod gamepad;
+mod grid;
+mod groups;
+mod heightmap;
+mod i18n;
+mod inspector;
+mod instance;
+mod integrity;
+mod keymap;
+mod layers;
+mod library;
+mod lint;
+mod maze;
+mod observer;
+mod overrides;
+mod palette;
+mod pipeline;
+mod png_export;
+mod presets;
+mod quadtree;
+mod recovery;
+mod regions;
+mod resize;
+mod resume;
+mod roads;
+mod rotations;
+mod samples;
+mod scatter;
+mod selfcheck;
+mod serve;
+mod stats;
+mod svg_export;
+mod tags;
+mod targets;
+mod templates;
+mod text_export;
+mod touch;
+mod trace;
+#[cfg(feature = "tui")]
+mod tui;
+mod ui;
+mod usage;
+mod voronoi;
+mod wand;
+mod wang;
+mod watch;
+mod wfc;
+mod worker;
+mod workspace;
+
+use audio::{Audio, Cue};
+use backup::PreOperation;
+use bias::BiasCell;
+use bookmarks::{Bookmark, GenerationSettings};
+use brushes::{BrushStyle, NamedBrush};
+use camera::Camera;
+use clap::Parser;
+use cli::{Cli, Commands, EditArgs, MapSize, PipelineCommand};
+use clipboard::{Fragment, SystemClipboard};
+use commands::{Command, Generation, Macro, MacroRecorder};
+use config::AppConfig;
+use config_meta::SavedConfig;
+use contradiction_heatmap::ContradictionHeatmap;
+use costs::TransitionCost;
+use decorations::DecorationBrush;
+use gamepad::PadAction;
+use grid::TileGrid;
+use inspector::Inspector;
+use instance::InstanceLock;
+use layers::{Layer, Layers};
+use library::LibraryConfig;
+use maze::MazeAlgorithm;
+use observer::GenerationObserver;
+use overrides::RuleOverride;
+use pipeline::Pipeline;
+use presets::{Axis, MinLandmass};
+use quadtree::QuadTree;
+use regions::MinRegionSize;
+use resize::ResizePolicy;
+use samples::SampleSetting;
+use scatter::{Feature, FeatureKind};
+use svg_export::SvgOptions;
+use targets::DistributionTargets;
+use text_export::TextOptions;
+use touch::{Gesture, TouchTracker};
+use ui::{Confirm, FillPreview, PanelAction, QuickEntry, SettingsPanel};
+use voronoi::BiomeSeed;
+use watch::{FileWatcher, Reload};
+use worker::GenerationJob;
+use workspace::Workspace;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Tile {
     pub colour: [f32; 4],
     pub tile_type: TileType,
     pub visible: bool,
+    // quarter turns clockwise, only meaningful for rotatable tiles
+    #[serde(default)]
+    pub rotation: u8,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -21,58 +141,253 @@ pub enum TileType {
     Coast,
     Water,
 }
+
+impl TileType {
+    pub const ALL: [TileType; 5] = [
+        TileType::Empty,
+        TileType::Mountain,
+        TileType::Land,
+        TileType::Coast,
+        TileType::Water,
+    ];
+
+    pub fn index(&self) -> usize {
+        match self {
+            TileType::Empty => 0,
+            TileType::Mountain => 1,
+            TileType::Land => 2,
+            TileType::Coast => 3,
+            TileType::Water => 4,
+        }
+    }
+
+    // the next (or previous) tile in ALL, wrapping round
+    pub fn cycled(&self, forward: bool) -> TileType {
+        let count = TileType::ALL.len();
+        let step = if forward { 1 } else { count - 1 };
+        TileType::ALL[(self.index() + step) % count].clone()
+    }
+
+    // case-insensitive tile name as typed at the console or in files
+    pub fn parse(name: &str) -> Option<Self> {
+        TileType::ALL
+            .into_iter()
+            .find(|tile| format!("{:?}", tile).eq_ignore_ascii_case(name))
+    }
+}
+
 impl Tile {
     pub fn new(tile_type: TileType, colour: [f32; 4]) -> Self {
         Tile {
             colour,
             tile_type,
             visible: true,
+            rotation: 0,
         }
     }
 
+    // colours come from the palette, see palette.rs
     pub fn empty() -> Self {
-        Tile::new(TileType::Empty, [0.0, 0.0, 0.0, 0.0])
+        Tile::new(TileType::Empty, palette::colour(&TileType::Empty))
     }
     pub fn mountain() -> Self {
-        Tile::new(TileType::Mountain, [0.5, 0.5, 0.5, 1.0])
+        Tile::new(TileType::Mountain, palette::colour(&TileType::Mountain))
     }
     pub fn land() -> Self {
-        Tile::new(TileType::Land, [0.3, 0.8, 0.4, 1.0])
+        Tile::new(TileType::Land, palette::colour(&TileType::Land))
     }
     pub fn coast() -> Self {
-        Tile::new(TileType::Coast, [0.8, 0.7, 0.6, 1.0])
+        Tile::new(TileType::Coast, palette::colour(&TileType::Coast))
     }
     pub fn water() -> Self {
-        Tile::new(TileType::Water, [0.2, 0.4, 0.8, 1.0])
+        Tile::new(TileType::Water, palette::colour(&TileType::Water))
+    }
+
+    pub fn from_type(tile_type: &TileType) -> Self {
+        match tile_type {
+            TileType::Empty => Tile::empty(),
+            TileType::Mountain => Tile::mountain(),
+            TileType::Land => Tile::land(),
+            TileType::Coast => Tile::coast(),
+            TileType::Water => Tile::water(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Annotation {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub text: String,
+}
+
+impl Annotation {
+    pub fn covers(&self, x: usize, y: usize) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Region {
+    pub fn from_corners(a: (usize, usize), b: (usize, usize)) -> Self {
+        Region {
+            x: a.0.min(b.0),
+            y: a.1.min(b.1),
+            width: a.0.abs_diff(b.0) + 1,
+            height: a.1.abs_diff(b.1) + 1,
+        }
+    }
+
+    pub fn covers(&self, x: usize, y: usize) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
     }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TileSystem {
-    pub tiles: Vec<Vec<Tile>>,
+    pub tiles: TileGrid,
     pub tile_size: f64,
     pub grid_width: usize,
     pub grid_height: usize,
     pub window_width: f64,
     pub window_height: f64,
-    pub saved_configs: HashMap<String, Vec<Vec<TileType>>>,
+    pub saved_configs: HashMap<String, SavedConfig>,
+    #[serde(default)]
+    pub locked_cells: HashSet<(usize, usize)>,
+    // cells the last dungeon layout locked, unlocked again by the next one
+    #[serde(default)]
+    pub dungeon_cells: HashSet<(usize, usize)>,
+    // labels for browsing saved configs, e.g. "island" or "training"
+    #[serde(default)]
+    pub config_tags: HashMap<String, BTreeSet<String>>,
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    // parts of the map that rules are learned from when no config is named
+    #[serde(default)]
+    pub training_regions: Vec<Region>,
+    // enable/weight per sample, keyed by SampleSource::id
+    #[serde(default)]
+    pub sample_settings: HashMap<String, SampleSetting>,
+    // heights in roughly 0..1, empty until erosion or an import creates them
+    #[serde(default)]
+    pub elevation: Vec<Vec<f64>>,
+    // markers scattered over the terrain, drawn above the tiles
+    #[serde(default)]
+    pub features: Vec<Feature>,
+    // painted per-cell pulls towards a tile, empty until painted
+    #[serde(default)]
+    pub bias: Vec<Vec<Option<BiasCell>>>,
+    // Voronoi sites whose dominant tiles bias generation
+    #[serde(default)]
+    pub biome_seeds: Vec<BiomeSeed>,
+    // seed gallery, Ctrl+B adds the last generation
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
+    // named sets of tiles, e.g. "wet", usable wherever a tile set is asked for
+    #[serde(default = "groups::default_groups")]
+    pub tile_groups: BTreeMap<String, Vec<TileType>>,
+    // merged over the learned adjacency whenever rules are learned
+    #[serde(default)]
+    pub rule_overrides: Vec<RuleOverride>,
+    // the numbered brush bank, Shift+1-9
+    #[serde(default)]
+    pub brushes: Vec<NamedBrush>,
+    #[serde(default)]
+    pub distribution_targets: Option<DistributionTargets>,
+    #[serde(default)]
+    pub min_region_size: Option<MinRegionSize>,
+    #[serde(default)]
+    pub transition_costs: Vec<TransitionCost>,
+    // set by constraint presets, see presets.rs
+    #[serde(default)]
+    pub symmetry: Option<Axis>,
+    #[serde(default)]
+    pub min_landmass: Option<MinLandmass>,
+    // tiles solved once per quarter turn, see rotations.rs
+    #[serde(default)]
+    pub rotatable_tiles: Vec<TileType>,
+    // tiles left out of the solver's domains, see usage.rs
+    #[serde(default)]
+    pub pruned_tiles: Vec<TileType>,
+    // how configs saved at another size are loaded when nobody is asked
+    #[serde(default)]
+    pub resize_policy: ResizePolicy,
+    #[serde(skip)]
+    pub last_generation: Option<Bookmark>,
+    #[serde(default)]
+    pub pre_operation: Option<PreOperation>,
+    // configs from the library directory, read-only and not part of the save
+    #[serde(skip)]
+    pub library: BTreeMap<String, LibraryConfig>,
+    // built on first use, see quadtree.rs
+    #[serde(skip)]
+    index: RefCell<Option<QuadTree>>,
+    #[serde(skip)]
+    undo_stack: Vec<UndoStep>,
+    // changed since the last save or load
+    #[serde(skip)]
+    pub dirty: bool,
+}
+
+// one step that Ctrl+Z can revert
+#[derive(Debug)]
+enum UndoStep {
+    Tiles(TileGrid),
+    // the decoration channel before a decoration brush stroke
+    Features(Vec<Feature>),
+    // the painted bias layer before a bias stroke
+    Bias(Vec<Vec<Option<BiasCell>>>),
+    // a config's previous contents, None if it didn't exist yet
+    Config(String, Option<SavedConfig>),
+    // a config renamed from the first name to the second
+    Renamed(String, String),
+    // the tiles and locks before a step that changes both
+    Locked {
+        tiles: TileGrid,
+        locked_cells: HashSet<(usize, usize)>,
+        dungeon_cells: HashSet<(usize, usize)>,
+    },
+    // the tiles, locks, Voronoi sites and solver settings before a bookmark's were
+    // put back
+    Setup(Box<Setup>),
+    // the tiles and elevation layer before a heightmap replaced both
+    Elevation {
+        tiles: TileGrid,
+        elevation: Vec<Vec<f64>>,
+    },
+}
+
+#[derive(Debug)]
+struct Setup {
+    tiles: TileGrid,
+    locked_cells: HashSet<(usize, usize)>,
+    dungeon_cells: HashSet<(usize, usize)>,
+    biome_seeds: Vec<BiomeSeed>,
+    settings: GenerationSettings,
 }
 
 impl TileSystem {
     const SAVE_FILE: &'static str = "tile_system.json";
+    const UNDO_LIMIT: usize = 50;
+    const MIN_TILE_SIZE: f64 = 4.0;
+    const MAX_TILE_SIZE: f64 = 128.0;
+    // per +/- press
+    const TILE_SIZE_STEP: f64 = 4.0;
 
     pub fn new(window_width: f64, window_height: f64, tile_size: f64) -> Self {
         let grid_width = (window_width / tile_size) as usize;
         let grid_height = (window_height / tile_size) as usize;
 
-        let mut tiles = Vec::new();
-        for _y in 0..grid_height {
-            let mut row = Vec::new();
-            for _x in 0..grid_width {
-                row.push(Tile::empty());
-            }
-            tiles.push(row);
-        }
+        let tiles = TileGrid::new(grid_width, grid_height, Tile::empty());
 
         TileSystem {
             tiles,
@@ -82,24 +397,189 @@ impl TileSystem {
             window_width,
             window_height,
             saved_configs: HashMap::new(),
+            locked_cells: HashSet::new(),
+            dungeon_cells: HashSet::new(),
+            config_tags: HashMap::new(),
+            annotations: Vec::new(),
+            training_regions: Vec::new(),
+            sample_settings: HashMap::new(),
+            elevation: Vec::new(),
+            features: Vec::new(),
+            bias: Vec::new(),
+            biome_seeds: Vec::new(),
+            bookmarks: Vec::new(),
+            tile_groups: groups::default_groups(),
+            rule_overrides: Vec::new(),
+            brushes: Vec::new(),
+            distribution_targets: None,
+            min_region_size: None,
+            transition_costs: Vec::new(),
+            symmetry: None,
+            min_landmass: None,
+            rotatable_tiles: Vec::new(),
+            pruned_tiles: Vec::new(),
+            resize_policy: ResizePolicy::default(),
+            last_generation: None,
+            pre_operation: None,
+            library: BTreeMap::new(),
+            index: RefCell::new(None),
+            undo_stack: Vec::new(),
+            dirty: false,
         }
     }
 
-    pub fn load_or_new() -> Self {
-        match fs::read_to_string(Self::SAVE_FILE) {
-            Ok(json_data) => match serde_json::from_str(&json_data) {
-                Ok(tile_system) => {
-                    println!("Loaded from previous save");
-                    tile_system
-                }
-                Err(e) => {
-                    println!("Error parsing save file: {}, starting fresh", e);
-                    Self::new(512.0, 512.0, 32.0)
+    // a blank map of the given size in cells, the window sized to fit it
+    pub fn with_size(size: &MapSize) -> Self {
+        let tile_size = size.tile_size.max(1.0);
+        Self::new(
+            size.width.max(1) as f64 * tile_size,
+            size.height.max(1) as f64 * tile_size,
+            tile_size,
+        )
+    }
+
+    pub fn load_or_new(size: &MapSize) -> Self {
+        match integrity::read_with_backup(Self::SAVE_FILE) {
+            Some(Ok(tile_system)) => {
+                say!("Loaded from previous save");
+                tile_system
+            }
+            Some(Err(_)) => {
+                say!("Save file is damaged and has no usable backup, starting fresh");
+                Self::with_size(size)
+            }
+            None => {
+                say!("No save file found, starting fresh");
+                Self::with_size(size)
+            }
+        }
+    }
+
+    pub fn load_from_path(path: &str) -> Option<Self> {
+        integrity::read_with_backup(path)?.ok()
+    }
+
+    // snapshot the tiles before an edit so it can be undone
+    pub fn push_undo(&mut self) {
+        self.push_undo_step(UndoStep::Tiles(self.tiles.clone()));
+    }
+
+    // snapshot the tiles and locks before an edit that locks cells too
+    pub fn push_lock_undo(&mut self) {
+        self.push_undo_step(UndoStep::Locked {
+            tiles: self.tiles.clone(),
+            locked_cells: self.locked_cells.clone(),
+            dungeon_cells: self.dungeon_cells.clone(),
+        });
+    }
+
+    // snapshot everything a regenerated bookmark puts back
+    pub fn push_setup_undo(&mut self) {
+        self.push_undo_step(UndoStep::Setup(Box::new(Setup {
+            tiles: self.tiles.clone(),
+            locked_cells: self.locked_cells.clone(),
+            dungeon_cells: self.dungeon_cells.clone(),
+            biome_seeds: self.biome_seeds.clone(),
+            settings: self.generation_settings(),
+        })));
+    }
+
+    // snapshot the tiles and elevation layer before an edit that replaces both
+    pub fn push_elevation_undo(&mut self) {
+        self.push_undo_step(UndoStep::Elevation {
+            tiles: self.tiles.clone(),
+            elevation: self.elevation.clone(),
+        });
+    }
+
+    // remember a config before it is overwritten or deleted
+    fn push_config_undo(&mut self, name: &str) {
+        let previous = self.saved_configs.get(name).cloned();
+        self.push_undo_step(UndoStep::Config(name.to_string(), previous));
+    }
+
+    fn push_undo_step(&mut self, step: UndoStep) {
+        self.dirty = true;
+        if self.undo_stack.len() == Self::UNDO_LIMIT {
+            self.undo_stack.remove(0);
+        }
+        self.undo_stack.push(step);
+    }
+
+    pub fn undo(&mut self) -> bool {
+        self.dirty |= !self.undo_stack.is_empty();
+        match self.undo_stack.pop() {
+            Some(UndoStep::Tiles(tiles)) => {
+                self.tiles = tiles;
+                self.sync_grid_size();
+                say!("Undone");
+                true
+            }
+            Some(UndoStep::Locked {
+                tiles,
+                locked_cells,
+                dungeon_cells,
+            }) => {
+                self.tiles = tiles;
+                self.locked_cells = locked_cells;
+                self.dungeon_cells = dungeon_cells;
+                self.sync_grid_size();
+                say!("Undone");
+                true
+            }
+            Some(UndoStep::Setup(setup)) => {
+                let setup = *setup;
+                self.tiles = setup.tiles;
+                self.locked_cells = setup.locked_cells;
+                self.dungeon_cells = setup.dungeon_cells;
+                self.biome_seeds = setup.biome_seeds;
+                self.set_generation_settings(setup.settings);
+                self.sync_grid_size();
+                say!("Undone");
+                true
+            }
+            Some(UndoStep::Elevation { tiles, elevation }) => {
+                self.tiles = tiles;
+                self.elevation = elevation;
+                self.sync_grid_size();
+                say!("Undone");
+                true
+            }
+            Some(UndoStep::Features(features)) => {
+                self.features = features;
+                say!("Undone");
+                true
+            }
+            Some(UndoStep::Bias(bias)) => {
+                self.bias = bias;
+                say!("Undone");
+                true
+            }
+            Some(UndoStep::Config(name, Some(config))) => {
+                self.saved_configs.insert(name.clone(), config);
+                say!("Restored configuration: {name}", name = name);
+                true
+            }
+            Some(UndoStep::Config(name, None)) => {
+                self.saved_configs.remove(&name);
+                say!("Removed configuration: {name}", name = name);
+                true
+            }
+            Some(UndoStep::Renamed(old, new)) => {
+                if let Some(config) = self.saved_configs.remove(&new) {
+                    self.saved_configs.insert(old.clone(), config);
                 }
-            },
-            Err(_) => {
-                println!("No save file found, starting fresh");
-                Self::new(512.0, 512.0, 32.0)
+                self.move_tags(&new, &old, false);
+                say!(
+                    "Renamed configuration '{new}' back to '{old}'",
+                    new = new,
+                    old = old
+                );
+                true
+            }
+            None => {
+                say!("Nothing to undo");
+                false
             }
         }
     }
@@ -107,106 +587,356 @@ impl TileSystem {
     // get tile at grid coords
     pub fn get_tile(&self, x: usize, y: usize) -> Option<&Tile> {
         if x < self.grid_width && y < self.grid_height {
-            Some(&self.tiles[y][x])
+            self.tiles.get(x, y)
         } else {
             None
         }
     }
 
     pub fn set_tile(&mut self, x: usize, y: usize, tile: Tile) -> bool {
-        if x < self.grid_width && y < self.grid_height {
-            self.tiles[x][y] = tile;
+        if x < self.grid_width && y < self.grid_height && !self.is_locked(x, y) {
+            self.put_tile(x, y, tile);
+            self.dirty = true;
             true
         } else {
             false
         }
     }
 
-    pub fn save_config(&mut self, name: String) {
+    pub fn is_locked(&self, x: usize, y: usize) -> bool {
+        self.locked_cells.contains(&(x, y))
+    }
+
+    // locked cells are skipped by brushes, fill, clear, config loads and generation
+    pub fn toggle_lock(&mut self, x: usize, y: usize) -> bool {
+        if x >= self.grid_width || y >= self.grid_height {
+            return false;
+        }
+        if !self.locked_cells.remove(&(x, y)) {
+            self.locked_cells.insert((x, y));
+        }
+        self.dirty = true;
+        self.is_locked(x, y)
+    }
+
+    // switch this map between dense and sparse storage
+    pub fn toggle_sparse(&mut self) -> bool {
+        self.tiles = if self.tiles.is_sparse() {
+            self.tiles.to_dense()
+        } else {
+            self.tiles.to_sparse(Tile::empty())
+        };
+        self.dirty = true;
+        say!(
+            "Map storage: {sparse}",
+            sparse = if self.tiles.is_sparse() {
+                "sparse"
+            } else {
+                "dense"
+            }
+        );
+        self.tiles.is_sparse()
+    }
+
+    pub fn unlock_all(&mut self) {
+        self.locked_cells.clear();
+        self.dungeon_cells.clear();
+        self.dirty = true;
+        say!("All cells unlocked");
+    }
+
+    pub fn tile_types(&self) -> Vec<Vec<TileType>> {
         let mut config = Vec::new();
-        for row in &self.tiles {
+        for y in 0..self.grid_height {
             let mut config_row = Vec::new();
-            for tile in row {
-                config_row.push(tile.tile_type.clone());
+            for x in 0..self.grid_width {
+                if let Some(tile) = self.tiles.get(x, y) {
+                    config_row.push(tile.tile_type.clone());
+                }
             }
             config.push(config_row);
         }
-        self.saved_configs.insert(name.clone(), config);
-        println!("Saved configuration: {}", name);
+        config
+    }
+
+    pub fn save_config(&mut self, name: String) {
+        let tiles = self.tile_types();
+        self.store_config(name, tiles);
+    }
+
+    // saves `tiles` under `name` as if they had been painted and saved
+    pub fn store_config(&mut self, name: String, tiles: Vec<Vec<TileType>>) {
+        self.push_config_undo(&name);
+        let now = config_meta::now();
+        match self.saved_configs.get_mut(&name) {
+            Some(config) => config.replace_tiles(tiles, now),
+            None => {
+                let config = SavedConfig::from_tiles(tiles, now, config_meta::current_author());
+                self.saved_configs.insert(name.clone(), config);
+            }
+        }
+        say!("Saved configuration: {name}", name = name);
     }
 
     pub fn load_config(&mut self, name: &str) -> bool {
-        if let Some(config) = self.saved_configs.get(name) {
-            for (y, row) in config.iter().enumerate() {
-                for (x, tile_type) in row.iter().enumerate() {
-                    if y < self.grid_height && x < self.grid_width {
-                        let tile = match tile_type {
-                            TileType::Empty => Tile::empty(),
-                            TileType::Mountain => Tile::mountain(),
-                            TileType::Land => Tile::land(),
-                            TileType::Coast => Tile::coast(),
-                            TileType::Water => Tile::water(),
-                        };
-                        self.tiles[y][x] = tile;
-                    }
+        self.load_config_with(name, self.resize_policy)
+    }
+
+    // tile distribution of the map against saved configs (the training samples)
+    pub fn distribution_report(&self, name: &str) {
+        let map_freqs = stats::tile_frequencies(&self.tile_types());
+        if !name.is_empty() {
+            match self.config(name) {
+                Some(config) => {
+                    let config_freqs = stats::tile_frequencies(config);
+                    stats::print_comparison("map", &map_freqs, name, &config_freqs);
                 }
+                None => say!("Configuration '{name}' not found", name = name),
             }
-            println!("Loaded configuration: {}", name);
-            true
-        } else {
-            println!("Configuration '{}' not found", name);
-            false
+            return;
+        }
+
+        stats::print_distribution("Current map", &map_freqs);
+        for name in self.config_names(None) {
+            let Some(config) = self.config(name) else {
+                continue;
+            };
+            let config_freqs = stats::tile_frequencies(config);
+            say!(
+                " - KL(map || {name}) = {kl} bits",
+                name = name,
+                kl = format!("{:.4}", stats::kl_divergence(&map_freqs, &config_freqs))
+            );
         }
     }
+
     pub fn list_configs(&self) {
-        if self.saved_configs.is_empty() {
-            println!("No saved configurations");
+        self.list_configs_tagged(None);
+    }
+
+    pub fn list_configs_tagged(&self, tag: Option<&str>) {
+        let names = self.config_names(tag);
+        if names.is_empty() {
+            match tag {
+                Some(tag) => say!("No saved configurations tagged '{tag}'", tag = tag),
+                None => say!("No saved configurations"),
+            }
         } else {
-            println!("Saved configurations:");
-            for name in self.saved_configs.keys() {
-                println!(" - {}", name);
+            say!("Saved configurations:");
+            for name in names {
+                let tags = match self.config_tags.get(name) {
+                    Some(_) => format!(" [{}]", self.tags_label(name)),
+                    None => String::new(),
+                };
+                match (self.config_source(name), self.config_meta(name)) {
+                    (Some(path), _) => say!(
+                        " - {name}{tags} (library: {path})",
+                        name = name,
+                        tags = tags,
+                        path = path
+                    ),
+                    (None, Some(meta)) => {
+                        println!(" - {}{} ({})", name, tags, meta.summary());
+                        if !meta.description.is_empty() {
+                            println!("     {}", meta.description);
+                        }
+                    }
+                    (None, None) => println!(" - {}{}", name, tags),
+                }
+            }
+        }
+    }
+
+    // paints the brush's footprint centred on the tile as one undo step
+    // `only` limits the brush to replacing those tiles
+    pub fn paint(
+        &mut self,
+        x: usize,
+        y: usize,
+        style: &BrushStyle,
+        tile: Tile,
+        only: Option<&[TileType]>,
+    ) {
+        self.push_undo();
+        self.paint_stroke(x, y, style, tile, only);
+    }
+
+    // a brush-width straight line from `from` to the tile, as one undo step
+    pub fn paint_line(
+        &mut self,
+        from: (usize, usize),
+        x: usize,
+        y: usize,
+        style: &BrushStyle,
+        tile: Tile,
+        only: Option<&[TileType]>,
+    ) {
+        self.push_undo();
+        for (px, py) in grid::line_cells(from, (x, y)) {
+            self.paint_stroke(px, py, style, tile.clone(), only);
+        }
+    }
+
+    // more of a stroke begun with paint, sharing its undo step
+    pub fn paint_stroke(
+        &mut self,
+        x: usize,
+        y: usize,
+        style: &BrushStyle,
+        tile: Tile,
+        only: Option<&[TileType]>,
+    ) {
+        for (px, py) in self.brush_cells(x, y, style) {
+            let allowed = only.is_none_or(|only| {
+                self.get_tile(px, py)
+                    .is_some_and(|t| only.contains(&t.tile_type))
+            });
+            // sparse brushes leave a random share of their cells alone
+            if allowed && (style.density >= 1.0 || rand::random::<f64>() < style.density) {
+                self.set_tile(px, py, tile.clone());
             }
         }
     }
 
     pub fn clear_map(&mut self) {
-        for row in &mut self.tiles {
-            for tile in row {
-                *tile = Tile::empty();
+        self.backup_before("clearing");
+        self.push_undo();
+        for y in 0..self.grid_height {
+            for x in 0..self.grid_width {
+                self.set_tile(x, y, Tile::empty());
             }
         }
-        println!("Map cleared");
+        say!("Map cleared");
     }
 
-    pub fn delete_config(&mut self, name: &str) -> Result<Vec<Vec<TileType>>, String> {
+    pub fn delete_config(&mut self, name: &str) -> Result<SavedConfig, String> {
+        if self.saved_configs.contains_key(name) {
+            self.push_config_undo(name);
+        }
         match self.saved_configs.remove(name) {
             Some(value) => {
-                println!("Removed '{}' successfully", name);
+                say!("Removed '{name}' successfully", name = name);
                 Ok(value)
             }
             None => {
-                let error = format!(" Item '{}' not found", name);
+                let error = t!(" Item '{name}' not found", name = name);
                 eprintln!("{}", error);
                 Err(error)
             }
         }
     }
 
-    pub fn save_to_file(&self) {
-        match serde_json::to_string_pretty(self) {
-            Ok(json_data) => {
-                if let Err(e) = fs::write(Self::SAVE_FILE, json_data) {
-                    eprintln!("Failed to save state: {}", e);
-                } else {
-                    println!("State saved");
-                }
-            }
-            Err(e) => {
-                eprintln!("Failed to serialize state: {}", e);
+    // existing config under `old`, and `new` free to take it
+    fn check_config_names(&self, old: &str, new: &str) -> Result<(), String> {
+        if !self.saved_configs.contains_key(old) {
+            Err(t!("Configuration '{old}' not found", old = old))
+        } else if new.is_empty() {
+            Err(t!("New name can't be blank"))
+        } else if self.saved_configs.contains_key(new) {
+            Err(t!("Configuration '{new}' already exists", new = new))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn rename_config(&mut self, old: &str, new: &str) -> Result<(), String> {
+        self.check_config_names(old, new)?;
+        if let Some(config) = self.saved_configs.remove(old) {
+            self.saved_configs.insert(new.to_string(), config);
+        }
+        self.move_tags(old, new, false);
+        self.push_undo_step(UndoStep::Renamed(old.to_string(), new.to_string()));
+        say!(
+            "Renamed configuration '{old}' to '{new}'",
+            old = old,
+            new = new
+        );
+        Ok(())
+    }
+
+    pub fn duplicate_config(&mut self, name: &str, copy_name: &str) -> Result<(), String> {
+        self.check_config_names(name, copy_name)?;
+        self.push_config_undo(copy_name);
+        let copy = self.saved_configs[name].clone();
+        self.saved_configs.insert(copy_name.to_string(), copy);
+        self.move_tags(name, copy_name, true);
+        say!(
+            "Copied configuration '{name}' to '{copy_name}'",
+            name = name,
+            copy_name = copy_name
+        );
+        Ok(())
+    }
+
+    pub fn annotate(&mut self, x: usize, y: usize, width: usize, height: usize, text: String) {
+        if x >= self.grid_width || y >= self.grid_height {
+            return;
+        }
+        let width = width.clamp(1, self.grid_width - x);
+        let height = height.clamp(1, self.grid_height - y);
+        say!(
+            "Added note at ({x}, {y}) {width}x{height}",
+            x = x,
+            y = y,
+            width = width,
+            height = height
+        );
+        self.dirty = true;
+        self.annotations.push(Annotation {
+            x,
+            y,
+            width,
+            height,
+            text,
+        });
+    }
+
+    pub fn annotations_at(&self, x: usize, y: usize) -> Vec<&Annotation> {
+        self.annotations.iter().filter(|a| a.covers(x, y)).collect()
+    }
+
+    pub fn remove_annotations_at(&mut self, x: usize, y: usize) -> usize {
+        let before = self.annotations.len();
+        self.annotations.retain(|a| !a.covers(x, y));
+        self.dirty |= self.annotations.len() != before;
+        before - self.annotations.len()
+    }
+
+    pub fn list_annotations(&self) {
+        if self.annotations.is_empty() {
+            say!("No notes");
+        } else {
+            say!("Notes:");
+            for note in &self.annotations {
+                say!(
+                    " - ({x}, {y}) {width}x{height}: {text}",
+                    x = note.x,
+                    y = note.y,
+                    width = note.width,
+                    height = note.height,
+                    text = note.text
+                );
             }
         }
     }
 
+    pub fn save_to_file(&mut self) {
+        self.save_to_path(Self::SAVE_FILE);
+    }
+
+    pub fn save_to_path(&mut self, path: &str) {
+        if let Err(e) = self.try_save_to_path(path) {
+            eprintln!("{}", e);
+        }
+    }
+
+    pub fn try_save_to_path(&mut self, path: &str) -> Result<(), String> {
+        integrity::write_checked(path, self).map_err(|e| t!("Failed to save state: {e}", e = e))?;
+        self.dirty = false;
+        say!("State saved");
+        Ok(())
+    }
+
     pub fn fill_to_border(&mut self, start_x: usize, start_y: usize, new_tile: Tile) {
         let original_tile = if let Some(tile) = self.get_tile(start_x, start_y) {
             tile.tile_type.clone()
@@ -217,6 +947,38 @@ impl TileSystem {
         if original_tile == new_tile.tile_type {
             return;
         }
+        self.push_undo();
+
+        for (x, y) in self.fill_cells(start_x, start_y, &new_tile.tile_type) {
+            self.put_tile(x, y, new_tile.clone());
+        }
+    }
+
+    // the cells fill_to_border would change, empty when the area already has the tile
+    pub fn fill_cells(
+        &self,
+        start_x: usize,
+        start_y: usize,
+        tile: &TileType,
+    ) -> Vec<(usize, usize)> {
+        match self.get_tile(start_x, start_y) {
+            Some(start) if &start.tile_type != tile => self.connected_cells(start_x, start_y, true),
+            _ => Vec::new(),
+        }
+    }
+
+    // the 4-connected area of the start cell's tile type, optionally stopping at locked cells
+    pub fn connected_cells(
+        &self,
+        start_x: usize,
+        start_y: usize,
+        skip_locked: bool,
+    ) -> Vec<(usize, usize)> {
+        let Some(original_tile) = self.get_tile(start_x, start_y).map(|t| t.tile_type.clone())
+        else {
+            return Vec::new();
+        };
+        let mut cells = Vec::new();
 
         let mut visited = vec![vec![false; self.grid_width]; self.grid_height];
 
@@ -228,7 +990,7 @@ impl TileSystem {
                 continue;
             }
 
-            if visited[x][y] {
+            if visited[y][x] || (skip_locked && self.is_locked(x, y)) {
                 continue;
             }
 
@@ -240,10 +1002,9 @@ impl TileSystem {
                 continue;
             }
 
-            visited[x][y] = true;
-            self.tiles[x][y] = new_tile.clone();
+            visited[y][x] = true;
+            cells.push((x, y));
 
-            //TODO: fix x and y flip flop thing.
             //left
             if x > 0 {
                 stack.push((x - 1, y));
@@ -261,6 +1022,7 @@ impl TileSystem {
                 stack.push((x, y + 1));
             }
         }
+        cells
     }
 
     pub fn grid_to_world(&self, grid_x: usize, grid_y: usize) -> (f64, f64) {
@@ -270,30 +1032,304 @@ impl TileSystem {
         )
     }
 
-    pub fn get_tile_at_pos(&self, world_x: f64, world_y: f64) -> Option<(usize, usize)> {
-        let grid_x = (world_x / self.tile_size) as usize;
-        let grid_y = (world_y / self.tile_size) as usize;
+    // rescales the map in place: the grid keeps its cells and the window its size,
+    // so bigger tiles zoom in and the camera pans over what no longer fits
+    pub fn set_tile_size(&mut self, tile_size: f64) -> f64 {
+        let tile_size = tile_size.clamp(Self::MIN_TILE_SIZE, Self::MAX_TILE_SIZE);
+        if tile_size != self.tile_size {
+            self.tile_size = tile_size;
+            self.dirty = true;
+        }
+        self.tile_size
+    }
+
+    // the grid is centred in the window; this is the margin left when the window
+    // isn't a whole number of tiles across
+    pub fn grid_offset(&self) -> (f64, f64) {
+        let spare_x = self.window_width - self.grid_width as f64 * self.tile_size;
+        let spare_y = self.window_height - self.grid_height as f64 * self.tile_size;
+        (spare_x.max(0.0) / 2.0, spare_y.max(0.0) / 2.0)
+    }
+
+    // takes window coordinates, so the margin around the grid is outside every cell
+    pub fn get_tile_at_pos(&self, window_x: f64, window_y: f64) -> Option<(usize, usize)> {
+        let (offset_x, offset_y) = self.grid_offset();
+        let grid_x = ((window_x - offset_x) / self.tile_size).floor();
+        let grid_y = ((window_y - offset_y) / self.tile_size).floor();
 
-        if grid_x < self.grid_width && grid_y < self.grid_height {
-            Some((grid_x, grid_y))
+        if grid_x >= 0.0
+            && grid_y >= 0.0
+            && (grid_x as usize) < self.grid_width
+            && (grid_y as usize) < self.grid_height
+        {
+            Some((grid_x as usize, grid_y as usize))
         } else {
             None
         }
     }
 
-    pub fn render(&self, c: Context, g: &mut G2d) {
-        for (y, row) in self.tiles.iter().enumerate() {
-            for (x, tile) in row.iter().enumerate() {
-                if tile.visible && tile.colour[3] > 0.0 {
+    pub fn render(&self, layers: &Layers, c: Context, g: &mut G2d) {
+        let (view, lod) = self.visible_cells(&c);
+        let terrain = layers.opacity(Layer::Terrain);
+        self.with_index(|index| {
+            index.visit(view, lod, &mut |x, y, w, h, tile| {
+                if tile.visible && tile.colour[3] > 0.0 && terrain > 0.0 {
                     let (world_x, world_y) = self.grid_to_world(x, y);
-
                     rectangle(
-                        tile.colour,
-                        [world_x, world_y, self.tile_size, self.tile_size],
+                        layers::faded(tile.colour, terrain),
+                        [
+                            world_x,
+                            world_y,
+                            w as f64 * self.tile_size,
+                            h as f64 * self.tile_size,
+                        ],
                         c.transform,
                         g,
                     );
                 }
+            });
+        });
+        self.render_rotations(terrain, c, g);
+        let in_view = |x: usize, y: usize| {
+            x >= view.x && y >= view.y && x < view.x + view.width && y < view.y + view.height
+        };
+
+        self.render_biomes(c, g);
+        self.render_features(layers.opacity(Layer::Decoration), c, g);
+        self.render_bias_preview(layers.opacity(Layer::Weights), c, g);
+
+        let locks = layers.opacity(Layer::Locks);
+        for &(x, y) in &self.locked_cells {
+            if in_view(x, y) && locks > 0.0 {
+                self.render_lock_hatch(x, y, locks, c, g);
+            }
+        }
+
+        for region in &self.training_regions {
+            let (world_x, world_y) = self.grid_to_world(region.x, region.y);
+            Rectangle::new_border([0.2, 0.9, 0.9, 0.8], 1.0).draw(
+                [
+                    world_x,
+                    world_y,
+                    region.width as f64 * self.tile_size,
+                    region.height as f64 * self.tile_size,
+                ],
+                &c.draw_state,
+                c.transform,
+                g,
+            );
+        }
+
+        // small corner tab on the top-left cell of each note
+        let annotations = layers.opacity(Layer::Annotations);
+        for note in self.annotations.iter().filter(|_| annotations > 0.0) {
+            let (world_x, world_y) = self.grid_to_world(note.x, note.y);
+            rectangle(
+                layers::faded([1.0, 0.9, 0.2, 0.9], annotations),
+                [world_x, world_y, self.tile_size / 4.0, self.tile_size / 4.0],
+                c.transform,
+                g,
+            );
+        }
+    }
+
+    fn render_lock_hatch(&self, x: usize, y: usize, opacity: f32, c: Context, g: &mut G2d) {
+        let (world_x, world_y) = self.grid_to_world(x, y);
+        let hatch_colour = layers::faded([0.0, 0.0, 0.0, 0.35], opacity);
+        let step = self.tile_size / 4.0;
+
+        for i in 1..4 {
+            let offset = step * i as f64;
+            line_from_to(
+                hatch_colour,
+                0.5,
+                [world_x + offset, world_y],
+                [world_x, world_y + offset],
+                c.transform,
+                g,
+            );
+            line_from_to(
+                hatch_colour,
+                0.5,
+                [world_x + self.tile_size, world_y + offset],
+                [world_x + offset, world_y + self.tile_size],
+                c.transform,
+                g,
+            );
+        }
+    }
+
+    pub fn reset_elevation(&mut self, seed: u64) {
+        self.elevation = erosion::elevation_from_tiles(&self.tile_types(), seed);
+        self.dirty = true;
+        say!("Elevation rebuilt from tiles");
+    }
+
+    pub fn erode(&mut self, seed: u64) {
+        let matches_grid = self.elevation.len() == self.grid_height
+            && self
+                .elevation
+                .iter()
+                .all(|row| row.len() == self.grid_width);
+        if !matches_grid {
+            self.reset_elevation(seed);
+        }
+        let settings = erosion::ErosionSettings::default();
+        erosion::hydraulic_erosion(&mut self.elevation, &settings, seed);
+        erosion::thermal_erosion(&mut self.elevation, &settings);
+        self.apply_elevation();
+        say!("Eroded terrain with seed {seed}", seed = seed);
+    }
+
+    // re-derive painted tiles from the elevation layer, Empty scratch cells stay empty
+    pub fn apply_elevation(&mut self) {
+        self.staged_update(|map, staged| map.classify_elevation(&map.elevation, staged));
+    }
+
+    // tiles for `elevation`, written into `staged`
+    fn classify_elevation(&self, elevation: &[Vec<f64>], staged: &mut TileGrid) {
+        for y in 0..self.grid_height {
+            for x in 0..self.grid_width {
+                let Some(height) = elevation.get(y).and_then(|row| row.get(x)) else {
+                    continue;
+                };
+                if self
+                    .get_tile(x, y)
+                    .is_some_and(|t| t.tile_type != TileType::Empty)
+                {
+                    staged.set(x, y, Tile::from_type(&erosion::classify(*height)));
+                }
+            }
+        }
+    }
+
+    pub fn add_training_region(&mut self, region: Region) {
+        say!(
+            "Training region at ({x}, {y}) {width}x{height}",
+            x = region.x,
+            y = region.y,
+            width = region.width,
+            height = region.height
+        );
+        self.training_regions.push(region);
+        self.dirty = true;
+    }
+
+    pub fn clear_training_regions(&mut self) {
+        self.training_regions.clear();
+        self.dirty = true;
+        say!("Training regions cleared");
+    }
+
+    // tile ids of locked cells, which generation has to keep
+    pub fn locked_tile_ids(&self) -> HashMap<(usize, usize), usize> {
+        let mut fixed = HashMap::new();
+        for &(x, y) in &self.locked_cells {
+            if let Some(tile) = self.get_tile(x, y) {
+                fixed.insert((x, y), tile.tile_type.index());
+            }
+        }
+        fixed
+    }
+
+    // writes the config's adjacency model at this map's size as DIMACS CNF or MiniZinc
+    pub fn export_constraints(&self, name: &str, minizinc: bool) -> Result<String, String> {
+        let rules = self.learn_rules(name)?;
+        let fixed = self.locked_tile_ids();
+        let model = constraint_export::ConstraintModel {
+            rules: &rules,
+            width: self.grid_width,
+            height: self.grid_height,
+            fixed: &fixed,
+        };
+
+        let stem = if name.is_empty() {
+            "combined_samples"
+        } else {
+            name
+        };
+        let (path, contents) = if minizinc {
+            (format!("{}.mzn", stem), model.to_minizinc())
+        } else {
+            (format!("{}.cnf", stem), model.to_dimacs())
+        };
+        fs::write(&path, contents)
+            .map_err(|e| t!("Failed to write {path}: {e}", path = path, e = e))?;
+        say!("Exported constraints to {path}", path = path);
+        Ok(path)
+    }
+
+    // fills the map from a saved config's adjacency rules with the constraint solver,
+    // keeping locked cells as they are
+    pub fn generate_with_csp(
+        &mut self,
+        name: &str,
+        seed: u64,
+        observer: &mut dyn GenerationObserver,
+    ) -> bool {
+        let rules = match self.learn_rules(name) {
+            Ok(rules) => rules,
+            Err(e) => {
+                println!("{}", e);
+                return false;
+            }
+        };
+        let states = self.state_table();
+        let fixed = self.locked_state_ids(&states);
+
+        let mut solver = csp::CspSolver::new(
+            &states.expand_rules(&rules),
+            states.len(),
+            self.grid_width,
+            self.grid_height,
+            seed,
+        );
+        solver.set_weighted_preferences(&states.expand_preferences(&self.bias_preferences()));
+        solver.set_targets(states.expand_targets(self.target_shares()));
+        solver.set_transition_costs(states.expand_costs(self.transition_cost_matrix()));
+        let result = solver.solve(&fixed, observer);
+        say!(
+            "{decisions} decisions, {backtracks} backtracks, {restarts} restarts",
+            decisions = solver.decisions,
+            backtracks = solver.backtracks,
+            restarts = solver.restarts
+        );
+        self.apply_generated_states(name, seed, result, &states, Backend::Sat)
+    }
+
+    // writes a solver result into the map as one undo step, locked cells untouched
+    pub fn apply_generated(
+        &mut self,
+        name: &str,
+        seed: u64,
+        result: Option<Vec<Vec<usize>>>,
+        backend: Backend,
+    ) -> bool {
+        let result = result
+            .map(|rows| self.enforce_symmetry(name, seed, rows))
+            .map(|rows| self.enforce_min_landmass(name, seed, rows))
+            .map(|rows| self.enforce_min_region_size(name, seed, rows));
+        match result {
+            Some(result) => {
+                self.backup_before_generation();
+                self.push_undo();
+                for (y, row) in result.iter().enumerate() {
+                    for (x, tile_id) in row.iter().enumerate() {
+                        self.set_tile(x, y, Tile::from_type(&TileType::ALL[*tile_id]));
+                    }
+                }
+                say!(
+                    "Generated from '{name}' with seed {seed}",
+                    name = name,
+                    seed = seed
+                );
+                self.report_targets();
+                self.remember_generation(name, seed, backend);
+                true
+            }
+            None => {
+                say!("No solution for '{name}' with these locks", name = name);
+                false
             }
         }
     }
@@ -310,13 +1346,14 @@ impl TileSystem {
 pub struct SuperpositionState {
     pub possible_tiles: HashSet<usize>,
     pub collapsed: bool,
-    pub entropy: usize,
+    // Shannon entropy of the remaining tiles, in nats
+    pub entropy: f64,
 }
 
 impl SuperpositionState {
     pub fn new(tile_count: usize) -> Self {
         let possible_tiles: HashSet<usize> = (0..tile_count).collect();
-        let entropy = possible_tiles.len();
+        let entropy = (possible_tiles.len() as f64).ln();
 
         Self {
             possible_tiles,
@@ -332,7 +1369,7 @@ impl SuperpositionState {
         Self {
             possible_tiles,
             collapsed: true,
-            entropy: 1,
+            entropy: 0.0,
         }
     }
 }
@@ -407,55 +1444,717 @@ pub fn sps_usage_test(input_grid: &Vec<Vec<TileType>>) {
         TileType::Coast => 3,
         TileType::Water => 4,
     };
-    let superposition_grid = build_adjacency_rules(input_grid, tile_to_id);
+    let superposition_grid = build_adjacency_rules(input_grid, &tile_to_id);
 
     //for row in spg, for col in row, DISPLAY>>> push through based on possibility?
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Wfc,
+    Sat,
 }
 
-fn main() {
-    let mut window: PistonWindow = WindowSettings::new("WaveFunctionCollapse", [512; 2])
-        .exit_on_esc(true)
-        .build()
-        .unwrap();
+// `--backend wfc|sat` picks the generator used by W
+fn parse_backend(name: &str) -> Backend {
+    match name {
+        "sat" => Backend::Sat,
+        _ => Backend::Wfc,
+    }
+}
 
-    let mut tile_system = TileSystem::load_or_new();
+fn cell_under(tile_system: &TileSystem, camera: &Camera, pos: [f64; 2]) -> Option<(usize, usize)> {
+    let [x, y] = camera.window_to_map(pos);
+    tile_system.get_tile_at_pos(x, y)
+}
 
-    let mut supr_state = SuperpositionState::new(256);
+// uniform scale and bar sizes that fit the map's window into the real one without
+// stretching it; the spare space on the longer side becomes black bars
+fn letterbox(tile_system: &TileSystem, window_size: [f64; 2]) -> (f64, [f64; 2]) {
+    let scale =
+        (window_size[0] / tile_system.window_width).min(window_size[1] / tile_system.window_height);
+    let bars = [
+        (window_size[0] - tile_system.window_width * scale) / 2.0,
+        (window_size[1] - tile_system.window_height * scale) / 2.0,
+    ];
+    (scale, bars)
+}
 
-    let mut mouse_pos = [0.0, 0.0];
+// starts under the mouse, then moves a cell per press
+fn step_cursor(
+    tile_system: &TileSystem,
+    cursor: Option<(usize, usize)>,
+    hovered: Option<(usize, usize)>,
+    direction: Direction,
+) -> (usize, usize) {
+    let Some((x, y)) = cursor else {
+        return hovered.unwrap_or((0, 0));
+    };
+    let (x, y) = match direction {
+        Direction::Up => (x, y.saturating_sub(1)),
+        Direction::Down => (x, y + 1),
+        Direction::Left => (x.saturating_sub(1), y),
+        Direction::Right => (x + 1, y),
+    };
+    (
+        x.min(tile_system.grid_width.saturating_sub(1)),
+        y.min(tile_system.grid_height.saturating_sub(1)),
+    )
+}
 
-    let mut selected_tile_type = TileType::Water;
+// the cells paint_with_brush would change, for the preview under the mouse;
+// sparse brushes may leave some of them alone
+fn brush_preview(
+    tile_system: &TileSystem,
+    panel: &SettingsPanel,
+    x: usize,
+    y: usize,
+    line_from: Option<(usize, usize)>,
+) -> BTreeSet<(usize, usize)> {
+    let style = if panel.decoration.is_some() {
+        // decorations always go down in a plain square
+        BrushStyle {
+            size: panel.brush.size,
+            ..BrushStyle::default()
+        }
+    } else {
+        panel.brush
+    };
+    let only = match &panel.brush_filter {
+        Some(spec) if panel.bias.is_none() && panel.decoration.is_none() => {
+            tile_system.resolve_tiles(spec).ok()
+        }
+        _ => None,
+    };
+    let centres = match line_from {
+        Some(from) => grid::line_cells(from, (x, y)),
+        None => vec![(x, y)],
+    };
+    centres
+        .into_iter()
+        .flat_map(|(cx, cy)| tile_system.brush_cells(cx, cy, &style))
+        .filter(|&(px, py)| {
+            only.as_ref().is_none_or(|only| {
+                tile_system
+                    .get_tile(px, py)
+                    .is_some_and(|t| only.contains(&t.tile_type))
+            })
+        })
+        .collect()
+}
 
-    // border pattern wall thing
-    for x in 0..tile_system.grid_width {
-        tile_system.set_tile(x, 0, Tile::mountain());
-        tile_system.set_tile(x, tile_system.grid_height - 1, Tile::mountain());
+// one stroke of the panel's brush, as a left click or Enter on the cursor does;
+// with `line_from` it paints a straight line from there instead
+fn paint_with_brush(
+    tile_system: &mut TileSystem,
+    panel: &SettingsPanel,
+    x: usize,
+    y: usize,
+    tile_type: &TileType,
+    line_from: Option<(usize, usize)>,
+) {
+    let only = match &panel.brush_filter {
+        Some(spec) => tile_system.resolve_tiles(spec).ok(),
+        None => None,
+    };
+    if let Some(strength) = panel.bias {
+        match line_from {
+            Some(from) => {
+                tile_system.paint_bias_line(from, x, y, &panel.brush, tile_type, strength)
+            }
+            None => tile_system.paint_bias(x, y, &panel.brush, tile_type, strength),
+        }
+        return;
     }
-    for y in 0..tile_system.grid_height {
-        tile_system.set_tile(0, y, Tile::mountain());
-        tile_system.set_tile(tile_system.grid_width - 1, y, Tile::mountain());
+    if let Some(brush) = panel.decoration {
+        match line_from {
+            Some(from) => tile_system.paint_decoration_line(from, x, y, panel.brush.size, brush),
+            None => tile_system.paint_decorations(x, y, panel.brush.size, brush),
+        }
+        return;
+    }
+    let tile = Tile::from_type(tile_type);
+    match line_from {
+        Some(from) => tile_system.paint_line(from, x, y, &panel.brush, tile, only.as_deref()),
+        None => tile_system.paint(x, y, &panel.brush, tile, only.as_deref()),
     }
+}
 
-    println!("Tile Controls:");
-    println!("1-5        -> Select tile type (Empty/Mountain/Land/Coast/Water)");
-    println!("Left click -> place a tile");
-    println!("L/S/P      -> Load/Save/Print Configuration");
-    println!("C          -> Clear map");
-    println!("ESC        -> Exit");
-    println!("Current tile: {:?}", selected_tile_type);
+pub fn time_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64)
+}
+
+// parses "WxH" region sizes typed at the console
+fn parse_region_size(input: &str) -> Option<(usize, usize)> {
+    let (w, h) = input.split_once(['x', 'X'])?;
+    Some((w.trim().parse().ok()?, h.trim().parse().ok()?))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+fn main() {
+    // no subcommand opens the editor, as it always has
+    let cli = Cli::parse();
+    i18n::init(cli.lang.as_deref());
+    let edit_args = match cli.command {
+        None => EditArgs::default(),
+        Some(Commands::Edit(args)) => args,
+        Some(Commands::Generate(args)) => std::process::exit(batch::run_headless(args)),
+        Some(Commands::Convert(args)) => std::process::exit(convert::run(args)),
+        Some(Commands::Serve(args)) => std::process::exit(serve::run(args)),
+        Some(Commands::Pipeline {
+            command: PipelineCommand::Run { file, size },
+        }) => std::process::exit(pipeline::run_headless(&file, &size)),
+        Some(Commands::Experiment(args)) => std::process::exit(experiment::run_headless(args)),
+        Some(Commands::Lint(args)) => std::process::exit(lint::run_headless(args)),
+        Some(Commands::Selfcheck(args)) => std::process::exit(selfcheck::run_headless(args)),
+        #[cfg(feature = "tui")]
+        Some(Commands::Tui(size)) => std::process::exit(tui::run(&size)),
+        #[cfg(not(feature = "tui"))]
+        Some(Commands::Tui(_)) => {
+            eprintln!(
+                "{}",
+                t!("Built without the terminal UI, rebuild with --features tui")
+            );
+            std::process::exit(2);
+        }
+    };
+
+    let backend = parse_backend(&edit_args.backend);
+
+    let mut app_config = AppConfig::load();
+    if let Err(e) = palette::apply(&app_config.palette) {
+        eprintln!("{}", e);
+    }
+    let mut audio = Audio::new(&app_config.audio);
+
+    // one editor per save; a second one gets the choice of opening read-only
+    let instance_lock = if edit_args.read_only {
+        None
+    } else {
+        match InstanceLock::acquire(TileSystem::SAVE_FILE) {
+            Ok(lock) => Some(lock),
+            Err(e) => {
+                use std::io::{self, Write};
+                println!("{}", e);
+                print!("{}", t!("Open read-only instead? [y/N]: "));
+                io::stdout().flush().unwrap();
+                let mut answer = String::new();
+                io::stdin().read_line(&mut answer).ok();
+                if !answer.trim().eq_ignore_ascii_case("y") {
+                    std::process::exit(1);
+                }
+                None
+            }
+        }
+    };
+    let read_only = instance_lock.is_none();
+    let mut title = edit_args
+        .title
+        .clone()
+        .unwrap_or_else(|| app_config.window_title.clone());
+
+    let mut workspace = Workspace::load(&edit_args.size);
+    workspace.read_only = read_only;
+    let library_dir = edit_args
+        .library
+        .clone()
+        .unwrap_or_else(|| app_config.library_dir.clone());
+    workspace.scan_library(&library_dir);
+    workspace.recolour();
+    if let Some(tile_size) = app_config.tile_size {
+        workspace.set_tile_size(tile_size);
+    }
+
+    // the window fits the first map's grid rather than the grid fitting the window
+    let first = workspace.current();
+    let window_size = [first.window_width as u32, first.window_height as u32];
+    let mut window: PistonWindow = WindowSettings::new(
+        instance::window_title(&title, read_only, false),
+        window_size,
+    )
+    .exit_on_esc(false)
+    .build()
+    .unwrap();
+    window.set_ups(worker::UPDATES_PER_SECOND);
+
+    let mut glyphs = match window.load_font(ui::FONT_PATH) {
+        Ok(glyphs) => Some(glyphs),
+        Err(e) => {
+            eprintln!(
+                "{}",
+                t!(
+                    "Failed to load font {font}: {e}",
+                    font = ui::FONT_PATH,
+                    e = e
+                )
+            );
+            None
+        }
+    };
+    let mut panel = SettingsPanel::new(app_config.ui_scale.clamp(0.5, 4.0));
+
+    // window size over map size; mouse input and drawing both use logical window
+    // coordinates, so this also absorbs the hidpi factor and window resizes
+    let mut view_scale = 1.0;
+    let mut view_bars = [0.0, 0.0];
+
+    let mut supr_state = SuperpositionState::new(256);
+
+    let mut mouse_pos = [0.0, 0.0];
+
+    // pinch zoom and two-finger pan of the map
+    let mut camera = Camera::default();
+    let mut touches = TouchTracker::default();
+    let mut last_stroke_cell: Option<(usize, usize)> = None;
+
+    let mut selected_tile_type = TileType::Water;
+
+    let mut hovered_cell: Option<(usize, usize)> = None;
+
+    // keyboard editing position, shown once an arrow key is pressed
+    let mut cursor: Option<(usize, usize)> = None;
+
+    let mut ctrl_held = false;
+
+    // held Alt turns left click into the eyedropper
+    let mut alt_held = false;
+
+    // Shift+click draws a line from the last painted cell
+    let mut shift_held = false;
+    // overlay of the latest propagation wave while a job runs
+    let mut show_wave = false;
+    let mut last_painted: Option<(usize, usize)> = None;
+
+    // constraint-backend generation running on a worker thread
+    let mut job: Option<GenerationJob> = None;
+    // contradiction counts over repeated runs; owns `job` while it still has runs to make
+    let mut heatmap: Option<ContradictionHeatmap> = None;
+
+    // destructive action waiting for Y/N
+    let mut pending: Option<Confirm> = None;
+
+    // replayable commands go through here so Ctrl+R can record them
+    let mut recorder = MacroRecorder::default();
+
+    // Ctrl+G seed bookmark gallery
+    let mut show_gallery = false;
+
+    // F1 cheat sheet
+    let mut show_keymap = false;
+
+    // Ctrl+O list of recent configs and maps while it is open
+    let mut quick_open: Option<Vec<QuickEntry>> = None;
+
+    // Shift+M model inspector
+    let mut inspector: Option<Inspector> = None;
+
+    // first corner of a training region being marked with T
+    let mut region_corner: Option<(usize, usize)> = None;
+
+    // area Ctrl+Shift+C copies to the OS clipboard, marked with O; the whole map without one
+    let mut selection: Option<Region> = None;
+    let mut wand: Option<wand::CellSet> = None;
+    let mut selection_corner: Option<(usize, usize)> = None;
+    // Shift+right-click shows what a fill would cover; releasing the button fills,
+    // letting go of Shift first cancels
+    let mut fill_preview: Option<FillPreview> = None;
+    // the last Shift+V lint's findings, stepped through with Shift+N
+    let mut lint_findings: Vec<lint::Finding> = Vec::new();
+    let mut next_finding = 0;
+    let mut system_clipboard = SystemClipboard::new();
+
+    say!("Press F1 in the window for the controls");
+    say!(
+        "Current tile: {selected_tile_type}",
+        selected_tile_type = format!("{:?}", selected_tile_type)
+    );
+
+    let watcher = if edit_args.watch {
+        FileWatcher::start()
+    } else {
+        None
+    };
+
+    // title shows a trailing * while anything is unsaved
+    let mut title_dirty = false;
 
     while let Some(event) = window.next() {
+        // background work moves on at the fixed update rate, not once per input or frame
+        let update = event.update_args();
+        if let Some(args) = update
+            && let Some(running) = &mut job
+        {
+            match heatmap.as_mut().filter(|h| h.is_running()) {
+                // heatmap runs go at full speed and are never applied to the map
+                Some(survey) => {
+                    if let Some(result) = running.poll(args.dt, None, survey) {
+                        let map = &workspace.maps[survey.map_index];
+                        job = survey.next_run(map, result.is_some(), &app_config);
+                    }
+                }
+                None => {
+                    if let Some(result) =
+                        running.poll(args.dt, app_config.generation_speed(), &mut audio)
+                    {
+                        running.finish(&mut workspace.maps[running.map_index], result);
+                        job = None;
+                    }
+                }
+            }
+        }
+
+        for reload in update
+            .and(watcher.as_ref())
+            .into_iter()
+            .flat_map(FileWatcher::poll)
+        {
+            match reload {
+                Reload::Config => {
+                    app_config = AppConfig::load();
+                    audio.muted = app_config.audio.muted;
+                    audio.volume = app_config.audio.volume.clamp(0.0, 1.0);
+                    panel.ui_scale = app_config.ui_scale.clamp(0.5, 4.0);
+                    if let Some(tile_size) = app_config.tile_size {
+                        workspace.set_tile_size(tile_size);
+                    }
+                    match palette::apply(&app_config.palette) {
+                        Ok(()) => workspace.recolour(),
+                        Err(e) => println!("{}", e),
+                    }
+                    if edit_args.title.is_none() {
+                        title = app_config.window_title.clone();
+                        window.set_title(instance::window_title(&title, read_only, title_dirty));
+                    }
+                    say!("Reloaded {file}", file = AppConfig::CONFIG_FILE);
+                }
+                Reload::Templates => workspace.reload_templates(),
+            }
+        }
+
+        let (active_map, map_count) = (workspace.active, workspace.maps.len());
+        let dirty = workspace.is_dirty();
+        if dirty != title_dirty {
+            title_dirty = dirty;
+            window.set_title(instance::window_title(&title, read_only, dirty));
+        }
+        let tile_system = workspace.current();
         match event {
             Event::Input(Input::Move(Motion::MouseCursor(pos)), _) => {
-                mouse_pos = pos;
+                mouse_pos = [
+                    (pos[0] - view_bars[0]) / view_scale,
+                    (pos[1] - view_bars[1]) / view_scale,
+                ];
+                let cell = cell_under(tile_system, &camera, mouse_pos);
+                if cell != hovered_cell {
+                    hovered_cell = cell;
+                    if fill_preview.is_some() {
+                        fill_preview = cell.map(|(x, y)| FillPreview {
+                            start: (x, y),
+                            cells: tile_system.fill_cells(x, y, &selected_tile_type),
+                        });
+                    }
+                    if let Some((x, y)) = cell {
+                        for note in tile_system.annotations_at(x, y) {
+                            say!("Note: {text}", text = note.text);
+                        }
+                    }
+                }
+            }
+            Event::Input(
+                Input::Button(ButtonArgs {
+                    state: ButtonState::Press,
+                    button: Button::Keyboard(key),
+                    ..
+                }),
+                _,
+            ) if pending.is_some() => match (key, pending.take()) {
+                (Key::Y, Some(action)) => match action {
+                    Confirm::ClearMap => {
+                        recorder.run(tile_system, Command::Clear);
+                    }
+                    Confirm::DeleteConfig(name) => {
+                        if tile_system.delete_config(&name).is_err() {
+                            audio.play(Cue::Error);
+                        }
+                    }
+                    Confirm::OverwriteConfig(name) => {
+                        tile_system.save_config(name);
+                        audio.play(Cue::Save);
+                    }
+                    Confirm::OpenFile(path) => {
+                        if !workspace.open_file(&path) {
+                            audio.play(Cue::Error);
+                        }
+                    }
+                    // the map is left alone unless the job can start
+                    Confirm::RegenerateFrom(_) if job.is_some() => {
+                        say!("A generation is already running");
+                        audio.play(Cue::Error);
+                    }
+                    Confirm::RegenerateFrom(path) => {
+                        let restored = TileSystem::read_png_stamp(&path)
+                            .and_then(|stamp| {
+                                stamp.ok_or_else(|| t!("That image has no generation settings"))
+                            })
+                            .and_then(|stamp| tile_system.restore_stamp(stamp));
+                        match restored.and_then(|(model, seed, backend)| {
+                            GenerationJob::spawn(
+                                tile_system,
+                                active_map,
+                                model,
+                                seed,
+                                backend,
+                                &app_config,
+                                edit_args.trace.as_deref(),
+                            )
+                        }) {
+                            Ok(spawned) => job = Some(spawned),
+                            Err(e) => {
+                                println!("{}", e);
+                                audio.play(Cue::Error);
+                            }
+                        }
+                    }
+                    Confirm::Exit => {
+                        workspace.save_all();
+                        window.set_should_close(true);
+                    }
+                },
+                (Key::N, Some(Confirm::Exit)) => {
+                    say!("Exiting without saving");
+                    window.set_should_close(true);
+                }
+                (Key::N | Key::Escape, _) => {
+                    say!("Cancelled");
+                }
+                (_, action) => {
+                    pending = action;
+                }
+            },
+            Event::Input(
+                Input::Button(ButtonArgs {
+                    state: ButtonState::Press,
+                    button: Button::Keyboard(key),
+                    ..
+                }),
+                _,
+            ) if quick_open.is_some() => {
+                // any key other than a listed digit just closes the list
+                let entries = quick_open.take().unwrap_or_default();
+                let digit = (Key::D1 as usize..=Key::D9 as usize).contains(&(key as usize));
+                match entries.get((key as usize).wrapping_sub(Key::D1 as usize)) {
+                    Some(QuickEntry::Config(name)) if digit => {
+                        if recorder.run(tile_system, Command::LoadConfig(name.clone())) {
+                            app_config.remember_config(name);
+                        } else {
+                            audio.play(Cue::Error);
+                        }
+                    }
+                    Some(QuickEntry::Map(index)) if digit => {
+                        workspace.switch_to(*index);
+                    }
+                    _ => {}
+                }
+            }
+            Event::Input(
+                Input::Button(ButtonArgs {
+                    state: ButtonState::Press,
+                    button: Button::Keyboard(key @ (Key::Escape | Key::S | Key::Slash | Key::M)),
+                    ..
+                }),
+                _,
+            ) if !shift_held
+                && !ctrl_held
+                && let Some(open) = inspector.as_mut() =>
+            {
+                use std::io::{self, Write};
+                let ask = |question: String| {
+                    print!("{}", question);
+                    io::stdout().flush().unwrap();
+                    let mut answer = String::new();
+                    io::stdin().read_line(&mut answer).ok();
+                    answer.trim().to_string()
+                };
+                match key {
+                    Key::S => open.cycle_sort(tile_system),
+                    Key::Slash => {
+                        open.filter = ask(t!("Show tiles matching (blank for all): "));
+                        open.refresh(tile_system);
+                    }
+                    Key::M => {
+                        tile_system.list_configs();
+                        open.model = ask(t!("Model to inspect (blank for enabled samples): "));
+                        open.refresh(tile_system);
+                    }
+                    _ => inspector = None,
+                }
+            }
+            Event::Input(Input::FileDrag(FileDrag::Drop(path)), _) if pending.is_none() => {
+                let extension = path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .unwrap_or_default()
+                    .to_lowercase();
+                match extension.as_str() {
+                    "json" => pending = Some(Confirm::OpenFile(path.display().to_string()).ask()),
+                    // only images with a stamp get as far as asking
+                    "png" if job.is_some() => {
+                        say!("A generation is already running");
+                        audio.play(Cue::Error);
+                    }
+                    "png" => match TileSystem::read_png_stamp(&path.display().to_string()) {
+                        Ok(Some(_)) => {
+                            pending =
+                                Some(Confirm::RegenerateFrom(path.display().to_string()).ask());
+                        }
+                        Ok(None) => {
+                            say!("That image has no generation settings");
+                            audio.play(Cue::Error);
+                        }
+                        Err(e) => {
+                            println!("{}", e);
+                            audio.play(Cue::Error);
+                        }
+                    },
+                    "tmx" => {
+                        say!(
+                            "Importing .{extension} files isn't supported yet",
+                            extension = extension
+                        );
+                        audio.play(Cue::Error);
+                    }
+                    _ => {
+                        say!("Don't know how to open {path}", path = path.display());
+                        audio.play(Cue::Error);
+                    }
+                }
+            }
+            Event::Input(
+                Input::Button(ButtonArgs {
+                    state: ButtonState::Press,
+                    button: button @ (Button::Controller(_) | Button::Hat(_)),
+                    ..
+                }),
+                _,
+            ) if pending.is_none() => match app_config.gamepad.action(&button) {
+                Some(PadAction::Move(direction)) => {
+                    cursor = Some(step_cursor(tile_system, cursor, hovered_cell, direction));
+                }
+                Some(action @ (PadAction::PreviousTile | PadAction::NextTile)) => {
+                    selected_tile_type = selected_tile_type.cycled(action == PadAction::NextTile);
+                    say!(
+                        "Selected: {selected_tile_type} tile",
+                        selected_tile_type = format!("{:?}", selected_tile_type)
+                    );
+                }
+                Some(PadAction::Place) => {
+                    let (x, y) = *cursor.get_or_insert(hovered_cell.unwrap_or((0, 0)));
+                    paint_with_brush(tile_system, &panel, x, y, &selected_tile_type, None);
+                }
+                Some(PadAction::Fill) => {
+                    let (x, y) = *cursor.get_or_insert(hovered_cell.unwrap_or((0, 0)));
+                    tile_system.fill_to_border(x, y, Tile::from_type(&selected_tile_type));
+                }
+                Some(PadAction::Generate) if job.is_none() => {
+                    let model = tile_system
+                        .last_generation
+                        .as_ref()
+                        .map(|last| last.model.clone())
+                        .unwrap_or_default();
+                    match GenerationJob::spawn(
+                        tile_system,
+                        active_map,
+                        model.clone(),
+                        time_seed(),
+                        backend,
+                        &app_config,
+                        edit_args.trace.as_deref(),
+                    ) {
+                        Ok(spawned) => {
+                            recorder.record(Command::Generate(Generation { model, backend }));
+                            job = Some(spawned);
+                        }
+                        Err(e) => {
+                            println!("{}", e);
+                            audio.play(Cue::Error);
+                        }
+                    }
+                }
+                Some(PadAction::Generate) => say!("A generation is already running"),
+                None => {}
+            },
+            Event::Input(Input::Move(Motion::Touch(args)), _) if pending.is_none() => {
+                let pos = [
+                    (args.position()[0] - view_bars[0]) / view_scale,
+                    (args.position()[1] - view_bars[1]) / view_scale,
+                ];
+                match touches.handle(&args, pos) {
+                    // taps on the panel are left to the platform's mouse emulation
+                    Some(Gesture::Paint { pos, .. }) if panel.contains(tile_system, pos) => {}
+                    Some(Gesture::Paint { pos, stroke }) => {
+                        let cell = cell_under(tile_system, &camera, pos);
+                        if let Some((x, y)) = cell
+                            && (!stroke || last_stroke_cell != cell)
+                        {
+                            if stroke && let Some(strength) = panel.bias {
+                                tile_system.paint_bias_stroke(
+                                    x,
+                                    y,
+                                    &panel.brush,
+                                    &selected_tile_type,
+                                    strength,
+                                );
+                            } else if stroke && let Some(brush) = panel.decoration {
+                                tile_system.paint_decoration_stroke(x, y, panel.brush.size, brush);
+                            } else if stroke {
+                                let only = panel
+                                    .brush_filter
+                                    .as_ref()
+                                    .and_then(|spec| tile_system.resolve_tiles(spec).ok());
+                                tile_system.paint_stroke(
+                                    x,
+                                    y,
+                                    &panel.brush,
+                                    Tile::from_type(&selected_tile_type),
+                                    only.as_deref(),
+                                );
+                            } else {
+                                paint_with_brush(
+                                    tile_system,
+                                    &panel,
+                                    x,
+                                    y,
+                                    &selected_tile_type,
+                                    None,
+                                );
+                            }
+                            last_stroke_cell = cell;
+                        }
+                    }
+                    Some(Gesture::PinchPan {
+                        centre,
+                        factor,
+                        delta,
+                    }) => {
+                        camera.pan(delta);
+                        camera.zoom_about(centre, factor);
+                    }
+                    None => {}
+                }
+            }
+            Event::Input(Input::Close(_), _) if dirty => {
+                window.set_should_close(false);
+                pending = Some(Confirm::Exit.ask());
             }
             Event::Input(
                 Input::Button(ButtonArgs {
@@ -465,72 +2164,1438 @@ fn main() {
                 }),
                 _,
             ) => match key {
+                Key::LCtrl | Key::RCtrl => {
+                    ctrl_held = true;
+                }
+                Key::LAlt | Key::RAlt => {
+                    alt_held = true;
+                }
+                Key::LShift | Key::RShift => {
+                    shift_held = true;
+                }
+                Key::Up | Key::Down | Key::Left | Key::Right => {
+                    let direction = match key {
+                        Key::Up => Direction::Up,
+                        Key::Down => Direction::Down,
+                        Key::Left => Direction::Left,
+                        _ => Direction::Right,
+                    };
+                    cursor = Some(step_cursor(tile_system, cursor, hovered_cell, direction));
+                }
+                Key::Home if !camera.is_default() => {
+                    camera = Camera::default();
+                    say!("View reset");
+                }
+                Key::Return | Key::Space => {
+                    if let Some((x, y)) = cursor {
+                        let from = last_painted.filter(|_| shift_held);
+                        paint_with_brush(tile_system, &panel, x, y, &selected_tile_type, from);
+                        last_painted = cursor;
+                    }
+                }
+                Key::Escape if dirty => {
+                    pending = Some(Confirm::Exit.ask());
+                }
+                Key::Escape => {
+                    window.set_should_close(true);
+                }
+                Key::S
+                    if ctrl_held
+                        && shift_held
+                        && heatmap.as_ref().is_some_and(|h| h.is_running()) =>
+                {
+                    say!("A generation is already running");
+                }
+                // a running generation is saved to a file, otherwise one is taken up again
+                Key::S if ctrl_held && shift_held => {
+                    use std::io::{self, Write};
+                    let prompt = if job.is_some() {
+                        t!(
+                            "Save the generation to (blank for {file}): ",
+                            file = resume::DEFAULT_FILE
+                        )
+                    } else {
+                        t!(
+                            "Resume the generation saved in (blank for {file}): ",
+                            file = resume::DEFAULT_FILE
+                        )
+                    };
+                    print!("{}", prompt);
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok() {
+                        let path = match input.trim() {
+                            "" => resume::DEFAULT_FILE.to_string(),
+                            path => path.to_string(),
+                        };
+                        let started = match job.as_mut() {
+                            Some(running) => running.suspend(path),
+                            None => resume::read(&path)
+                                .and_then(|saved| {
+                                    GenerationJob::resume(tile_system, active_map, saved)
+                                })
+                                .map(|resumed| job = Some(resumed)),
+                        };
+                        if let Err(e) = started {
+                            println!("{}", e);
+                            audio.play(Cue::Error);
+                        }
+                    }
+                }
+                Key::S if ctrl_held => {
+                    workspace.save_all();
+                    audio.play(Cue::Save);
+                }
+                Key::O if ctrl_held => {
+                    let current = &workspace.maps[workspace.active];
+                    let configs = app_config
+                        .recent_configs
+                        .iter()
+                        .filter(|name| current.config(name).is_some())
+                        .map(|name| QuickEntry::Config(name.clone()));
+                    let maps = workspace
+                        .recent_maps
+                        .iter()
+                        .filter(|&&index| index != workspace.active)
+                        .map(|&index| QuickEntry::Map(index));
+                    let entries: Vec<QuickEntry> =
+                        configs.chain(maps).take(AppConfig::RECENT_LIMIT).collect();
+                    if entries.is_empty() {
+                        say!("Nothing recent yet");
+                    } else {
+                        for (i, entry) in entries.iter().enumerate() {
+                            println!("{}  {}", i + 1, entry.label());
+                        }
+                        quick_open = Some(entries);
+                    }
+                }
+                Key::Tab => {
+                    workspace.next_map();
+                }
+                Key::M if shift_held => {
+                    inspector = match inspector {
+                        Some(_) => None,
+                        None => {
+                            let model = tile_system
+                                .last_generation
+                                .as_ref()
+                                .map(|last| last.model.clone())
+                                .unwrap_or_default();
+                            Some(Inspector::open(tile_system, model))
+                        }
+                    };
+                }
+                Key::M if ctrl_held => {
+                    app_config.audio.muted = audio.toggle_mute();
+                    app_config.save();
+                }
+                Key::M => {
+                    use std::io::{self, Write};
+                    workspace.list_templates();
+                    print!("{}", t!("Enter template for new map (blank for empty): "));
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok() {
+                        workspace.open_new_map(input.trim());
+                    }
+                }
+                Key::C if ctrl_held && shift_held => {
+                    let region = selection.unwrap_or(Region {
+                        x: 0,
+                        y: 0,
+                        width: tile_system.grid_width,
+                        height: tile_system.grid_height,
+                    });
+                    let fragment = tile_system.copy_fragment(region);
+                    match system_clipboard.set(fragment.to_json()) {
+                        Ok(()) => say!(
+                            "Copied a {width}x{height} fragment to the clipboard",
+                            width = fragment.width,
+                            height = fragment.height
+                        ),
+                        Err(e) => {
+                            println!("{}", e);
+                            audio.play(Cue::Error);
+                        }
+                    }
+                }
+                Key::V if ctrl_held && shift_held => {
+                    // lands at the selection, else the keyboard cursor, else the mouse
+                    let (x, y) = selection
+                        .map(|region| (region.x, region.y))
+                        .or(cursor)
+                        .or(hovered_cell)
+                        .unwrap_or((0, 0));
+                    match system_clipboard
+                        .get()
+                        .and_then(|text| Fragment::parse(&text))
+                    {
+                        Ok(fragment) => tile_system.paste_fragment(&fragment, x, y),
+                        Err(e) => {
+                            println!("{}", e);
+                            audio.play(Cue::Error);
+                        }
+                    }
+                }
+                Key::C if shift_held => {
+                    use std::io::{self, Write};
+                    print!(
+                        "{}",
+                        t!(
+                            "Palette: \"import <file>\" or \"export <file>\", .gpl or .json (blank to skip): "
+                        )
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok() {
+                        let result = match input.trim().split_once(' ') {
+                            Some(("import", path)) => {
+                                palette::load(path.trim()).and_then(|imported| {
+                                    palette::apply(&imported)?;
+                                    app_config.palette = imported;
+                                    app_config.save();
+                                    workspace.recolour();
+                                    say!("Imported the palette from {path}", path = path.trim());
+                                    Ok(())
+                                })
+                            }
+                            Some(("export", path)) => {
+                                palette::save(path.trim(), &palette::current()).map(|()| {
+                                    say!("Exported the palette to {path}", path = path.trim())
+                                })
+                            }
+                            None if input.trim().is_empty() => Ok(()),
+                            _ => Err(t!("Expected \"import <file>\" or \"export <file>\"")),
+                        };
+                        if let Err(e) = result {
+                            println!("{}", e);
+                            audio.play(Cue::Error);
+                        }
+                    }
+                }
+                Key::C if ctrl_held => {
+                    workspace.copy();
+                }
+                Key::V if ctrl_held => {
+                    workspace.paste();
+                }
+                Key::V if shift_held => {
+                    let model = tile_system
+                        .last_generation
+                        .as_ref()
+                        .map(|last| last.model.clone())
+                        .unwrap_or_default();
+                    match tile_system.lint(&model) {
+                        Ok(findings) => {
+                            TileSystem::print_lint(&findings);
+                            if !findings.is_empty() {
+                                say!("Shift+N jumps to each finding in turn");
+                            }
+                            lint_findings = findings;
+                            next_finding = 0;
+                        }
+                        Err(e) => {
+                            println!("{}", e);
+                            audio.play(Cue::Error);
+                        }
+                    }
+                }
+                Key::Z if ctrl_held => {
+                    recorder.run(tile_system, Command::Undo);
+                }
+                Key::R if ctrl_held && shift_held => {
+                    if let Some(cell) = cursor.or(hovered_cell) {
+                        let model = tile_system
+                            .last_generation
+                            .as_ref()
+                            .map(|last| last.model.clone())
+                            .unwrap_or_default();
+                        if let Err(e) = tile_system.reroll_around(
+                            cell,
+                            app_config.reroll_size,
+                            &model,
+                            time_seed(),
+                        ) {
+                            println!("{}", e);
+                            audio.play(Cue::Error);
+                        }
+                    }
+                }
+                Key::R if shift_held => {
+                    use std::io::{self, Write};
+                    print!(
+                        "{}",
+                        t!(
+                            "Smallest allowed region, \"<tiles> <cells>\" (e.g. \"water,mountain 4\") or \"off\" (blank to skip): "
+                        )
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok()
+                        && !input.trim().is_empty()
+                        && let Err(e) = tile_system.set_min_region_size(input.trim())
+                    {
+                        println!("{}", e);
+                    }
+                }
+                Key::R if ctrl_held => {
+                    if !recorder.is_recording() {
+                        recorder.start();
+                    } else {
+                        use std::io::{self, Write};
+                        let commands = recorder.stop();
+                        print!("{}", t!("Name for the macro (blank discards it): "));
+                        io::stdout().flush().unwrap();
+                        let mut name = String::new();
+                        io::stdin().read_line(&mut name).ok();
+                        print!("{}", t!("Bind to F2-F9 (2-9, blank for none): "));
+                        io::stdout().flush().unwrap();
+                        let mut key = String::new();
+                        io::stdin().read_line(&mut key).ok();
+                        let key = key.trim().parse().ok().filter(|k| (2..=9).contains(k));
+                        if name.trim().is_empty() || commands.is_empty() {
+                            say!("Macro discarded");
+                        } else {
+                            say!("Saved macro '{name}'", name = name.trim());
+                            app_config.add_macro(Macro {
+                                name: name.trim().to_string(),
+                                key,
+                                commands,
+                            });
+                        }
+                    }
+                }
+                Key::F2 | Key::F3 | Key::F4 | Key::F5 | Key::F6 | Key::F7 | Key::F8 | Key::F9 => {
+                    let number = (key as usize - Key::F1 as usize + 1) as u8;
+                    match app_config.macros.iter().find(|m| m.key == Some(number)) {
+                        Some(recorded) => recorder.play(tile_system, recorded),
+                        None => say!(
+                            "No macro on F{number}, record one with Ctrl+R",
+                            number = number
+                        ),
+                    }
+                }
+                Key::D1
+                | Key::D2
+                | Key::D3
+                | Key::D4
+                | Key::D5
+                | Key::D6
+                | Key::D7
+                | Key::D8
+                | Key::D9
+                    if ctrl_held =>
+                {
+                    let index = key as usize - Key::D1 as usize;
+                    workspace.switch_to(index);
+                }
+                Key::D1
+                | Key::D2
+                | Key::D3
+                | Key::D4
+                | Key::D5
+                | Key::D6
+                | Key::D7
+                | Key::D8
+                | Key::D9
+                    if shift_held =>
+                {
+                    let slot = key as usize - Key::D1 as usize;
+                    match tile_system.brushes.get(slot) {
+                        Some(brush) => {
+                            selected_tile_type = brush.tile.clone();
+                            panel.brush = brush.style;
+                            panel.brush_filter = brush.filter.clone();
+                            panel.decoration = None;
+                            say!("Brush {slot}: {name}", slot = slot + 1, name = brush.name);
+                        }
+                        None => say!(
+                            "Brush slot {slot} is empty, Shift+B saves one",
+                            slot = slot + 1
+                        ),
+                    }
+                }
                 Key::D1 => {
                     selected_tile_type = TileType::Empty;
-                    println!("Selected: Empty tile");
+                    panel.decoration = None;
+                    say!("Selected: Empty tile");
+                }
+                Key::D2 => {
+                    selected_tile_type = TileType::Mountain;
+                    panel.decoration = None;
+                    say!("Selected: Mountain tile");
+                }
+                Key::D3 => {
+                    selected_tile_type = TileType::Land;
+                    panel.decoration = None;
+                    say!("Selected: Land tile");
+                }
+                Key::D4 => {
+                    selected_tile_type = TileType::Coast;
+                    panel.decoration = None;
+                    say!("Selected: Coast tile");
+                }
+                Key::D5 => {
+                    selected_tile_type = TileType::Water;
+                    panel.decoration = None;
+                    say!("Selected: Water tile");
+                }
+                Key::S => {
+                    use std::io::{self, Write};
+                    print!("{}", t!("Enter name for saved configuration: "));
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok() {
+                        let name = input.trim().to_string();
+                        if tile_system.saved_configs.contains_key(&name) {
+                            pending = Some(Confirm::OverwriteConfig(name).ask());
+                        } else if !name.is_empty() {
+                            tile_system.save_config(name);
+                            audio.play(Cue::Save);
+                        }
+                    }
+                }
+                Key::L if shift_held => {
+                    let layer = panel.layers.cycle_active();
+                    say!(
+                        "Active layer: {layer}",
+                        layer = panel.layers.describe(layer)
+                    );
+                }
+                Key::L if ctrl_held => {
+                    use std::io::{self, Write};
+                    print!("{}", t!("Pipeline file to run: "));
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok()
+                        && let Err(e) = Pipeline::load(input.trim())
+                            .and_then(|pipeline| tile_system.run_pipeline(&pipeline))
+                    {
+                        eprintln!("{}", e);
+                        audio.play(Cue::Error);
+                    }
+                }
+                Key::L => {
+                    use std::io::{self, Write};
+                    tile_system.list_configs();
+                    print!("{}", t!("Enter name of configuration to load: "));
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok() {
+                        let name = input.trim();
+                        if tile_system.config_size(name).is_some_and(|size| {
+                            size != (tile_system.grid_width, tile_system.grid_height)
+                        }) {
+                            print!(
+                                "{}",
+                                t!(
+                                    "Sizes differ; crop, center, scale or fit (blank for {resize_policy}): ",
+                                    resize_policy = format!("{:?}", tile_system.resize_policy)
+                                )
+                            );
+                            io::stdout().flush().unwrap();
+                            let mut policy = String::new();
+                            io::stdin().read_line(&mut policy).ok();
+                            if let Err(e) = tile_system.set_resize_policy(policy.trim()) {
+                                println!("{}", e);
+                            }
+                        }
+                        if recorder.run(tile_system, Command::LoadConfig(name.to_string())) {
+                            app_config.remember_config(name);
+                        } else {
+                            audio.play(Cue::Error);
+                        }
+                    }
+                }
+                Key::D | Key::N if ctrl_held => {
+                    use std::io::{self, Write};
+                    let ask = |question: &str| {
+                        print!("{}", question);
+                        io::stdout().flush().unwrap();
+                        let mut answer = String::new();
+                        io::stdin().read_line(&mut answer).ok();
+                        answer.trim().to_string()
+                    };
+                    tile_system.list_configs();
+                    let name = ask("Enter configuration: ");
+                    let result = if key == Key::N {
+                        let new_name = ask("Rename to: ");
+                        tile_system
+                            .rename_config(&name, &new_name)
+                            .map(|_| app_config.rename_recent(&name, &new_name))
+                    } else {
+                        let default_copy = format!("{} copy", name);
+                        let copy_name = ask(&format!(
+                            "Name of the copy (blank for '{}'): ",
+                            default_copy
+                        ));
+                        let copy_name = if copy_name.is_empty() {
+                            default_copy
+                        } else {
+                            copy_name
+                        };
+                        tile_system.duplicate_config(&name, &copy_name)
+                    };
+                    if let Err(e) = result {
+                        println!("{}", e);
+                        audio.play(Cue::Error);
+                    }
+                }
+                Key::D => {
+                    use std::io::{self, Write};
+                    tile_system.list_configs();
+                    print!("{}", t!("Enter name of configuration to delete: "));
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok() {
+                        let name = input.trim();
+                        if tile_system.saved_configs.contains_key(name) {
+                            pending = Some(Confirm::DeleteConfig(name.to_string()).ask());
+                        } else if tile_system.delete_config(name).is_err() {
+                            audio.play(Cue::Error);
+                        }
+                    }
+                }
+                Key::C => {
+                    pending = Some(Confirm::ClearMap.ask());
+                }
+                Key::P if ctrl_held && shift_held => {
+                    use std::io::{self, Write};
+                    let presets = presets::load_presets();
+                    tile_system.list_presets(&presets);
+                    print!(
+                        "{}",
+                        t!(
+                            "Presets to apply, e.g. \"bordered_island width=3 + river_crossing\", or \"off\" (blank to skip): "
+                        )
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok()
+                        && !input.trim().is_empty()
+                        && let Err(e) = tile_system.apply_presets(&presets, input.trim())
+                    {
+                        println!("{}", e);
+                        audio.play(Cue::Error);
+                    }
+                }
+                Key::P if shift_held => match job.as_mut() {
+                    Some(running) => {
+                        // overrides, targets and costs edited while paused carry on into the run
+                        if running.is_paused()
+                            && running.map_index == active_map
+                            && let Err(e) = running.reload_model(tile_system)
+                        {
+                            println!("{}", e);
+                            audio.play(Cue::Error);
+                        }
+                        running.toggle_pause();
+                    }
+                    None => say!("No generation running"),
+                },
+                Key::P if ctrl_held => {
+                    workspace.scan_library(&library_dir);
+                }
+                Key::I if shift_held => {
+                    use std::io::{self, Write};
+                    tile_system.list_configs();
+                    print!("{}", t!("Describe which configuration: "));
+                    io::stdout().flush().unwrap();
+                    let mut name = String::new();
+                    if io::stdin().read_line(&mut name).is_ok() && !name.trim().is_empty() {
+                        print!("{}", t!("Description: "));
+                        io::stdout().flush().unwrap();
+                        let mut description = String::new();
+                        io::stdin().read_line(&mut description).ok();
+                        if let Err(e) = tile_system.describe_config(name.trim(), description.trim())
+                        {
+                            println!("{}", e);
+                        }
+                    }
+                }
+                Key::I if ctrl_held => {
+                    use std::io::{self, Write};
+                    tile_system.list_configs();
+                    print!(
+                        "{}",
+                        t!(
+                            "Share which configuration to {library_dir}: ",
+                            library_dir = library_dir
+                        )
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut name = String::new();
+                    if io::stdin().read_line(&mut name).is_ok() {
+                        match tile_system.share_config(name.trim(), &library_dir) {
+                            Ok(_) => workspace.scan_library(&library_dir),
+                            Err(e) => {
+                                println!("{}", e);
+                                audio.play(Cue::Error);
+                            }
+                        }
+                    }
+                }
+                Key::P => {
+                    use std::io::{self, Write};
+                    let tags = tile_system.all_tags();
+                    if tags.is_empty() {
+                        tile_system.list_configs();
+                    } else {
+                        let tags: Vec<&str> = tags.into_iter().map(String::as_str).collect();
+                        print!(
+                            "{}",
+                            t!(
+                                "Filter by tag ({tags}; blank for all): ",
+                                tags = tags.join(", ")
+                            )
+                        );
+                        io::stdout().flush().unwrap();
+                        let mut input = String::new();
+                        if io::stdin().read_line(&mut input).is_ok() {
+                            let tag = Some(input.trim().to_lowercase()).filter(|t| !t.is_empty());
+                            tile_system.list_configs_tagged(tag.as_deref());
+                            // the panel's config list follows the same filter
+                            panel.config_filter = tag;
+                        }
+                    }
+                }
+                Key::I => {
+                    use std::io::{self, Write};
+                    tile_system.list_configs();
+                    print!("{}", t!("Tag which configuration: "));
+                    io::stdout().flush().unwrap();
+                    let mut name = String::new();
+                    if io::stdin().read_line(&mut name).is_ok() {
+                        print!("{}", t!("Tags to add, -tag to remove: "));
+                        io::stdout().flush().unwrap();
+                        let mut spec = String::new();
+                        if io::stdin().read_line(&mut spec).is_ok()
+                            && let Err(e) = tile_system.tag_config(name.trim(), &spec)
+                        {
+                            println!("{}", e);
+                            audio.play(Cue::Error);
+                        }
+                    }
+                }
+                Key::R => {
+                    use std::io::{self, Write};
+                    tile_system.list_configs();
+                    print!("{}", t!("Compare map with configuration (blank for all): "));
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok() {
+                        tile_system.distribution_report(input.trim());
+                    }
+                }
+                Key::U if shift_held => {
+                    use std::io::{self, Write};
+                    tile_system.print_tile_usage();
+                    print!(
+                        "{}",
+                        t!("Enter \"unused\" to prune or \"restore\" (blank to skip): ")
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok()
+                        && !input.trim().is_empty()
+                        && let Err(e) = tile_system.prune_tiles(input.trim())
+                    {
+                        println!("{}", e);
+                    }
+                }
+                Key::U if ctrl_held => {
+                    tile_system.restore_pre_operation();
+                }
+                Key::U => {
+                    recorder.run(tile_system, Command::UnlockAll);
+                }
+                Key::G if shift_held => {
+                    use std::io::{self, Write};
+                    print!(
+                        "{}",
+                        t!(
+                            "Wang set, \"corner <tiles>\" or \"edge <tiles>\" (blank for corner Water,Land): "
+                        )
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok() {
+                        let spec = match input.trim() {
+                            "" => "corner Water,Land",
+                            spec => spec,
+                        };
+                        if let Err(e) = tile_system.generate_wang(spec, time_seed()) {
+                            println!("{}", e);
+                            audio.play(Cue::Error);
+                        }
+                    }
+                }
+                Key::G if ctrl_held => {
+                    show_gallery = !show_gallery;
+                    if show_gallery && tile_system.bookmarks.is_empty() {
+                        say!("No bookmarks yet, generate and press Ctrl+B");
+                    }
+                }
+                Key::G => {
+                    panel.toggle();
+                }
+                Key::F1 => {
+                    show_keymap = !show_keymap;
+                    if show_keymap && glyphs.is_none() {
+                        keymap::print_keymap();
+                    }
+                }
+                Key::F if ctrl_held => {
+                    use std::io::{self, Write};
+                    tile_system.list_groups();
+                    print!(
+                        "{}",
+                        t!(
+                            "Enter \"<name> = <Tile>+<Tile>\" (\"<name> =\" removes, blank to skip): "
+                        )
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok()
+                        && !input.trim().is_empty()
+                        && let Err(e) = tile_system.edit_group(input.trim())
+                    {
+                        println!("{}", e);
+                    }
+                }
+                Key::F => {
+                    use std::io::{self, Write};
+                    print!(
+                        "{}",
+                        t!("Brush only replaces this group or tile (blank for anything): ")
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok() {
+                        let spec = input.trim();
+                        if spec.is_empty() {
+                            panel.brush_filter = None;
+                            say!("Brush replaces anything");
+                        } else if let Err(e) = tile_system.resolve_tiles(spec) {
+                            println!("{}", e);
+                        } else {
+                            panel.brush_filter = Some(spec.to_string());
+                            say!("Brush only replaces '{spec}'", spec = spec);
+                        }
+                    }
+                }
+                Key::B if shift_held => {
+                    use std::io::{self, Write};
+                    tile_system.list_brushes();
+                    say!(
+                        "Current brush: {selected_tile_type}, {brush}",
+                        selected_tile_type = format!("{:?}", selected_tile_type),
+                        brush = panel.brush.label()
+                    );
+                    print!(
+                        "{}",
+                        t!(
+                            "Enter \"shape|size|density|mirror <value>\", \"save <name>\" or \"delete <name>\" (blank to skip): "
+                        )
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    io::stdin().read_line(&mut input).ok();
+                    let input = input.trim();
+                    let result = if let Some(name) = input.strip_prefix("save ") {
+                        tile_system
+                            .save_brush(NamedBrush {
+                                name: name.trim().to_string(),
+                                tile: selected_tile_type.clone(),
+                                style: panel.brush,
+                                filter: panel.brush_filter.clone(),
+                            })
+                            .map(|_| ())
+                    } else if let Some(name) = input.strip_prefix("delete ") {
+                        tile_system.delete_brush(name.trim())
+                    } else if input.is_empty() {
+                        Ok(())
+                    } else {
+                        panel
+                            .brush
+                            .edit(input)
+                            .map(|()| say!("Brush: {brush}", brush = panel.brush.label()))
+                    };
+                    if let Err(e) = result {
+                        println!("{}", e);
+                    }
                 }
-                Key::D2 => {
-                    selected_tile_type = TileType::Mountain;
-                    println!("Selected: Mountain tile");
+                Key::B if ctrl_held => {
+                    use std::io::{self, Write};
+                    print!("{}", t!("Bookmark name (blank for model and seed): "));
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok() {
+                        tile_system.bookmark_last_generation(input.trim());
+                    }
                 }
-                Key::D3 => {
-                    selected_tile_type = TileType::Land;
-                    println!("Selected: Land tile");
+                Key::B => {
+                    tile_system.toggle_sparse();
                 }
-                Key::D4 => {
-                    selected_tile_type = TileType::Coast;
-                    println!("Selected: Coast tile");
+                Key::N if shift_held => {
+                    if lint_findings.is_empty() {
+                        say!("Nothing to jump to, lint the map with Shift+V first");
+                    } else {
+                        let finding = &lint_findings[next_finding % lint_findings.len()];
+                        let (x, y) = finding.cell;
+                        let (world_x, world_y) = tile_system.grid_to_world(x, y);
+                        let (offset_x, offset_y) = tile_system.grid_offset();
+                        let half = tile_system.tile_size / 2.0;
+                        camera.centre_on(
+                            [world_x + offset_x + half, world_y + offset_y + half],
+                            [tile_system.window_width, tile_system.window_height],
+                        );
+                        cursor = Some((x, y));
+                        say!(
+                            "{number}/{count} ({x}, {y}) {message}",
+                            number = next_finding % lint_findings.len() + 1,
+                            count = lint_findings.len(),
+                            x = x,
+                            y = y,
+                            message = finding.message
+                        );
+                        next_finding += 1;
+                    }
                 }
-                Key::D5 => {
-                    selected_tile_type = TileType::Water;
-                    println!("Selected: Water tile");
+                Key::N => {
+                    use std::io::{self, Write};
+                    if let Some((x, y)) = hovered_cell {
+                        print!(
+                            "{}",
+                            t!(
+                                "Enter note for ({x}, {y}) (blank removes notes here): ",
+                                x = x,
+                                y = y
+                            )
+                        );
+                        io::stdout().flush().unwrap();
+                        let mut input = String::new();
+                        if io::stdin().read_line(&mut input).is_ok() {
+                            let text = input.trim().to_string();
+                            if text.is_empty() {
+                                let removed = tile_system.remove_annotations_at(x, y);
+                                say!("Removed {removed} note(s)", removed = removed);
+                            } else {
+                                print!("{}", t!("Region size as WxH (blank for single tile): "));
+                                io::stdout().flush().unwrap();
+                                let mut size = String::new();
+                                let (width, height) = match io::stdin().read_line(&mut size) {
+                                    Ok(_) => parse_region_size(size.trim()).unwrap_or((1, 1)),
+                                    Err(_) => (1, 1),
+                                };
+                                tile_system.annotate(x, y, width, height, text);
+                            }
+                        }
+                    }
                 }
-                Key::S => {
+                Key::A if ctrl_held => {
+                    if let Some((x, y)) = cursor.or(hovered_cell) {
+                        if wand.as_ref().is_some_and(|cells| cells.contains(&(x, y))) {
+                            wand = None;
+                            say!("Island selection cleared");
+                        } else {
+                            wand = tile_system.select_island(x, y);
+                        }
+                    }
+                }
+                Key::A => {
+                    tile_system.list_annotations();
+                }
+                Key::H if shift_held => {
                     use std::io::{self, Write};
-                    print!("Enter name for saved configuration: ");
+                    print!(
+                        "{}",
+                        t!(
+                            "Heightmap: \"import <file.png>\" or \"export <file.png>\" (blank to skip): "
+                        )
+                    );
                     io::stdout().flush().unwrap();
                     let mut input = String::new();
                     if io::stdin().read_line(&mut input).is_ok() {
-                        let name = input.trim().to_string();
-                        if !name.is_empty() {
-                            tile_system.save_config(name);
+                        let result = match input.trim().split_once(' ') {
+                            Some(("import", path)) => tile_system.import_heightmap(path.trim()),
+                            Some(("export", path)) => tile_system.export_heightmap(path.trim()),
+                            None if input.trim().is_empty() => Ok(()),
+                            _ => Err(t!("Expected \"import <file>\" or \"export <file>\"")),
+                        };
+                        if let Err(e) = result {
+                            println!("{}", e);
+                            audio.play(Cue::Error);
                         }
                     }
                 }
-                Key::L => {
+                Key::H if ctrl_held && wand.is_none() => {
+                    say!("Select an island with Ctrl+A first");
+                }
+                Key::H if ctrl_held => {
                     use std::io::{self, Write};
-                    tile_system.list_configs();
-                    print!("Enter name of configuration to load: ");
+                    let cells = wand.as_ref().unwrap();
+                    print!(
+                        "{}",
+                        t!(
+                            "Enter \"replace <tile>\", \"move <dx> <dy>\" or \"regenerate [config]\" (blank to skip): "
+                        )
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok() && !input.trim().is_empty() {
+                        match tile_system.edit_cells(cells, input.trim()) {
+                            Ok(cells) => wand = Some(cells),
+                            Err(e) => println!("{}", e),
+                        }
+                    }
+                }
+                Key::Delete => {
+                    if let Some(cells) = &wand {
+                        tile_system.replace_cells(cells, &TileType::Empty);
+                    }
+                }
+                Key::V => {
+                    use std::io::{self, Write};
+                    print!(
+                        "{}",
+                        t!("Number of Voronoi regions (blank for 6, 0 clears): ")
+                    );
                     io::stdout().flush().unwrap();
                     let mut input = String::new();
                     if io::stdin().read_line(&mut input).is_ok() {
-                        let name = input.trim();
-                        tile_system.load_config(name);
+                        let command = match input.trim().parse().unwrap_or(6) {
+                            0 => Command::ClearBiomes,
+                            count => Command::SeedBiomes(count),
+                        };
+                        recorder.run(tile_system, command);
                     }
                 }
-                Key::D => {
+                Key::Q if ctrl_held => {
+                    recorder.run(tile_system, Command::Dungeon);
+                }
+                Key::Q => {
                     use std::io::{self, Write};
-                    tile_system.list_configs();
-                    print!("Enter name of configuration to delete: ");
+                    print!(
+                        "{}",
+                        t!("Maze algorithm (backtracker/wilson, blank for backtracker): ")
+                    );
                     io::stdout().flush().unwrap();
                     let mut input = String::new();
                     if io::stdin().read_line(&mut input).is_ok() {
-                        let name = input.trim();
-                        tile_system.delete_config(name);
+                        match MazeAlgorithm::parse(input.trim()) {
+                            Some(algorithm) => {
+                                recorder.run(tile_system, Command::Maze(algorithm));
+                                print!(
+                                    "{}",
+                                    t!("Save as a training config (name, blank to skip): ")
+                                );
+                                io::stdout().flush().unwrap();
+                                let mut name = String::new();
+                                io::stdin().read_line(&mut name).ok();
+                                let name = name.trim().to_string();
+                                if tile_system.saved_configs.contains_key(&name) {
+                                    pending = Some(Confirm::OverwriteConfig(name).ask());
+                                } else if !name.is_empty() {
+                                    tile_system.save_config(name);
+                                    say!("Enable it in the sample manager (J) to learn from it");
+                                }
+                            }
+                            None => {
+                                say!("Unknown maze algorithm '{input}'", input = input.trim());
+                                audio.play(Cue::Error);
+                            }
+                        }
                     }
                 }
-                Key::C => {
-                    tile_system.clear_map();
-                    println!("Map cleared");
+                Key::Y if shift_held => {
+                    use std::io::{self, Write};
+                    print!(
+                        "{}",
+                        t!(
+                            "Bias strength for {selected_tile_type} 0-100, 0 to erase, \"clear\" (blank to stop painting bias): ",
+                            selected_tile_type = format!("{:?}", selected_tile_type)
+                        )
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    io::stdin().read_line(&mut input).ok();
+                    match input.trim() {
+                        "" => {
+                            panel.bias = None;
+                            say!("Painting terrain");
+                        }
+                        "clear" => tile_system.clear_bias(),
+                        strength => match strength.parse::<f64>() {
+                            Ok(percent) if (0.0..=100.0).contains(&percent) => {
+                                panel.bias = Some(percent / 100.0);
+                                panel.decoration = None;
+                                say!("Painting bias at {percent}%", percent = percent);
+                            }
+                            _ => say!(
+                                "'{strength}' isn't a strength from 0 to 100",
+                                strength = strength
+                            ),
+                        },
+                    }
                 }
-                Key::P => {
+                Key::Y if ctrl_held => {
+                    panel.bias = None;
+                    panel.decoration = DecorationBrush::cycled(panel.decoration);
+                    match panel.decoration {
+                        Some(brush) => say!("Decoration brush: {brush}", brush = brush.label()),
+                        None => say!("Painting terrain"),
+                    }
+                }
+                Key::K if shift_held => {
+                    recorder.run(tile_system, Command::LayRoads);
+                }
+                Key::K if ctrl_held => {
+                    recorder.run(tile_system, Command::ClearFeatures);
+                }
+                Key::K => {
+                    use std::io::{self, Write};
+                    print!("{}", t!("Feature to scatter (tree/village/rock/house): "));
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok() {
+                        match FeatureKind::parse(input.trim()) {
+                            Some(kind) => {
+                                print!(
+                                    "{}",
+                                    t!(
+                                        "Minimum spacing in tiles (blank for {default_spacing}): ",
+                                        default_spacing = kind.default_spacing()
+                                    )
+                                );
+                                io::stdout().flush().unwrap();
+                                let mut spacing = String::new();
+                                io::stdin().read_line(&mut spacing).ok();
+                                let spacing = spacing
+                                    .trim()
+                                    .parse()
+                                    .unwrap_or_else(|_| kind.default_spacing());
+                                recorder
+                                    .run(tile_system, Command::ScatterFeatures { kind, spacing });
+                            }
+                            None => {
+                                let names: Vec<String> = FeatureKind::ALL
+                                    .iter()
+                                    .map(|k| format!("{:?}", k).to_lowercase())
+                                    .collect();
+                                say!(
+                                    "Unknown feature, expected one of {names}",
+                                    names = names.join("/")
+                                );
+                            }
+                        }
+                    }
+                }
+                Key::Equals | Key::NumPadPlus => {
+                    let size = tile_system
+                        .set_tile_size(tile_system.tile_size + TileSystem::TILE_SIZE_STEP);
+                    say!("Tile size: {size}px", size = size);
+                }
+                Key::Minus | Key::NumPadMinus => {
+                    let size = tile_system
+                        .set_tile_size(tile_system.tile_size - TileSystem::TILE_SIZE_STEP);
+                    say!("Tile size: {size}px", size = size);
+                }
+                Key::E if shift_held => {
+                    use std::io::{self, Write};
+                    tile_system.list_edge_matches();
+                    print!(
+                        "{}",
+                        t!(
+                            "Generate a chunk for \"<side>=<config> ... [model=<config>]\" (blank to skip): "
+                        )
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok() && !input.trim().is_empty() {
+                        let result = edges::ChunkRequest::parse(input.trim())
+                            .and_then(|request| tile_system.generate_chunk(&request, time_seed()));
+                        if let Err(e) = result {
+                            println!("{}", e);
+                            audio.play(Cue::Error);
+                        }
+                    }
+                }
+                Key::E if ctrl_held => {
+                    recorder.run(tile_system, Command::RebuildElevation);
+                }
+                Key::E => {
+                    recorder.run(tile_system, Command::Erode);
+                }
+                Key::T if ctrl_held && shift_held => {
+                    use std::io::{self, Write};
+                    let names: Vec<String> = tile_system
+                        .rotatable_tiles
+                        .iter()
+                        .map(|t| format!("{:?}", t))
+                        .collect();
+                    say!("Rotatable: {names}", names = names.join(", "));
+                    print!(
+                        "{}",
+                        t!("Enter rotatable tiles or a group, or \"off\" (blank to skip): ")
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok()
+                        && !input.trim().is_empty()
+                        && let Err(e) = tile_system.set_rotatable_tiles(input.trim())
+                    {
+                        println!("{}", e);
+                    }
+                }
+                Key::T if shift_held => {
+                    if let Some((x, y)) = hovered_cell {
+                        tile_system.rotate_cell(x, y);
+                    }
+                }
+                Key::T if ctrl_held => {
+                    tile_system.clear_training_regions();
+                }
+                Key::T => {
+                    if let Some(cell) = hovered_cell {
+                        match region_corner.take() {
+                            Some(corner) => {
+                                tile_system.add_training_region(Region::from_corners(corner, cell));
+                            }
+                            None => {
+                                region_corner = Some(cell);
+                                say!(
+                                    "Region corner at {cell}, press T on the opposite corner",
+                                    cell = format!("{:?}", cell)
+                                );
+                            }
+                        }
+                    }
+                }
+                Key::O if shift_held => {
+                    let layer = panel.layers.active;
+                    panel.layers.toggle(layer);
+                    say!("Layer {layer}", layer = panel.layers.describe(layer));
+                }
+                Key::LeftBracket | Key::RightBracket => {
+                    let layer = panel.layers.active;
+                    let steps = if key == Key::RightBracket { 1 } else { -1 };
+                    panel.layers.adjust_opacity(layer, steps);
+                    say!("Layer {layer}", layer = panel.layers.describe(layer));
+                }
+                Key::O if selection.is_some() && selection_corner.is_none() => {
+                    selection = None;
+                    say!("Selection cleared");
+                }
+                Key::O => {
+                    if let Some(cell) = cursor.or(hovered_cell) {
+                        match selection_corner.take() {
+                            Some(corner) => {
+                                let region = Region::from_corners(corner, cell);
+                                let counts = tile_system.tile_counts(region);
+                                let summary: Vec<String> = TileType::ALL
+                                    .iter()
+                                    .zip(counts)
+                                    .filter(|(_, count)| *count > 0)
+                                    .map(|(tile, count)| format!("{} {:?}", count, tile))
+                                    .collect();
+                                say!(
+                                    "Selected {width}x{height}: {summary}",
+                                    width = region.width,
+                                    height = region.height,
+                                    summary = summary.join(", ")
+                                );
+                                selection = Some(region);
+                            }
+                            None => {
+                                selection_corner = Some(cell);
+                                say!(
+                                    "Selection corner at {cell}, press O on the opposite corner",
+                                    cell = format!("{:?}", cell)
+                                );
+                            }
+                        }
+                    }
+                }
+                Key::W if ctrl_held && shift_held => {
+                    use std::io::{self, Write};
+                    if job.is_some() {
+                        say!("A generation is already running");
+                    } else if heatmap.take().is_some() {
+                        say!("Contradiction heatmap hidden");
+                    } else {
+                        tile_system.list_configs();
+                        print!(
+                            "{}",
+                            t!("Enter configuration to run (blank for enabled samples): ")
+                        );
+                        io::stdout().flush().unwrap();
+                        let mut model = String::new();
+                        if io::stdin().read_line(&mut model).is_ok() {
+                            print!(
+                                "{}",
+                                t!(
+                                    "How many runs (blank for {runs}): ",
+                                    runs = contradiction_heatmap::DEFAULT_RUNS
+                                )
+                            );
+                            io::stdout().flush().unwrap();
+                            let mut runs = String::new();
+                            io::stdin().read_line(&mut runs).ok();
+                            let runs = runs
+                                .trim()
+                                .parse()
+                                .unwrap_or(contradiction_heatmap::DEFAULT_RUNS);
+                            match ContradictionHeatmap::start(
+                                tile_system,
+                                active_map,
+                                model.trim().to_string(),
+                                runs,
+                                backend,
+                                &app_config,
+                            ) {
+                                Ok((started, spawned)) => {
+                                    heatmap = Some(started);
+                                    job = Some(spawned);
+                                }
+                                Err(e) => {
+                                    println!("{}", e);
+                                    audio.play(Cue::Error);
+                                }
+                            }
+                        }
+                    }
+                }
+                Key::W if shift_held => {
+                    show_wave = !show_wave;
+                    say!(
+                        "Propagation waves {state}",
+                        state = if show_wave { t!("shown") } else { t!("hidden") }
+                    );
+                }
+                Key::W if ctrl_held => {
+                    use std::io::{self, Write};
+                    tile_system.list_distribution_targets();
+                    print!(
+                        "{}",
+                        t!(
+                            "Enter \"<Tile> <percent> ... [tol <points>]\" or \"clear\" (blank to skip): "
+                        )
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok()
+                        && !input.trim().is_empty()
+                        && let Err(e) = tile_system.set_distribution_targets(input.trim())
+                    {
+                        println!("{}", e);
+                    }
+                }
+                Key::X if shift_held => {
+                    use std::io::{self, Write};
+                    tile_system.list_transition_costs();
+                    print!(
+                        "{}",
+                        t!("Enter \"<tile> <tile> <cost>\" or \"clear\" (blank to skip): ")
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok()
+                        && !input.trim().is_empty()
+                        && let Err(e) = tile_system.edit_transition_cost(input.trim())
+                    {
+                        println!("{}", e);
+                    }
+                }
+                Key::J if ctrl_held => {
+                    use std::io::{self, Write};
+                    tile_system.list_overrides();
+                    print!(
+                        "{}",
+                        t!(
+                            "Enter \"allow|never <tile> <tile> [direction]\" or \"clear\" (blank to skip): "
+                        )
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok()
+                        && !input.trim().is_empty()
+                        && let Err(e) = tile_system.edit_override(input.trim())
+                    {
+                        println!("{}", e);
+                    }
+                }
+                Key::J if shift_held => {
+                    use std::io::{self, Write};
+                    tile_system.list_samples();
+                    print!(
+                        "{}",
+                        t!("Hold out which samples (indices, blank for each in turn): ")
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok()
+                        && let Err(e) = tile_system.print_cross_validation(input.trim())
+                    {
+                        println!("{}", e);
+                    }
+                }
+                Key::J => {
+                    use std::io::{self, Write};
+                    tile_system.list_samples();
+                    print!(
+                        "{}",
+                        t!("Enter \"<index> on|off\" or \"<index> weight <w>\" (blank to skip): ")
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if io::stdin().read_line(&mut input).is_ok()
+                        && !input.trim().is_empty()
+                        && let Err(e) = tile_system.edit_sample(input.trim())
+                    {
+                        println!("{}", e);
+                    }
+                }
+                Key::X if ctrl_held => {
+                    use std::io::{self, Write};
+                    let ask = |question: &str| {
+                        print!("{}", question);
+                        io::stdout().flush().unwrap();
+                        let mut answer = String::new();
+                        io::stdin().read_line(&mut answer).ok();
+                        answer.trim().to_string()
+                    };
+                    let format = ask(&t!("Format, svg, text or png (blank for svg): "));
+                    let stem = ask(&t!("File name (blank for map): "));
+                    let result = if format.eq_ignore_ascii_case("text") {
+                        let options = TextOptions {
+                            glyphs: app_config.text_glyphs.clone(),
+                            colour: ask(&t!("ANSI colours? (y/N): ")).eq_ignore_ascii_case("y"),
+                        };
+                        print!("{}", text_export::to_text(tile_system, &options));
+                        tile_system.export_text(&stem, &options)
+                    } else if format.eq_ignore_ascii_case("png") {
+                        let scale = ask(&t!("Pixels per tile (blank for 8): "))
+                            .parse()
+                            .unwrap_or(8);
+                        tile_system.export_png(&stem, scale)
+                    } else {
+                        let options = SvgOptions {
+                            gridlines: ask(&t!("Gridlines? (y/N): ")).eq_ignore_ascii_case("y"),
+                            legend: ask(&t!("Legend? (y/N): ")).eq_ignore_ascii_case("y"),
+                        };
+                        tile_system.export_svg(&stem, options)
+                    };
+                    if let Err(e) = result {
+                        eprintln!("{}", e);
+                        audio.play(Cue::Error);
+                    }
+                }
+                Key::X => {
+                    use std::io::{self, Write};
                     tile_system.list_configs();
+                    print!(
+                        "{}",
+                        t!(
+                            "Enter configuration to export constraints for (blank for enabled samples): "
+                        )
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut name = String::new();
+                    if io::stdin().read_line(&mut name).is_ok() {
+                        print!("{}", t!("Format, dimacs or minizinc (blank for dimacs): "));
+                        io::stdout().flush().unwrap();
+                        let mut format = String::new();
+                        if io::stdin().read_line(&mut format).is_ok() {
+                            let minizinc = matches!(format.trim(), "minizinc" | "mzn");
+                            if let Err(e) = tile_system.export_constraints(name.trim(), minizinc) {
+                                eprintln!("{}", e);
+                                audio.play(Cue::Error);
+                            }
+                        }
+                    }
                 }
                 Key::W => {
-                    //wrapper function here that calls together all parts?
+                    use std::io::{self, Write};
+                    tile_system.list_configs();
+                    print!(
+                        "{}",
+                        t!("Enter configuration to generate from (blank for enabled samples): ")
+                    );
+                    io::stdout().flush().unwrap();
+                    let mut input = String::new();
+                    if job.is_some() {
+                        say!("A generation is already running");
+                    } else if io::stdin().read_line(&mut input).is_ok() {
+                        let model = input.trim().to_string();
+                        match GenerationJob::spawn(
+                            tile_system,
+                            active_map,
+                            model.clone(),
+                            time_seed(),
+                            backend,
+                            &app_config,
+                            edit_args.trace.as_deref(),
+                        ) {
+                            Ok(spawned) => {
+                                recorder.record(Command::Generate(Generation { model, backend }));
+                                job = Some(spawned);
+                            }
+                            Err(e) => {
+                                println!("{}", e);
+                                audio.play(Cue::Error);
+                            }
+                        }
+                    }
                 }
                 _ => {}
             },
+            Event::Input(
+                Input::Button(ButtonArgs {
+                    state: ButtonState::Release,
+                    button: Button::Keyboard(Key::LCtrl | Key::RCtrl),
+                    ..
+                }),
+                _,
+            ) => {
+                ctrl_held = false;
+            }
+            Event::Input(
+                Input::Button(ButtonArgs {
+                    state: ButtonState::Release,
+                    button: Button::Keyboard(Key::LAlt | Key::RAlt),
+                    ..
+                }),
+                _,
+            ) => {
+                alt_held = false;
+            }
+            Event::Input(
+                Input::Button(ButtonArgs {
+                    state: ButtonState::Release,
+                    button: Button::Keyboard(Key::LShift | Key::RShift),
+                    ..
+                }),
+                _,
+            ) => {
+                shift_held = false;
+                if fill_preview.take().is_some() {
+                    say!("Fill cancelled");
+                }
+            }
+            // wheel down moves forward through the palette, Ctrl+wheel sizes the brush
+            Event::Input(Input::Move(Motion::MouseScroll([_, dy])), _) if dy != 0.0 => {
+                if ctrl_held {
+                    let size = panel.adjust_brush_size(if dy > 0.0 { 1 } else { -1 });
+                    say!("Brush size: {size}", size = size);
+                } else {
+                    selected_tile_type = selected_tile_type.cycled(dy < 0.0);
+                    say!(
+                        "Selected: {selected_tile_type} tile",
+                        selected_tile_type = format!("{:?}", selected_tile_type)
+                    );
+                }
+            }
             Event::Input(
                 Input::Button(ButtonArgs {
                     state: ButtonState::Press,
@@ -539,18 +3604,93 @@ fn main() {
                 }),
                 _,
             ) => {
-                if let Some((grid_x, grid_y)) =
-                    tile_system.get_tile_at_pos(mouse_pos[1], mouse_pos[0])
-                {
-                    let tile_to_place = match selected_tile_type {
-                        TileType::Empty => Tile::empty(),
-                        TileType::Mountain => Tile::mountain(),
-                        TileType::Land => Tile::land(),
-                        TileType::Coast => Tile::coast(),
-                        TileType::Water => Tile::water(),
-                    };
-
-                    tile_system.set_tile(grid_x, grid_y, tile_to_place);
+                if show_gallery {
+                    show_gallery = false;
+                    let clicked = tile_system.gallery_hit(mouse_pos);
+                    if let Some((model, seed, backend)) =
+                        clicked.and_then(|i| tile_system.restore_bookmark_setup(i))
+                    {
+                        if job.is_some() {
+                            say!("A generation is already running");
+                        } else {
+                            match GenerationJob::spawn(
+                                tile_system,
+                                active_map,
+                                model,
+                                seed,
+                                backend,
+                                &app_config,
+                                edit_args.trace.as_deref(),
+                            ) {
+                                Ok(spawned) => job = Some(spawned),
+                                Err(e) => {
+                                    println!("{}", e);
+                                    audio.play(Cue::Error);
+                                }
+                            }
+                        }
+                    }
+                } else if panel.contains(tile_system, mouse_pos) {
+                    match panel.click(tile_system, &selected_tile_type, mouse_pos) {
+                        Some(PanelAction::SelectTile(tile_type)) => {
+                            selected_tile_type = tile_type;
+                            panel.decoration = None;
+                            say!(
+                                "Selected: {selected_tile_type} tile",
+                                selected_tile_type = format!("{:?}", selected_tile_type)
+                            );
+                        }
+                        Some(PanelAction::SelectDecoration(brush)) => match brush {
+                            Some(brush) => say!("Decoration brush: {brush}", brush = brush.label()),
+                            None => say!("Painting terrain"),
+                        },
+                        Some(PanelAction::SetBrushSize(size)) => {
+                            say!("Brush size: {size}", size = size);
+                        }
+                        Some(PanelAction::LoadConfig(name)) => {
+                            if recorder.run(tile_system, Command::LoadConfig(name.clone())) {
+                                app_config.remember_config(&name);
+                            } else {
+                                audio.play(Cue::Error);
+                            }
+                        }
+                        Some(PanelAction::Undo) => {
+                            recorder.run(tile_system, Command::Undo);
+                        }
+                        Some(PanelAction::Clear) => {
+                            pending = Some(Confirm::ClearMap.ask());
+                        }
+                        Some(PanelAction::NextMap) => {
+                            workspace.next_map();
+                        }
+                        Some(PanelAction::ToggleLayer(layer)) => {
+                            panel.layers.toggle(layer);
+                            panel.layers.active = layer;
+                            say!("Layer {layer}", layer = panel.layers.describe(layer));
+                        }
+                        None => {}
+                    }
+                } else if let Some((grid_x, grid_y)) = cell_under(tile_system, &camera, mouse_pos) {
+                    if alt_held {
+                        if let Some(tile) = tile_system.get_tile(grid_x, grid_y) {
+                            selected_tile_type = tile.tile_type.clone();
+                            say!(
+                                "Picked: {selected_tile_type} tile",
+                                selected_tile_type = format!("{:?}", selected_tile_type)
+                            );
+                        }
+                    } else {
+                        let from = last_painted.filter(|_| shift_held);
+                        paint_with_brush(
+                            tile_system,
+                            &panel,
+                            grid_x,
+                            grid_y,
+                            &selected_tile_type,
+                            from,
+                        );
+                        last_painted = Some((grid_x, grid_y));
+                    }
                     // println!(
                     //     "Placed {:?} at ({}, {})",
                     //     selected_tile_type, grid_x, grid_y
@@ -565,10 +3705,54 @@ fn main() {
                     ..
                 }),
                 _,
+            ) if shift_held => {
+                if let Some((grid_x, grid_y)) = cell_under(tile_system, &camera, mouse_pos) {
+                    let cells = tile_system.fill_cells(grid_x, grid_y, &selected_tile_type);
+                    say!(
+                        "Fill would change {count} cell(s), release to fill or let go of Shift to cancel",
+                        count = cells.len()
+                    );
+                    fill_preview = Some(FillPreview {
+                        start: (grid_x, grid_y),
+                        cells,
+                    });
+                }
+            }
+            Event::Input(
+                Input::Button(ButtonArgs {
+                    state: ButtonState::Release,
+                    button: Button::Mouse(MouseButton::Right),
+                    ..
+                }),
+                _,
             ) => {
-                if let Some((grid_x, grid_y)) =
-                    tile_system.get_tile_at_pos(mouse_pos[1], mouse_pos[0])
+                if let Some(FillPreview {
+                    start: (grid_x, grid_y),
+                    ..
+                }) = fill_preview.take()
                 {
+                    tile_system.fill_to_border(
+                        grid_x,
+                        grid_y,
+                        Tile::from_type(&selected_tile_type),
+                    );
+                    say!(
+                        "Filled {selected_tile_type} at ({grid_x}, {grid_y})",
+                        selected_tile_type = format!("{:?}", selected_tile_type),
+                        grid_x = grid_x,
+                        grid_y = grid_y
+                    );
+                }
+            }
+            Event::Input(
+                Input::Button(ButtonArgs {
+                    state: ButtonState::Press,
+                    button: Button::Mouse(MouseButton::Right),
+                    ..
+                }),
+                _,
+            ) => {
+                if let Some((grid_x, grid_y)) = cell_under(tile_system, &camera, mouse_pos) {
                     let tile_to_fill = match selected_tile_type {
                         TileType::Empty => Tile::empty(),
                         TileType::Mountain => Tile::mountain(),
@@ -578,21 +3762,162 @@ fn main() {
                     };
 
                     tile_system.fill_to_border(grid_x, grid_y, tile_to_fill);
-                    println!(
-                        "Filled {:?} at ({}, {})",
-                        selected_tile_type, grid_x, grid_y
+                    say!(
+                        "Filled {selected_tile_type} at ({grid_x}, {grid_y})",
+                        selected_tile_type = format!("{:?}", selected_tile_type),
+                        grid_x = grid_x,
+                        grid_y = grid_y
                     );
                 }
             }
 
-            Event::Loop(_) => {
-                window.draw_2d(&event, |c, g, _| {
+            Event::Input(
+                Input::Button(ButtonArgs {
+                    state: ButtonState::Press,
+                    button: Button::Mouse(MouseButton::Middle),
+                    ..
+                }),
+                _,
+            ) => {
+                if let Some((grid_x, grid_y)) = cell_under(tile_system, &camera, mouse_pos) {
+                    let locked = tile_system.toggle_lock(grid_x, grid_y);
+                    if locked {
+                        say!(
+                            "Locked ({grid_x}, {grid_y})",
+                            grid_x = grid_x,
+                            grid_y = grid_y
+                        );
+                    } else {
+                        say!(
+                            "Unlocked ({grid_x}, {grid_y})",
+                            grid_x = grid_x,
+                            grid_y = grid_y
+                        );
+                    }
+                }
+            }
+
+            Event::Loop(Loop::Render(args)) => {
+                (view_scale, view_bars) = letterbox(tile_system, args.window_size);
+                let mut map_label = t!(
+                    "Map {number}/{count}",
+                    number = active_map + 1,
+                    count = map_count
+                );
+                if alt_held {
+                    map_label += &t!(" - eyedropper");
+                }
+                panel.progress = job.as_ref().map(|running| ui::GenerationProgress {
+                    done: running.progress(),
+                    contradictions: running.contradictions(),
+                    eta: running.eta(),
+                });
+                if let Some(running) = &job {
+                    map_label += &t!(
+                        " - generating {percent}%, {peak} stack",
+                        percent = format!("{:.0}", running.progress() * 100.0),
+                        peak = observer::format_bytes(running.peak_memory())
+                    );
+                    if running.is_paused() {
+                        map_label += &t!(" (paused)");
+                    }
+                }
+                let preview_cells = hovered_cell
+                    .filter(|_| {
+                        fill_preview.is_none()
+                            && pending.is_none()
+                            && !alt_held
+                            && !panel.contains(tile_system, mouse_pos)
+                    })
+                    .map(|(x, y)| {
+                        let from = last_painted.filter(|_| shift_held);
+                        brush_preview(tile_system, &panel, x, y, from)
+                    });
+                let preview_tint = if panel.decoration.is_some() {
+                    [1.0, 1.0, 1.0, 0.75]
+                } else {
+                    Tile::from_type(&selected_tile_type).colour
+                };
+                window.draw_2d(&event, |c, g, device| {
+                    let c = c
+                        .trans(view_bars[0], view_bars[1])
+                        .scale(view_scale, view_scale);
                     clear([0.0, 0.0, 0.0, 1.0], g);
-                    tile_system.render(c, g);
+                    let (offset_x, offset_y) = tile_system.grid_offset();
+                    let map_c = camera.transform(c).trans(offset_x, offset_y);
+                    tile_system.render(&panel.layers, map_c, g);
+                    if let Some(running) = job.as_ref().filter(|j| j.map_index == active_map) {
+                        running.render_preview(tile_system, map_c, g);
+                        if show_wave {
+                            running.render_wave(tile_system, map_c, g);
+                        }
+                    }
+                    if let Some(heatmap) = heatmap.as_ref().filter(|h| h.map_index == active_map) {
+                        heatmap.render(tile_system, map_c, g);
+                    }
+                    if let Some(region) = selection {
+                        ui::render_selection(tile_system, region, map_c, g);
+                    }
+                    if let Some(cells) = &wand {
+                        ui::render_cells(tile_system, cells, map_c, g);
+                    }
+                    if let Some(cells) = &preview_cells {
+                        ui::render_brush_preview(tile_system, cells, preview_tint, map_c, g);
+                    }
+                    if let Some(preview) = &fill_preview {
+                        ui::render_fill_preview(
+                            tile_system,
+                            preview,
+                            &selected_tile_type,
+                            map_c,
+                            g,
+                        );
+                    }
+                    if let Some(cell) = cursor {
+                        ui::render_cursor(tile_system, cell, map_c, g);
+                    }
+                    if let Some(running) = job.as_ref().filter(|j| j.map_index == active_map)
+                        && let Some(cell) = hovered_cell
+                        && let Some(candidates) = running.candidates(cell)
+                    {
+                        ui::render_candidates(
+                            tile_system,
+                            cell,
+                            &candidates,
+                            glyphs.as_mut(),
+                            map_c,
+                            g,
+                        );
+                    }
+                    panel.render(
+                        tile_system,
+                        &selected_tile_type,
+                        &map_label,
+                        glyphs.as_mut(),
+                        c,
+                        g,
+                    );
+                    if show_gallery {
+                        tile_system.render_gallery(glyphs.as_mut(), c, g);
+                    }
+                    if let Some(inspector) = &inspector {
+                        inspector.render(tile_system, glyphs.as_mut(), c, g);
+                    }
+                    if show_keymap {
+                        ui::render_keymap(tile_system, glyphs.as_mut(), c, g);
+                    }
+                    if let Some(entries) = &quick_open {
+                        ui::render_quick_open(entries, tile_system, glyphs.as_mut(), c, g);
+                    }
+                    if let Some(confirm) = &pending {
+                        ui::render_confirm(confirm, tile_system, glyphs.as_mut(), c, g);
+                    }
+                    if let Some(glyphs) = glyphs.as_mut() {
+                        glyphs.factory.encoder.flush(device);
+                    }
                 });
             }
             _ => {}
         }
     }
-    tile_system.save_to_file();
 }
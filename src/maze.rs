@@ -0,0 +1,161 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::say;
+use crate::{Tile, TileSystem, TileType};
+
+pub const WALL: TileType = TileType::Mountain;
+pub const FLOOR: TileType = TileType::Land;
+
+const STEPS: [(isize, isize); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum MazeAlgorithm {
+    // long winding corridors, few dead ends
+    #[default]
+    Backtracker,
+    // uniform spanning tree, no bias towards any corridor shape
+    Wilson,
+}
+
+impl MazeAlgorithm {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "" | "backtracker" | "dfs" => Some(MazeAlgorithm::Backtracker),
+            "wilson" => Some(MazeAlgorithm::Wilson),
+            _ => None,
+        }
+    }
+}
+
+// perfect maze over a width x height tile grid, true for floor
+// maze cells sit on odd coordinates with the walls between them on even ones
+pub fn generate(
+    width: usize,
+    height: usize,
+    algorithm: MazeAlgorithm,
+    seed: u64,
+) -> Vec<Vec<bool>> {
+    let mut floor = vec![vec![false; width]; height];
+    let (cols, rows) = (width.saturating_sub(1) / 2, height.saturating_sub(1) / 2);
+    if cols == 0 || rows == 0 {
+        return floor;
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let passages = match algorithm {
+        MazeAlgorithm::Backtracker => backtracker(cols, rows, &mut rng),
+        MazeAlgorithm::Wilson => wilson(cols, rows, &mut rng),
+    };
+
+    for row in 0..rows {
+        for col in 0..cols {
+            floor[row * 2 + 1][col * 2 + 1] = true;
+        }
+    }
+    for ((ax, ay), (bx, by)) in passages {
+        floor[ay + by + 1][ax + bx + 1] = true;
+    }
+    floor
+}
+
+fn neighbour(
+    cell: (usize, usize),
+    step: (isize, isize),
+    cols: usize,
+    rows: usize,
+) -> Option<(usize, usize)> {
+    let x = cell.0.checked_add_signed(step.0)?;
+    let y = cell.1.checked_add_signed(step.1)?;
+    (x < cols && y < rows).then_some((x, y))
+}
+
+type Passage = ((usize, usize), (usize, usize));
+
+fn backtracker(cols: usize, rows: usize, rng: &mut StdRng) -> Vec<Passage> {
+    let mut visited = vec![vec![false; cols]; rows];
+    let mut passages = Vec::new();
+    let start = (rng.gen_range(0..cols), rng.gen_range(0..rows));
+    visited[start.1][start.0] = true;
+    let mut stack = vec![start];
+
+    while let Some(&cell) = stack.last() {
+        let mut steps = STEPS;
+        steps.shuffle(rng);
+        let next = steps
+            .iter()
+            .filter_map(|&step| neighbour(cell, step, cols, rows))
+            .find(|&(x, y)| !visited[y][x]);
+        match next {
+            Some(next) => {
+                visited[next.1][next.0] = true;
+                passages.push((cell, next));
+                stack.push(next);
+            }
+            None => {
+                stack.pop();
+            }
+        }
+    }
+    passages
+}
+
+fn wilson(cols: usize, rows: usize, rng: &mut StdRng) -> Vec<Passage> {
+    let mut in_maze = vec![vec![false; cols]; rows];
+    let mut passages = Vec::new();
+    in_maze[rng.gen_range(0..rows)][rng.gen_range(0..cols)] = true;
+
+    let mut remaining: Vec<(usize, usize)> = (0..rows)
+        .flat_map(|y| (0..cols).map(move |x| (x, y)))
+        .collect();
+    remaining.shuffle(rng);
+
+    // direction last taken out of each cell; revisits overwrite it, which erases loops
+    let mut exit: Vec<Vec<Option<(usize, usize)>>> = vec![vec![None; cols]; rows];
+    for start in remaining {
+        if in_maze[start.1][start.0] {
+            continue;
+        }
+        let mut cell = start;
+        while !in_maze[cell.1][cell.0] {
+            let next = loop {
+                let step = STEPS[rng.gen_range(0..STEPS.len())];
+                if let Some(next) = neighbour(cell, step, cols, rows) {
+                    break next;
+                }
+            };
+            exit[cell.1][cell.0] = Some(next);
+            cell = next;
+        }
+
+        let mut cell = start;
+        while !in_maze[cell.1][cell.0] {
+            in_maze[cell.1][cell.0] = true;
+            if let Some(next) = exit[cell.1][cell.0] {
+                passages.push((cell, next));
+                cell = next;
+            }
+        }
+    }
+    passages
+}
+
+impl TileSystem {
+    // overwrite the map with a maze, locked cells kept; one undo step
+    pub fn generate_maze(&mut self, algorithm: MazeAlgorithm, seed: u64) {
+        self.push_undo();
+        let floor = generate(self.grid_width, self.grid_height, algorithm, seed);
+        for (y, row) in floor.iter().enumerate() {
+            for (x, &is_floor) in row.iter().enumerate() {
+                let tile_type = if is_floor { FLOOR } else { WALL };
+                self.set_tile(x, y, Tile::from_type(&tile_type));
+            }
+        }
+        say!(
+            "Generated {algorithm} maze with seed {seed}",
+            algorithm = format!("{:?}", algorithm),
+            seed = seed
+        );
+    }
+}
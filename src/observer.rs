@@ -0,0 +1,134 @@
+use crate::i18n::{say, t};
+
+// hooks a solver calls as it works, so features can follow generation without
+// reaching into the solver loop; every method defaults to doing nothing
+pub trait GenerationObserver {
+    // the solver chose a tile for a cell, a decision it may later undo;
+    // on_collapse follows for the same cell
+    fn on_observe(&mut self, _cell: (usize, usize), _tile: usize) {}
+    // a cell narrowed to a single tile
+    fn on_collapse(&mut self, _cell: (usize, usize), _tile: usize) {}
+    // a tile was removed from a cell's options
+    fn on_eliminate(&mut self, _cell: (usize, usize), _tile: usize) {}
+    // a cell ran out of options
+    fn on_contradiction(&mut self, _cell: (usize, usize)) {}
+    // the decision at a cell led to a contradiction and is being undone
+    fn on_backtrack(&mut self, _cell: (usize, usize)) {}
+    // the search is starting over from the beginning
+    fn on_restart(&mut self) {}
+    // the solver's saved state reached a new peak size
+    fn on_memory(&mut self, _bytes: usize) {}
+    // about to pick the next cell: every cell's remaining tiles as bits, and the
+    // value-order weight per tile, empty while values are picked uniformly
+    fn on_step(&mut self, _domains: &[u32], _weights: &[f64]) {}
+    fn on_complete(&mut self, _success: bool) {}
+}
+
+// forwards every event to several observers
+pub struct Observers<'a>(pub Vec<&'a mut dyn GenerationObserver>);
+
+impl GenerationObserver for Observers<'_> {
+    fn on_observe(&mut self, cell: (usize, usize), tile: usize) {
+        for observer in &mut self.0 {
+            observer.on_observe(cell, tile);
+        }
+    }
+
+    fn on_collapse(&mut self, cell: (usize, usize), tile: usize) {
+        for observer in &mut self.0 {
+            observer.on_collapse(cell, tile);
+        }
+    }
+
+    fn on_eliminate(&mut self, cell: (usize, usize), tile: usize) {
+        for observer in &mut self.0 {
+            observer.on_eliminate(cell, tile);
+        }
+    }
+
+    fn on_contradiction(&mut self, cell: (usize, usize)) {
+        for observer in &mut self.0 {
+            observer.on_contradiction(cell);
+        }
+    }
+
+    fn on_backtrack(&mut self, cell: (usize, usize)) {
+        for observer in &mut self.0 {
+            observer.on_backtrack(cell);
+        }
+    }
+
+    fn on_restart(&mut self) {
+        for observer in &mut self.0 {
+            observer.on_restart();
+        }
+    }
+
+    fn on_memory(&mut self, bytes: usize) {
+        for observer in &mut self.0 {
+            observer.on_memory(bytes);
+        }
+    }
+
+    fn on_step(&mut self, domains: &[u32], weights: &[f64]) {
+        for observer in &mut self.0 {
+            observer.on_step(domains, weights);
+        }
+    }
+
+    fn on_complete(&mut self, success: bool) {
+        for observer in &mut self.0 {
+            observer.on_complete(success);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct StatsObserver {
+    pub collapses: usize,
+    pub eliminations: usize,
+    pub contradictions: usize,
+    pub peak_memory: usize,
+}
+
+impl GenerationObserver for StatsObserver {
+    fn on_collapse(&mut self, _cell: (usize, usize), _tile: usize) {
+        self.collapses += 1;
+    }
+
+    fn on_eliminate(&mut self, _cell: (usize, usize), _tile: usize) {
+        self.eliminations += 1;
+    }
+
+    fn on_contradiction(&mut self, _cell: (usize, usize)) {
+        self.contradictions += 1;
+    }
+
+    fn on_memory(&mut self, bytes: usize) {
+        self.peak_memory = self.peak_memory.max(bytes);
+    }
+
+    fn on_complete(&mut self, success: bool) {
+        say!(
+            "Generation {success}: {collapses} collapses, {eliminations} eliminations, {contradictions} contradictions, {peak} peak stack",
+            success = if success {
+                t!("finished")
+            } else {
+                t!("failed")
+            },
+            collapses = self.collapses,
+            eliminations = self.eliminations,
+            contradictions = self.contradictions,
+            peak = format_bytes(self.peak_memory)
+        );
+    }
+}
+
+pub fn format_bytes(bytes: usize) -> String {
+    const MIB: f64 = 1024.0 * 1024.0;
+    if bytes as f64 >= MIB {
+        format!("{:.1} MiB", bytes as f64 / MIB)
+    } else {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    }
+}
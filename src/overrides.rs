@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::i18n::say;
+use crate::{Direction, TileSystem, TileType};
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Down,
+    Direction::Left,
+    Direction::Right,
+];
+
+fn opposite(dir: Direction) -> Direction {
+    match dir {
+        Direction::Up => Direction::Down,
+        Direction::Down => Direction::Up,
+        Direction::Left => Direction::Right,
+        Direction::Right => Direction::Left,
+    }
+}
+
+fn parse_direction(name: &str) -> Option<Direction> {
+    DIRECTIONS
+        .into_iter()
+        .find(|dir| format!("{:?}", dir).eq_ignore_ascii_case(name))
+}
+
+// a hand-made tweak to the learned adjacency, kept apart from the samples so it
+// still applies after they change
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RuleOverride {
+    pub allow: bool,
+    pub tile: TileType,
+    pub neighbour: TileType,
+    // which side of `tile` the neighbour is on, None for every side
+    pub direction: Option<Direction>,
+}
+
+impl RuleOverride {
+    fn describe(&self) -> String {
+        format!(
+            "{} {:?} {:?}{}",
+            if self.allow { "allow" } else { "never" },
+            self.tile,
+            self.neighbour,
+            self.direction
+                .map_or(String::new(), |dir| format!(" {:?}", dir).to_lowercase())
+        )
+    }
+}
+
+impl TileSystem {
+    // applies the overrides in order over rules learned from samples or a config;
+    // both sides of each pair change so the rules stay symmetric
+    pub fn apply_overrides(&self, rules: &mut HashMap<usize, HashSet<(Direction, usize)>>) {
+        for rule in &self.rule_overrides {
+            let directions = match rule.direction {
+                Some(dir) => vec![dir],
+                None => DIRECTIONS.to_vec(),
+            };
+            let (tile, neighbour) = (rule.tile.index(), rule.neighbour.index());
+            for dir in directions {
+                for (from, dir, to) in [(tile, dir, neighbour), (neighbour, opposite(dir), tile)] {
+                    let allowed = rules.entry(from).or_default();
+                    if rule.allow {
+                        allowed.insert((dir, to));
+                    } else {
+                        allowed.remove(&(dir, to));
+                    }
+                }
+            }
+        }
+    }
+
+    // "allow|never <tiles> <tiles> [up|down|left|right]", tiles as names or groups,
+    // or "clear"; a new override replaces any earlier one for the same pair and side
+    pub fn edit_override(&mut self, command: &str) -> Result<(), String> {
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        if parts == ["clear"] {
+            self.rule_overrides.clear();
+            self.dirty = true;
+            say!("Adjacency overrides cleared");
+            return Ok(());
+        }
+        let usage = "Expected \"allow|never <tile> <tile> [up|down|left|right]\" or \"clear\"";
+        let (allow, tiles, neighbours, direction) = match parts.as_slice() {
+            [verb, tiles, neighbours] => (*verb, *tiles, *neighbours, None),
+            [verb, tiles, neighbours, dir] => (
+                *verb,
+                *tiles,
+                *neighbours,
+                Some(parse_direction(dir).ok_or(format!("Unknown direction '{}'", dir))?),
+            ),
+            _ => return Err(usage.to_string()),
+        };
+        let allow = match allow {
+            "allow" => true,
+            "never" => false,
+            _ => return Err(usage.to_string()),
+        };
+        let tiles = self.resolve_tiles(tiles)?;
+        let neighbours = self.resolve_tiles(neighbours)?;
+        for tile in &tiles {
+            for neighbour in &neighbours {
+                let rule = RuleOverride {
+                    allow,
+                    tile: tile.clone(),
+                    neighbour: neighbour.clone(),
+                    direction,
+                };
+                self.rule_overrides.retain(|old| {
+                    old.tile != rule.tile
+                        || old.neighbour != rule.neighbour
+                        || old.direction != rule.direction
+                });
+                say!("Added override: {rule}", rule = rule.describe());
+                self.rule_overrides.push(rule);
+            }
+        }
+        self.dirty = true;
+        Ok(())
+    }
+
+    pub fn list_overrides(&self) {
+        if self.rule_overrides.is_empty() {
+            say!("No adjacency overrides");
+            return;
+        }
+        say!("Adjacency overrides, applied in order:");
+        for rule in &self.rule_overrides {
+            println!(" - {}", rule.describe());
+        }
+    }
+}
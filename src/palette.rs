@@ -0,0 +1,196 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::RwLock;
+
+use crate::grid::TileGrid;
+use crate::i18n::{say, t};
+use crate::{Tile, TileSystem, TileType};
+
+// tile name to "#rrggbb" or "#rrggbbaa"; the shape of palette JSON files and of
+// `palette` in config.json, where tiles left out keep their built-in colour
+pub type Palette = BTreeMap<String, String>;
+
+const DEFAULTS: [[f32; 4]; TileType::ALL.len()] = [
+    [0.0, 0.0, 0.0, 0.0],
+    [0.5, 0.5, 0.5, 1.0],
+    [0.3, 0.8, 0.4, 1.0],
+    [0.8, 0.7, 0.6, 1.0],
+    [0.2, 0.4, 0.8, 1.0],
+];
+
+static COLOURS: RwLock<[[f32; 4]; TileType::ALL.len()]> = RwLock::new(DEFAULTS);
+
+// what new tiles of `tile` are painted with
+pub fn colour(tile: &TileType) -> [f32; 4] {
+    COLOURS.read().unwrap()[tile.index()]
+}
+
+fn parse_hex(hex: &str) -> Option<[f32; 4]> {
+    let digits = hex.trim().strip_prefix('#')?;
+    if !matches!(digits.len(), 6 | 8) || !digits.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| {
+        digits
+            .get(i * 2..i * 2 + 2)
+            .map(|pair| u8::from_str_radix(pair, 16).map(|v| v as f32 / 255.0))
+    };
+    Some([
+        channel(0)?.ok()?,
+        channel(1)?.ok()?,
+        channel(2)?.ok()?,
+        channel(3).unwrap_or(Ok(1.0)).ok()?,
+    ])
+}
+
+fn to_bytes(colour: [f32; 4]) -> [u8; 4] {
+    colour.map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+fn to_hex(colour: [f32; 4]) -> String {
+    let [r, g, b, a] = to_bytes(colour);
+    if a == 255 {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+    }
+}
+
+// the built-in colours with `palette` laid over them
+pub fn apply(palette: &Palette) -> Result<(), String> {
+    let mut colours = DEFAULTS;
+    for (name, hex) in palette {
+        let tile = TileType::parse(name).ok_or_else(|| t!("Unknown tile '{name}'", name = name))?;
+        colours[tile.index()] = parse_hex(hex).ok_or_else(|| {
+            t!(
+                "'{hex}' for {name} isn't a #rrggbb colour",
+                hex = hex,
+                name = name
+            )
+        })?;
+    }
+    *COLOURS.write().unwrap() = colours;
+    Ok(())
+}
+
+// every tile's colour as it stands
+pub fn current() -> Palette {
+    TileType::ALL
+        .iter()
+        .map(|tile| (format!("{:?}", tile), to_hex(colour(tile))))
+        .collect()
+}
+
+fn is_gpl(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gpl"))
+}
+
+// GIMP palette: "R G B name" per line after the header; entries named after tiles
+// colour those tiles, and a palette with no tile names is taken in order instead
+fn parse_gpl(text: &str) -> Result<Palette, String> {
+    let mut lines = text.lines();
+    if lines.next().map(str::trim) != Some("GIMP Palette") {
+        return Err(t!("Not a GIMP palette"));
+    }
+    let mut entries = Vec::new();
+    for line in lines {
+        let line = line.trim();
+        if line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with("Name:")
+            || line.starts_with("Columns:")
+        {
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        let mut channel = || parts.next().and_then(|v| v.parse::<u8>().ok());
+        let (Some(r), Some(g), Some(b)) = (channel(), channel(), channel()) else {
+            return Err(t!("Bad palette line '{line}'", line = line));
+        };
+        let name = parts.collect::<Vec<_>>().join(" ");
+        entries.push((name, format!("#{:02x}{:02x}{:02x}", r, g, b)));
+    }
+
+    let named: Palette = entries
+        .iter()
+        .filter_map(|(name, hex)| Some((format!("{:?}", TileType::parse(name)?), hex.clone())))
+        .collect();
+    if !named.is_empty() {
+        if named.len() < entries.len() {
+            say!(
+                "Skipped {count} colour(s) not named after a tile",
+                count = entries.len() - named.len()
+            );
+        }
+        return Ok(named);
+    }
+    // Empty stays see-through
+    Ok(TileType::ALL[1..]
+        .iter()
+        .zip(entries)
+        .map(|(tile, (_, hex))| (format!("{:?}", tile), hex))
+        .collect())
+}
+
+fn write_gpl(name: &str, palette: &Palette) -> String {
+    let mut text = format!("GIMP Palette\nName: {}\nColumns: 0\n#\n", name);
+    for (tile, hex) in palette {
+        let Some([r, g, b, a]) = parse_hex(hex).map(to_bytes) else {
+            continue;
+        };
+        // .gpl has no alpha, so fully transparent tiles (Empty) are left out
+        if a > 0 {
+            text += &format!("{:3} {:3} {:3}\t{}\n", r, g, b, tile);
+        }
+    }
+    text
+}
+
+// a .gpl or palette JSON file, by extension
+pub fn load(path: &str) -> Result<Palette, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    if is_gpl(path) {
+        parse_gpl(&text).map_err(|e| format!("{}: {}", path, e))
+    } else {
+        serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+}
+
+pub fn save(path: &str, palette: &Palette) -> Result<(), String> {
+    let text = if is_gpl(path) {
+        let name = Path::new(path)
+            .file_stem()
+            .map_or("wfc".into(), |stem| stem.to_string_lossy());
+        write_gpl(&name, palette)
+    } else {
+        serde_json::to_string_pretty(palette).map_err(|e| e.to_string())?
+    };
+    fs::write(path, text).map_err(|e| t!("Failed to write {path}: {e}", path = path, e = e))
+}
+
+impl TileSystem {
+    // repaints every cell in the current palette; cells keep their colour in the save,
+    // so this runs whenever the palette changes or a map is opened
+    pub fn recolour(&mut self) {
+        if let TileGrid::Sparse { default, .. } = &mut self.tiles {
+            default.colour = colour(&default.tile_type);
+            self.invalidate_index();
+        }
+        let stale: Vec<(usize, usize, Tile)> = self
+            .tiles
+            .cells()
+            .filter(|(_, _, tile)| tile.colour != colour(&tile.tile_type))
+            .map(|(x, y, tile)| {
+                let mut tile = tile.clone();
+                tile.colour = colour(&tile.tile_type);
+                (x, y, tile)
+            })
+            .collect();
+        for (x, y, tile) in stale {
+            self.put_tile(x, y, tile);
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::cli::MapSize;
+use crate::exit::{self, CliError, Failure};
+use crate::i18n::say;
+use crate::maze::MazeAlgorithm;
+use crate::observer::StatsObserver;
+use crate::resize::ResizePolicy;
+use crate::scatter::FeatureKind;
+use crate::svg_export::SvgOptions;
+use crate::text_export::{DEFAULT_GLYPHS, TextOptions};
+use crate::{TileSystem, templates, time_seed};
+
+// a generation workflow written down as ordered stages, e.g. pipelines/islands.toml:
+//
+//   seed = 42
+//   [[stages]]
+//   type = "template"
+//   name = "island_skeleton"
+//   [[stages]]
+//   type = "generate"
+//   model = "coastline"
+//   [[stages]]
+//   type = "export_svg"
+//   path = "islands"
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Pipeline {
+    // base seed, stage i runs with seed + i; left out, a fresh one is picked and printed
+    pub seed: Option<u64>,
+    pub stages: Vec<Stage>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Stage {
+    Clear,
+    Template {
+        name: String,
+    },
+    LoadConfig {
+        name: String,
+        // the map's own policy when not given
+        #[serde(default)]
+        resize: Option<ResizePolicy>,
+    },
+    Maze {
+        #[serde(default)]
+        algorithm: MazeAlgorithm,
+    },
+    Dungeon,
+    Biomes {
+        count: usize,
+    },
+    Erode,
+    // blank model learns from the enabled samples
+    Generate {
+        #[serde(default)]
+        model: String,
+    },
+    Scatter {
+        kind: FeatureKind,
+        spacing: Option<f64>,
+    },
+    // joins the villages and houses placed so far
+    Roads,
+    SaveConfig {
+        name: String,
+    },
+    ExportSvg {
+        path: String,
+        #[serde(default)]
+        gridlines: bool,
+        #[serde(default)]
+        legend: bool,
+    },
+    ExportText {
+        path: String,
+        #[serde(default)]
+        colour: bool,
+        // one character per tile type, see text_export::DEFAULT_GLYPHS
+        glyphs: Option<String>,
+    },
+    // stamped with the last generation's seed and model while the map still matches it
+    ExportPng {
+        path: String,
+        // pixels per tile, 8 when not given
+        scale: Option<usize>,
+    },
+    // writes the whole map state, like the save on exit but to any path
+    SaveMap {
+        path: String,
+    },
+}
+
+impl Pipeline {
+    pub fn load(path: &str) -> Result<Self, CliError> {
+        let text = fs::read_to_string(path).map_err(|e| {
+            CliError::new(
+                Failure::from_io(&e),
+                format!("Failed to read {}: {}", path, e),
+            )
+        })?;
+        toml::from_str(&text).map_err(|e| {
+            CliError::new(Failure::Invalid, format!("Failed to parse {}: {}", path, e))
+        })
+    }
+}
+
+impl TileSystem {
+    // stops at the first failing stage
+    pub fn run_pipeline(&mut self, pipeline: &Pipeline) -> Result<(), CliError> {
+        let seed = pipeline.seed.unwrap_or_else(time_seed);
+        say!(
+            "Running {count} stage(s) with seed {seed}",
+            count = pipeline.stages.len(),
+            seed = seed
+        );
+
+        for (i, stage) in pipeline.stages.iter().enumerate() {
+            let stage_seed = seed.wrapping_add(i as u64);
+            say!(
+                "Stage {i}: {stage}",
+                i = i + 1,
+                stage = format!("{:?}", stage)
+            );
+            match stage {
+                Stage::Clear => self.clear_map(),
+                Stage::Template { name } => {
+                    let templates = templates::load_templates();
+                    let template = templates::find_template(&templates, name).ok_or_else(|| {
+                        CliError::new(
+                            Failure::NotFound,
+                            format!("Stage {} failed: no template '{}'", i + 1, name),
+                        )
+                    })?;
+                    template.apply(self);
+                }
+                Stage::LoadConfig { name, resize } => {
+                    let policy = resize.unwrap_or(self.resize_policy);
+                    if !self.load_config_with(name, policy) {
+                        return Err(CliError::new(
+                            Failure::NotFound,
+                            format!("Stage {} failed: no config '{}'", i + 1, name),
+                        ));
+                    }
+                }
+                Stage::Maze { algorithm } => self.generate_maze(*algorithm, stage_seed),
+                Stage::Dungeon => {
+                    self.generate_dungeon(stage_seed);
+                }
+                Stage::Biomes { count } => self.seed_biomes(*count, stage_seed),
+                Stage::Erode => self.erode(stage_seed),
+                Stage::Generate { model } => {
+                    // a missing model and an unsolvable one exit differently
+                    self.learn_rules(model).map_err(|e| {
+                        CliError::new(Failure::NotFound, format!("Stage {} failed: {}", i + 1, e))
+                    })?;
+                    if !self.generate_with_csp(model, stage_seed, &mut StatsObserver::default()) {
+                        return Err(CliError::new(
+                            Failure::Unsolvable,
+                            format!("Stage {} failed: generation found no solution", i + 1),
+                        ));
+                    }
+                }
+                Stage::Scatter { kind, spacing } => {
+                    let spacing = spacing.unwrap_or_else(|| kind.default_spacing());
+                    self.scatter_features(*kind, spacing, stage_seed);
+                }
+                Stage::Roads => {
+                    self.lay_roads();
+                }
+                Stage::SaveConfig { name } => self.save_config(name.clone()),
+                Stage::ExportSvg {
+                    path,
+                    gridlines,
+                    legend,
+                } => {
+                    let options = SvgOptions {
+                        gridlines: *gridlines,
+                        legend: *legend,
+                    };
+                    self.export_svg(path.trim_end_matches(".svg"), options)
+                        .map_err(|e| CliError::new(Failure::Io, e))?;
+                }
+                Stage::ExportText {
+                    path,
+                    colour,
+                    glyphs,
+                } => {
+                    let options = TextOptions {
+                        glyphs: glyphs.clone().unwrap_or_else(|| DEFAULT_GLYPHS.to_string()),
+                        colour: *colour,
+                    };
+                    self.export_text(path.trim_end_matches(".txt"), &options)
+                        .map_err(|e| CliError::new(Failure::Io, e))?;
+                }
+                Stage::ExportPng { path, scale } => {
+                    self.export_png(path.trim_end_matches(".png"), scale.unwrap_or(8))
+                        .map_err(|e| CliError::new(Failure::Io, e))?;
+                }
+                Stage::SaveMap { path } => self
+                    .try_save_to_path(path)
+                    .map_err(|e| CliError::new(Failure::Io, e))?,
+            }
+        }
+        say!("Pipeline finished");
+        Ok(())
+    }
+}
+
+// `pipeline run <file>` without opening a window, starting from the saved map
+pub fn run_headless(path: &str, size: &MapSize) -> i32 {
+    exit::report(Pipeline::load(path).and_then(|pipeline| {
+        let mut tile_system = TileSystem::load_headless(size)?;
+        tile_system.run_pipeline(&pipeline)
+    }))
+}
@@ -0,0 +1,316 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::bookmarks::GenerationSettings;
+use crate::i18n::{say, t};
+use crate::voronoi::BiomeSeed;
+use crate::{Backend, Tile, TileSystem, TileType, integrity};
+
+pub const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+// tEXt keyword holding the GenerationStamp as JSON
+const STAMP_KEY: &str = "wfc-generation";
+// largest stored deflate block
+const BLOCK: usize = 0xffff;
+
+// what a generation was run with, written into exported PNGs so an image can be
+// regenerated exactly: same model (checked by hash), seed, locks, Voronoi sites and
+// solver settings
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GenerationStamp {
+    pub model: String,
+    pub model_hash: String,
+    pub seed: u64,
+    #[serde(default = "crate::bookmarks::constraint_backend")]
+    pub backend: Backend,
+    pub width: usize,
+    pub height: usize,
+    pub locked: Vec<(usize, usize, TileType)>,
+    pub biome_seeds: Vec<BiomeSeed>,
+    // None in images exported before these were kept
+    #[serde(default)]
+    pub settings: Option<GenerationSettings>,
+}
+
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+pub fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in bytes {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+// zlib stream of stored (uncompressed) blocks, which every PNG reader accepts
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = if data.is_empty() {
+        vec![&[]]
+    } else {
+        data.chunks(BLOCK).collect()
+    };
+    for (i, block) in blocks.iter().enumerate() {
+        out.push((i + 1 == blocks.len()) as u8);
+        let len = block.len() as u16;
+        out.extend(len.to_le_bytes());
+        out.extend((!len).to_le_bytes());
+        out.extend(*block);
+    }
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+// 8-bit RGB image, `rgb` row by row, with a tEXt chunk per (keyword, text)
+pub fn encode(width: usize, height: usize, rgb: &[u8], text: &[(&str, String)]) -> Vec<u8> {
+    let mut png = SIGNATURE.to_vec();
+    let mut header = Vec::new();
+    header.extend((width as u32).to_be_bytes());
+    header.extend((height as u32).to_be_bytes());
+    // bit depth 8, colour type 2 (RGB), default compression, filter and no interlace
+    header.extend([8, 2, 0, 0, 0]);
+    chunk(&mut png, b"IHDR", &header);
+    for (keyword, value) in text {
+        let mut data = keyword.as_bytes().to_vec();
+        data.push(0);
+        data.extend(value.as_bytes());
+        chunk(&mut png, b"tEXt", &data);
+    }
+    // each scanline starts with filter type 0
+    let mut raw = Vec::with_capacity((width * 3 + 1) * height);
+    for row in rgb.chunks((width * 3).max(1)) {
+        raw.push(0);
+        raw.extend(row);
+    }
+    chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+// 16-bit greyscale image, `samples` row by row, for height data
+pub fn encode_grey16(width: usize, height: usize, samples: &[u16]) -> Vec<u8> {
+    let mut png = SIGNATURE.to_vec();
+    let mut header = Vec::new();
+    header.extend((width as u32).to_be_bytes());
+    header.extend((height as u32).to_be_bytes());
+    // bit depth 16, colour type 0 (greyscale)
+    header.extend([16, 0, 0, 0, 0]);
+    chunk(&mut png, b"IHDR", &header);
+    chunk(
+        &mut png,
+        b"tEXt",
+        b"Software\0WaveFunctionCollapse".as_slice(),
+    );
+    let mut raw = Vec::with_capacity((width * 2 + 1) * height);
+    for row in samples.chunks(width.max(1)) {
+        raw.push(0);
+        raw.extend(row.iter().flat_map(|sample| sample.to_be_bytes()));
+    }
+    chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+// the tEXt chunks of any PNG, as (keyword, text)
+pub fn read_text_chunks(bytes: &[u8]) -> Result<Vec<(String, String)>, String> {
+    if !bytes.starts_with(&SIGNATURE) {
+        return Err(t!("Not a PNG file"));
+    }
+    let mut text = Vec::new();
+    let mut at = SIGNATURE.len();
+    while at + 8 <= bytes.len() {
+        let len = u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap()) as usize;
+        let kind = &bytes[at + 4..at + 8];
+        let data = bytes
+            .get(at + 8..at + 8 + len)
+            .ok_or_else(|| t!("The PNG is cut short"))?;
+        if kind == b"tEXt"
+            && let Some(split) = data.iter().position(|b| *b == 0)
+        {
+            text.push((
+                String::from_utf8_lossy(&data[..split]).into_owned(),
+                String::from_utf8_lossy(&data[split + 1..]).into_owned(),
+            ));
+        }
+        if kind == b"IEND" {
+            break;
+        }
+        at += 12 + len;
+    }
+    Ok(text)
+}
+
+impl TileSystem {
+    // fingerprint of the rules a model learns, and the tile weights too for the WFC
+    // solver, which samples by them, so a stamp can tell whether the model has
+    // changed since the image was made
+    pub fn model_hash(&self, model: &str, backend: Backend) -> Result<String, String> {
+        let rules = self.learn_rules(model)?;
+        let mut pairs: Vec<String> = rules
+            .iter()
+            .flat_map(|(tile, allowed)| {
+                allowed
+                    .iter()
+                    .map(move |(dir, neighbour)| format!("{} {:?} {}", tile, dir, neighbour))
+            })
+            .collect();
+        pairs.sort();
+        if backend == Backend::Wfc {
+            pairs.extend(
+                self.learned_weights(model)
+                    .iter()
+                    .enumerate()
+                    .map(|(tile, weight)| format!("{} weight {:016x}", tile, weight.to_bits())),
+            );
+        }
+        Ok(format!(
+            "{:016x}",
+            integrity::fnv1a(pairs.join("\n").as_bytes())
+        ))
+    }
+
+    // the last generation's settings, if the map still shows what it produced
+    pub fn generation_stamp(&self) -> Option<GenerationStamp> {
+        let last = self.last_generation.as_ref()?;
+        if last.thumbnail != self.tile_types() {
+            return None;
+        }
+        Some(GenerationStamp {
+            model: last.model.clone(),
+            model_hash: self.model_hash(&last.model, last.backend).ok()?,
+            seed: last.seed,
+            backend: last.backend,
+            width: self.grid_width,
+            height: self.grid_height,
+            locked: last.locked.clone(),
+            biome_seeds: last.biome_seeds.clone(),
+            settings: last.settings.clone(),
+        })
+    }
+
+    // `scale` pixels per cell, with the generation stamp embedded when there is one
+    pub fn to_png(&self, scale: usize) -> Vec<u8> {
+        let scale = scale.max(1);
+        let (width, height) = (self.grid_width * scale, self.grid_height * scale);
+        let mut rgb = Vec::with_capacity(width * height * 3);
+        let channel = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        for y in 0..height {
+            for x in 0..width {
+                let colour = self
+                    .get_tile(x / scale, y / scale)
+                    .filter(|tile| tile.visible)
+                    .map_or([0.0; 4], |tile| tile.colour);
+                // flattened onto black
+                rgb.extend(colour[..3].iter().map(|v| channel(v * colour[3])));
+            }
+        }
+        let mut text = vec![("Software", "WaveFunctionCollapse".to_string())];
+        if let Some(stamp) = self.generation_stamp() {
+            text.push((
+                "Comment",
+                format!(
+                    "seed {}, model '{}', model hash {}",
+                    stamp.seed, stamp.model, stamp.model_hash
+                ),
+            ));
+            if let Ok(json) = serde_json::to_string(&stamp) {
+                text.push((STAMP_KEY, json));
+            }
+        }
+        encode(width, height, &rgb, &text)
+    }
+
+    pub fn export_png(&self, stem: &str, scale: usize) -> Result<String, String> {
+        let path = format!("{}.png", if stem.is_empty() { "map" } else { stem });
+        fs::write(&path, self.to_png(scale))
+            .map_err(|e| t!("Failed to write {path}: {e}", path = path, e = e))?;
+        if self.generation_stamp().is_some() {
+            say!(
+                "Exported PNG with its generation settings to {path}",
+                path = path
+            );
+        } else {
+            say!(
+                "Exported PNG to {path} (no generation settings, the map has changed since it was generated)",
+                path = path
+            );
+        }
+        Ok(path)
+    }
+
+    // the stamp in an exported PNG, None for images without one
+    pub fn read_png_stamp(path: &str) -> Result<Option<GenerationStamp>, String> {
+        let bytes = fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+        let text = read_text_chunks(&bytes).map_err(|e| format!("{}: {}", path, e))?;
+        text.iter()
+            .find(|(keyword, _)| keyword == STAMP_KEY)
+            .map(|(_, json)| serde_json::from_str(json).map_err(|e| format!("{}: {}", path, e)))
+            .transpose()
+    }
+
+    // puts the stamp's locks, sites and tile settings back and hands over the
+    // model and seed to regenerate with; warns when the model has changed since
+    pub fn restore_stamp(
+        &mut self,
+        stamp: GenerationStamp,
+    ) -> Result<(String, u64, Backend), String> {
+        if (stamp.width, stamp.height) != (self.grid_width, self.grid_height) {
+            return Err(t!(
+                "The image was generated at {width}x{height} but the map is {grid_width}x{grid_height}",
+                width = stamp.width,
+                height = stamp.height,
+                grid_width = self.grid_width,
+                grid_height = self.grid_height
+            ));
+        }
+        self.push_setup_undo();
+        self.locked_cells.clear();
+        self.dungeon_cells.clear();
+        for (x, y, tile_type) in &stamp.locked {
+            self.set_tile(*x, *y, Tile::from_type(tile_type));
+            self.locked_cells.insert((*x, *y));
+        }
+        self.biome_seeds = stamp.biome_seeds;
+        match stamp.settings {
+            Some(settings) => self.set_generation_settings(settings),
+            None => say!(
+                "The image doesn't record its rule overrides, targets, costs, bias, rotations or pruned tiles, using the current ones"
+            ),
+        }
+        // after the settings, since rule overrides change what the model learns
+        if self.model_hash(&stamp.model, stamp.backend).ok().as_ref() != Some(&stamp.model_hash) {
+            say!(
+                "Model '{model}' has changed since the image was made, the result will differ",
+                model = stamp.model
+            );
+        }
+        say!(
+            "Regenerating '{model}' with seed {seed}",
+            model = stamp.model,
+            seed = stamp.seed
+        );
+        Ok((stamp.model, stamp.seed, stamp.backend))
+    }
+}
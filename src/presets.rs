@@ -0,0 +1,535 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+use crate::csp::CspSolver;
+use crate::grid::line_cells;
+use crate::i18n::{say, t};
+use crate::regions::Quiet;
+use crate::{Direction, Tile, TileSystem, TileType, groups};
+
+// shipped presets, also found in presets/ next to the binary
+const BUILTIN_PRESETS: [&str; 4] = [
+    include_str!("../presets/bordered_island.json"),
+    include_str!("../presets/river_crossing.json"),
+    include_str!("../presets/symmetric_arena.json"),
+    include_str!("../presets/connected_landmass.json"),
+];
+
+pub const PRESET_DIR: &str = "presets";
+
+// fresh seeds tried for a map whose landmass is big enough
+const LANDMASS_TRIES: u64 = 8;
+
+// a named set of constraints; "$name" anywhere in `constraints` stands for that
+// parameter, taken from `params` unless given when the preset is picked
+#[derive(Clone, Debug, Deserialize)]
+pub struct ConstraintPreset {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub params: Map<String, Value>,
+    pub constraints: Vec<Value>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Axis {
+    // the left half mirrored onto the right
+    #[serde(rename = "x")]
+    X,
+    // the top half mirrored onto the bottom
+    #[serde(rename = "y")]
+    Y,
+}
+
+// the largest connected patch of `tiles` must cover at least `share` of the map
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MinLandmass {
+    pub tiles: Vec<TileType>,
+    pub share: f64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Constraint {
+    // locks every cell within `width` of the edge
+    Border {
+        tile: TileType,
+        width: usize,
+    },
+    // locks the cells inside, centre and radius as fractions of the map like templates
+    Ellipse {
+        tile: TileType,
+        centre: [f64; 2],
+        radius: [f64; 2],
+    },
+    // locks a `width` cell wide line between two points given as fractions of the map
+    River {
+        tile: TileType,
+        from: [f64; 2],
+        to: [f64; 2],
+        width: usize,
+    },
+    Mirror {
+        axis: Axis,
+    },
+    // a tile or group and the share of the map it must hold in one piece
+    Landmass {
+        tiles: String,
+        share: f64,
+    },
+}
+
+fn substitute(value: &Value, params: &Map<String, Value>) -> Value {
+    match value {
+        Value::String(text) => text
+            .strip_prefix('$')
+            .and_then(|name| params.get(name))
+            .unwrap_or(value)
+            .clone(),
+        Value::Array(items) => Value::Array(items.iter().map(|v| substitute(v, params)).collect()),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(k, v)| (k.clone(), substitute(v, params)))
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+// "width=3" values are JSON where they parse as JSON, tile names in any case
+fn parse_param(text: &str) -> Value {
+    if let Some(tile) = TileType::parse(text) {
+        return Value::String(format!("{:?}", tile));
+    }
+    serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()))
+}
+
+// size of the largest 4-connected patch made of any of `tiles`
+fn largest_patch(rows: &[Vec<usize>], tiles: &[usize]) -> usize {
+    let height = rows.len();
+    let width = rows.first().map_or(0, Vec::len);
+    let mut seen = vec![vec![false; width]; height];
+    let mut largest = 0;
+    for y in 0..height {
+        for x in 0..width {
+            if seen[y][x] || !tiles.contains(&rows[y][x]) {
+                continue;
+            }
+            seen[y][x] = true;
+            let mut size = 0;
+            let mut stack = vec![(x, y)];
+            while let Some((cx, cy)) = stack.pop() {
+                size += 1;
+                for (nx, ny) in [
+                    (cx.wrapping_sub(1), cy),
+                    (cx + 1, cy),
+                    (cx, cy.wrapping_sub(1)),
+                    (cx, cy + 1),
+                ] {
+                    if nx < width && ny < height && !seen[ny][nx] && tiles.contains(&rows[ny][nx]) {
+                        seen[ny][nx] = true;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+            largest = largest.max(size);
+        }
+    }
+    largest
+}
+
+// the same step with the map's axes swapped
+fn transposed(dir: Direction) -> Direction {
+    match dir {
+        Direction::Up => Direction::Left,
+        Direction::Down => Direction::Right,
+        Direction::Left => Direction::Up,
+        Direction::Right => Direction::Down,
+    }
+}
+
+// built-in presets first, user files in presets/ override them by name
+pub fn load_presets() -> Vec<ConstraintPreset> {
+    let mut presets: Vec<ConstraintPreset> = BUILTIN_PRESETS
+        .iter()
+        .filter_map(|json| serde_json::from_str(json).ok())
+        .collect();
+
+    let Ok(entries) = fs::read_dir(PRESET_DIR) else {
+        return presets;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let preset: ConstraintPreset = match fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+        {
+            Ok(preset) => preset,
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    t!("Skipping preset {path}: {e}", path = path.display(), e = e)
+                );
+                continue;
+            }
+        };
+        presets.retain(|p| p.name != preset.name);
+        presets.push(preset);
+    }
+    presets
+}
+
+impl TileSystem {
+    pub fn list_presets(&self, presets: &[ConstraintPreset]) {
+        say!("Constraint presets:");
+        for preset in presets {
+            let params: Vec<String> = preset
+                .params
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect();
+            println!(
+                " - {} ({}): {}",
+                preset.name,
+                params.join(" "),
+                preset.description
+            );
+        }
+        if let Some(axis) = self.symmetry {
+            say!(
+                "Generation mirrors the map across {axis}",
+                axis = format!("{:?}", axis).to_lowercase()
+            );
+        }
+        if let Some(limit) = &self.min_landmass {
+            let names: Vec<String> = limit.tiles.iter().map(|t| format!("{:?}", t)).collect();
+            say!(
+                "Generation needs {percent}% of the map as one piece of {tiles}",
+                percent = format!("{:.0}", limit.share * 100.0),
+                tiles = names.join("/")
+            );
+        }
+    }
+
+    fn lock_as(&mut self, x: usize, y: usize, tile: &TileType) {
+        if x < self.grid_width && y < self.grid_height {
+            self.locked_cells.remove(&(x, y));
+            self.set_tile(x, y, Tile::from_type(tile));
+            self.locked_cells.insert((x, y));
+        }
+    }
+
+    // "bordered_island width=3 + river_crossing", later presets drawn over earlier ones;
+    // "off" drops the mirror and landmass checks, locks stay until unlocked
+    pub fn apply_presets(
+        &mut self,
+        presets: &[ConstraintPreset],
+        spec: &str,
+    ) -> Result<(), String> {
+        if spec == "off" {
+            self.symmetry = None;
+            self.min_landmass = None;
+            self.dirty = true;
+            say!("Mirror and landmass checks off, preset locks stay until unlocked");
+            return Ok(());
+        }
+
+        // everything is checked before the map is touched
+        let mut constraints = Vec::new();
+        for part in spec.split('+') {
+            let mut words = part.split_whitespace();
+            let Some(name) = words.next() else {
+                continue;
+            };
+            let preset = presets
+                .iter()
+                .find(|p| p.name == name)
+                .ok_or_else(|| t!("No preset named '{name}'", name = name))?;
+            let mut params = preset.params.clone();
+            for word in words {
+                let (key, value) = word
+                    .split_once('=')
+                    .ok_or_else(|| t!("Expected <param>=<value>, got '{word}'", word = word))?;
+                if !params.contains_key(key) {
+                    return Err(t!(
+                        "Preset '{name}' has no parameter '{key}'",
+                        name = name,
+                        key = key
+                    ));
+                }
+                params.insert(key.to_string(), parse_param(value));
+            }
+            for constraint in &preset.constraints {
+                let constraint: Constraint =
+                    serde_json::from_value(substitute(constraint, &params))
+                        .map_err(|e| format!("{}: {}", name, e))?;
+                constraints.push(constraint);
+            }
+        }
+        if constraints.is_empty() {
+            return Err(t!("No presets given"));
+        }
+
+        let mut landmass = None;
+        for constraint in &constraints {
+            if let Constraint::Landmass { tiles, share } = constraint {
+                let tiles = self
+                    .resolve_tiles(tiles.trim())
+                    .or_else(|e| groups::parse_tiles(tiles.trim()).map_err(|_| e))?;
+                landmass = Some(MinLandmass {
+                    tiles,
+                    share: share.clamp(0.0, 1.0),
+                });
+            }
+        }
+
+        self.push_undo();
+        let (width, height) = (self.grid_width, self.grid_height);
+        let cell = |f: f64, size: usize| ((f * size as f64) as usize).min(size.saturating_sub(1));
+        for constraint in constraints {
+            match constraint {
+                Constraint::Border { tile, width: ring } => {
+                    for y in 0..height {
+                        for x in 0..width {
+                            if x.min(y).min(width - 1 - x).min(height - 1 - y) < ring {
+                                self.lock_as(x, y, &tile);
+                            }
+                        }
+                    }
+                }
+                Constraint::Ellipse {
+                    tile,
+                    centre,
+                    radius,
+                } => {
+                    for y in 0..height {
+                        for x in 0..width {
+                            let dx = ((x as f64 + 0.5) / width as f64 - centre[0]) / radius[0];
+                            let dy = ((y as f64 + 0.5) / height as f64 - centre[1]) / radius[1];
+                            if dx * dx + dy * dy <= 1.0 {
+                                self.lock_as(x, y, &tile);
+                            }
+                        }
+                    }
+                }
+                Constraint::River {
+                    tile,
+                    from,
+                    to,
+                    width: thickness,
+                } => {
+                    let thickness = thickness.max(1);
+                    let back = (thickness - 1) / 2;
+                    let line = line_cells(
+                        (cell(from[0], width), cell(from[1], height)),
+                        (cell(to[0], width), cell(to[1], height)),
+                    );
+                    for (x, y) in line {
+                        for dy in 0..thickness {
+                            for dx in 0..thickness {
+                                if let (Some(nx), Some(ny)) =
+                                    ((x + dx).checked_sub(back), (y + dy).checked_sub(back))
+                                {
+                                    self.lock_as(nx, ny, &tile);
+                                }
+                            }
+                        }
+                    }
+                }
+                Constraint::Mirror { axis } => self.symmetry = Some(axis),
+                Constraint::Landmass { .. } => {}
+            }
+        }
+        if landmass.is_some() {
+            self.min_landmass = landmass;
+        }
+        self.dirty = true;
+        say!(
+            "Applied presets: {spec}, {count} cell(s) locked",
+            spec = spec,
+            count = self.locked_cells.len()
+        );
+        Ok(())
+    }
+
+    // one half solved under the rules that hold both ways across the axis, then
+    // mirrored onto the other half; locks on the far half pin their mirror cell
+    fn solve_mirrored(
+        &self,
+        rules: &HashMap<usize, HashSet<(Direction, usize)>>,
+        axis: Axis,
+        seed: u64,
+    ) -> Option<Vec<Vec<usize>>> {
+        // solved as a left/right mirror, with the axes swapped for a top/bottom one
+        let (width, height) = match axis {
+            Axis::X => (self.grid_width, self.grid_height),
+            Axis::Y => (self.grid_height, self.grid_width),
+        };
+        let swap = |(x, y): (usize, usize)| match axis {
+            Axis::X => (x, y),
+            Axis::Y => (y, x),
+        };
+        let turned: HashMap<usize, HashSet<(Direction, usize)>> = rules
+            .iter()
+            .map(|(tile, allowed)| {
+                let allowed = allowed
+                    .iter()
+                    .map(|&(dir, n)| match axis {
+                        Axis::X => (dir, n),
+                        Axis::Y => (transposed(dir), n),
+                    })
+                    .collect();
+                (*tile, allowed)
+            })
+            .collect();
+        let allows = |a: usize, dir: Direction, b: usize| {
+            turned
+                .get(&a)
+                .is_some_and(|allowed| allowed.contains(&(dir, b)))
+        };
+        // a beside b mirrors to b beside a
+        let symmetric: HashMap<usize, HashSet<(Direction, usize)>> = turned
+            .iter()
+            .map(|(&tile, allowed)| {
+                let kept = allowed
+                    .iter()
+                    .filter(|&&(dir, n)| {
+                        !matches!(dir, Direction::Left | Direction::Right) || allows(n, dir, tile)
+                    })
+                    .copied()
+                    .collect();
+                (tile, kept)
+            })
+            .collect();
+
+        let half = width.div_ceil(2);
+        let mut fixed = HashMap::new();
+        for (cell, tile) in self.locked_tile_ids() {
+            let (x, y) = swap(cell);
+            fixed.entry((x.min(width - 1 - x), y)).or_insert(tile);
+        }
+        let mut solver = CspSolver::new(&symmetric, TileType::ALL.len(), half, height, seed);
+        // on an even map the middle column sits beside its own mirror
+        if width % 2 == 0 {
+            for y in 0..height {
+                for tile in 0..TileType::ALL.len() {
+                    if !symmetric
+                        .get(&tile)
+                        .is_some_and(|allowed| allowed.contains(&(Direction::Right, tile)))
+                    {
+                        solver.exclude((half - 1, y), tile);
+                    }
+                }
+            }
+        }
+        let left = solver.solve(&fixed, &mut Quiet)?;
+
+        let mut rows = vec![vec![0; self.grid_width]; self.grid_height];
+        for (y, row) in left.iter().enumerate() {
+            for x in 0..width {
+                let (mx, my) = swap((x, y));
+                rows[my][mx] = row[x.min(width - 1 - x)];
+            }
+        }
+        Some(rows)
+    }
+
+    // post-step: the generated map is replaced by a mirrored one when a mirror is set
+    pub fn enforce_symmetry(
+        &self,
+        name: &str,
+        seed: u64,
+        rows: Vec<Vec<usize>>,
+    ) -> Vec<Vec<usize>> {
+        let Some(axis) = self.symmetry else {
+            return rows;
+        };
+        let rules = match self.learn_rules(name) {
+            Ok(rules) => rules,
+            Err(e) => {
+                say!("Skipping the mirror: {e}", e = e);
+                return rows;
+            }
+        };
+        match self.solve_mirrored(&rules, axis, seed) {
+            Some(mirrored) => mirrored,
+            None => {
+                say!(
+                    "No mirrored map for '{name}' with these locks, keeping the unmirrored one",
+                    name = name
+                );
+                rows
+            }
+        }
+    }
+
+    // post-check: too small a landmass sends generation round a few more seeds, keeping
+    // the biggest landmass found
+    pub fn enforce_min_landmass(
+        &self,
+        name: &str,
+        seed: u64,
+        rows: Vec<Vec<usize>>,
+    ) -> Vec<Vec<usize>> {
+        let Some(limit) = &self.min_landmass else {
+            return rows;
+        };
+        let tiles: Vec<usize> = limit.tiles.iter().map(TileType::index).collect();
+        let goal = (limit.share * (self.grid_width * self.grid_height) as f64).ceil() as usize;
+        let mut best_size = largest_patch(&rows, &tiles);
+        if best_size >= goal {
+            return rows;
+        }
+        let rules = match self.learn_rules(name) {
+            Ok(rules) => rules,
+            Err(e) => {
+                say!("Skipping the landmass check: {e}", e = e);
+                return rows;
+            }
+        };
+        let fixed = self.locked_tile_ids();
+        let mut best = rows;
+        for attempt in 1..=LANDMASS_TRIES {
+            let retry_seed = seed.wrapping_add(attempt);
+            let candidate = match self.symmetry {
+                Some(axis) => self.solve_mirrored(&rules, axis, retry_seed),
+                None => CspSolver::new(
+                    &rules,
+                    TileType::ALL.len(),
+                    self.grid_width,
+                    self.grid_height,
+                    retry_seed,
+                )
+                .solve(&fixed, &mut Quiet),
+            };
+            let Some(candidate) = candidate else {
+                continue;
+            };
+            let size = largest_patch(&candidate, &tiles);
+            if size > best_size {
+                best_size = size;
+                best = candidate;
+            }
+            if best_size >= goal {
+                say!("Landmass share met on retry {attempt}", attempt = attempt);
+                return best;
+            }
+        }
+        say!(
+            "The largest landmass covers {percent}% of the map, short of {target}%",
+            percent = format!(
+                "{:.0}",
+                best_size as f64 * 100.0 / (self.grid_width * self.grid_height).max(1) as f64
+            ),
+            target = format!("{:.0}", limit.share * 100.0)
+        );
+        best
+    }
+}
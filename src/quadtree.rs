@@ -0,0 +1,309 @@
+use piston_window::Context;
+
+use crate::grid::TileGrid;
+use crate::{Region, Tile, TileSystem, TileType};
+
+// a square of cells that is all one tile, or split into four quarters;
+// None marks padding beyond the map's edge
+#[derive(Clone, Debug)]
+enum Node {
+    Leaf(Option<Tile>),
+    Split(Box<[Node; 4]>),
+}
+
+impl Node {
+    // any real tile in the node, standing in for the whole of it when zoomed far out
+    fn sample(&self) -> Option<&Tile> {
+        match self {
+            Node::Leaf(tile) => tile.as_ref(),
+            Node::Split(children) => children.iter().find_map(Node::sample),
+        }
+    }
+
+    // collapses four identical leaves back into one
+    fn merge(&mut self) {
+        if let Node::Split(children) = self
+            && let Node::Leaf(first) = &children[0]
+            && children[1..]
+                .iter()
+                .all(|child| matches!(child, Node::Leaf(tile) if tile == first))
+        {
+            *self = Node::Leaf(first.clone());
+        }
+    }
+}
+
+// quadtree over a map's cells for viewport culling and region queries; uniform
+// areas are a single node, so large zoomed-out maps draw a rect per area, not per cell
+#[derive(Clone, Debug)]
+pub struct QuadTree {
+    width: usize,
+    height: usize,
+    // side of the root square, the smallest power of two covering the map
+    size: usize,
+    root: Node,
+}
+
+// quarter `i` of the square at (x, y): top-left, top-right, bottom-left, bottom-right
+fn quarter(x: usize, y: usize, half: usize, i: usize) -> (usize, usize) {
+    (x + (i % 2) * half, y + (i / 2) * half)
+}
+
+impl QuadTree {
+    pub fn build(grid: &TileGrid) -> Self {
+        let (width, height) = (grid.width(), grid.height());
+        let size = width.max(height).max(1).next_power_of_two();
+        let Some(default) = grid.default_tile() else {
+            let root = Self::build_node(grid, 0, 0, size);
+            return QuadTree {
+                width,
+                height,
+                size,
+                root,
+            };
+        };
+        // a sparse grid starts as its default tile and only its stored cells are set,
+        // so the blank bulk of it is never walked cell by cell
+        let mut tree = QuadTree {
+            width,
+            height,
+            size,
+            root: Self::fill_node(width, height, default, 0, 0, size),
+        };
+        for (x, y, tile) in grid.cells() {
+            tree.set(x, y, tile);
+        }
+        tree
+    }
+
+    // `tile` over the part of the square inside the map, padding beyond it
+    fn fill_node(
+        width: usize,
+        height: usize,
+        tile: &Tile,
+        x: usize,
+        y: usize,
+        size: usize,
+    ) -> Node {
+        if x >= width || y >= height {
+            return Node::Leaf(None);
+        }
+        if x + size <= width && y + size <= height {
+            return Node::Leaf(Some(tile.clone()));
+        }
+        let half = size / 2;
+        Node::Split(Box::new([0, 1, 2, 3].map(|i| {
+            let (cx, cy) = quarter(x, y, half, i);
+            Self::fill_node(width, height, tile, cx, cy, half)
+        })))
+    }
+
+    fn build_node(grid: &TileGrid, x: usize, y: usize, size: usize) -> Node {
+        if x >= grid.width() || y >= grid.height() {
+            return Node::Leaf(None);
+        }
+        if size == 1 {
+            return Node::Leaf(grid.get(x, y).cloned());
+        }
+        let half = size / 2;
+        let children = [0, 1, 2, 3].map(|i| {
+            let (cx, cy) = quarter(x, y, half, i);
+            Self::build_node(grid, cx, cy, half)
+        });
+        let mut node = Node::Split(Box::new(children));
+        node.merge();
+        node
+    }
+
+    // keeps the tree in step with a single cell change
+    pub fn set(&mut self, x: usize, y: usize, tile: &Tile) {
+        if x < self.width && y < self.height {
+            Self::set_node(&mut self.root, 0, 0, self.size, x, y, tile);
+        }
+    }
+
+    fn set_node(
+        node: &mut Node,
+        nx: usize,
+        ny: usize,
+        size: usize,
+        x: usize,
+        y: usize,
+        tile: &Tile,
+    ) {
+        if size == 1 {
+            *node = Node::Leaf(Some(tile.clone()));
+            return;
+        }
+        if let Node::Leaf(current) = node {
+            if current.as_ref() == Some(tile) {
+                return;
+            }
+            let current = current.clone();
+            *node = Node::Split(Box::new([0, 1, 2, 3].map(|_| Node::Leaf(current.clone()))));
+        }
+        let half = size / 2;
+        let i = usize::from(x >= nx + half) + 2 * usize::from(y >= ny + half);
+        let (cx, cy) = quarter(nx, ny, half, i);
+        if let Node::Split(children) = node {
+            Self::set_node(&mut children[i], cx, cy, half, x, y, tile);
+        }
+        node.merge();
+    }
+
+    // calls `f(x, y, width, height, tile)` for each uniform area overlapping `region`,
+    // clipped to the map; squares of `lod` cells or fewer are reported whole, as one tile
+    pub fn visit(
+        &self,
+        region: Region,
+        lod: usize,
+        f: &mut dyn FnMut(usize, usize, usize, usize, &Tile),
+    ) {
+        let mut visitor = Visitor {
+            width: self.width,
+            height: self.height,
+            region,
+            lod: lod.max(1),
+            f,
+        };
+        visitor.visit(&self.root, 0, 0, self.size);
+    }
+}
+
+struct Visitor<'a> {
+    width: usize,
+    height: usize,
+    region: Region,
+    lod: usize,
+    f: &'a mut dyn FnMut(usize, usize, usize, usize, &Tile),
+}
+
+impl Visitor<'_> {
+    fn visit(&mut self, node: &Node, x: usize, y: usize, size: usize) {
+        let region = &self.region;
+        if x >= region.x + region.width
+            || y >= region.y + region.height
+            || x + size <= region.x
+            || y + size <= region.y
+        {
+            return;
+        }
+        let clipped_w = size.min(self.width.saturating_sub(x));
+        let clipped_h = size.min(self.height.saturating_sub(y));
+        match node {
+            Node::Leaf(None) => {}
+            Node::Leaf(Some(tile)) => (self.f)(x, y, clipped_w, clipped_h, tile),
+            Node::Split(_) if size <= self.lod => {
+                if let Some(tile) = node.sample() {
+                    (self.f)(x, y, clipped_w, clipped_h, tile);
+                }
+            }
+            Node::Split(children) => {
+                let half = size / 2;
+                for (i, child) in children.iter().enumerate() {
+                    let (cx, cy) = quarter(x, y, half, i);
+                    self.visit(child, cx, cy, half);
+                }
+            }
+        }
+    }
+}
+
+impl TileSystem {
+    // the spatial index, rebuilt after whole-grid changes like undo
+    pub fn with_index<R>(&self, f: impl FnOnce(&QuadTree) -> R) -> R {
+        let mut index = self.index.borrow_mut();
+        f(index.get_or_insert_with(|| QuadTree::build(&self.tiles)))
+    }
+
+    // call after replacing `tiles` wholesale
+    pub fn invalidate_index(&mut self) {
+        *self.index.get_mut() = None;
+    }
+
+    // writes a cell and keeps the index in step
+    pub fn put_tile(&mut self, x: usize, y: usize, tile: Tile) {
+        if let Some(index) = self.index.get_mut() {
+            index.set(x, y, &tile);
+        }
+        self.tiles.set(x, y, tile);
+    }
+
+    // the cells the transform puts inside the window, and how many cells wide a
+    // pixel is there, so squares that small are drawn as one rect
+    pub fn visible_cells(&self, c: &Context) -> (Region, usize) {
+        let full = Region {
+            x: 0,
+            y: 0,
+            width: self.grid_width,
+            height: self.grid_height,
+        };
+        let m = c.transform;
+        let det = m[0][0] * m[1][1] - m[0][1] * m[1][0];
+        if det == 0.0 {
+            return (full, 1);
+        }
+        // the window is -1..1 in device coordinates; map its corners back to map space
+        let to_map = |nx: f64, ny: f64| {
+            let (dx, dy) = (nx - m[0][2], ny - m[1][2]);
+            (
+                (m[1][1] * dx - m[0][1] * dy) / det,
+                (m[0][0] * dy - m[1][0] * dx) / det,
+            )
+        };
+        let corners = [
+            to_map(-1.0, -1.0),
+            to_map(1.0, -1.0),
+            to_map(-1.0, 1.0),
+            to_map(1.0, 1.0),
+        ];
+        let cell = |v: f64, limit: usize| ((v / self.tile_size).max(0.0) as usize).min(limit);
+        let x0 = cell(
+            corners.iter().map(|p| p.0).fold(f64::MAX, f64::min),
+            self.grid_width,
+        );
+        let y0 = cell(
+            corners.iter().map(|p| p.1).fold(f64::MAX, f64::min),
+            self.grid_height,
+        );
+        let x1 = cell(
+            corners.iter().map(|p| p.0).fold(f64::MIN, f64::max) + self.tile_size,
+            self.grid_width,
+        );
+        let y1 = cell(
+            corners.iter().map(|p| p.1).fold(f64::MIN, f64::max) + self.tile_size,
+            self.grid_height,
+        );
+
+        let pixels_per_cell = c.viewport.map_or(f64::MAX, |v| {
+            self.tile_size * m[0][0].abs() * v.window_size[0] / 2.0
+        });
+        let lod = if pixels_per_cell < 1.0 {
+            (1.0 / pixels_per_cell) as usize
+        } else {
+            1
+        };
+        let view = Region {
+            x: x0,
+            y: y0,
+            width: x1 - x0,
+            height: y1 - y0,
+        };
+        (view, lod)
+    }
+
+    // how many cells of each type in TileType::ALL order lie in `region`
+    pub fn tile_counts(&self, region: Region) -> [usize; TileType::ALL.len()] {
+        let mut counts = [0; TileType::ALL.len()];
+        let (x0, y0) = (region.x, region.y);
+        let (x1, y1) = (region.x + region.width, region.y + region.height);
+        self.with_index(|index| {
+            index.visit(region, 1, &mut |x, y, w, h, tile| {
+                let overlap_w = (x + w).min(x1) - x.max(x0);
+                let overlap_h = (y + h).min(y1) - y.max(y0);
+                counts[tile.tile_type.index()] += overlap_w * overlap_h;
+            });
+        });
+        counts
+    }
+}
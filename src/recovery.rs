@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::cli::MapSize;
+use crate::config_meta::SavedConfig;
+use crate::i18n::{say, t};
+use crate::{TileSystem, integrity};
+
+// index just past the string starting at `start` (which must be a quote)
+fn skip_string(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+// index just past the array or object starting at `start`, None if the file ends first
+fn skip_nested(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                i = skip_string(bytes, i)?;
+                continue;
+            }
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+fn skip_blank(bytes: &[u8], mut i: usize, extra: &[u8]) -> usize {
+    while i < bytes.len() && (bytes[i].is_ascii_whitespace() || extra.contains(&bytes[i])) {
+        i += 1;
+    }
+    i
+}
+
+// pulls whatever configs still parse out of the `saved_configs` object, one
+// entry at a time, so a truncated or garbled file gives back everything before the damage
+pub fn salvage_configs(json: &str) -> HashMap<String, SavedConfig> {
+    let mut configs = HashMap::new();
+    let bytes = json.as_bytes();
+    let Some(key) = json.find("\"saved_configs\"") else {
+        return configs;
+    };
+    let mut i = skip_blank(bytes, key + "\"saved_configs\"".len(), b":");
+    if bytes.get(i) != Some(&b'{') {
+        return configs;
+    }
+    i += 1;
+    loop {
+        i = skip_blank(bytes, i, b",");
+        if bytes.get(i) != Some(&b'"') {
+            break;
+        }
+        let Some(name_end) = skip_string(bytes, i) else {
+            break;
+        };
+        let Ok(name) = serde_json::from_str::<String>(&json[i..name_end]) else {
+            break;
+        };
+        i = skip_blank(bytes, name_end, b":");
+        let Some(value_end) = skip_nested(bytes, i) else {
+            break;
+        };
+        match serde_json::from_str::<SavedConfig>(&json[i..value_end]) {
+            Ok(config) => {
+                configs.insert(name, config);
+            }
+            Err(e) => eprintln!(
+                "{}",
+                t!("Couldn't recover '{name}': {e}", name = name, e = e)
+            ),
+        }
+        i = value_end;
+    }
+    configs
+}
+
+impl TileSystem {
+    // like load_or_new, but asks what to do with a damaged save instead of starting fresh
+    pub fn load_or_recover(size: &MapSize) -> Self {
+        let path = Self::SAVE_FILE;
+        match integrity::read_with_backup(path) {
+            Some(Ok(tile_system)) => {
+                say!("Loaded from previous save");
+                tile_system
+            }
+            Some(Err(_)) => Self::recover(path, size),
+            None => {
+                say!("No save file found, starting fresh");
+                Self::with_size(size)
+            }
+        }
+    }
+
+    // an extra map's save, None when there is none; a damaged one gets the same choices
+    // as the main save
+    pub fn open_or_recover(path: &str, size: &MapSize) -> Option<Self> {
+        Some(match integrity::read_with_backup(path)? {
+            Ok(tile_system) => tile_system,
+            Err(_) => Self::recover(path, size),
+        })
+    }
+
+    fn recover(path: &str, size: &MapSize) -> Self {
+        let backup = integrity::backup_path(path);
+        let has_backup = Path::new(&backup).exists();
+        say!("{path} is damaged. What should happen to it?", path = path);
+        if has_backup {
+            say!(
+                " 1) load the backup {backup} as it is, ignoring its checksum",
+                backup = backup
+            );
+        }
+        say!(" 2) recover the saved configurations from it onto a fresh map");
+        say!(" 3) move it aside and start fresh");
+        print!("{}", t!("Choice (blank for 3): "));
+        io::stdout().flush().unwrap();
+        let mut choice = String::new();
+        io::stdin().read_line(&mut choice).ok();
+
+        let recovered = match choice.trim() {
+            "1" if has_backup => match integrity::read_unchecked::<TileSystem>(&backup) {
+                Ok(tile_system) => {
+                    say!("Loaded {backup}", backup = backup);
+                    Some(tile_system)
+                }
+                Err(e) => {
+                    eprintln!("{}", t!("Backup is unusable too: {e}", e = e));
+                    None
+                }
+            },
+            "2" => {
+                let mut tile_system = Self::with_size(size);
+                let json = fs::read(path)
+                    .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+                    .unwrap_or_default();
+                tile_system.saved_configs = salvage_configs(&json);
+                say!(
+                    "Recovered {count} configuration(s)",
+                    count = tile_system.saved_configs.len()
+                );
+                tile_system.dirty = true;
+                Some(tile_system)
+            }
+            _ => None,
+        };
+
+        // always keep the damaged file, just not where the next save would back it up
+        match integrity::quarantine(path) {
+            Ok(target) => say!("Moved the damaged save to {target}", target = target),
+            Err(e) => eprintln!("{}", e),
+        }
+        recovered.unwrap_or_else(|| {
+            say!("Starting fresh");
+            Self::with_size(size)
+        })
+    }
+}
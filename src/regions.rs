@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::csp::CspSolver;
+use crate::i18n::{say, t};
+use crate::observer::GenerationObserver;
+use crate::{TileSystem, TileType, groups};
+
+// rounds of re-solving small regions before generation settles for what it has
+const REPAIR_ROUNDS: usize = 8;
+
+// repair runs shouldn't print a summary each round
+pub struct Quiet;
+
+impl GenerationObserver for Quiet {}
+
+// no connected patch of these tiles may be smaller than `min_cells`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MinRegionSize {
+    pub tiles: Vec<TileType>,
+    pub min_cells: usize,
+}
+
+// 4-connected patches of one tile id smaller than `min_cells`, for tiles in `tiles`
+fn small_regions(
+    rows: &[Vec<usize>],
+    tiles: &[usize],
+    min_cells: usize,
+) -> Vec<(usize, Vec<(usize, usize)>)> {
+    let height = rows.len();
+    let width = rows.first().map_or(0, Vec::len);
+    let mut seen = vec![vec![false; width]; height];
+    let mut small = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let tile = rows[y][x];
+            if seen[y][x] || !tiles.contains(&tile) {
+                continue;
+            }
+            seen[y][x] = true;
+            let mut cells = Vec::new();
+            let mut stack = vec![(x, y)];
+            while let Some((cx, cy)) = stack.pop() {
+                cells.push((cx, cy));
+                let neighbours = [
+                    (cx.wrapping_sub(1), cy),
+                    (cx + 1, cy),
+                    (cx, cy.wrapping_sub(1)),
+                    (cx, cy + 1),
+                ];
+                for (nx, ny) in neighbours {
+                    if nx < width && ny < height && !seen[ny][nx] && rows[ny][nx] == tile {
+                        seen[ny][nx] = true;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+            if cells.len() < min_cells {
+                small.push((tile, cells));
+            }
+        }
+    }
+    small
+}
+
+impl TileSystem {
+    // "<tiles> <cells>", e.g. "water,mountain 4", or "off"
+    pub fn set_min_region_size(&mut self, spec: &str) -> Result<(), String> {
+        if spec == "off" {
+            self.min_region_size = None;
+            self.dirty = true;
+            say!("Minimum region size off");
+            return Ok(());
+        }
+        let (tiles, cells) = spec
+            .rsplit_once(' ')
+            .ok_or_else(|| t!("Expected \"<tiles> <cells>\" or \"off\""))?;
+        let min_cells: usize = cells
+            .trim()
+            .parse()
+            .map_err(|_| t!("'{cells}' isn't a cell count", cells = cells.trim()))?;
+        let tiles = self
+            .resolve_tiles(tiles.trim())
+            .or_else(|e| groups::parse_tiles(tiles.trim()).map_err(|_| e))?;
+        let names: Vec<String> = tiles.iter().map(|t| format!("{:?}", t)).collect();
+        say!(
+            "{names} regions must have at least {min_cells} cells",
+            names = names.join("/"),
+            min_cells = min_cells
+        );
+        self.min_region_size = Some(MinRegionSize { tiles, min_cells });
+        self.dirty = true;
+        Ok(())
+    }
+
+    // post-check and repair: cells of a too-small region, and the ring around them, are
+    // solved again with that tile ruled out in the region, the rest of the map held
+    pub fn enforce_min_region_size(
+        &self,
+        name: &str,
+        seed: u64,
+        rows: Vec<Vec<usize>>,
+    ) -> Vec<Vec<usize>> {
+        let Some(limit) = &self.min_region_size else {
+            return rows;
+        };
+        let tiles: Vec<usize> = limit.tiles.iter().map(TileType::index).collect();
+        let rules = match self.learn_rules(name) {
+            Ok(rules) => rules,
+            Err(e) => {
+                say!("Skipping the minimum region size: {e}", e = e);
+                return rows;
+            }
+        };
+        let locked = self.locked_tile_ids();
+        let mut rows = rows;
+        for round in 0..REPAIR_ROUNDS {
+            // regions with locked cells in them are the user's to keep
+            let small: Vec<_> = small_regions(&rows, &tiles, limit.min_cells)
+                .into_iter()
+                .filter(|(_, cells)| !cells.iter().any(|cell| locked.contains_key(cell)))
+                .collect();
+            if small.is_empty() {
+                if round > 0 {
+                    say!("Repaired small regions in {round} round(s)", round = round);
+                }
+                return rows;
+            }
+            let mut solver = CspSolver::new(
+                &rules,
+                TileType::ALL.len(),
+                self.grid_width,
+                self.grid_height,
+                seed.wrapping_add(round as u64 + 1),
+            );
+            let mut free = HashSet::new();
+            for (tile, cells) in &small {
+                for &(x, y) in cells {
+                    solver.exclude((x, y), *tile);
+                    free.insert((x, y));
+                    for (nx, ny) in [
+                        (x.wrapping_sub(1), y),
+                        (x + 1, y),
+                        (x, y.wrapping_sub(1)),
+                        (x, y + 1),
+                    ] {
+                        if nx < self.grid_width && ny < self.grid_height {
+                            free.insert((nx, ny));
+                        }
+                    }
+                }
+            }
+            let mut fixed = locked.clone();
+            for (y, row) in rows.iter().enumerate() {
+                for (x, tile) in row.iter().enumerate() {
+                    if !free.contains(&(x, y)) {
+                        fixed.entry((x, y)).or_insert(*tile);
+                    }
+                }
+            }
+            match solver.solve(&fixed, &mut Quiet) {
+                Some(repaired) => rows = repaired,
+                None => break,
+            }
+        }
+        let left = small_regions(&rows, &tiles, limit.min_cells)
+            .iter()
+            .filter(|(_, cells)| !cells.iter().any(|cell| locked.contains_key(cell)))
+            .count();
+        if left > 0 {
+            say!(
+                "{left} region(s) under {min_cells} cells couldn't be repaired with these rules",
+                left = left,
+                min_cells = limit.min_cells
+            );
+        }
+        rows
+    }
+}
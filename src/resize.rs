@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::say;
+use crate::{Tile, TileSystem};
+
+// how a config saved at another size is laid over the map
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResizePolicy {
+    // top-left corners aligned, whatever doesn't overlap is left out
+    #[default]
+    Crop,
+    // centres aligned, cut evenly from both sides
+    Center,
+    // stretched or squashed to the map with nearest-neighbour sampling
+    Scale,
+    // the grid is resized to the config first
+    Fit,
+}
+
+impl ResizePolicy {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "crop" => Some(ResizePolicy::Crop),
+            "center" | "centre" => Some(ResizePolicy::Center),
+            "scale" | "nearest" => Some(ResizePolicy::Scale),
+            "fit" | "resize" => Some(ResizePolicy::Fit),
+            _ => None,
+        }
+    }
+
+    // the config cell that lands on map cell (x, y), if any
+    fn source(
+        &self,
+        (x, y): (usize, usize),
+        (map_width, map_height): (usize, usize),
+        (width, height): (usize, usize),
+    ) -> Option<(usize, usize)> {
+        let (sx, sy) = match self {
+            ResizePolicy::Crop | ResizePolicy::Fit => (x, y),
+            ResizePolicy::Center => {
+                let shift = |map: usize, config: usize, v: usize| {
+                    (v as isize + (config as isize - map as isize) / 2)
+                        .try_into()
+                        .ok()
+                };
+                (shift(map_width, width, x)?, shift(map_height, height, y)?)
+            }
+            ResizePolicy::Scale => (x * width / map_width, y * height / map_height),
+        };
+        (sx < width && sy < height).then_some((sx, sy))
+    }
+}
+
+impl TileSystem {
+    // the config's tiles laid over the map by `policy`; cells the config doesn't
+    // cover and locked cells keep their tiles
+    pub fn load_config_with(&mut self, name: &str, policy: ResizePolicy) -> bool {
+        let Some(config) = self.config(name).cloned() else {
+            say!("Configuration '{name}' not found", name = name);
+            return false;
+        };
+        let width = config.iter().map(Vec::len).max().unwrap_or(0);
+        let height = config.len();
+        self.check_config_size(name, policy);
+        self.backup_before(&format!("loading '{}'", name));
+        self.push_undo();
+        if policy == ResizePolicy::Fit {
+            self.resize_grid(width, height);
+        }
+
+        let map_size = (self.grid_width, self.grid_height);
+        for y in 0..self.grid_height {
+            for x in 0..self.grid_width {
+                let Some((sx, sy)) = policy.source((x, y), map_size, (width, height)) else {
+                    continue;
+                };
+                if let Some(tile_type) = config[sy].get(sx)
+                    && !self.is_locked(x, y)
+                {
+                    self.put_tile(x, y, Tile::from_type(tile_type));
+                }
+            }
+        }
+        say!("Loaded configuration: {name}", name = name);
+        true
+    }
+
+    // new grid dimensions, keeping the top-left overlap
+    pub fn resize_grid(&mut self, width: usize, height: usize) {
+        let (width, height) = (width.max(1), height.max(1));
+        if (width, height) == (self.grid_width, self.grid_height) {
+            return;
+        }
+        let mut tiles = self.tiles.blank(width, height);
+        for (x, y, tile) in self.tiles.cells() {
+            if x < width && y < height {
+                tiles.set(x, y, tile.clone());
+            }
+        }
+        self.tiles = tiles;
+        self.sync_grid_size();
+        self.dirty = true;
+
+        self.locked_cells.retain(|&(x, y)| x < width && y < height);
+        self.features.retain(|f| f.x < width && f.y < height);
+        if !self.elevation.is_empty() {
+            self.elevation.resize(height, Vec::new());
+            for row in &mut self.elevation {
+                row.resize(width, 0.0);
+            }
+        }
+        say!(
+            "Map resized to {width}x{height}",
+            width = width,
+            height = height
+        );
+    }
+
+    // grid dimensions from the tile storage, after it is replaced wholesale; the map's
+    // window follows, and letterboxing fits it to the real one
+    pub fn sync_grid_size(&mut self) {
+        self.grid_width = self.tiles.width();
+        self.grid_height = self.tiles.height();
+        self.window_width = self.grid_width as f64 * self.tile_size;
+        self.window_height = self.grid_height as f64 * self.tile_size;
+        self.invalidate_index();
+    }
+
+    // a one-word policy name typed at the console, blank for the map's current one
+    pub fn set_resize_policy(&mut self, input: &str) -> Result<ResizePolicy, String> {
+        if input.is_empty() {
+            return Ok(self.resize_policy);
+        }
+        let policy = ResizePolicy::parse(input).ok_or_else(|| {
+            format!(
+                "Unknown resize policy '{}', expected crop, center, scale or fit",
+                input
+            )
+        })?;
+        self.resize_policy = policy;
+        self.dirty = true;
+        Ok(policy)
+    }
+
+    pub fn config_size(&self, name: &str) -> Option<(usize, usize)> {
+        let config = self.config(name)?;
+        Some((config.iter().map(Vec::len).max().unwrap_or(0), config.len()))
+    }
+}
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::Write;
+
+use crate::Backend;
+use crate::i18n::t;
+use crate::worker::Solver;
+
+// written when no file is named
+pub const DEFAULT_FILE: &str = "generation.resume.json";
+
+// a generation suspended between decisions: the whole solver, with its domains,
+// decision trail and rng where they were, plus what the window needs to take it up
+// again on a map of the same size and tiles
+#[derive(Serialize, Deserialize)]
+pub struct SavedRun {
+    pub label: String,
+    pub seed: u64,
+    pub backend: Backend,
+    pub width: usize,
+    pub height: usize,
+    // what the solver's ids stand for, see StateTable::labels
+    pub states: Vec<String>,
+    pub solver: Solver,
+}
+
+pub fn write(file: &mut File, path: &str, run: &SavedRun) -> Result<(), String> {
+    let json = serde_json::to_string(run).map_err(|e| e.to_string())?;
+    file.write_all(json.as_bytes())
+        .map_err(|e| t!("Failed to write {path}: {e}", path = path, e = e))
+}
+
+pub fn read(path: &str) -> Result<SavedRun, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    serde_json::from_str(&json).map_err(|e| format!("{}: {}", path, e))
+}
@@ -0,0 +1,122 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::i18n::say;
+use crate::scatter::{Feature, FeatureKind};
+use crate::{TileSystem, TileType};
+
+// features roads run between
+const POINTS_OF_INTEREST: [FeatureKind; 2] = [FeatureKind::Village, FeatureKind::House];
+
+// cost of stepping onto a tile; water is only crossed where a bridge saves a long detour
+fn step_cost(tile: &TileType) -> u32 {
+    match tile {
+        TileType::Empty | TileType::Land => 10,
+        TileType::Coast => 15,
+        TileType::Mountain => 60,
+        TileType::Water => 200,
+    }
+}
+
+// following a road already laid is cheap, so branches join the network instead of
+// running alongside it
+const ROAD_COST: u32 = 3;
+
+impl TileSystem {
+    // the cheapest path from any cell in `network` to any of `targets`, as cells
+    // from the target back to where it left the network
+    fn cheapest_path(
+        &self,
+        network: &HashSet<(usize, usize)>,
+        targets: &HashSet<(usize, usize)>,
+    ) -> Option<Vec<(usize, usize)>> {
+        let mut best: HashMap<(usize, usize), u32> = HashMap::new();
+        let mut previous: HashMap<(usize, usize), (usize, usize)> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        for &cell in network {
+            best.insert(cell, 0);
+            queue.push(Reverse((0, cell)));
+        }
+        while let Some(Reverse((cost, (x, y)))) = queue.pop() {
+            if best.get(&(x, y)).is_some_and(|b| *b < cost) {
+                continue;
+            }
+            if targets.contains(&(x, y)) {
+                let mut path = vec![(x, y)];
+                let mut cell = (x, y);
+                while let Some(&before) = previous.get(&cell) {
+                    path.push(before);
+                    cell = before;
+                }
+                return Some(path);
+            }
+            for (nx, ny) in [
+                (x.wrapping_sub(1), y),
+                (x + 1, y),
+                (x, y.wrapping_sub(1)),
+                (x, y + 1),
+            ] {
+                let Some(tile) = self.get_tile(nx, ny) else {
+                    continue;
+                };
+                let step = if self.decoration_at(nx, ny) == Some(FeatureKind::Road) {
+                    ROAD_COST
+                } else {
+                    step_cost(&tile.tile_type)
+                };
+                let next = cost + step;
+                if best.get(&(nx, ny)).is_none_or(|b| next < *b) {
+                    best.insert((nx, ny), next);
+                    previous.insert((nx, ny), (x, y));
+                    queue.push(Reverse((next, (nx, ny))));
+                }
+            }
+        }
+        None
+    }
+
+    // replaces any roads with a network joining every village and house: starting from
+    // one, the cheapest path to the nearest unconnected one is added until all are joined
+    pub fn lay_roads(&mut self) -> usize {
+        let points: Vec<(usize, usize)> = self
+            .features
+            .iter()
+            .filter(|f| POINTS_OF_INTEREST.contains(&f.kind))
+            .map(|f| (f.x, f.y))
+            .collect();
+        if points.len() < 2 {
+            say!("Roads need at least two villages or houses, scatter or paint some first");
+            return 0;
+        }
+        self.push_decoration_undo();
+        self.features.retain(|f| f.kind != FeatureKind::Road);
+
+        let mut network: HashSet<(usize, usize)> = HashSet::from([points[0]]);
+        let mut unconnected: HashSet<(usize, usize)> = points[1..].iter().copied().collect();
+        let mut laid = 0;
+        while !unconnected.is_empty() {
+            let Some(path) = self.cheapest_path(&network, &unconnected) else {
+                break;
+            };
+            for &(x, y) in &path {
+                if self.decoration_at(x, y).is_none() {
+                    self.features.push(Feature {
+                        x,
+                        y,
+                        kind: FeatureKind::Road,
+                    });
+                    laid += 1;
+                }
+                network.insert((x, y));
+                unconnected.remove(&(x, y));
+            }
+        }
+        self.dirty = true;
+        say!(
+            "Laid {laid} road cell(s) joining {count} points of interest",
+            laid = laid,
+            count = points.len()
+        );
+        laid
+    }
+}
@@ -0,0 +1,359 @@
+use piston_window::*;
+use std::collections::{HashMap, HashSet};
+
+use crate::csp::TargetShares;
+use crate::i18n::say;
+use crate::{Backend, Direction, Tile, TileSystem, TileType};
+
+// quarter turns a rotatable tile can take
+pub const ROTATIONS: u8 = 4;
+
+// a quarter turn clockwise, applied `turns` times
+fn turned(dir: Direction, turns: u8) -> Direction {
+    (0..turns % ROTATIONS).fold(dir, |dir, _| match dir {
+        Direction::Up => Direction::Right,
+        Direction::Right => Direction::Down,
+        Direction::Down => Direction::Left,
+        Direction::Left => Direction::Up,
+    })
+}
+
+// what the solver picks between: each tile once, and each rotatable tile once per
+// quarter turn, leaving out pruned tiles; with nothing rotatable or pruned the ids
+// are the plain tile ids
+#[derive(Clone, Debug)]
+pub struct StateTable {
+    states: Vec<(TileType, u8)>,
+    rotatable: Vec<TileType>,
+}
+
+impl StateTable {
+    pub fn new(rotatable: &[TileType], pruned: &[TileType]) -> Self {
+        let mut states = Vec::new();
+        for tile in TileType::ALL {
+            if pruned.contains(&tile) {
+                continue;
+            }
+            let turns = if rotatable.contains(&tile) {
+                ROTATIONS
+            } else {
+                1
+            };
+            for rotation in 0..turns {
+                states.push((tile.clone(), rotation));
+            }
+        }
+        StateTable {
+            states,
+            rotatable: rotatable.to_vec(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_rotatable(&self, tile: &TileType) -> bool {
+        self.rotatable.contains(tile)
+    }
+
+    pub fn state(&self, id: usize) -> &(TileType, u8) {
+        &self.states[id]
+    }
+
+    // the tile, with its turn when it is rotatable
+    pub fn label(&self, id: usize) -> String {
+        let (tile, rotation) = &self.states[id];
+        if self.is_rotatable(tile) {
+            format!("{:?} {}°", tile, *rotation as u32 * 90)
+        } else {
+            format!("{:?}", tile)
+        }
+    }
+
+    // every state's label, indexed by id
+    pub fn labels(&self) -> Vec<String> {
+        (0..self.len()).map(|id| self.label(id)).collect()
+    }
+
+    // None for a pruned tile
+    pub fn id(&self, tile: &TileType, rotation: u8) -> Option<usize> {
+        let rotation = if self.rotatable.contains(tile) {
+            rotation % ROTATIONS
+        } else {
+            0
+        };
+        self.states
+            .iter()
+            .position(|(t, r)| t == tile && *r == rotation)
+    }
+
+    // the sample's rules seen from every quarter turn: two states may meet if the
+    // sample, turned by the rotatable one's rotation, has their tiles meeting that way;
+    // two rotatable states only meet when they're turned the same way
+    pub fn expand_rules(
+        &self,
+        rules: &HashMap<usize, HashSet<(Direction, usize)>>,
+    ) -> HashMap<usize, HashSet<(Direction, usize)>> {
+        let mut expanded: HashMap<usize, HashSet<(Direction, usize)>> = HashMap::new();
+        for (a, (tile_a, turn_a)) in self.states.iter().enumerate() {
+            let allowed = expanded.entry(a).or_default();
+            let Some(base) = rules.get(&tile_a.index()) else {
+                continue;
+            };
+            for (b, (tile_b, turn_b)) in self.states.iter().enumerate() {
+                let turns = match (
+                    self.rotatable.contains(tile_a),
+                    self.rotatable.contains(tile_b),
+                ) {
+                    (true, true) if turn_a != turn_b => continue,
+                    (true, _) => *turn_a,
+                    (false, true) => *turn_b,
+                    (false, false) => 0,
+                };
+                for &(dir, neighbour) in base {
+                    if neighbour == tile_b.index() {
+                        allowed.insert((turned(dir, turns), b));
+                    }
+                }
+            }
+        }
+        expanded
+    }
+
+    // tile-id preferences point at a tile's unturned state
+    pub fn expand_preferences(
+        &self,
+        preferences: &HashMap<(usize, usize), (usize, f64)>,
+    ) -> HashMap<(usize, usize), (usize, f64)> {
+        preferences
+            .iter()
+            .filter_map(|(&cell, &(tile, strength))| {
+                Some((cell, (self.id(&TileType::ALL[tile], 0)?, strength)))
+            })
+            .collect()
+    }
+
+    // a tile's share is split evenly between its rotations
+    pub fn expand_targets(&self, targets: Option<TargetShares>) -> Option<TargetShares> {
+        let targets = targets?;
+        let shares = self
+            .states
+            .iter()
+            .map(|(tile, _)| {
+                let turns = if self.rotatable.contains(tile) {
+                    ROTATIONS
+                } else {
+                    1
+                };
+                targets
+                    .shares
+                    .get(tile.index())
+                    .copied()
+                    .flatten()
+                    .map(|share| share / turns as f64)
+            })
+            .collect();
+        Some(TargetShares {
+            shares,
+            tolerance: targets.tolerance,
+        })
+    }
+
+    // per-tile weights split evenly between each tile's rotations, like targets
+    pub fn expand_weights(&self, weights: &[f64]) -> Vec<f64> {
+        self.states
+            .iter()
+            .map(|(tile, _)| {
+                let turns = if self.rotatable.contains(tile) {
+                    ROTATIONS
+                } else {
+                    1
+                };
+                weights.get(tile.index()).copied().unwrap_or(0.0) / turns as f64
+            })
+            .collect()
+    }
+
+    // every rotation of a tile costs what the tile does
+    pub fn expand_costs(&self, costs: Option<Vec<Vec<f64>>>) -> Option<Vec<Vec<f64>>> {
+        let costs = costs?;
+        Some(
+            self.states
+                .iter()
+                .map(|(a, _)| {
+                    self.states
+                        .iter()
+                        .map(|(b, _)| costs[a.index()][b.index()])
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    // solver rows of state ids as rows of tile ids and rows of rotations
+    pub fn split(&self, rows: Vec<Vec<usize>>) -> (Vec<Vec<usize>>, Vec<Vec<u8>>) {
+        let tiles = rows
+            .iter()
+            .map(|row| row.iter().map(|&id| self.states[id].0.index()).collect())
+            .collect();
+        let turns = rows
+            .iter()
+            .map(|row| row.iter().map(|&id| self.states[id].1).collect())
+            .collect();
+        (tiles, turns)
+    }
+}
+
+impl TileSystem {
+    // a pruned tile stays in while some locked cell holds it, so the lock still holds
+    pub fn state_table(&self) -> StateTable {
+        let pruned: Vec<TileType> = self
+            .pruned_tiles
+            .iter()
+            .filter(|tile| {
+                !self
+                    .locked_cells
+                    .iter()
+                    .any(|&(x, y)| self.get_tile(x, y).is_some_and(|t| &t.tile_type == *tile))
+            })
+            .cloned()
+            .collect();
+        StateTable::new(&self.rotatable_tiles, &pruned)
+    }
+
+    pub fn is_rotatable(&self, tile: &TileType) -> bool {
+        self.rotatable_tiles.contains(tile)
+    }
+
+    // "coast, water" or a group name marks tiles as rotatable, "off" clears them
+    pub fn set_rotatable_tiles(&mut self, spec: &str) -> Result<(), String> {
+        if spec == "off" {
+            self.rotatable_tiles.clear();
+            self.dirty = true;
+            say!("No tiles are rotatable");
+            return Ok(());
+        }
+        let tiles = self
+            .resolve_tiles(spec)
+            .or_else(|e| crate::groups::parse_tiles(spec).map_err(|_| e))?;
+        let names: Vec<String> = tiles.iter().map(|t| format!("{:?}", t)).collect();
+        say!(
+            "{names} rotatable, solved as {tiles} states",
+            names = names.join("/"),
+            tiles = StateTable::new(&tiles, &self.pruned_tiles).len()
+        );
+        self.rotatable_tiles = tiles;
+        self.dirty = true;
+        Ok(())
+    }
+
+    // turns the cell a quarter clockwise if its tile is rotatable
+    pub fn rotate_cell(&mut self, x: usize, y: usize) {
+        let Some(tile) = self.get_tile(x, y).cloned() else {
+            return;
+        };
+        if !self.is_rotatable(&tile.tile_type) {
+            say!(
+                "{tile_type} isn't rotatable",
+                tile_type = format!("{:?}", tile.tile_type)
+            );
+            return;
+        }
+        self.push_undo();
+        let rotation = (tile.rotation + 1) % ROTATIONS;
+        self.set_tile(x, y, Tile { rotation, ..tile });
+    }
+
+    // locked cells as state ids, keeping the rotation they were locked with
+    pub fn locked_state_ids(&self, states: &StateTable) -> HashMap<(usize, usize), usize> {
+        let mut fixed = HashMap::new();
+        for &(x, y) in &self.locked_cells {
+            if let Some(tile) = self.get_tile(x, y)
+                && let Some(id) = states.id(&tile.tile_type, tile.rotation)
+            {
+                fixed.insert((x, y), id);
+            }
+        }
+        fixed
+    }
+
+    // like apply_generated for rows of state ids; rotations are set afterwards on the
+    // cells the minimum-size repair left as the solver had them
+    pub fn apply_generated_states(
+        &mut self,
+        name: &str,
+        seed: u64,
+        result: Option<Vec<Vec<usize>>>,
+        states: &StateTable,
+        backend: Backend,
+    ) -> bool {
+        let Some(rows) = result else {
+            return self.apply_generated(name, seed, None, backend);
+        };
+        let (tiles, turns) = states.split(rows);
+        if !self.apply_generated(name, seed, Some(tiles.clone()), backend) {
+            return false;
+        }
+        for (y, row) in turns.iter().enumerate() {
+            for (x, &rotation) in row.iter().enumerate() {
+                if rotation == 0 || self.locked_cells.contains(&(x, y)) {
+                    continue;
+                }
+                if let Some(tile) = self.get_tile(x, y).cloned()
+                    && tile.tile_type.index() == tiles[y][x]
+                {
+                    self.set_tile(x, y, Tile { rotation, ..tile });
+                }
+            }
+        }
+        true
+    }
+
+    // a bend from the centre to the top and right edges, turned with the cell, so a
+    // rotatable tile shows which way it faces
+    pub fn render_rotations(&self, opacity: f32, c: Context, g: &mut G2d) {
+        if self.rotatable_tiles.is_empty() || opacity <= 0.0 {
+            return;
+        }
+        // too small to read once cells are merged for drawing
+        let (view, lod) = self.visible_cells(&c);
+        if lod > 1 {
+            return;
+        }
+        let size = self.tile_size;
+        let band = size / 4.0;
+        for y in view.y..view.y + view.height {
+            for x in view.x..view.x + view.width {
+                let Some(tile) = self.get_tile(x, y) else {
+                    continue;
+                };
+                if !tile.visible || !self.is_rotatable(&tile.tile_type) {
+                    continue;
+                }
+                let (world_x, world_y) = self.grid_to_world(x, y);
+                let turned = c
+                    .transform
+                    .trans(world_x + size / 2.0, world_y + size / 2.0)
+                    .rot_deg(90.0 * tile.rotation as f64);
+                let mut colour = tile.colour;
+                colour[0] *= 0.6;
+                colour[1] *= 0.6;
+                colour[2] *= 0.6;
+                colour[3] *= opacity;
+                rectangle(
+                    colour,
+                    [-band / 2.0, -size / 2.0, band, size / 2.0 + band / 2.0],
+                    turned,
+                    g,
+                );
+                rectangle(
+                    colour,
+                    [-band / 2.0, -band / 2.0, size / 2.0 + band / 2.0, band],
+                    turned,
+                    g,
+                );
+            }
+        }
+    }
+}
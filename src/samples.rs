@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::i18n::{say, t};
+use crate::{Direction, Region, TileSystem, TileType, build_adjacency_rules};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SampleSetting {
+    pub enabled: bool,
+    // relative importance when samples are combined into one model
+    pub weight: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SampleSource {
+    Config(String),
+    Region(Region),
+}
+
+impl SampleSource {
+    // key into TileSystem::sample_settings
+    pub fn id(&self) -> String {
+        match self {
+            SampleSource::Config(name) => format!("config:{}", name),
+            SampleSource::Region(r) => format!("region:{},{},{}x{}", r.x, r.y, r.width, r.height),
+        }
+    }
+
+    // regions are marked for training so they start enabled, configs are opt-in
+    fn default_setting(&self) -> SampleSetting {
+        SampleSetting {
+            enabled: matches!(self, SampleSource::Region(_)),
+            weight: 1.0,
+        }
+    }
+}
+
+impl TileSystem {
+    // every sample the combined model could learn from, configs first by name
+    pub fn samples(&self) -> Vec<(SampleSource, SampleSetting)> {
+        self.config_names(None)
+            .into_iter()
+            .map(|name| SampleSource::Config(name.clone()))
+            .chain(
+                self.training_regions
+                    .iter()
+                    .copied()
+                    .map(SampleSource::Region),
+            )
+            .map(|source| {
+                let setting = self
+                    .sample_settings
+                    .get(&source.id())
+                    .copied()
+                    .unwrap_or_else(|| source.default_setting());
+                (source, setting)
+            })
+            .collect()
+    }
+
+    pub fn sample_grid(&self, source: &SampleSource) -> Vec<Vec<TileType>> {
+        match source {
+            SampleSource::Config(name) => self.config(name).cloned().unwrap_or_default(),
+            SampleSource::Region(region) => self
+                .tile_types()
+                .iter()
+                .skip(region.y)
+                .take(region.height)
+                .map(|row| {
+                    row.iter()
+                        .skip(region.x)
+                        .take(region.width)
+                        .cloned()
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    pub fn set_sample_setting(&mut self, source: &SampleSource, setting: SampleSetting) {
+        self.sample_settings.insert(source.id(), setting);
+        self.dirty = true;
+    }
+
+    // rules from a saved config, or from every enabled sample when name is blank,
+    // with the adjacency overrides applied on top
+    pub fn learn_rules(
+        &self,
+        name: &str,
+    ) -> Result<HashMap<usize, HashSet<(Direction, usize)>>, String> {
+        let mut rules = self.learn_sample_rules(name)?;
+        self.apply_overrides(&mut rules);
+        Ok(rules)
+    }
+
+    fn learn_sample_rules(
+        &self,
+        name: &str,
+    ) -> Result<HashMap<usize, HashSet<(Direction, usize)>>, String> {
+        let tile_to_id = |tile: &TileType| tile.index();
+        if !name.is_empty() {
+            return self
+                .config(name)
+                .map(|sample| build_adjacency_rules(sample, &tile_to_id))
+                .ok_or_else(|| t!("Configuration '{name}' not found", name = name));
+        }
+
+        let enabled: Vec<SampleSource> = self
+            .samples()
+            .into_iter()
+            .filter(|(_, setting)| setting.enabled && setting.weight > 0.0)
+            .map(|(source, _)| source)
+            .collect();
+        if enabled.is_empty() {
+            return Err(t!("No configuration named and no samples enabled"));
+        }
+        // samples are learned separately so no rule comes from across a region edge
+        let mut rules: HashMap<usize, HashSet<(Direction, usize)>> = HashMap::new();
+        for source in enabled {
+            for (tile, neighbours) in build_adjacency_rules(&self.sample_grid(&source), &tile_to_id)
+            {
+                rules.entry(tile).or_default().extend(neighbours);
+            }
+        }
+        Ok(rules)
+    }
+
+    pub fn list_samples(&self) {
+        let samples = self.samples();
+        if samples.is_empty() {
+            say!("No samples, save a config or mark a training region");
+            return;
+        }
+        say!("Samples:");
+        for (i, (source, setting)) in samples.iter().enumerate() {
+            println!(
+                " {:>2} [{}] {:<28} weight {:.2}",
+                i,
+                if setting.enabled { "x" } else { " " },
+                source.id(),
+                setting.weight
+            );
+        }
+    }
+
+    // "<index> on|off" or "<index> weight <w>" typed into the sample manager
+    pub fn edit_sample(&mut self, command: &str) -> Result<(), String> {
+        let mut parts = command.split_whitespace();
+        let index: usize = parts
+            .next()
+            .and_then(|i| i.parse().ok())
+            .ok_or_else(|| t!("Expected a sample index"))?;
+        let (source, mut setting) = self
+            .samples()
+            .into_iter()
+            .nth(index)
+            .ok_or_else(|| t!("No sample {index}", index = index))?;
+        match (parts.next(), parts.next()) {
+            (Some("on"), None) => setting.enabled = true,
+            (Some("off"), None) => setting.enabled = false,
+            (Some("weight"), Some(weight)) => {
+                setting.weight = weight
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|w| *w >= 0.0)
+                    .ok_or_else(|| t!("Bad weight '{weight}'", weight = weight))?;
+            }
+            _ => return Err(t!("Expected on, off or weight <w>")),
+        }
+        self.set_sample_setting(&source, setting);
+        println!("{}: {:?}", source.id(), setting);
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+use piston_window::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::f64::consts::TAU;
+
+use crate::i18n::say;
+use crate::layers;
+use crate::{TileSystem, TileType};
+
+// candidates tried around each active point before it is retired
+const ATTEMPTS: usize = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FeatureKind {
+    Tree,
+    Village,
+    Rock,
+    House,
+    // laid by the road generator rather than scattered
+    Road,
+}
+
+impl FeatureKind {
+    pub const ALL: [FeatureKind; 4] = [
+        FeatureKind::Tree,
+        FeatureKind::Village,
+        FeatureKind::Rock,
+        FeatureKind::House,
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "tree" | "trees" => Some(FeatureKind::Tree),
+            "village" | "villages" => Some(FeatureKind::Village),
+            "rock" | "rocks" => Some(FeatureKind::Rock),
+            "house" | "houses" => Some(FeatureKind::House),
+            _ => None,
+        }
+    }
+
+    // terrain a feature may sit on
+    pub fn eligible(&self) -> &'static [TileType] {
+        match self {
+            FeatureKind::Tree => &[TileType::Land],
+            FeatureKind::Village => &[TileType::Land, TileType::Coast],
+            FeatureKind::Rock => &[TileType::Mountain, TileType::Land],
+            FeatureKind::House => &[TileType::Land, TileType::Coast],
+            FeatureKind::Road => &[TileType::Land, TileType::Coast, TileType::Empty],
+        }
+    }
+
+    // minimum distance between two features of this kind, in tiles
+    pub fn default_spacing(&self) -> f64 {
+        match self {
+            FeatureKind::Tree => 1.5,
+            FeatureKind::Village => 5.0,
+            FeatureKind::Rock => 2.5,
+            FeatureKind::House => 2.0,
+            FeatureKind::Road => 1.0,
+        }
+    }
+
+    pub fn colour(&self) -> [f32; 4] {
+        match self {
+            FeatureKind::Tree => [0.05, 0.35, 0.1, 1.0],
+            FeatureKind::Village => [0.7, 0.25, 0.15, 1.0],
+            FeatureKind::Rock => [0.35, 0.35, 0.35, 1.0],
+            FeatureKind::House => [0.85, 0.7, 0.3, 1.0],
+            FeatureKind::Road => [0.55, 0.45, 0.3, 1.0],
+        }
+    }
+
+    // drawn over the terrain glyph in text exports
+    pub fn glyph(&self) -> char {
+        match self {
+            FeatureKind::Tree => 'T',
+            FeatureKind::Village => 'V',
+            FeatureKind::Rock => 'o',
+            FeatureKind::House => 'H',
+            FeatureKind::Road => '#',
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Feature {
+    pub x: usize,
+    pub y: usize,
+    pub kind: FeatureKind,
+}
+
+// Bridson's Poisson-disk sampling over a width x height area, no two points closer than spacing
+pub fn poisson_disk(width: f64, height: f64, spacing: f64, seed: u64) -> Vec<[f64; 2]> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let cell_size = spacing / 2f64.sqrt();
+    let cols = (width / cell_size).ceil() as usize;
+    let rows = (height / cell_size).ceil() as usize;
+    if cols == 0 || rows == 0 {
+        return Vec::new();
+    }
+    // background grid holding at most one point per cell
+    let mut grid: Vec<Option<usize>> = vec![None; cols * rows];
+    let cell_of = |p: [f64; 2]| {
+        let col = ((p[0] / cell_size) as usize).min(cols - 1);
+        let row = ((p[1] / cell_size) as usize).min(rows - 1);
+        (col, row)
+    };
+
+    let first = [rng.gen_range(0.0..width), rng.gen_range(0.0..height)];
+    let (col, row) = cell_of(first);
+    grid[row * cols + col] = Some(0);
+    let mut points = vec![first];
+    let mut active = vec![0];
+
+    while !active.is_empty() {
+        let slot = rng.gen_range(0..active.len());
+        let centre = points[active[slot]];
+        let mut placed = false;
+
+        for _ in 0..ATTEMPTS {
+            let angle = rng.gen_range(0.0..TAU);
+            let distance = rng.gen_range(spacing..2.0 * spacing);
+            let candidate = [
+                centre[0] + angle.cos() * distance,
+                centre[1] + angle.sin() * distance,
+            ];
+            if candidate[0] < 0.0
+                || candidate[1] < 0.0
+                || candidate[0] >= width
+                || candidate[1] >= height
+            {
+                continue;
+            }
+
+            let (col, row) = cell_of(candidate);
+            let too_close = (row.saturating_sub(2)..(row + 3).min(rows)).any(|r| {
+                (col.saturating_sub(2)..(col + 3).min(cols)).any(|c| {
+                    grid[r * cols + c].is_some_and(|i| {
+                        let dx = points[i][0] - candidate[0];
+                        let dy = points[i][1] - candidate[1];
+                        dx * dx + dy * dy < spacing * spacing
+                    })
+                })
+            });
+            if !too_close {
+                grid[row * cols + col] = Some(points.len());
+                active.push(points.len());
+                points.push(candidate);
+                placed = true;
+                break;
+            }
+        }
+
+        if !placed {
+            active.swap_remove(slot);
+        }
+    }
+    points
+}
+
+impl TileSystem {
+    // replaces this kind's features; the whole map is sampled then filtered so every
+    // island gets coverage, not just the one the first point landed on
+    pub fn scatter_features(&mut self, kind: FeatureKind, spacing: f64, seed: u64) -> usize {
+        let spacing = spacing.max(1.0);
+        self.features.retain(|f| f.kind != kind);
+
+        let mut taken: HashSet<(usize, usize)> = self.features.iter().map(|f| (f.x, f.y)).collect();
+        let points = poisson_disk(
+            self.grid_width as f64,
+            self.grid_height as f64,
+            spacing,
+            seed,
+        );
+        let mut placed = 0;
+        for point in points {
+            let (x, y) = (point[0] as usize, point[1] as usize);
+            let eligible = self
+                .get_tile(x, y)
+                .is_some_and(|tile| kind.eligible().contains(&tile.tile_type));
+            if eligible && taken.insert((x, y)) {
+                self.features.push(Feature { x, y, kind });
+                placed += 1;
+            }
+        }
+        self.dirty = true;
+        say!(
+            "Scattered {placed} {kind} feature(s), spacing {spacing} with seed {seed}",
+            placed = placed,
+            kind = format!("{:?}", kind),
+            spacing = spacing,
+            seed = seed
+        );
+        placed
+    }
+
+    pub fn clear_features(&mut self) {
+        self.dirty |= !self.features.is_empty();
+        self.features.clear();
+        say!("Features cleared");
+    }
+
+    pub fn render_features(&self, opacity: f32, c: Context, g: &mut G2d) {
+        if opacity <= 0.0 {
+            return;
+        }
+        let size = self.tile_size / 2.0;
+        for feature in &self.features {
+            let colour = layers::faded(feature.kind.colour(), opacity);
+            let (world_x, world_y) = self.grid_to_world(feature.x, feature.y);
+            let area = [world_x + size / 2.0, world_y + size / 2.0, size, size];
+            // roads are squares so neighbouring cells read as one path
+            if feature.kind == FeatureKind::Road {
+                rectangle(colour, area, c.transform, g);
+            } else {
+                ellipse(colour, area, c.transform, g);
+            }
+        }
+    }
+}
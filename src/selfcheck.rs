@@ -0,0 +1,298 @@
+use clap::Args;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+
+use crate::csp::{CellOrder, CspSolver, SolverSettings, Strategy};
+use crate::exit::{self, CliError, Failure};
+use crate::i18n::{say, t};
+use crate::observer::StatsObserver;
+use crate::wfc::{WfcOptions, WfcSolver};
+use crate::{Backend, Direction, TileType, build_adjacency_rules, integrity};
+
+// case name to the hash of its map, recorded with --bless on a build known to be right;
+// a selfcheck/golden.json under the working directory wins over the one built in
+const GOLDEN_FILE: &str = "selfcheck/golden.json";
+const BUNDLED_GOLDEN: &str = include_str!("../selfcheck/golden.json");
+
+// the bundled sample: ~ water, . coast, " land, ^ mountain
+const SAMPLE: [&str; 10] = [
+    "~~~~~~~~~~~~",
+    "~~~....~~~~~",
+    "~~.\"\"\"\"..~~~",
+    "~.\"\"^^\"\"\".~~",
+    "~.\"^^^^\"\"\".~",
+    "~.\"\"^^\"\"\"\".~",
+    "~~.\"\"\"\"\"\".~~",
+    "~~~..\"\"..~~~",
+    "~~~~~..~~~~~",
+    "~~~~~~~~~~~~",
+];
+
+#[derive(Args)]
+pub struct SelfcheckArgs {
+    /// Record this build's hashes as the golden ones in selfcheck/golden.json
+    #[arg(long)]
+    bless: bool,
+}
+
+// one fixed generation; every solver feature that changes the result gets a case
+struct Case {
+    name: &'static str,
+    width: usize,
+    height: usize,
+    seed: u64,
+    backend: Backend,
+    // the constraint solver's settings, which the WFC solver has no counterpart for
+    cell_order: CellOrder,
+    strategy: Strategy,
+    // Water next to Mountain costs this much, 0 for no cost matrix
+    water_mountain_cost: f64,
+}
+
+const CASES: [Case; 8] = [
+    Case {
+        name: "min-remaining-backtrack-16x16-seed-1",
+        width: 16,
+        height: 16,
+        seed: 1,
+        backend: Backend::Sat,
+        cell_order: CellOrder::MinimumRemaining,
+        strategy: Strategy::Backtrack,
+        water_mountain_cost: 0.0,
+    },
+    Case {
+        name: "min-remaining-backtrack-48x32-seed-7",
+        width: 48,
+        height: 32,
+        seed: 7,
+        backend: Backend::Sat,
+        cell_order: CellOrder::MinimumRemaining,
+        strategy: Strategy::Backtrack,
+        water_mountain_cost: 0.0,
+    },
+    Case {
+        name: "scanline-backtrack-16x16-seed-3",
+        width: 16,
+        height: 16,
+        seed: 3,
+        backend: Backend::Sat,
+        cell_order: CellOrder::Scanline,
+        strategy: Strategy::Backtrack,
+        water_mountain_cost: 0.0,
+    },
+    Case {
+        name: "min-remaining-restart-24x24-seed-42",
+        width: 24,
+        height: 24,
+        seed: 42,
+        backend: Backend::Sat,
+        cell_order: CellOrder::MinimumRemaining,
+        strategy: Strategy::Restart,
+        water_mountain_cost: 0.0,
+    },
+    Case {
+        name: "transition-costs-24x24-seed-5",
+        width: 24,
+        height: 24,
+        seed: 5,
+        backend: Backend::Sat,
+        cell_order: CellOrder::MinimumRemaining,
+        strategy: Strategy::Backtrack,
+        water_mountain_cost: 3.0,
+    },
+    Case {
+        name: "wfc-16x16-seed-1",
+        width: 16,
+        height: 16,
+        seed: 1,
+        backend: Backend::Wfc,
+        cell_order: CellOrder::MinimumRemaining,
+        strategy: Strategy::Backtrack,
+        water_mountain_cost: 0.0,
+    },
+    Case {
+        name: "wfc-48x32-seed-7",
+        width: 48,
+        height: 32,
+        seed: 7,
+        backend: Backend::Wfc,
+        cell_order: CellOrder::MinimumRemaining,
+        strategy: Strategy::Backtrack,
+        water_mountain_cost: 0.0,
+    },
+    Case {
+        name: "wfc-transition-costs-24x24-seed-5",
+        width: 24,
+        height: 24,
+        seed: 5,
+        backend: Backend::Wfc,
+        cell_order: CellOrder::MinimumRemaining,
+        strategy: Strategy::Backtrack,
+        water_mountain_cost: 3.0,
+    },
+];
+
+fn sample_grid() -> Vec<Vec<TileType>> {
+    SAMPLE
+        .iter()
+        .map(|row| {
+            row.chars()
+                .map(|glyph| match glyph {
+                    '~' => TileType::Water,
+                    '.' => TileType::Coast,
+                    '"' => TileType::Land,
+                    _ => TileType::Mountain,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Water next to Mountain costing `cost` either way
+fn water_mountain_costs(cost: f64) -> Vec<Vec<f64>> {
+    let mut costs = vec![vec![0.0; TileType::ALL.len()]; TileType::ALL.len()];
+    let (water, mountain) = (TileType::Water.index(), TileType::Mountain.index());
+    costs[water][mountain] = cost;
+    costs[mountain][water] = cost;
+    costs
+}
+
+// how often each tile appears in the sample, which the WFC solver picks by
+fn sample_weights() -> Vec<f64> {
+    let mut counts = vec![0.0; TileType::ALL.len()];
+    for tile in sample_grid().iter().flatten() {
+        counts[tile.index()] += 1.0;
+    }
+    counts
+}
+
+// hash of the finished map, "unsolvable" when there was none
+fn run_case(case: &Case, rules: &HashMap<usize, HashSet<(Direction, usize)>>) -> String {
+    let costs =
+        (case.water_mountain_cost > 0.0).then(|| water_mountain_costs(case.water_mountain_cost));
+    let rows = match case.backend {
+        Backend::Sat => {
+            let mut solver = CspSolver::new(
+                rules,
+                TileType::ALL.len(),
+                case.width,
+                case.height,
+                case.seed,
+            )
+            .with_settings(SolverSettings {
+                cell_order: case.cell_order,
+                strategy: case.strategy,
+                ..SolverSettings::default()
+            });
+            solver.set_transition_costs(costs);
+            solver.solve(&HashMap::new(), &mut StatsObserver::default())
+        }
+        Backend::Wfc => {
+            let mut solver = WfcSolver::new(
+                rules,
+                TileType::ALL.len(),
+                case.width,
+                case.height,
+                case.seed,
+            )
+            .with_options(WfcOptions::default());
+            solver.set_weights(&sample_weights());
+            solver.set_transition_costs(costs);
+            solver.solve(&mut StatsObserver::default())
+        }
+    };
+    let Some(rows) = rows else {
+        return "unsolvable".to_string();
+    };
+    let bytes: Vec<u8> = rows.iter().flatten().map(|&tile| tile as u8).collect();
+    format!("{:016x}", integrity::fnv1a(&bytes))
+}
+
+fn load_golden() -> Result<BTreeMap<String, String>, CliError> {
+    let json = fs::read_to_string(GOLDEN_FILE).unwrap_or_else(|_| BUNDLED_GOLDEN.to_string());
+    serde_json::from_str(&json)
+        .map_err(|e| CliError::new(Failure::Invalid, format!("{}: {}", GOLDEN_FILE, e)))
+}
+
+// `selfcheck [--bless]`: solves the bundled sample with fixed seeds, twice each, and
+// compares the maps' hashes with the golden ones
+pub fn run_headless(args: SelfcheckArgs) -> i32 {
+    exit::report(selfcheck(args))
+}
+
+fn selfcheck(args: SelfcheckArgs) -> Result<(), CliError> {
+    let rules = build_adjacency_rules(&sample_grid(), &|tile: &TileType| tile.index());
+    let golden = load_golden()?;
+    let mut hashes = BTreeMap::new();
+    let mut diverged = 0;
+    let mut missing = 0;
+    for case in &CASES {
+        let hash = run_case(case, &rules);
+        // a second run in the same process catches anything that depends on hash map
+        // order or other state outside the seed
+        if run_case(case, &rules) != hash {
+            say!("UNSTABLE {name}: two runs disagree", name = case.name);
+            diverged += 1;
+        } else {
+            match golden.get(case.name) {
+                Some(expected) if *expected == hash => {
+                    say!("ok       {name} {hash}", name = case.name, hash = hash)
+                }
+                Some(expected) => {
+                    say!(
+                        "DIVERGED {name}: expected {expected}, got {hash}",
+                        name = case.name,
+                        expected = expected,
+                        hash = hash
+                    );
+                    diverged += 1;
+                }
+                None => {
+                    say!("new      {name} {hash}", name = case.name, hash = hash);
+                    missing += 1;
+                }
+            }
+        }
+        hashes.insert(case.name.to_string(), hash);
+    }
+
+    if args.bless {
+        let json = serde_json::to_string_pretty(&hashes)
+            .map_err(|e| CliError::new(Failure::Io, e.to_string()))?;
+        fs::create_dir_all("selfcheck")
+            .and_then(|()| fs::write(GOLDEN_FILE, json + "\n"))
+            .map_err(|e| {
+                CliError::new(
+                    Failure::from_io(&e),
+                    t!("Failed to write {path}: {e}", path = GOLDEN_FILE, e = e),
+                )
+            })?;
+        say!(
+            "Recorded {count} golden hash(es) in {path}",
+            count = hashes.len(),
+            path = GOLDEN_FILE
+        );
+        return Ok(());
+    }
+    if missing > 0 {
+        return Err(CliError::new(
+            Failure::Invalid,
+            t!(
+                "{count} case(s) have no golden hash, record them with `selfcheck --bless` on a build known to be right",
+                count = missing
+            ),
+        ));
+    }
+    if diverged > 0 {
+        return Err(CliError::new(
+            Failure::Invalid,
+            t!(
+                "{count} of {total} case(s) diverged",
+                count = diverged,
+                total = CASES.len()
+            ),
+        ));
+    }
+    say!("Solver output matches");
+    Ok(())
+}
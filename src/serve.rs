@@ -0,0 +1,263 @@
+use clap::Args;
+use std::io::{self, Read};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+use crate::cli::MapSize;
+use crate::config::AppConfig;
+use crate::exit::{self, CliError, Failure};
+use crate::i18n::{say, t};
+use crate::observer::StatsObserver;
+use crate::text_export::{TextOptions, to_text};
+use crate::{TileSystem, library, time_seed};
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Port to listen on
+    #[arg(long, default_value_t = 8080)]
+    port: u16,
+    /// Address to bind, 0.0.0.0 to allow other machines
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+    #[command(flatten)]
+    size: MapSize,
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+// how long a client may take to send its request or read the answer before it's
+// dropped, so one stalled connection can't hold up the rest
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+// the most a whole request may take to arrive, however steadily it trickles in
+const REQUEST_DEADLINE: Duration = Duration::from_secs(10);
+
+// form bodies larger than this are refused
+const MAX_BODY: usize = 64 * 1024;
+
+// longest request line or header line, and the most header lines, before a request
+// is refused unread
+const MAX_LINE: usize = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+
+// the client's side of a connection, its reads cut short once the request's deadline
+// has passed
+struct DeadlineReader {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "request took too long to arrive",
+            ));
+        }
+        self.stream.set_read_timeout(Some(left.min(IO_TIMEOUT)))?;
+        self.stream.read(buf)
+    }
+}
+
+// one line of at most MAX_LINE bytes; false when it was cut off there
+fn read_capped_line(reader: &mut impl BufRead, line: &mut String) -> io::Result<bool> {
+    line.clear();
+    let read = reader.take(MAX_LINE as u64).read_line(line)?;
+    Ok(read < MAX_LINE || line.ends_with('\n'))
+}
+
+impl Response {
+    fn json(body: String) -> Self {
+        Response {
+            status: "200 OK",
+            content_type: "application/json",
+            body,
+        }
+    }
+
+    fn error(status: &'static str, message: &str) -> Self {
+        Response {
+            status,
+            content_type: "text/plain",
+            body: format!("{}\n", message),
+        }
+    }
+}
+
+// `%20` and `+` in query values
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn query_value(query: &str, key: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| decode(v))
+}
+
+impl TileSystem {
+    // GET routes read the map, POST /generate changes it; every answer is JSON apart
+    // from /text
+    fn respond(&mut self, method: &str, path: &str, query: &str) -> Response {
+        let allowed = if path == "/generate" { "POST" } else { "GET" };
+        if method != allowed {
+            return Response::error(
+                "405 Method Not Allowed",
+                &format!("{} takes {}", path, allowed),
+            );
+        }
+        match path {
+            "/map" => Response::json(serde_json::to_string(&self.tile_types()).unwrap_or_default()),
+            "/configs" => {
+                Response::json(serde_json::to_string(&self.config_names(None)).unwrap_or_default())
+            }
+            "/text" => Response {
+                status: "200 OK",
+                content_type: "text/plain; charset=utf-8",
+                body: to_text(self, &TextOptions::default()),
+            },
+            "/generate" => {
+                let model = query_value(query, "model").unwrap_or_default();
+                let seed = match query_value(query, "seed") {
+                    Some(seed) => match seed.parse() {
+                        Ok(seed) => seed,
+                        Err(_) => {
+                            return Response::error("400 Bad Request", "seed must be a number");
+                        }
+                    },
+                    None => time_seed(),
+                };
+                if self.generate_with_csp(&model, seed, &mut StatsObserver::default()) {
+                    Response::json(serde_json::to_string(&self.tile_types()).unwrap_or_default())
+                } else {
+                    Response::error("422 Unprocessable Entity", "no solution for that model")
+                }
+            }
+            _ => Response::error("404 Not Found", "try /map, /configs, /text or /generate"),
+        }
+    }
+}
+
+fn handle(stream: TcpStream, tile_system: &mut TileSystem) -> std::io::Result<()> {
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let mut reader = BufReader::new(DeadlineReader {
+        stream: stream.try_clone()?,
+        deadline: Instant::now() + REQUEST_DEADLINE,
+    });
+    let mut request_line = String::new();
+    let mut refused = None;
+    if !read_capped_line(&mut reader, &mut request_line)? {
+        refused = Some(Response::error(
+            "414 URI Too Long",
+            "request line is too long",
+        ));
+    }
+    // only Content-Length matters, the other headers are read and ignored
+    let mut content_length = 0;
+    let mut header = String::new();
+    let mut headers = 0;
+    while refused.is_none() {
+        let whole = read_capped_line(&mut reader, &mut header)?;
+        if whole && header.len() <= 2 {
+            break;
+        }
+        if !whole || headers == MAX_HEADERS {
+            refused = Some(Response::error(
+                "431 Request Header Fields Too Large",
+                "too many or too long header lines",
+            ));
+            break;
+        }
+        headers += 1;
+        if let Some((name, value)) = header.split_once(':')
+            && name.trim().eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().unwrap_or(0);
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let target = parts.next().unwrap_or("/");
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let response = if let Some(response) = refused {
+        response
+    } else if content_length > MAX_BODY {
+        Response::error("413 Payload Too Large", "request body is too large")
+    } else {
+        // a form body takes the place of the query string
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+        let body = String::from_utf8_lossy(&body);
+        let params = if body.is_empty() { query } else { body.trim() };
+        tile_system.respond(method, path, params)
+    };
+    println!("{} {} -> {}", method, target, response.status);
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.content_type,
+        response.body.len(),
+        response.body
+    )?;
+    stream.flush()
+}
+
+// `serve [--host addr] [--port N]`: the saved map over HTTP, one request at a time
+pub fn run(args: ServeArgs) -> i32 {
+    exit::report(serve(args))
+}
+
+fn serve(args: ServeArgs) -> Result<(), CliError> {
+    let mut tile_system = TileSystem::load_headless(&args.size)?;
+    let (configs, tags) = library::scan(&AppConfig::load().library_dir);
+    tile_system.set_library(configs, &tags);
+
+    let address = format!("{}:{}", args.host, args.port);
+    let listener = TcpListener::bind(&address).map_err(|e| {
+        CliError::new(
+            Failure::Io,
+            format!("Failed to listen on {}: {}", address, e),
+        )
+    })?;
+    say!("Serving on http://{address}", address = address);
+    for stream in listener.incoming() {
+        let result = stream.and_then(|stream| handle(stream, &mut tile_system));
+        if let Err(e) = result {
+            eprintln!("{}", t!("Request failed: {e}", e = e));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use crate::TileType;
+use crate::i18n::say;
+
+// avoids infinite divergence when a tile type is missing from one side
+const SMOOTHING: f64 = 1e-6;
+
+// fraction of cells holding each tile type, in TileType::ALL order
+pub fn tile_frequencies(grid: &[Vec<TileType>]) -> Vec<f64> {
+    let mut counts = vec![0usize; TileType::ALL.len()];
+    let mut total = 0;
+    for tile_type in grid.iter().flatten() {
+        counts[tile_type.index()] += 1;
+        total += 1;
+    }
+    counts
+        .into_iter()
+        .map(|count| {
+            if total == 0 {
+                0.0
+            } else {
+                count as f64 / total as f64
+            }
+        })
+        .collect()
+}
+
+// KL(p || q) in bits
+pub fn kl_divergence(p: &[f64], q: &[f64]) -> f64 {
+    let normalise = |dist: &[f64]| -> Vec<f64> {
+        let total: f64 = dist.iter().map(|v| v + SMOOTHING).sum();
+        dist.iter().map(|v| (v + SMOOTHING) / total).collect()
+    };
+    let p = normalise(p);
+    let q = normalise(q);
+    p.iter()
+        .zip(&q)
+        .map(|(p, q)| if *p > 0.0 { p * (p / q).log2() } else { 0.0 })
+        .sum()
+}
+
+pub fn print_distribution(label: &str, freqs: &[f64]) {
+    println!("{}:", label);
+    for (tile_type, freq) in TileType::ALL.iter().zip(freqs) {
+        println!(
+            "  {:<10} {:>6.1}%",
+            format!("{:?}", tile_type),
+            freq * 100.0
+        );
+    }
+}
+
+pub fn print_comparison(label_a: &str, a: &[f64], label_b: &str, b: &[f64]) {
+    println!(
+        "{:<10} {:>10} {:>10} {:>8}",
+        "Tile", label_a, label_b, "Delta"
+    );
+    for (i, tile_type) in TileType::ALL.iter().enumerate() {
+        println!(
+            "{:<10} {:>9.1}% {:>9.1}% {:>+7.1}%",
+            format!("{:?}", tile_type),
+            a[i] * 100.0,
+            b[i] * 100.0,
+            (a[i] - b[i]) * 100.0
+        );
+    }
+    say!(
+        "KL({label_a} || {label_b}) = {kl_divergence} bits",
+        label_a = label_a,
+        label_b = label_b,
+        kl_divergence = format!("{:.4}", kl_divergence(a, b))
+    );
+}
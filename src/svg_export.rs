@@ -0,0 +1,165 @@
+use std::fmt::Write;
+
+use crate::i18n::{say, t};
+use crate::scatter::FeatureKind;
+use crate::{Tile, TileSystem, TileType};
+
+const LEGEND_ROW: f64 = 20.0;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SvgOptions {
+    pub gridlines: bool,
+    pub legend: bool,
+}
+
+fn fill(colour: [f32; 4]) -> String {
+    let channel = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "fill=\"#{:02x}{:02x}{:02x}\" fill-opacity=\"{}\"",
+        channel(colour[0]),
+        channel(colour[1]),
+        channel(colour[2]),
+        colour[3]
+    )
+}
+
+// one rect per horizontal run of identical tiles, at the map's own tile size
+pub fn to_svg(tile_system: &TileSystem, options: SvgOptions) -> String {
+    let size = tile_system.tile_size;
+    let width = tile_system.grid_width as f64 * size;
+    let map_height = tile_system.grid_height as f64 * size;
+    let legend_height = if options.legend {
+        LEGEND_ROW * TileType::ALL.len() as f64 + LEGEND_ROW / 2.0
+    } else {
+        0.0
+    };
+    let height = map_height + legend_height;
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+        w = width,
+        h = height
+    )
+    .ok();
+
+    for y in 0..tile_system.grid_height {
+        let mut x = 0;
+        while x < tile_system.grid_width {
+            let Some(tile) = tile_system.get_tile(x, y) else {
+                x += 1;
+                continue;
+            };
+            let mut run = 1;
+            while tile_system
+                .get_tile(x + run, y)
+                .is_some_and(|next| next.colour == tile.colour && next.visible == tile.visible)
+            {
+                run += 1;
+            }
+            if tile.visible && tile.colour[3] > 0.0 {
+                writeln!(
+                    svg,
+                    "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {}/>",
+                    x as f64 * size,
+                    y as f64 * size,
+                    run as f64 * size,
+                    size,
+                    fill(tile.colour)
+                )
+                .ok();
+            }
+            x += run;
+        }
+    }
+
+    // decorations, drawn as markers over the terrain like the editor does
+    for feature in &tile_system.features {
+        let radius = size / 4.0;
+        let (cx, cy) = (
+            (feature.x as f64 + 0.5) * size,
+            (feature.y as f64 + 0.5) * size,
+        );
+        if feature.kind == FeatureKind::Road {
+            writeln!(
+                svg,
+                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {}/>",
+                cx - radius,
+                cy - radius,
+                radius * 2.0,
+                radius * 2.0,
+                fill(feature.kind.colour())
+            )
+            .ok();
+        } else {
+            writeln!(
+                svg,
+                "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" {}/>",
+                cx,
+                cy,
+                radius,
+                fill(feature.kind.colour())
+            )
+            .ok();
+        }
+    }
+
+    if options.gridlines {
+        writeln!(
+            svg,
+            "  <g stroke=\"#000000\" stroke-opacity=\"0.3\" stroke-width=\"0.5\">"
+        )
+        .ok();
+        for column in 0..=tile_system.grid_width {
+            let x = column as f64 * size;
+            writeln!(
+                svg,
+                "    <line x1=\"{x}\" y1=\"0\" x2=\"{x}\" y2=\"{map_height}\"/>"
+            )
+            .ok();
+        }
+        for row in 0..=tile_system.grid_height {
+            let y = row as f64 * size;
+            writeln!(
+                svg,
+                "    <line x1=\"0\" y1=\"{y}\" x2=\"{width}\" y2=\"{y}\"/>"
+            )
+            .ok();
+        }
+        writeln!(svg, "  </g>").ok();
+    }
+
+    if options.legend {
+        for (i, tile_type) in TileType::ALL.iter().enumerate() {
+            let y = map_height + LEGEND_ROW / 2.0 + i as f64 * LEGEND_ROW;
+            writeln!(
+                svg,
+                "  <rect x=\"4\" y=\"{}\" width=\"14\" height=\"14\" {} stroke=\"#000000\" stroke-width=\"0.5\"/>",
+                y,
+                fill(Tile::from_type(tile_type).colour)
+            )
+            .ok();
+            writeln!(
+                svg,
+                "  <text x=\"24\" y=\"{}\" font-family=\"sans-serif\" font-size=\"12\">{:?}</text>",
+                y + 12.0,
+                tile_type
+            )
+            .ok();
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+impl TileSystem {
+    pub fn export_svg(&self, stem: &str, options: SvgOptions) -> Result<String, String> {
+        let path = format!("{}.svg", if stem.is_empty() { "map" } else { stem });
+        std::fs::write(&path, to_svg(self, options))
+            .map_err(|e| t!("Failed to write {path}: {e}", path = path, e = e))?;
+        say!("Exported SVG to {path}", path = path);
+        Ok(path)
+    }
+}
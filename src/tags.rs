@@ -0,0 +1,79 @@
+use std::collections::BTreeSet;
+
+use crate::TileSystem;
+use crate::i18n::{say, t};
+
+impl TileSystem {
+    // "island coast -training": plain words add tags, a leading '-' removes one
+    pub fn tag_config(&mut self, name: &str, spec: &str) -> Result<(), String> {
+        if self.config(name).is_none() {
+            return Err(t!("Configuration '{name}' not found", name = name));
+        }
+        let tags = self.config_tags.entry(name.to_string()).or_default();
+        for word in spec.split_whitespace() {
+            match word.strip_prefix('-') {
+                Some(tag) => {
+                    tags.remove(&tag.to_lowercase());
+                }
+                None => {
+                    tags.insert(word.to_lowercase());
+                }
+            }
+        }
+        if tags.is_empty() {
+            self.config_tags.remove(name);
+        }
+        self.dirty = true;
+        say!(
+            "Tags for '{name}': {tags_label}",
+            name = name,
+            tags_label = self.tags_label(name)
+        );
+        Ok(())
+    }
+
+    pub fn tags_label(&self, name: &str) -> String {
+        match self.config_tags.get(name) {
+            Some(tags) => tags.iter().cloned().collect::<Vec<_>>().join(", "),
+            None => "none".to_string(),
+        }
+    }
+
+    // sorted config names, only those carrying `tag` when one is given
+    pub fn config_names(&self, tag: Option<&str>) -> Vec<&String> {
+        let mut names: Vec<&String> = self
+            .saved_configs
+            .keys()
+            .chain(
+                self.library
+                    .keys()
+                    .filter(|name| !self.saved_configs.contains_key(*name)),
+            )
+            .filter(|name| {
+                tag.is_none_or(|tag| {
+                    self.config_tags
+                        .get(*name)
+                        .is_some_and(|tags| tags.contains(&tag.to_lowercase()))
+                })
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    pub fn all_tags(&self) -> BTreeSet<&String> {
+        self.config_tags.values().flatten().collect()
+    }
+
+    // tags follow a config through renames and copies
+    pub fn move_tags(&mut self, old: &str, new: &str, keep_old: bool) {
+        let tags = if keep_old {
+            self.config_tags.get(old).cloned()
+        } else {
+            self.config_tags.remove(old)
+        };
+        if let Some(tags) = tags {
+            self.config_tags.insert(new.to_string(), tags);
+        }
+    }
+}
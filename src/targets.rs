@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+
+use crate::csp::TargetShares;
+use crate::i18n::{say, t};
+use crate::{TileSystem, TileType};
+
+const DEFAULT_TOLERANCE: f64 = 5.0;
+
+// percentages the solver steers generation towards; tiles left out share what remains
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DistributionTargets {
+    pub percents: Vec<(TileType, f64)>,
+    // percentage points either side of a target that count as on target
+    pub tolerance: f64,
+}
+
+impl TileSystem {
+    pub fn target_shares(&self) -> Option<TargetShares> {
+        let targets = self.distribution_targets.as_ref()?;
+        let shares = TileType::ALL
+            .iter()
+            .map(|tile| {
+                targets
+                    .percents
+                    .iter()
+                    .find(|(target, _)| target == tile)
+                    .map(|(_, percent)| percent / 100.0)
+            })
+            .collect();
+        Some(TargetShares {
+            shares,
+            tolerance: targets.tolerance / 100.0,
+        })
+    }
+
+    // "<Tile> <percent> ... [tol <points>]", e.g. "Water 40 Land 35 tol 3", or "clear"
+    pub fn set_distribution_targets(&mut self, spec: &str) -> Result<(), String> {
+        if spec.trim() == "clear" {
+            self.distribution_targets = None;
+            self.dirty = true;
+            say!("Tile targets cleared");
+            return Ok(());
+        }
+        let parts: Vec<&str> = spec.split_whitespace().collect();
+        if parts.is_empty() || !parts.len().is_multiple_of(2) {
+            return Err(
+                "Expected \"<Tile> <percent> ... [tol <points>]\" or \"clear\"".to_string(),
+            );
+        }
+        let mut targets = DistributionTargets {
+            percents: Vec::new(),
+            tolerance: DEFAULT_TOLERANCE,
+        };
+        for pair in parts.chunks(2) {
+            let value: f64 = pair[1]
+                .trim_end_matches('%')
+                .parse()
+                .map_err(|_| t!("'{pair}' isn't a number", pair = pair[1]))?;
+            if !(0.0..=100.0).contains(&value) {
+                return Err(t!("{value} is outside 0-100", value = value));
+            }
+            if pair[0] == "tol" {
+                targets.tolerance = value;
+                continue;
+            }
+            let tile = TileType::parse(pair[0]).ok_or(format!("Unknown tile '{}'", pair[0]))?;
+            targets.percents.retain(|(old, _)| *old != tile);
+            targets.percents.push((tile, value));
+        }
+        let total: f64 = targets.percents.iter().map(|(_, percent)| percent).sum();
+        if total > 100.0 {
+            return Err(t!("Targets add up to {total}%", total = total));
+        }
+        say!("Tile targets: {targets}", targets = describe(&targets));
+        self.distribution_targets = Some(targets);
+        self.dirty = true;
+        Ok(())
+    }
+
+    // how the map compares with the targets, after a generation
+    pub fn report_targets(&self) {
+        let Some(targets) = &self.distribution_targets else {
+            return;
+        };
+        let cells = (self.grid_width * self.grid_height).max(1) as f64;
+        let tiles = self.tile_types();
+        let mut within = true;
+        for (tile, target) in &targets.percents {
+            let count = tiles.iter().flatten().filter(|t| *t == tile).count();
+            let actual = count as f64 * 100.0 / cells;
+            let off = (actual - target).abs() > targets.tolerance;
+            within &= !off;
+            println!(
+                " {:<9} target {:>5.1}%  got {:>5.1}%{}",
+                format!("{:?}", tile),
+                target,
+                actual,
+                if off { "  (off target)" } else { "" }
+            );
+        }
+        if within {
+            say!(
+                "All tile targets met within {tolerance}%",
+                tolerance = targets.tolerance
+            );
+        }
+    }
+
+    pub fn list_distribution_targets(&self) {
+        match &self.distribution_targets {
+            Some(targets) => say!("Tile targets: {targets}", targets = describe(targets)),
+            None => say!("No tile targets"),
+        }
+    }
+}
+
+fn describe(targets: &DistributionTargets) -> String {
+    let mut parts: Vec<String> = targets
+        .percents
+        .iter()
+        .map(|(tile, percent)| format!("{:?} {}%", tile, percent))
+        .collect();
+    parts.push(format!("tolerance {}%", targets.tolerance));
+    parts.join(", ")
+}
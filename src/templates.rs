@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::i18n::{say, t};
+use crate::{Tile, TileSystem, TileType};
+
+// shipped templates, also found in templates/ next to the binary
+const BUILTIN_TEMPLATES: [&str; 3] = [
+    include_str!("../templates/empty_ocean.json"),
+    include_str!("../templates/bordered_arena.json"),
+    include_str!("../templates/island_skeleton.json"),
+];
+
+pub const TEMPLATE_DIR: &str = "templates";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MapTemplate {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub fill: TileType,
+    #[serde(default)]
+    pub border: Option<TileType>,
+    #[serde(default)]
+    pub ellipses: Vec<TemplateEllipse>,
+}
+
+// centre and radius are fractions of the grid so templates work at any map size
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TemplateEllipse {
+    pub tile: TileType,
+    pub centre: [f64; 2],
+    pub radius: [f64; 2],
+}
+
+impl MapTemplate {
+    pub fn apply(&self, tile_system: &mut TileSystem) {
+        let width = tile_system.grid_width;
+        let height = tile_system.grid_height;
+
+        for y in 0..height {
+            for x in 0..width {
+                let on_border = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+                let mut tile_type = match &self.border {
+                    Some(border) if on_border => border,
+                    _ => &self.fill,
+                };
+
+                // sample at the cell centre, later ellipses paint over earlier ones
+                let fx = (x as f64 + 0.5) / width as f64;
+                let fy = (y as f64 + 0.5) / height as f64;
+                for ellipse in &self.ellipses {
+                    let dx = (fx - ellipse.centre[0]) / ellipse.radius[0];
+                    let dy = (fy - ellipse.centre[1]) / ellipse.radius[1];
+                    if dx * dx + dy * dy <= 1.0 {
+                        tile_type = &ellipse.tile;
+                    }
+                }
+
+                tile_system.set_tile(x, y, Tile::from_type(tile_type));
+            }
+        }
+        say!("Applied template: {name}", name = self.name);
+    }
+}
+
+// built-in templates first, user files in templates/ override them by name
+pub fn load_templates() -> Vec<MapTemplate> {
+    let mut templates: Vec<MapTemplate> = BUILTIN_TEMPLATES
+        .iter()
+        .filter_map(|json| serde_json::from_str(json).ok())
+        .collect();
+
+    let Ok(entries) = fs::read_dir(TEMPLATE_DIR) else {
+        return templates;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let template: MapTemplate = match fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+        {
+            Ok(template) => template,
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    t!(
+                        "Skipping template {path}: {e}",
+                        path = path.display(),
+                        e = e
+                    )
+                );
+                continue;
+            }
+        };
+        templates.retain(|t| t.name != template.name);
+        templates.push(template);
+    }
+    templates
+}
+
+pub fn find_template<'a>(templates: &'a [MapTemplate], name: &str) -> Option<&'a MapTemplate> {
+    templates.iter().find(|t| t.name == name)
+}
+
+pub fn list_templates(templates: &[MapTemplate]) {
+    say!("Templates:");
+    for template in templates {
+        println!(" - {}: {}", template.name, template.description);
+    }
+}
@@ -0,0 +1,87 @@
+use crate::i18n::{say, t};
+use crate::{Tile, TileSystem, TileType};
+
+// one character per tile in TileType::ALL order: empty, mountain, land, coast, water
+pub const DEFAULT_GLYPHS: &str = " ^.,~";
+
+#[derive(Clone, Debug)]
+pub struct TextOptions {
+    pub glyphs: String,
+    // 24-bit ANSI colour escapes, for viewing with `cat` in a terminal
+    pub colour: bool,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        TextOptions {
+            glyphs: DEFAULT_GLYPHS.to_string(),
+            colour: false,
+        }
+    }
+}
+
+impl TextOptions {
+    fn glyph_table(&self) -> Vec<char> {
+        let glyphs: Vec<char> = self.glyphs.chars().collect();
+        if glyphs.len() == TileType::ALL.len() {
+            glyphs
+        } else {
+            say!(
+                "Glyphs '{glyphs}' need one character per tile type ({count}), using '{default}'",
+                glyphs = self.glyphs,
+                count = TileType::ALL.len(),
+                default = DEFAULT_GLYPHS
+            );
+            DEFAULT_GLYPHS.chars().collect()
+        }
+    }
+}
+
+fn ansi_colour(colour: [f32; 4]) -> String {
+    let channel = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "\x1b[38;2;{};{};{}m",
+        channel(colour[0]),
+        channel(colour[1]),
+        channel(colour[2])
+    )
+}
+
+// one line per row; with colour, escapes only where the tile type changes
+// decorations replace the terrain glyph of their cell, keeping its colour
+pub fn to_text(tile_system: &TileSystem, options: &TextOptions) -> String {
+    let glyphs = options.glyph_table();
+    let mut text = String::new();
+    for y in 0..tile_system.grid_height {
+        let mut previous = None;
+        for x in 0..tile_system.grid_width {
+            let tile_type = tile_system
+                .get_tile(x, y)
+                .map_or(TileType::Empty, |tile| tile.tile_type.clone());
+            if options.colour && previous.as_ref() != Some(&tile_type) {
+                text.push_str(&ansi_colour(Tile::from_type(&tile_type).colour));
+            }
+            match tile_system.decoration_at(x, y) {
+                Some(kind) => text.push(kind.glyph()),
+                None => text.push(glyphs[tile_type.index()]),
+            }
+            previous = Some(tile_type);
+        }
+        if options.colour {
+            text.push_str("\x1b[0m");
+        }
+        text.push('\n');
+    }
+    text
+}
+
+impl TileSystem {
+    pub fn export_text(&self, stem: &str, options: &TextOptions) -> Result<String, String> {
+        let path = format!("{}.txt", if stem.is_empty() { "map" } else { stem });
+        let text = to_text(self, options);
+        std::fs::write(&path, &text)
+            .map_err(|e| t!("Failed to write {path}: {e}", path = path, e = e))?;
+        say!("Exported text to {path}", path = path);
+        Ok(path)
+    }
+}
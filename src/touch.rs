@@ -0,0 +1,79 @@
+use piston_window::{Touch, TouchArgs};
+use std::collections::HashMap;
+
+pub enum Gesture {
+    // one finger down or dragging: paint here, `stroke` once it's a continuation
+    Paint {
+        pos: [f64; 2],
+        stroke: bool,
+    },
+    // two fingers moved: scale about their midpoint and shift by how far it moved
+    PinchPan {
+        centre: [f64; 2],
+        factor: f64,
+        delta: [f64; 2],
+    },
+}
+
+// fingers currently on the screen, by touch id
+#[derive(Default)]
+pub struct TouchTracker {
+    touches: HashMap<i64, [f64; 2]>,
+    // a second finger came down; no painting until every finger has lifted
+    gesturing: bool,
+}
+
+fn midpoint_and_spread(points: &[[f64; 2]]) -> ([f64; 2], f64) {
+    let [a, b] = [points[0], points[1]];
+    let centre = [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0];
+    (
+        centre,
+        ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt(),
+    )
+}
+
+impl TouchTracker {
+    fn pair(&self) -> Option<Vec<[f64; 2]>> {
+        let mut ids: Vec<&i64> = self.touches.keys().collect();
+        ids.sort();
+        (ids.len() == 2).then(|| ids.iter().map(|id| self.touches[*id]).collect())
+    }
+
+    // `pos` is the touch position in the same window coordinates as the mouse
+    pub fn handle(&mut self, args: &TouchArgs, pos: [f64; 2]) -> Option<Gesture> {
+        match args.touch {
+            Touch::Start => {
+                self.touches.insert(args.id, pos);
+                self.gesturing |= self.touches.len() > 1;
+                (!self.gesturing).then_some(Gesture::Paint { pos, stroke: false })
+            }
+            Touch::Move => {
+                let before = self.pair();
+                self.touches.insert(args.id, pos);
+                match (self.touches.len(), before, self.pair()) {
+                    (1, _, _) if !self.gesturing => Some(Gesture::Paint { pos, stroke: true }),
+                    (2, Some(before), Some(after)) => {
+                        let (old_centre, old_spread) = midpoint_and_spread(&before);
+                        let (centre, spread) = midpoint_and_spread(&after);
+                        let factor = if old_spread > 1.0 {
+                            spread / old_spread
+                        } else {
+                            1.0
+                        };
+                        Some(Gesture::PinchPan {
+                            centre,
+                            factor,
+                            delta: [centre[0] - old_centre[0], centre[1] - old_centre[1]],
+                        })
+                    }
+                    _ => None,
+                }
+            }
+            Touch::End | Touch::Cancel => {
+                self.touches.remove(&args.id);
+                self.gesturing &= !self.touches.is_empty();
+                None
+            }
+        }
+    }
+}
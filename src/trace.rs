@@ -0,0 +1,168 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+use crate::i18n::{say, t};
+use crate::observer::GenerationObserver;
+
+// one JSON object per line; `t_us` is microseconds since the solver started
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum TraceEvent {
+    Start {
+        width: usize,
+        height: usize,
+        seed: u64,
+        // tile ids in the other events index this list
+        tiles: Vec<String>,
+    },
+    Observe {
+        t_us: u128,
+        x: usize,
+        y: usize,
+        tile: usize,
+    },
+    Collapse {
+        t_us: u128,
+        x: usize,
+        y: usize,
+        tile: usize,
+    },
+    Eliminate {
+        t_us: u128,
+        x: usize,
+        y: usize,
+        tile: usize,
+    },
+    Contradiction {
+        t_us: u128,
+        x: usize,
+        y: usize,
+    },
+    Backtrack {
+        t_us: u128,
+        x: usize,
+        y: usize,
+    },
+    Restart {
+        t_us: u128,
+    },
+    Memory {
+        t_us: u128,
+        bytes: usize,
+    },
+    Complete {
+        t_us: u128,
+        success: bool,
+    },
+}
+
+// writes every solver event to a JSONL file for notebooks and other tools
+pub struct TraceObserver {
+    writer: BufWriter<File>,
+    started: Instant,
+    path: String,
+    // set on the first failed write, after which the rest are dropped
+    broken: bool,
+}
+
+impl TraceObserver {
+    // `tiles` names what each solver id stands for, see StateTable::labels
+    pub fn create(
+        path: &str,
+        width: usize,
+        height: usize,
+        seed: u64,
+        tiles: Vec<String>,
+    ) -> Result<Self, String> {
+        if let Some(dir) = Path::new(path)
+            .parent()
+            .filter(|d| !d.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| t!("Failed to create {dir}: {e}", dir = dir.display(), e = e))?;
+        }
+        let file = File::create(path)
+            .map_err(|e| t!("Failed to write {path}: {e}", path = path, e = e))?;
+        let mut trace = TraceObserver {
+            writer: BufWriter::new(file),
+            started: Instant::now(),
+            path: path.to_string(),
+            broken: false,
+        };
+        trace.write(&TraceEvent::Start {
+            width,
+            height,
+            seed,
+            tiles,
+        });
+        Ok(trace)
+    }
+
+    fn elapsed(&self) -> u128 {
+        self.started.elapsed().as_micros()
+    }
+
+    fn write(&mut self, event: &TraceEvent) {
+        if self.broken {
+            return;
+        }
+        let written = serde_json::to_string(event)
+            .map_err(|e| e.to_string())
+            .and_then(|line| writeln!(self.writer, "{}", line).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            eprintln!(
+                "{}",
+                t!("Trace {path} stopped: {e}", path = self.path, e = e)
+            );
+            self.broken = true;
+        }
+    }
+}
+
+impl GenerationObserver for TraceObserver {
+    fn on_observe(&mut self, (x, y): (usize, usize), tile: usize) {
+        let t_us = self.elapsed();
+        self.write(&TraceEvent::Observe { t_us, x, y, tile });
+    }
+
+    fn on_collapse(&mut self, (x, y): (usize, usize), tile: usize) {
+        let t_us = self.elapsed();
+        self.write(&TraceEvent::Collapse { t_us, x, y, tile });
+    }
+
+    fn on_eliminate(&mut self, (x, y): (usize, usize), tile: usize) {
+        let t_us = self.elapsed();
+        self.write(&TraceEvent::Eliminate { t_us, x, y, tile });
+    }
+
+    fn on_contradiction(&mut self, (x, y): (usize, usize)) {
+        let t_us = self.elapsed();
+        self.write(&TraceEvent::Contradiction { t_us, x, y });
+    }
+
+    fn on_backtrack(&mut self, (x, y): (usize, usize)) {
+        let t_us = self.elapsed();
+        self.write(&TraceEvent::Backtrack { t_us, x, y });
+    }
+
+    fn on_restart(&mut self) {
+        let t_us = self.elapsed();
+        self.write(&TraceEvent::Restart { t_us });
+    }
+
+    fn on_memory(&mut self, bytes: usize) {
+        let t_us = self.elapsed();
+        self.write(&TraceEvent::Memory { t_us, bytes });
+    }
+
+    fn on_complete(&mut self, success: bool) {
+        let t_us = self.elapsed();
+        self.write(&TraceEvent::Complete { t_us, success });
+        if !self.broken && self.writer.flush().is_ok() {
+            say!("Solver trace written to {path}", path = self.path);
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::io;
+
+use crate::brushes::BrushStyle;
+use crate::cli::MapSize;
+use crate::commands::{Command, Generation};
+use crate::exit::Failure;
+use crate::i18n::t;
+use crate::instance::InstanceLock;
+use crate::{Backend, Tile, TileSystem, TileType};
+
+// what the line under the map is reading keys for
+enum Mode {
+    Normal,
+    // typing a config name
+    Prompt(Prompt, String),
+    ConfirmClear,
+    // unsaved changes on quit: y saves, n discards
+    ConfirmQuit,
+}
+
+#[derive(Clone, Copy)]
+enum Prompt {
+    Generate,
+    LoadConfig,
+    SaveConfig,
+}
+
+impl Prompt {
+    fn label(self) -> &'static str {
+        match self {
+            Prompt::Generate => "Generate from (blank for enabled samples)",
+            Prompt::LoadConfig => "Load configuration",
+            Prompt::SaveConfig => "Save configuration as",
+        }
+    }
+}
+
+const HELP: &str = "arrows move  1-5 tile  space paint  f fill  m lock  u unlock  \
+                    c clear  z undo  w generate  l/s load/save config  e erode  \
+                    ctrl+s save  q quit";
+
+struct App {
+    tile_system: TileSystem,
+    cursor: (usize, usize),
+    selected: TileType,
+    mode: Mode,
+    status: String,
+    quit: bool,
+}
+
+fn tile_colour(tile: &Tile) -> Color {
+    let [r, g, b, _] = tile.colour.map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8);
+    Color::Rgb(r, g, b)
+}
+
+impl App {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && self.handle_key(key)
+            {
+                // the map code reports to stdout, which scribbles over the screen
+                terminal.clear()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [map_area, status_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(2)]).areas(frame.area());
+
+        let rows: Vec<Line> = (0..self.tile_system.grid_height)
+            .map(|y| {
+                let cells: Vec<Span> = (0..self.tile_system.grid_width)
+                    .map(|x| {
+                        let tile = self.tile_system.get_tile(x, y);
+                        let empty = tile.is_none_or(|t| t.tile_type == TileType::Empty);
+                        let colour = tile.map(tile_colour).unwrap_or(Color::Reset);
+                        let text = if (x, y) == self.cursor {
+                            "[]"
+                        } else if self.tile_system.is_locked(x, y) {
+                            "▓▓"
+                        } else if empty {
+                            "··"
+                        } else {
+                            "██"
+                        };
+                        let style = if (x, y) == self.cursor {
+                            Style::default()
+                                .fg(Color::White)
+                                .bg(colour)
+                                .add_modifier(Modifier::BOLD)
+                        } else if empty {
+                            Style::default().fg(Color::DarkGray)
+                        } else {
+                            Style::default().fg(colour)
+                        };
+                        Span::styled(text, style)
+                    })
+                    .collect();
+                Line::from(cells)
+            })
+            .collect();
+        let title = format!(
+            " WaveFunctionCollapse{} ",
+            if self.tile_system.dirty { " *" } else { "" }
+        );
+        frame.render_widget(
+            Paragraph::new(rows).block(Block::bordered().title(title)),
+            map_area,
+        );
+
+        let bottom = match &self.mode {
+            Mode::Normal => HELP.to_string(),
+            Mode::Prompt(prompt, input) => format!("{}: {}_", prompt.label(), input),
+            Mode::ConfirmClear => "Clear the whole map? y/n".to_string(),
+            Mode::ConfirmQuit => {
+                "Save changes before quitting? y saves, n discards, esc cancels".to_string()
+            }
+        };
+        let status = vec![
+            Line::from(vec![
+                Span::styled(
+                    format!(" {:?} ", self.selected),
+                    Style::default()
+                        .fg(Color::Black)
+                        .bg(tile_colour(&Tile::from_type(&self.selected))),
+                ),
+                Span::raw(format!(
+                    " ({}, {})  {}",
+                    self.cursor.0, self.cursor.1, self.status
+                )),
+            ]),
+            Line::from(Span::styled(bottom, Style::default().fg(Color::Gray))),
+        ];
+        frame.render_widget(Paragraph::new(status), status_area);
+    }
+
+    fn command(&mut self, command: Command, done: &str, failed: &str) {
+        self.status = if self.tile_system.run_command(&command) {
+            done.to_string()
+        } else {
+            failed.to_string()
+        };
+    }
+
+    // true when the map code ran and may have printed
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match std::mem::replace(&mut self.mode, Mode::Normal) {
+            Mode::Prompt(prompt, mut input) => {
+                match key.code {
+                    KeyCode::Enter => return self.submit(prompt, input.trim().to_string()),
+                    KeyCode::Esc => self.status = "Cancelled".to_string(),
+                    KeyCode::Backspace => {
+                        input.pop();
+                        self.mode = Mode::Prompt(prompt, input);
+                    }
+                    KeyCode::Char(c) => {
+                        input.push(c);
+                        self.mode = Mode::Prompt(prompt, input);
+                    }
+                    _ => self.mode = Mode::Prompt(prompt, input),
+                }
+                false
+            }
+            Mode::ConfirmClear => {
+                if key.code == KeyCode::Char('y') {
+                    self.command(Command::Clear, "Map cleared", "");
+                    return true;
+                }
+                self.status = "Cancelled".to_string();
+                false
+            }
+            Mode::ConfirmQuit => match key.code {
+                KeyCode::Char('y') => {
+                    self.tile_system.save_to_file();
+                    self.quit = true;
+                    true
+                }
+                KeyCode::Char('n') => {
+                    self.quit = true;
+                    false
+                }
+                _ => {
+                    self.status = "Cancelled".to_string();
+                    false
+                }
+            },
+            Mode::Normal => self.handle_normal_key(key.code, ctrl),
+        }
+    }
+
+    fn handle_normal_key(&mut self, code: KeyCode, ctrl: bool) -> bool {
+        let (x, y) = self.cursor;
+        match code {
+            KeyCode::Left => self.cursor.0 = x.saturating_sub(1),
+            KeyCode::Right => {
+                self.cursor.0 = (x + 1).min(self.tile_system.grid_width.saturating_sub(1));
+            }
+            KeyCode::Up => self.cursor.1 = y.saturating_sub(1),
+            KeyCode::Down => {
+                self.cursor.1 = (y + 1).min(self.tile_system.grid_height.saturating_sub(1));
+            }
+            KeyCode::Char(c @ '1'..='5') => {
+                self.selected = TileType::ALL[c as usize - '1' as usize].clone();
+            }
+            KeyCode::Char(' ') => {
+                self.tile_system.paint(
+                    x,
+                    y,
+                    &BrushStyle::default(),
+                    Tile::from_type(&self.selected),
+                    None,
+                );
+            }
+            KeyCode::Char('f') => {
+                self.tile_system.push_undo();
+                self.tile_system
+                    .fill_to_border(x, y, Tile::from_type(&self.selected));
+                return true;
+            }
+            KeyCode::Char('m') => {
+                self.tile_system.toggle_lock(x, y);
+            }
+            KeyCode::Char('u') => {
+                self.command(Command::UnlockAll, "All cells unlocked", "");
+                return true;
+            }
+            KeyCode::Char('c') => self.mode = Mode::ConfirmClear,
+            KeyCode::Char('z') => {
+                self.command(Command::Undo, "Undone", "Nothing to undo");
+                return true;
+            }
+            KeyCode::Char('e') => {
+                self.command(Command::Erode, "Eroded", "");
+                return true;
+            }
+            KeyCode::Char('s') if ctrl => {
+                self.tile_system.save_to_file();
+                self.status = "Saved".to_string();
+                return true;
+            }
+            KeyCode::Char('w') => self.mode = Mode::Prompt(Prompt::Generate, String::new()),
+            KeyCode::Char('l') => self.mode = Mode::Prompt(Prompt::LoadConfig, String::new()),
+            KeyCode::Char('s') => self.mode = Mode::Prompt(Prompt::SaveConfig, String::new()),
+            KeyCode::Char('q') | KeyCode::Esc if self.tile_system.dirty => {
+                self.mode = Mode::ConfirmQuit;
+            }
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            _ => {}
+        }
+        false
+    }
+
+    fn submit(&mut self, prompt: Prompt, name: String) -> bool {
+        match prompt {
+            Prompt::Generate => self.command(
+                Command::Generate(Generation {
+                    model: name,
+                    backend: Backend::Sat,
+                }),
+                "Generated",
+                "Generation failed, see the log above",
+            ),
+            Prompt::LoadConfig => self.command(
+                Command::LoadConfig(name.clone()),
+                &format!("Loaded '{}'", name),
+                &format!("No configuration '{}'", name),
+            ),
+            Prompt::SaveConfig if name.is_empty() => {
+                self.status = "Configuration needs a name".to_string();
+            }
+            Prompt::SaveConfig => {
+                self.status = format!("Saved configuration '{}'", name);
+                self.tile_system.save_config(name);
+            }
+        }
+        true
+    }
+}
+
+// `tui`: edits and generates the main map in the terminal, no window needed
+pub fn run(size: &MapSize) -> i32 {
+    // saves go to the same file as the window editor's, so one of them at a time
+    let _lock = match InstanceLock::acquire(TileSystem::SAVE_FILE) {
+        Ok(lock) => lock,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Failure::Io.code();
+        }
+    };
+    let mut app = App {
+        tile_system: TileSystem::load_or_new(size),
+        cursor: (0, 0),
+        selected: TileType::Water,
+        mode: Mode::Normal,
+        status: String::new(),
+        quit: false,
+    };
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", t!("Terminal error: {e}", e = e));
+            1
+        }
+    }
+}
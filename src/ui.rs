@@ -0,0 +1,726 @@
+use std::collections::BTreeSet;
+
+use piston_window::*;
+
+use crate::brushes::BrushStyle;
+use crate::decorations::DecorationBrush;
+use crate::i18n::t;
+use crate::keymap::{self, KEYMAP};
+use crate::layers::{Layer, Layers};
+use crate::{Region, Tile, TileSystem, TileType};
+
+pub const FONT_PATH: &str = "assets/DejaVuSans.ttf";
+
+const PANEL_WIDTH: f64 = 176.0;
+const PADDING: f64 = 8.0;
+const ROW_HEIGHT: f64 = 20.0;
+const SWATCH_SIZE: f64 = 24.0;
+const SLIDER_WIDTH: f64 = PANEL_WIDTH - 2.0 * PADDING;
+const MAX_BRUSH_SIZE: usize = 8;
+const MAX_LISTED_CONFIGS: usize = 10;
+
+const PANEL_COLOUR: [f32; 4] = [0.1, 0.1, 0.12, 0.9];
+const TEXT_COLOUR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+const BUTTON_COLOUR: [f32; 4] = [0.25, 0.25, 0.3, 1.0];
+const HIGHLIGHT_COLOUR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const CURSOR_COLOUR: [f32; 4] = [1.0, 0.85, 0.1, 1.0];
+const SELECTION_COLOUR: [f32; 4] = [0.3, 0.6, 1.0, 0.2];
+
+pub const PALETTE: [TileType; 5] = TileType::ALL;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PanelAction {
+    SelectTile(TileType),
+    // None switches the brush back to terrain
+    SelectDecoration(Option<DecorationBrush>),
+    SetBrushSize(usize),
+    LoadConfig(String),
+    Undo,
+    Clear,
+    NextMap,
+    ToggleLayer(Layer),
+}
+
+// destructive actions that wait for a Y/N answer before running
+#[derive(Clone, Debug, PartialEq)]
+pub enum Confirm {
+    ClearMap,
+    DeleteConfig(String),
+    OverwriteConfig(String),
+    // a map file dropped onto the window
+    OpenFile(String),
+    // an exported PNG dropped onto the window, regenerated from its stamp
+    RegenerateFrom(String),
+    // unsaved changes on exit: Y saves, N discards
+    Exit,
+}
+
+impl Confirm {
+    pub fn message(&self) -> String {
+        match self {
+            Confirm::ClearMap => t!("Clear the whole map?"),
+            Confirm::DeleteConfig(name) => t!("Delete configuration '{name}'?", name = name),
+            Confirm::OverwriteConfig(name) => t!("Overwrite configuration '{name}'?", name = name),
+            Confirm::OpenFile(path) => t!("Open {path} as a new map?", path = path),
+            Confirm::RegenerateFrom(path) => t!(
+                "Regenerate this map with the settings in {path}?",
+                path = path
+            ),
+            Confirm::Exit => t!("Save changes before exiting?"),
+        }
+    }
+
+    pub fn hint(&self) -> String {
+        match self {
+            Confirm::Exit => t!("Y to save, N to discard, Esc to cancel"),
+            _ => t!("Y to confirm, N to cancel"),
+        }
+    }
+
+    // echo the question to the console too, for when no font is loaded
+    pub fn ask(self) -> Self {
+        println!("{} {}", self.message(), self.hint());
+        self
+    }
+}
+
+// one row in the Ctrl+O quick-open list
+#[derive(Clone, Debug, PartialEq)]
+pub enum QuickEntry {
+    Config(String),
+    Map(usize),
+}
+
+impl QuickEntry {
+    pub fn label(&self) -> String {
+        match self {
+            QuickEntry::Config(name) => t!("Config: {name}", name = name),
+            QuickEntry::Map(index) => t!("Map {number}", number = index + 1),
+        }
+    }
+}
+
+// how far the running generation has got, shown under the map label
+#[derive(Clone, Debug, PartialEq)]
+pub struct GenerationProgress {
+    // share of cells collapsed, 0 to 1
+    pub done: f64,
+    pub contradictions: usize,
+    // seconds left, None until the first cell has collapsed
+    pub eta: Option<f64>,
+}
+
+// `42s` or `3m 05s`
+fn format_eta(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    if seconds < 60 {
+        format!("{}s", seconds)
+    } else {
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    }
+}
+
+enum Widget {
+    Label(String),
+    // filled by a share from 0 to 1, not clickable
+    Bar(f64),
+    Swatch(TileType),
+    DecorationSwatch(DecorationBrush),
+    Slider { value: usize, max: usize },
+    Button(String, PanelAction),
+}
+
+struct Layout {
+    left: f64,
+    y: f64,
+    widgets: Vec<([f64; 4], Widget)>,
+}
+
+impl Layout {
+    fn row(&mut self, height: f64, widget: Widget) {
+        self.widgets
+            .push(([self.left, self.y, SLIDER_WIDTH, height], widget));
+        self.y += height + 4.0;
+    }
+
+    fn swatches(&mut self) {
+        for (i, tile_type) in PALETTE.iter().enumerate() {
+            let x = self.left + i as f64 * (SWATCH_SIZE + 6.0);
+            self.widgets.push((
+                [x, self.y, SWATCH_SIZE, SWATCH_SIZE],
+                Widget::Swatch(tile_type.clone()),
+            ));
+        }
+        self.y += SWATCH_SIZE + 8.0;
+    }
+
+    fn decoration_swatches(&mut self) {
+        for (i, brush) in DecorationBrush::ALL.iter().enumerate() {
+            let x = self.left + i as f64 * (SWATCH_SIZE + 6.0);
+            self.widgets.push((
+                [x, self.y, SWATCH_SIZE, SWATCH_SIZE],
+                Widget::DecorationSwatch(*brush),
+            ));
+        }
+        self.y += SWATCH_SIZE + 8.0;
+    }
+}
+
+// settings side panel, laid out fresh every frame so drawing and clicking share one layout
+// widgets are laid out in panel-local units, scaled by ui_scale when drawn
+pub struct SettingsPanel {
+    pub visible: bool,
+    pub brush: BrushStyle,
+    // painting decorations instead of terrain when set
+    pub decoration: Option<DecorationBrush>,
+    // painting the bias layer at this strength instead of terrain when set
+    pub bias: Option<f64>,
+    // group or tile name the brush is limited to replacing
+    pub brush_filter: Option<String>,
+    // only configs with this tag are listed
+    pub config_filter: Option<String>,
+    pub layers: Layers,
+    pub ui_scale: f64,
+    // set every frame while a generation runs
+    pub progress: Option<GenerationProgress>,
+}
+
+impl SettingsPanel {
+    pub fn new(ui_scale: f64) -> Self {
+        SettingsPanel {
+            visible: false,
+            brush: BrushStyle::default(),
+            decoration: None,
+            bias: None,
+            brush_filter: None,
+            config_filter: None,
+            layers: Layers::default(),
+            ui_scale,
+            progress: None,
+        }
+    }
+
+    pub fn adjust_brush_size(&mut self, delta: isize) -> usize {
+        self.brush.size = self
+            .brush
+            .size
+            .saturating_add_signed(delta)
+            .clamp(1, MAX_BRUSH_SIZE);
+        self.brush.size
+    }
+
+    fn origin_x(&self, tile_system: &TileSystem) -> f64 {
+        tile_system.window_width - PANEL_WIDTH * self.ui_scale
+    }
+
+    fn to_local(&self, tile_system: &TileSystem, pos: [f64; 2]) -> [f64; 2] {
+        [
+            (pos[0] - self.origin_x(tile_system)) / self.ui_scale,
+            pos[1] / self.ui_scale,
+        ]
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn contains(&self, tile_system: &TileSystem, pos: [f64; 2]) -> bool {
+        self.visible && pos[0] >= self.origin_x(tile_system)
+    }
+
+    fn layout(&self, tile_system: &TileSystem, selected: &TileType, map_label: &str) -> Layout {
+        let mut layout = Layout {
+            left: PADDING,
+            y: PADDING,
+            widgets: Vec::new(),
+        };
+
+        layout.row(ROW_HEIGHT, Widget::Label(map_label.to_string()));
+        if let Some(progress) = &self.progress {
+            layout.row(10.0, Widget::Bar(progress.done));
+            let eta = match progress.eta {
+                Some(seconds) => t!("ETA {time}", time = format_eta(seconds)),
+                None => t!("ETA unknown"),
+            };
+            layout.row(
+                ROW_HEIGHT,
+                Widget::Label(t!(
+                    "{percent}% done, {eta}",
+                    percent = format!("{:.0}", progress.done * 100.0),
+                    eta = eta
+                )),
+            );
+            layout.row(
+                ROW_HEIGHT,
+                Widget::Label(t!(
+                    "Contradictions: {count}",
+                    count = progress.contradictions
+                )),
+            );
+        }
+        layout.row(
+            ROW_HEIGHT,
+            Widget::Label(t!("Tile: {tile}", tile = format!("{:?}", selected))),
+        );
+        layout.swatches();
+        let decoration = match &self.decoration {
+            Some(brush) => t!("Decoration: {brush}", brush = brush.label()),
+            None => t!("Decoration: off"),
+        };
+        layout.row(ROW_HEIGHT, Widget::Label(decoration));
+        layout.decoration_swatches();
+        if let Some(strength) = self.bias {
+            layout.row(
+                ROW_HEIGHT,
+                Widget::Label(t!(
+                    "Bias: {tile} {percent}%",
+                    tile = format!("{:?}", selected),
+                    percent = format!("{:.0}", strength * 100.0)
+                )),
+            );
+        }
+        layout.row(
+            ROW_HEIGHT,
+            Widget::Label(t!("Brush: {brush}", brush = self.brush.label())),
+        );
+        if let Some(filter) = &self.brush_filter {
+            layout.row(
+                ROW_HEIGHT,
+                Widget::Label(t!("Only replaces: {filter}", filter = filter)),
+            );
+        }
+        layout.row(
+            10.0,
+            Widget::Slider {
+                value: self.brush.size.min(MAX_BRUSH_SIZE),
+                max: MAX_BRUSH_SIZE,
+            },
+        );
+        layout.row(ROW_HEIGHT, Widget::Button(t!("Undo"), PanelAction::Undo));
+        layout.row(
+            ROW_HEIGHT,
+            Widget::Button(t!("Clear map"), PanelAction::Clear),
+        );
+        layout.row(
+            ROW_HEIGHT,
+            Widget::Button(t!("Next map"), PanelAction::NextMap),
+        );
+        layout.row(ROW_HEIGHT, Widget::Label(t!("Layers:")));
+        for layer in Layer::ALL {
+            let marker = if layer == self.layers.active {
+                "> "
+            } else {
+                ""
+            };
+            layout.row(
+                ROW_HEIGHT,
+                Widget::Button(
+                    format!("{}{}", marker, self.layers.describe(layer)),
+                    PanelAction::ToggleLayer(layer),
+                ),
+            );
+        }
+        let heading = match &self.config_filter {
+            Some(tag) => t!("Configs tagged {tag}:", tag = tag),
+            None => t!("Configs:"),
+        };
+        layout.row(ROW_HEIGHT, Widget::Label(heading));
+
+        let names = tile_system.config_names(self.config_filter.as_deref());
+        for name in names.into_iter().take(MAX_LISTED_CONFIGS) {
+            let label = match (
+                tile_system.config_source(name),
+                tile_system.config_meta(name),
+            ) {
+                (Some(_), _) => format!("{} [lib]", name),
+                (None, Some(meta)) => format!("{} {}x{}", name, meta.width, meta.height),
+                (None, None) => name.clone(),
+            };
+            layout.row(
+                ROW_HEIGHT,
+                Widget::Button(label, PanelAction::LoadConfig(name.clone())),
+            );
+        }
+        layout
+    }
+
+    pub fn click(
+        &mut self,
+        tile_system: &TileSystem,
+        selected: &TileType,
+        pos: [f64; 2],
+    ) -> Option<PanelAction> {
+        let pos = self.to_local(tile_system, pos);
+        for (rect, widget) in self.layout(tile_system, selected, "").widgets {
+            let inside = pos[0] >= rect[0]
+                && pos[0] < rect[0] + rect[2]
+                && pos[1] >= rect[1]
+                && pos[1] < rect[1] + rect[3];
+            if !inside {
+                continue;
+            }
+            return match widget {
+                Widget::Swatch(tile_type) => Some(PanelAction::SelectTile(tile_type)),
+                Widget::DecorationSwatch(brush) => {
+                    // clicking the active one goes back to terrain
+                    self.decoration = Some(brush).filter(|b| self.decoration != Some(*b));
+                    Some(PanelAction::SelectDecoration(self.decoration))
+                }
+                Widget::Slider { max, .. } => {
+                    let fraction = (pos[0] - rect[0]) / rect[2];
+                    let size = 1 + (fraction * max as f64) as usize;
+                    self.brush.size = size.min(max);
+                    Some(PanelAction::SetBrushSize(self.brush.size))
+                }
+                Widget::Button(_, action) => Some(action),
+                Widget::Label(_) | Widget::Bar(_) => None,
+            };
+        }
+        None
+    }
+
+    pub fn render(
+        &self,
+        tile_system: &TileSystem,
+        selected: &TileType,
+        map_label: &str,
+        glyphs: Option<&mut Glyphs>,
+        c: Context,
+        g: &mut G2d,
+    ) {
+        if !self.visible {
+            return;
+        }
+        let c = c
+            .trans(self.origin_x(tile_system), 0.0)
+            .scale(self.ui_scale, self.ui_scale);
+        rectangle(
+            PANEL_COLOUR,
+            [
+                0.0,
+                0.0,
+                PANEL_WIDTH,
+                tile_system.window_height / self.ui_scale,
+            ],
+            c.transform,
+            g,
+        );
+
+        let mut glyphs = glyphs;
+        for (rect, widget) in self.layout(tile_system, selected, map_label).widgets {
+            let label = match &widget {
+                Widget::Label(label) => Some(label.as_str()),
+                Widget::Button(label, _) => {
+                    rectangle(BUTTON_COLOUR, rect, c.transform, g);
+                    Some(label.as_str())
+                }
+                Widget::Swatch(tile_type) => {
+                    rectangle(Tile::from_type(tile_type).colour, rect, c.transform, g);
+                    if tile_type == selected {
+                        Rectangle::new_border(HIGHLIGHT_COLOUR, 1.5).draw(
+                            rect,
+                            &c.draw_state,
+                            c.transform,
+                            g,
+                        );
+                    }
+                    None
+                }
+                Widget::DecorationSwatch(brush) => {
+                    rectangle(BUTTON_COLOUR, rect, c.transform, g);
+                    if let DecorationBrush::Place(kind) = brush {
+                        let inset = rect[2] / 4.0;
+                        ellipse(
+                            kind.colour(),
+                            [
+                                rect[0] + inset,
+                                rect[1] + inset,
+                                rect[2] / 2.0,
+                                rect[3] / 2.0,
+                            ],
+                            c.transform,
+                            g,
+                        );
+                    }
+                    if self.decoration == Some(*brush) {
+                        Rectangle::new_border(HIGHLIGHT_COLOUR, 1.5).draw(
+                            rect,
+                            &c.draw_state,
+                            c.transform,
+                            g,
+                        );
+                    }
+                    None
+                }
+                Widget::Slider { value, max } => {
+                    rectangle(BUTTON_COLOUR, rect, c.transform, g);
+                    let filled = rect[2] * *value as f64 / *max as f64;
+                    rectangle(
+                        HIGHLIGHT_COLOUR,
+                        [rect[0], rect[1], filled, rect[3]],
+                        c.transform,
+                        g,
+                    );
+                    None
+                }
+                Widget::Bar(share) => {
+                    rectangle(BUTTON_COLOUR, rect, c.transform, g);
+                    let filled = rect[2] * share.clamp(0.0, 1.0);
+                    rectangle(
+                        CURSOR_COLOUR,
+                        [rect[0], rect[1], filled, rect[3]],
+                        c.transform,
+                        g,
+                    );
+                    None
+                }
+            };
+
+            if let (Some(label), Some(glyphs)) = (label, glyphs.as_deref_mut()) {
+                let transform = c.transform.trans(rect[0] + 4.0, rect[1] + rect[3] - 5.0);
+                text(TEXT_COLOUR, 12, label, glyphs, transform, g).ok();
+            }
+        }
+    }
+}
+
+// tints each cell of a magic wand selection
+pub fn render_cells(
+    tile_system: &TileSystem,
+    cells: &BTreeSet<(usize, usize)>,
+    c: Context,
+    g: &mut G2d,
+) {
+    let size = tile_system.tile_size;
+    for &(x, y) in cells {
+        let (world_x, world_y) = tile_system.grid_to_world(x, y);
+        rectangle(
+            SELECTION_COLOUR,
+            [world_x, world_y, size, size],
+            c.transform,
+            g,
+        );
+    }
+}
+
+// the cells the brush would paint under the mouse, tinted with what it paints;
+// outlined too so erasing with Empty still shows
+pub fn render_brush_preview(
+    tile_system: &TileSystem,
+    cells: &BTreeSet<(usize, usize)>,
+    tint: [f32; 4],
+    c: Context,
+    g: &mut G2d,
+) {
+    let size = tile_system.tile_size;
+    let mut colour = tint;
+    colour[3] *= 0.4;
+    let mut outline = CURSOR_COLOUR;
+    outline[3] = 0.5;
+    for &(x, y) in cells {
+        let (world_x, world_y) = tile_system.grid_to_world(x, y);
+        rectangle(colour, [world_x, world_y, size, size], c.transform, g);
+        Rectangle::new_border(outline, 0.5).draw(
+            [world_x, world_y, size, size],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+    }
+}
+
+// a right-click fill waiting for the button's release, and the cells it would change
+pub struct FillPreview {
+    pub start: (usize, usize),
+    pub cells: Vec<(usize, usize)>,
+}
+
+// the cells a held fill would change, in the fill tile's colour with an outline
+pub fn render_fill_preview(
+    tile_system: &TileSystem,
+    preview: &FillPreview,
+    tile: &TileType,
+    c: Context,
+    g: &mut G2d,
+) {
+    let size = tile_system.tile_size;
+    let mut colour = Tile::from_type(tile).colour;
+    colour[3] = 0.5;
+    for &(x, y) in &preview.cells {
+        let (world_x, world_y) = tile_system.grid_to_world(x, y);
+        rectangle(colour, [world_x, world_y, size, size], c.transform, g);
+        Rectangle::new_border(CURSOR_COLOUR, 0.5).draw(
+            [world_x, world_y, size, size],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+    }
+}
+
+pub fn render_selection(tile_system: &TileSystem, region: Region, c: Context, g: &mut G2d) {
+    let (x, y) = tile_system.grid_to_world(region.x, region.y);
+    let size = tile_system.tile_size;
+    rectangle(
+        SELECTION_COLOUR,
+        [
+            x,
+            y,
+            region.width as f64 * size,
+            region.height as f64 * size,
+        ],
+        c.transform,
+        g,
+    );
+    Rectangle::new_border(HIGHLIGHT_COLOUR, 1.0).draw(
+        [
+            x,
+            y,
+            region.width as f64 * size,
+            region.height as f64 * size,
+        ],
+        &c.draw_state,
+        c.transform,
+        g,
+    );
+}
+
+// the keyboard cursor, a thick amber frame so it can't be mistaken for the mouse
+pub fn render_cursor(tile_system: &TileSystem, cell: (usize, usize), c: Context, g: &mut G2d) {
+    let (x, y) = tile_system.grid_to_world(cell.0, cell.1);
+    let size = tile_system.tile_size;
+    Rectangle::new_border(CURSOR_COLOUR, 2.0).draw(
+        [x + 2.0, y + 2.0, size - 4.0, size - 4.0],
+        &c.draw_state,
+        c.transform,
+        g,
+    );
+}
+
+pub fn render_confirm(
+    confirm: &Confirm,
+    tile_system: &TileSystem,
+    glyphs: Option<&mut Glyphs>,
+    c: Context,
+    g: &mut G2d,
+) {
+    let (width, height) = (260.0, 56.0);
+    let x = (tile_system.window_width - width) / 2.0;
+    let y = (tile_system.window_height - height) / 2.0;
+    rectangle(PANEL_COLOUR, [x, y, width, height], c.transform, g);
+    Rectangle::new_border(HIGHLIGHT_COLOUR, 1.0).draw(
+        [x, y, width, height],
+        &c.draw_state,
+        c.transform,
+        g,
+    );
+
+    if let Some(glyphs) = glyphs {
+        let lines = [confirm.message(), confirm.hint()];
+        for (i, line) in lines.iter().enumerate() {
+            let transform = c
+                .transform
+                .trans(x + PADDING, y + 22.0 + i as f64 * ROW_HEIGHT);
+            text(TEXT_COLOUR, 12, line, glyphs, transform, g).ok();
+        }
+    }
+}
+
+// the hovered cell's remaining tiles and their chances, beside the cell
+pub fn render_candidates(
+    tile_system: &TileSystem,
+    cell: (usize, usize),
+    candidates: &[(String, f64)],
+    glyphs: Option<&mut Glyphs>,
+    c: Context,
+    g: &mut G2d,
+) {
+    let Some(glyphs) = glyphs else {
+        return;
+    };
+    let (cell_x, cell_y) = tile_system.grid_to_world(cell.0, cell.1);
+    let line_height = 14.0;
+    let width = 130.0;
+    let height = PADDING * 2.0 + line_height * candidates.len() as f64;
+    let (x, y) = (cell_x + tile_system.tile_size + 4.0, cell_y);
+    rectangle(PANEL_COLOUR, [x, y, width, height], c.transform, g);
+    for (i, (label, share)) in candidates.iter().enumerate() {
+        let line = format!("{:<12} {:>5.1}%", label, share * 100.0);
+        let transform = c
+            .transform
+            .trans(x + PADDING, y + PADDING + 11.0 + i as f64 * line_height);
+        text(TEXT_COLOUR, 11, &line, glyphs, transform, g).ok();
+    }
+}
+
+// numbered overlay for the quick-open list, picked with the digit keys
+pub fn render_quick_open(
+    entries: &[QuickEntry],
+    tile_system: &TileSystem,
+    glyphs: Option<&mut Glyphs>,
+    c: Context,
+    g: &mut G2d,
+) {
+    let width = 260.0;
+    let height = PADDING * 2.0 + ROW_HEIGHT * (entries.len() + 1) as f64;
+    let x = (tile_system.window_width - width) / 2.0;
+    let y = PADDING * 4.0;
+    rectangle(PANEL_COLOUR, [x, y, width, height], c.transform, g);
+
+    if let Some(glyphs) = glyphs {
+        let title = t!("Quick open (1-9, Esc to close)");
+        let rows = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| format!("{}  {}", i + 1, entry.label()));
+        for (i, line) in std::iter::once(title).chain(rows).enumerate() {
+            let transform = c
+                .transform
+                .trans(x + PADDING, y + PADDING + 14.0 + i as f64 * ROW_HEIGHT);
+            text(TEXT_COLOUR, 12, &line, glyphs, transform, g).ok();
+        }
+    }
+}
+
+// F1 cheat sheet, one block per category flowing down then across in columns
+pub fn render_keymap(
+    tile_system: &TileSystem,
+    glyphs: Option<&mut Glyphs>,
+    c: Context,
+    g: &mut G2d,
+) {
+    let (width, height) = (tile_system.window_width, tile_system.window_height);
+    rectangle(PANEL_COLOUR, [0.0, 0.0, width, height], c.transform, g);
+    let Some(glyphs) = glyphs else {
+        return;
+    };
+
+    let line_height = 14.0;
+    let column_width = (width - PADDING * 2.0) / 2.0;
+    let (mut x, mut y) = (PADDING, PADDING + line_height);
+    for category in keymap::categories() {
+        let bindings: Vec<_> = KEYMAP.iter().filter(|b| b.category == category).collect();
+        if y + line_height * (bindings.len() + 1) as f64 > height && y > PADDING + line_height {
+            x += column_width;
+            y = PADDING + line_height;
+        }
+        text(
+            HIGHLIGHT_COLOUR,
+            12,
+            &t!(category),
+            glyphs,
+            c.transform.trans(x, y),
+            g,
+        )
+        .ok();
+        y += line_height;
+        for binding in bindings {
+            let transform = c.transform.trans(x + PADDING, y);
+            text(TEXT_COLOUR, 11, &t!(binding.keys), glyphs, transform, g).ok();
+            let transform = c.transform.trans(x + PADDING + 84.0, y);
+            text(TEXT_COLOUR, 11, &t!(binding.action), glyphs, transform, g).ok();
+            y += line_height;
+        }
+        y += line_height / 2.0;
+    }
+}
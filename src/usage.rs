@@ -0,0 +1,104 @@
+use crate::i18n::{say, t};
+use crate::{TileSystem, TileType};
+
+// where a tile turns up: cells in the enabled samples, cells on the map, and
+// whether the learned rules let it sit next to anything at all
+#[derive(Clone, Debug)]
+pub struct TileUsage {
+    pub tile: TileType,
+    pub in_samples: usize,
+    pub on_map: usize,
+    pub in_rules: bool,
+    pub pruned: bool,
+}
+
+impl TileUsage {
+    // a tile the model can't place anyway, so dropping it loses nothing
+    pub fn unused(&self) -> bool {
+        self.in_samples == 0 && !self.in_rules
+    }
+}
+
+impl TileSystem {
+    pub fn tile_usage(&self) -> Vec<TileUsage> {
+        let mut in_samples = [0; TileType::ALL.len()];
+        for (source, setting) in self.samples() {
+            if !setting.enabled || setting.weight <= 0.0 {
+                continue;
+            }
+            for tile in self.sample_grid(&source).iter().flatten() {
+                in_samples[tile.index()] += 1;
+            }
+        }
+        let mut on_map = [0; TileType::ALL.len()];
+        for tile in self.tile_types().iter().flatten() {
+            on_map[tile.index()] += 1;
+        }
+        let rules = self.learn_rules("").unwrap_or_default();
+        TileType::ALL
+            .iter()
+            .map(|tile| TileUsage {
+                tile: tile.clone(),
+                in_samples: in_samples[tile.index()],
+                on_map: on_map[tile.index()],
+                in_rules: rules
+                    .get(&tile.index())
+                    .is_some_and(|allowed| !allowed.is_empty()),
+                pruned: self.pruned_tiles.contains(tile),
+            })
+            .collect()
+    }
+
+    pub fn print_tile_usage(&self) {
+        println!("{:<10} {:>8} {:>8}  rules", "Tile", "samples", "map");
+        for usage in self.tile_usage() {
+            let note = if usage.pruned {
+                " (pruned)"
+            } else if usage.unused() {
+                " (unused)"
+            } else {
+                ""
+            };
+            println!(
+                "{:<10} {:>8} {:>8}  {}{}",
+                format!("{:?}", usage.tile),
+                usage.in_samples,
+                usage.on_map,
+                if usage.in_rules { "yes" } else { "no" },
+                note
+            );
+        }
+    }
+
+    // "unused" drops every tile the enabled samples never use from the solver's
+    // domains, "restore" puts them all back
+    pub fn prune_tiles(&mut self, command: &str) -> Result<(), String> {
+        match command {
+            "unused" => {
+                if !self.samples().iter().any(|(_, setting)| setting.enabled) {
+                    return Err(t!("No samples enabled to judge usage by"));
+                }
+                let unused: Vec<TileType> = self
+                    .tile_usage()
+                    .into_iter()
+                    .filter(TileUsage::unused)
+                    .map(|usage| usage.tile)
+                    .collect();
+                if unused.is_empty() {
+                    say!("Every tile is used by the enabled samples");
+                } else {
+                    let names: Vec<String> = unused.iter().map(|t| format!("{:?}", t)).collect();
+                    say!("Pruned {names} from the model", names = names.join(", "));
+                }
+                self.pruned_tiles = unused;
+            }
+            "restore" => {
+                self.pruned_tiles.clear();
+                say!("Every tile is back in the model");
+            }
+            _ => return Err(t!("Expected \"unused\" or \"restore\"")),
+        }
+        self.dirty = true;
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+use piston_window::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::i18n::say;
+use crate::{TileSystem, TileType};
+
+// dominant tiles handed out to regions, with relative odds
+const BIOMES: [(TileType, f64); 3] = [
+    (TileType::Water, 0.45),
+    (TileType::Land, 0.4),
+    (TileType::Mountain, 0.15),
+];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BiomeSeed {
+    pub x: usize,
+    pub y: usize,
+    pub tile: TileType,
+}
+
+fn pick_biome(rng: &mut StdRng) -> TileType {
+    let total: f64 = BIOMES.iter().map(|(_, odds)| odds).sum();
+    let mut roll = rng.gen_range(0.0..total);
+    for (tile, odds) in BIOMES {
+        if roll < odds {
+            return tile;
+        }
+        roll -= odds;
+    }
+    BIOMES[0].0.clone()
+}
+
+impl TileSystem {
+    // random Voronoi sites, each giving its region a dominant tile that generation prefers
+    pub fn seed_biomes(&mut self, count: usize, seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        self.biome_seeds = (0..count)
+            .map(|_| BiomeSeed {
+                x: rng.gen_range(0..self.grid_width),
+                y: rng.gen_range(0..self.grid_height),
+                tile: pick_biome(&mut rng),
+            })
+            .collect();
+        self.dirty = true;
+        say!(
+            "Seeded {count} Voronoi region(s) with seed {seed}, generate (W) to fill them",
+            count = count,
+            seed = seed
+        );
+    }
+
+    pub fn clear_biomes(&mut self) {
+        self.dirty |= !self.biome_seeds.is_empty();
+        self.biome_seeds.clear();
+        say!("Voronoi regions cleared");
+    }
+
+    // index of the nearest site
+    fn biome_region(&self, x: usize, y: usize) -> Option<usize> {
+        (0..self.biome_seeds.len()).min_by_key(|&i| {
+            let site = &self.biome_seeds[i];
+            let (dx, dy) = (site.x.abs_diff(x), site.y.abs_diff(y));
+            dx * dx + dy * dy
+        })
+    }
+
+    // soft per-cell tile hints for the solver, empty without sites
+    pub fn biome_preferences(&self) -> HashMap<(usize, usize), usize> {
+        let mut preferences = HashMap::new();
+        for y in 0..self.grid_height {
+            for x in 0..self.grid_width {
+                if let Some(region) = self.biome_region(x, y) {
+                    preferences.insert((x, y), self.biome_seeds[region].tile.index());
+                }
+            }
+        }
+        preferences
+    }
+
+    // region borders as thin lines along cell edges where the nearest site changes
+    pub fn render_biomes(&self, c: Context, g: &mut G2d) {
+        if self.biome_seeds.is_empty() {
+            return;
+        }
+        let colour = [1.0, 1.0, 1.0, 0.5];
+        for y in 0..self.grid_height {
+            for x in 0..self.grid_width {
+                let region = self.biome_region(x, y);
+                let (world_x, world_y) = self.grid_to_world(x, y);
+                let (right, bottom) = (world_x + self.tile_size, world_y + self.tile_size);
+                if x + 1 < self.grid_width && self.biome_region(x + 1, y) != region {
+                    line_from_to(
+                        colour,
+                        0.75,
+                        [right, world_y],
+                        [right, bottom],
+                        c.transform,
+                        g,
+                    );
+                }
+                if y + 1 < self.grid_height && self.biome_region(x, y + 1) != region {
+                    line_from_to(
+                        colour,
+                        0.75,
+                        [world_x, bottom],
+                        [right, bottom],
+                        c.transform,
+                        g,
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::BTreeSet;
+
+use crate::csp::CspSolver;
+use crate::i18n::{say, t};
+use crate::observer::StatsObserver;
+use crate::{Tile, TileSystem, TileType, time_seed};
+
+pub type CellSet = BTreeSet<(usize, usize)>;
+
+impl TileSystem {
+    // magic wand: the island of same-type tiles under (x, y), locked cells included
+    pub fn select_island(&self, x: usize, y: usize) -> Option<CellSet> {
+        let cells: CellSet = self.connected_cells(x, y, false).into_iter().collect();
+        let tile = self.get_tile(x, y)?;
+        say!(
+            "Selected {count} {tile_type} cell(s)",
+            count = cells.len(),
+            tile_type = format!("{:?}", tile.tile_type)
+        );
+        Some(cells)
+    }
+
+    // one undo step; locked cells keep their tile
+    pub fn replace_cells(&mut self, cells: &CellSet, tile: &TileType) {
+        self.push_undo();
+        for &(x, y) in cells {
+            self.set_tile(x, y, Tile::from_type(tile));
+        }
+        say!(
+            "Set {count} cell(s) to {tile}",
+            count = cells.len(),
+            tile = format!("{:?}", tile)
+        );
+    }
+
+    // shifts the cells' tiles, leaving Empty behind; returns where the selection ended up
+    pub fn move_cells(&mut self, cells: &CellSet, dx: isize, dy: isize) -> CellSet {
+        self.push_undo();
+        let moved: Vec<((usize, usize), TileType)> = cells
+            .iter()
+            .filter_map(|&(x, y)| {
+                let tile = self.get_tile(x, y)?.tile_type.clone();
+                let nx = x
+                    .checked_add_signed(dx)
+                    .filter(|nx| *nx < self.grid_width)?;
+                let ny = y
+                    .checked_add_signed(dy)
+                    .filter(|ny| *ny < self.grid_height)?;
+                Some(((nx, ny), tile))
+            })
+            .collect();
+        for &(x, y) in cells {
+            self.set_tile(x, y, Tile::empty());
+        }
+        for ((x, y), tile) in &moved {
+            self.set_tile(*x, *y, Tile::from_type(tile));
+        }
+        say!(
+            "Moved {count} cell(s) by ({dx}, {dy})",
+            count = moved.len(),
+            dx = dx,
+            dy = dy
+        );
+        moved.into_iter().map(|(cell, _)| cell).collect()
+    }
+
+    // solves just the selected cells against the rest of the map, which stays as it is
+    pub fn regenerate_cells(
+        &mut self,
+        cells: &CellSet,
+        model: &str,
+        seed: u64,
+    ) -> Result<(), String> {
+        let rules = self.learn_rules(model)?;
+        let mut fixed = self.locked_tile_ids();
+        for y in 0..self.grid_height {
+            for x in 0..self.grid_width {
+                // empty cells outside aren't pinned, the rules rarely allow Empty
+                if let Some(tile) = self.get_tile(x, y)
+                    && tile.tile_type != TileType::Empty
+                    && !cells.contains(&(x, y))
+                {
+                    fixed.insert((x, y), tile.tile_type.index());
+                }
+            }
+        }
+        let mut solver = CspSolver::new(
+            &rules,
+            TileType::ALL.len(),
+            self.grid_width,
+            self.grid_height,
+            seed,
+        );
+        solver.set_preferences(&self.biome_preferences());
+        let result = solver
+            .solve(&fixed, &mut StatsObserver::default())
+            .ok_or_else(|| "No solution for the selection with the tiles around it".to_string())?;
+        self.push_undo();
+        for &(x, y) in cells {
+            self.set_tile(x, y, Tile::from_type(&TileType::ALL[result[y][x]]));
+        }
+        say!(
+            "Regenerated {count} cell(s) from '{model}' with seed {seed}",
+            count = cells.len(),
+            model = model,
+            seed = seed
+        );
+        Ok(())
+    }
+
+    // the size x size square centred on (x, y), clipped to the map and without locked
+    // cells, solved again against what surrounds it
+    pub fn reroll_around(
+        &mut self,
+        (x, y): (usize, usize),
+        size: usize,
+        model: &str,
+        seed: u64,
+    ) -> Result<(), String> {
+        let size = size.max(1);
+        let (left, top) = (x.saturating_sub(size / 2), y.saturating_sub(size / 2));
+        let cells: CellSet = (top..(top + size).min(self.grid_height))
+            .flat_map(|cy| (left..(left + size).min(self.grid_width)).map(move |cx| (cx, cy)))
+            .filter(|cell| !self.locked_cells.contains(cell))
+            .collect();
+        if cells.is_empty() {
+            return Err(t!("Every cell around the cursor is locked"));
+        }
+        self.regenerate_cells(&cells, model, seed)
+    }
+
+    // "replace <tile>", "move <dx> <dy>" or "regenerate [config]" typed at the console;
+    // returns the selection as it is afterwards
+    pub fn edit_cells(&mut self, cells: &CellSet, command: &str) -> Result<CellSet, String> {
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        match parts.as_slice() {
+            ["replace", tile] => {
+                let tile = TileType::parse(tile).ok_or(format!("Unknown tile '{}'", tile))?;
+                self.replace_cells(cells, &tile);
+                Ok(cells.clone())
+            }
+            ["move", dx, dy] => {
+                let dx = dx
+                    .parse()
+                    .map_err(|_| t!("'{dx}' isn't a number", dx = dx))?;
+                let dy = dy
+                    .parse()
+                    .map_err(|_| t!("'{dy}' isn't a number", dy = dy))?;
+                Ok(self.move_cells(cells, dx, dy))
+            }
+            ["regenerate"] => self
+                .regenerate_cells(cells, "", time_seed())
+                .map(|()| cells.clone()),
+            ["regenerate", model] => self
+                .regenerate_cells(cells, model, time_seed())
+                .map(|()| cells.clone()),
+            _ => Err(
+                "Expected \"replace <tile>\", \"move <dx> <dy>\" or \"regenerate [config]\""
+                    .to_string(),
+            ),
+        }
+    }
+}
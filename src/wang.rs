@@ -0,0 +1,231 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::csp::CspSolver;
+use crate::i18n::{say, t};
+use crate::observer::StatsObserver;
+use crate::{Direction, Tile, TileSystem, TileType, groups};
+
+// cells per side of one Wang tile when drawn on the map
+const BLOCK: usize = 3;
+// Wang tiles across and down in the sample registered for a set
+const SAMPLE_TILES: usize = 6;
+// seeds tried for a sample that shows every tile of the set
+const SAMPLE_TRIES: u64 = 64;
+// the solver's domains are u32 bitmasks
+const MAX_TILES: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WangKind {
+    // colours sit on the corners and are shared by the four tiles meeting there
+    Corner,
+    // colours sit on the sides and must match across each shared side
+    Edge,
+}
+
+// a complete Wang set: one tile for every way of colouring its four corners or
+// sides, so any colouring of the map's corners or sides can be tiled
+#[derive(Clone, Debug)]
+pub struct WangSet {
+    pub kind: WangKind,
+    pub colours: Vec<TileType>,
+}
+
+// the colour listed first wins where a cell sits between two
+fn pick(a: usize, b: usize) -> usize {
+    a.min(b)
+}
+
+// the most common of four colours, the earlier listed on a tie
+fn majority(colours: [usize; 4]) -> usize {
+    let count = |c: usize| colours.iter().filter(|&&x| x == c).count();
+    colours
+        .iter()
+        .copied()
+        .max_by(|&a, &b| count(a).cmp(&count(b)).then(b.cmp(&a)))
+        .unwrap_or(0)
+}
+
+impl WangSet {
+    // "corner water,land" or "edge land+mountain"
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (kind, colours) = spec
+            .trim()
+            .split_once(' ')
+            .ok_or_else(|| t!("Expected \"corner <tiles>\" or \"edge <tiles>\""))?;
+        let kind = match kind.to_lowercase().as_str() {
+            "corner" => WangKind::Corner,
+            "edge" => WangKind::Edge,
+            other => return Err(t!("Unknown Wang set kind '{other}'", other = other)),
+        };
+        let colours = groups::parse_tiles(colours.trim())?;
+        if colours.len() < 2 {
+            return Err(t!("A Wang set needs at least two colours"));
+        }
+        if colours
+            .iter()
+            .enumerate()
+            .any(|(i, c)| colours[..i].contains(c))
+        {
+            return Err(t!("Each colour can only be listed once"));
+        }
+        let set = WangSet { kind, colours };
+        if set.tile_count() > MAX_TILES {
+            return Err(t!(
+                "{count} colours make {tiles} tiles, the solver handles at most {max}",
+                count = set.colours.len(),
+                tiles = set.tile_count(),
+                max = MAX_TILES
+            ));
+        }
+        Ok(set)
+    }
+
+    // the config the set is registered under, e.g. "wang-corner-water-land"
+    pub fn name(&self) -> String {
+        let kind = match self.kind {
+            WangKind::Corner => "corner",
+            WangKind::Edge => "edge",
+        };
+        let colours: Vec<String> = self
+            .colours
+            .iter()
+            .map(|c| format!("{:?}", c).to_lowercase())
+            .collect();
+        format!("wang-{}-{}", kind, colours.join("-"))
+    }
+
+    pub fn tile_count(&self) -> usize {
+        self.colours.len().pow(4)
+    }
+
+    // colour indices of tile `id`: nw, ne, se, sw for corner sets, n, e, s, w for edge sets
+    pub fn tile(&self, id: usize) -> [usize; 4] {
+        let n = self.colours.len();
+        [id % n, id / n % n, id / (n * n) % n, id / (n * n * n) % n]
+    }
+
+    // which tiles may sit to the right of and below which, matching shared colours
+    pub fn rules(&self) -> HashMap<usize, HashSet<(Direction, usize)>> {
+        let fits_right = |a: [usize; 4], b: [usize; 4]| match self.kind {
+            WangKind::Corner => a[1] == b[0] && a[2] == b[3],
+            WangKind::Edge => a[1] == b[3],
+        };
+        let fits_below = |a: [usize; 4], b: [usize; 4]| match self.kind {
+            WangKind::Corner => a[3] == b[0] && a[2] == b[1],
+            WangKind::Edge => a[2] == b[0],
+        };
+        let mut rules: HashMap<usize, HashSet<(Direction, usize)>> = HashMap::new();
+        for a in 0..self.tile_count() {
+            for b in 0..self.tile_count() {
+                if fits_right(self.tile(a), self.tile(b)) {
+                    rules.entry(a).or_default().insert((Direction::Right, b));
+                    rules.entry(b).or_default().insert((Direction::Left, a));
+                }
+                if fits_below(self.tile(a), self.tile(b)) {
+                    rules.entry(a).or_default().insert((Direction::Down, b));
+                    rules.entry(b).or_default().insert((Direction::Up, a));
+                }
+            }
+        }
+        rules
+    }
+
+    // colour indices of the BLOCK x BLOCK cells drawing tile `id`
+    fn block(&self, id: usize) -> [[usize; BLOCK]; BLOCK] {
+        let [a, b, c, d] = self.tile(id);
+        let centre = majority([a, b, c, d]);
+        match self.kind {
+            WangKind::Corner => [
+                [a, pick(a, b), b],
+                [pick(a, d), centre, pick(b, c)],
+                [d, pick(d, c), c],
+            ],
+            WangKind::Edge => [
+                [pick(d, a), a, pick(a, b)],
+                [d, centre, b],
+                [pick(d, c), c, pick(c, b)],
+            ],
+        }
+    }
+
+    // tile ids as rows, drawn out as map cells
+    pub fn render(&self, ids: &[Vec<usize>]) -> Vec<Vec<TileType>> {
+        let mut rows = Vec::new();
+        for row in ids {
+            for y in 0..BLOCK {
+                rows.push(
+                    row.iter()
+                        .flat_map(|&id| self.block(id)[y])
+                        .map(|colour| self.colours[colour].clone())
+                        .collect(),
+                );
+            }
+        }
+        rows
+    }
+
+    fn solve(&self, width: usize, height: usize, seed: u64) -> Option<Vec<Vec<usize>>> {
+        let mut solver = CspSolver::new(&self.rules(), self.tile_count(), width, height, seed);
+        solver.solve(&HashMap::new(), &mut StatsObserver::default())
+    }
+}
+
+impl TileSystem {
+    // saves a tiling that shows off the set as a config, so the usual sample tools
+    // can read it, and fills the map with a fresh tiling of it
+    pub fn generate_wang(&mut self, spec: &str, seed: u64) -> Result<(), String> {
+        let set = WangSet::parse(spec)?;
+        let (width, height) = (self.grid_width / BLOCK, self.grid_height / BLOCK);
+        if width == 0 || height == 0 {
+            return Err(t!(
+                "The map needs to be at least {size}x{size} cells for Wang tiles",
+                size = BLOCK
+            ));
+        }
+
+        // the sample using the most different tiles out of a few tries
+        let distinct = |ids: &Vec<Vec<usize>>| ids.iter().flatten().collect::<HashSet<_>>().len();
+        let mut sample: Option<Vec<Vec<usize>>> = None;
+        for i in 0..SAMPLE_TRIES {
+            let Some(ids) = set.solve(SAMPLE_TILES, SAMPLE_TILES, seed.wrapping_add(i)) else {
+                continue;
+            };
+            if sample
+                .as_ref()
+                .is_none_or(|best| distinct(&ids) > distinct(best))
+            {
+                sample = Some(ids);
+            }
+            if sample
+                .as_ref()
+                .is_some_and(|best| distinct(best) == set.tile_count())
+            {
+                break;
+            }
+        }
+        let sample = sample.ok_or_else(|| t!("The Wang set has no tiling"))?;
+        self.store_config(set.name(), set.render(&sample));
+
+        let ids = set
+            .solve(width, height, seed)
+            .ok_or_else(|| t!("The Wang set has no tiling"))?;
+        self.staged_update(|_, staged| {
+            for (y, row) in set.render(&ids).iter().enumerate() {
+                for (x, tile) in row.iter().enumerate() {
+                    staged.set(x, y, Tile::from_type(tile));
+                }
+            }
+        });
+        say!(
+            "Tiled the map with {count} {kind} Wang tiles ({name}) using seed {seed}",
+            count = set.tile_count(),
+            kind = match set.kind {
+                WangKind::Corner => t!("corner"),
+                WangKind::Edge => t!("edge"),
+            },
+            name = set.name(),
+            seed = seed
+        );
+        Ok(())
+    }
+}
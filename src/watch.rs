@@ -0,0 +1,84 @@
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+
+use crate::config::AppConfig;
+use crate::i18n::{say, t};
+use crate::templates::TEMPLATE_DIR;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reload {
+    Config,
+    Templates,
+}
+
+// --watch: notices external edits to config.json and the templates folder
+pub struct FileWatcher {
+    // dropping the watcher stops the events
+    _watcher: RecommendedWatcher,
+    receiver: Receiver<notify::Result<Event>>,
+}
+
+impl FileWatcher {
+    pub fn start() -> Option<Self> {
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = match notify::recommended_watcher(sender) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                eprintln!("{}", t!("File watching unavailable: {e}", e = e));
+                return None;
+            }
+        };
+        // the config file is watched through its folder so editors that replace it still count
+        if let Err(e) = watcher.watch(Path::new("."), RecursiveMode::NonRecursive) {
+            eprintln!("{}", t!("Failed to watch the working folder: {e}", e = e));
+            return None;
+        }
+        if Path::new(TEMPLATE_DIR).is_dir()
+            && let Err(e) = watcher.watch(Path::new(TEMPLATE_DIR), RecursiveMode::NonRecursive)
+        {
+            eprintln!(
+                "{}",
+                t!("Failed to watch {dir}: {e}", dir = TEMPLATE_DIR, e = e)
+            );
+        }
+        say!(
+            "Watching {file} and {dir}/ for changes",
+            file = AppConfig::CONFIG_FILE,
+            dir = TEMPLATE_DIR
+        );
+        Some(FileWatcher {
+            _watcher: watcher,
+            receiver,
+        })
+    }
+
+    // what needs reloading since the last poll, each at most once
+    pub fn poll(&self) -> Vec<Reload> {
+        let mut reloads = Vec::new();
+        while let Ok(event) = self.receiver.try_recv() {
+            let Ok(event) = event else {
+                continue;
+            };
+            if !matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            ) {
+                continue;
+            }
+            for path in &event.paths {
+                let reload = if path.file_name() == Some(AppConfig::CONFIG_FILE.as_ref()) {
+                    Reload::Config
+                } else if path.parent().and_then(Path::file_name) == Some(TEMPLATE_DIR.as_ref()) {
+                    Reload::Templates
+                } else {
+                    continue;
+                };
+                if !reloads.contains(&reload) {
+                    reloads.push(reload);
+                }
+            }
+        }
+        reloads
+    }
+}
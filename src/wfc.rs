@@ -0,0 +1,921 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::mpsc::Receiver;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+use serde::{Deserialize, Serialize};
+
+use crate::csp::{DEFAULT_MEMORY_BUDGET, SolverModel, TargetShares, allowed_masks, dir_index};
+use crate::i18n::{say, t};
+use crate::observer::GenerationObserver;
+use crate::rotations::StateTable;
+use crate::worker::solver_model;
+use crate::{Backend, Direction, TileSystem};
+
+const DIRECTIONS: [(Direction, isize, isize); 4] = [
+    (Direction::Up, 0, -1),
+    (Direction::Down, 0, 1),
+    (Direction::Left, -1, 0),
+    (Direction::Right, 1, 0),
+];
+
+// tie-breaking noise added to each cell's entropy when observing, well under the
+// gap between any two different entropies
+const ENTROPY_NOISE: f64 = 1e-6;
+
+// a domain as it was before a decision narrowed it
+const TRAIL_ENTRY: usize = size_of::<(usize, u32)>();
+
+// `wfc` in config.json
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WfcOptions {
+    // fresh seeds tried after the first run ends in a contradiction
+    pub max_retries: u32,
+    // undone observations a run may spend before it counts as failed
+    pub max_backtracks: usize,
+}
+
+impl Default for WfcOptions {
+    fn default() -> Self {
+        WfcOptions {
+            max_retries: 10,
+            max_backtracks: 10_000,
+        }
+    }
+}
+
+// an observation: the cell, the tile it collapsed to, and each domain that and its
+// propagation narrowed, as it was before, so undoing it needs no copy of the grid
+#[derive(Serialize, Deserialize)]
+struct Decision {
+    cell: usize,
+    tile: usize,
+    narrowed: Vec<(usize, u32)>,
+}
+
+// Shannon entropy, in nats, of picking one of the tiles in `domain` by weight;
+// uniform when none of them has any weight
+pub fn entropy(domain: u32, weights: &[f64]) -> f64 {
+    let weight = |tile: usize| weights.get(tile).copied().unwrap_or(0.0);
+    let total: f64 = tiles_in(domain).map(weight).sum();
+    if total > 0.0 {
+        let sum_w_log_w: f64 = tiles_in(domain)
+            .map(weight)
+            .filter(|w| *w > 0.0)
+            .map(|w| w * w.ln())
+            .sum();
+        total.ln() - sum_w_log_w / total
+    } else {
+        (domain.count_ones().max(1) as f64).ln()
+    }
+}
+
+fn tiles_in(domain: u32) -> impl Iterator<Item = usize> + Clone {
+    (0..u32::BITS as usize).filter(move |tile| domain & (1 << tile) != 0)
+}
+
+// wave function collapse: observe the cell with the least entropy, collapse it to
+// one of its tiles by weight, then narrow its neighbours to tiles the rules allow
+// beside it, until every cell is down to one tile or one runs out of tiles.
+// serializable whole, so a suspended run can be saved and resumed elsewhere
+#[derive(Serialize, Deserialize)]
+pub struct WfcSolver {
+    width: usize,
+    height: usize,
+    // allowed[tile][direction] = bitmask of tiles that may sit in that direction
+    allowed: Vec<[u32; 4]>,
+    // tiles each cell could still be, one bit per tile
+    domains: Vec<u32>,
+    // each domain's entropy under `weights`, kept up to date as it narrows
+    entropies: Vec<f64>,
+    // how likely each tile is to be picked when a cell collapses
+    weights: Vec<f64>,
+    // cells pinned to a tile, which every attempt starts from
+    fixed: HashMap<usize, usize>,
+    // per-cell tile picked first on that share of the collapses, see bias.rs
+    preferred: Vec<Option<(usize, f64)>>,
+    // shares the picks lean towards in place of `weights`, see targets.rs
+    targets: Option<TargetShares>,
+    // cost between each pair of tile ids, see costs.rs
+    transition_costs: Option<Vec<Vec<f64>>>,
+    // replacement models sent while the run goes on, picked up between observations
+    #[serde(skip)]
+    model_updates: Option<Receiver<SolverModel>>,
+    // asks the run to stop before its next observation and keep where it got to
+    #[serde(skip)]
+    suspend_requests: Option<Receiver<()>>,
+    suspended: bool,
+    // observations that can still be undone, oldest first, and the bytes they hold
+    trail: VecDeque<Decision>,
+    trail_bytes: usize,
+    memory_budget: usize,
+    rng: ChaCha12Rng,
+    options: WfcOptions,
+    // the first attempt's seed, which retries count on from
+    first_seed: u64,
+    // the current attempt's seed, the one that worked once `solve` succeeds
+    pub seed: u64,
+    // observations the current attempt undid after a contradiction
+    pub backtracks: usize,
+    // most bytes the trail held at once
+    pub peak_memory: usize,
+}
+
+impl WfcSolver {
+    pub fn new(
+        rules: &HashMap<usize, HashSet<(Direction, usize)>>,
+        tile_count: usize,
+        width: usize,
+        height: usize,
+        seed: u64,
+    ) -> Self {
+        assert!(tile_count <= 32, "domains are u32 bitmasks");
+        let mut solver = WfcSolver {
+            width,
+            height,
+            allowed: allowed_masks(rules, tile_count),
+            domains: Vec::new(),
+            entropies: Vec::new(),
+            weights: vec![1.0; tile_count],
+            fixed: HashMap::new(),
+            preferred: vec![None; width * height],
+            targets: None,
+            transition_costs: None,
+            model_updates: None,
+            suspend_requests: None,
+            suspended: false,
+            trail: VecDeque::new(),
+            trail_bytes: 0,
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+            rng: ChaCha12Rng::seed_from_u64(seed),
+            options: WfcOptions::default(),
+            first_seed: seed,
+            seed,
+            backtracks: 0,
+            peak_memory: 0,
+        };
+        solver.reset(seed);
+        solver
+    }
+
+    pub fn with_options(mut self, options: WfcOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    // bytes the trail may hold; past it the oldest observations are forgotten, so a
+    // contradiction can only be undone back to the oldest one still kept
+    pub fn with_memory_budget(mut self, memory_budget: usize) -> Self {
+        self.memory_budget = memory_budget;
+        self
+    }
+
+    // per-tile weights, usually how often each tile appears in the sample
+    pub fn set_weights(&mut self, weights: &[f64]) {
+        self.weights = weights.to_vec();
+        for (entropy_of, &domain) in self.entropies.iter_mut().zip(&self.domains) {
+            *entropy_of = entropy(domain, &self.weights);
+        }
+    }
+
+    // pins cells to a tile before the run, as locked cells are
+    pub fn fix(&mut self, fixed: &HashMap<(usize, usize), usize>) {
+        for (&(x, y), &tile) in fixed {
+            if x < self.width && y < self.height && tile < self.allowed.len() {
+                self.fixed.insert(y * self.width + x, tile);
+            }
+        }
+        self.reset(self.seed);
+    }
+
+    // a preferred tile is picked on `strength` of the collapses of its cell
+    pub fn set_weighted_preferences(&mut self, preferred: &HashMap<(usize, usize), (usize, f64)>) {
+        for (&(x, y), &(tile, strength)) in preferred {
+            if x < self.width && y < self.height && strength > 0.0 {
+                self.preferred[y * self.width + x] = Some((tile, strength.min(1.0)));
+            }
+        }
+    }
+
+    pub fn set_targets(&mut self, targets: Option<TargetShares>) {
+        self.targets = targets.filter(|targets| targets.shares.len() == self.allowed.len());
+    }
+
+    pub fn set_transition_costs(&mut self, costs: Option<Vec<Vec<f64>>>) {
+        self.transition_costs = costs.filter(|costs| costs.len() == self.allowed.len());
+    }
+
+    // the run swaps in the latest model from `updates` before each observation and
+    // reopens the cells it hasn't decided under it
+    pub fn set_model_updates(&mut self, updates: Receiver<SolverModel>) {
+        self.model_updates = Some(updates);
+    }
+
+    // a request on `requests` stops the run before its next observation; `solve` then
+    // returns None without completing and `resume` goes on from there
+    pub fn set_suspend_requests(&mut self, requests: Receiver<()>) {
+        self.suspend_requests = Some(requests);
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    // every cell's remaining tiles, one bit per tile
+    pub fn domains(&self) -> &[u32] {
+        &self.domains
+    }
+
+    fn apply_model(&mut self, model: SolverModel) {
+        self.allowed = allowed_masks(&model.rules, self.allowed.len());
+        self.set_targets(model.targets);
+        self.set_transition_costs(model.transition_costs);
+    }
+
+    // tiles with at least one neighbour in the rules; the sample never showed the
+    // others, so no cell starts with them
+    fn known_tiles(&self) -> u32 {
+        (0..self.allowed.len())
+            .filter(|tile| self.allowed[*tile].iter().any(|mask| *mask != 0))
+            .fold(0u32, |mask, tile| mask | 1 << tile)
+    }
+
+    // back to the start of an attempt: every cell open but the fixed ones
+    fn reset(&mut self, seed: u64) {
+        let known = self.known_tiles();
+        let cell_count = self.width * self.height;
+        self.domains = (0..cell_count)
+            .map(|cell| self.fixed.get(&cell).map_or(known, |&tile| 1 << tile))
+            .collect();
+        self.entropies = self
+            .domains
+            .iter()
+            .map(|&domain| entropy(domain, &self.weights))
+            .collect();
+        self.trail.clear();
+        self.trail_bytes = 0;
+        self.rng = ChaCha12Rng::seed_from_u64(seed);
+        self.seed = seed;
+        self.backtracks = 0;
+    }
+
+    // after a model change: decided cells stay, every open cell gets back all the
+    // tiles the new model knows, so loosened rules widen it as tightened ones narrow
+    // it. the trail was made under the old model and is dropped; false when what's
+    // decided doesn't fit the new model
+    fn reopen(&mut self, observer: &mut dyn GenerationObserver) -> bool {
+        let known = self.known_tiles();
+        for (domain, entropy_of) in self.domains.iter_mut().zip(&mut self.entropies) {
+            if domain.count_ones() > 1 {
+                *domain = known;
+                *entropy_of = entropy(known, &self.weights);
+            }
+        }
+        self.trail.clear();
+        self.trail_bytes = 0;
+        let cell_count = self.domains.len();
+        !self.domains.contains(&0) && self.propagate(0..cell_count, observer)
+    }
+
+    // pick weight per tile: the target shares corrected for what's decided so far,
+    // otherwise `weights`
+    fn value_weights(&self) -> Vec<f64> {
+        let Some(targets) = &self.targets else {
+            return self.weights.clone();
+        };
+        let mut counts = vec![0; self.allowed.len()];
+        for domain in self.domains.iter().filter(|d| d.count_ones() == 1) {
+            counts[domain.trailing_zeros() as usize] += 1;
+        }
+        targets.weights(&counts)
+    }
+
+    // scales each tile's weight down by e^-cost for every decided neighbour of `cell`
+    fn apply_transition_costs(&self, cell: usize, weights: &mut [f64]) {
+        let Some(costs) = &self.transition_costs else {
+            return;
+        };
+        for (_, dx, dy) in DIRECTIONS {
+            let Some(neighbour) = self.neighbour(cell, dx, dy) else {
+                continue;
+            };
+            let domain = self.domains[neighbour];
+            if domain.count_ones() != 1 {
+                continue;
+            }
+            let neighbour = domain.trailing_zeros() as usize;
+            for (tile, weight) in weights.iter_mut().enumerate() {
+                *weight *= (-costs[tile][neighbour]).exp();
+            }
+        }
+    }
+
+    fn cell_xy(&self, cell: usize) -> (usize, usize) {
+        (cell % self.width, cell / self.width)
+    }
+
+    fn neighbour(&self, cell: usize, dx: isize, dy: isize) -> Option<usize> {
+        let (x, y) = self.cell_xy(cell);
+        let (nx, ny) = (x.checked_add_signed(dx)?, y.checked_add_signed(dy)?);
+        (nx < self.width && ny < self.height).then_some(ny * self.width + nx)
+    }
+
+    // the next cell to collapse: the open cell with the lowest entropy, ties broken
+    // by noise so no scan direction shows in the output
+    fn observe(&mut self) -> Option<usize> {
+        let mut best = None;
+        let mut lowest = f64::INFINITY;
+        for cell in 0..self.domains.len() {
+            if self.domains[cell].count_ones() < 2 {
+                continue;
+            }
+            let entropy = self.entropies[cell] + self.rng.gen_range(0.0..ENTROPY_NOISE);
+            if entropy < lowest {
+                lowest = entropy;
+                best = Some(cell);
+            }
+        }
+        best
+    }
+
+    // picks one of the cell's tiles, its preferred one or else by weight, and starts a
+    // decision for it on the trail
+    fn collapse(
+        &mut self,
+        cell: usize,
+        weights: &[f64],
+        observer: &mut dyn GenerationObserver,
+    ) -> usize {
+        let options: Vec<usize> = tiles_in(self.domains[cell]).collect();
+        let mut weights = weights.to_vec();
+        self.apply_transition_costs(cell, &mut weights);
+        let weight = |tile: usize| weights.get(tile).copied().unwrap_or(0.0);
+        let total: f64 = options.iter().map(|&tile| weight(tile)).sum();
+        let preferred = self.preferred[cell]
+            .filter(|&(tile, _)| options.contains(&tile))
+            .filter(|&(_, strength)| strength >= 1.0 || self.rng.gen_bool(strength));
+        let tile = if let Some((tile, _)) = preferred {
+            tile
+        } else if total > 0.0 {
+            let mut left = self.rng.gen_range(0.0..total);
+            *options
+                .iter()
+                .find(|&&tile| {
+                    left -= weight(tile);
+                    left < 0.0
+                })
+                .unwrap_or(options.last().unwrap())
+        } else {
+            options[self.rng.gen_range(0..options.len())]
+        };
+        observer.on_observe(self.cell_xy(cell), tile);
+        self.trail.push_back(Decision {
+            cell,
+            tile,
+            narrowed: Vec::new(),
+        });
+        self.trail_bytes += size_of::<Decision>();
+        self.narrow(cell, 1 << tile, observer);
+        tile
+    }
+
+    // shrinks a cell's domain to the tiles in `keep`, noting what it was on the latest
+    // decision; false when nothing is left
+    fn narrow(&mut self, cell: usize, keep: u32, observer: &mut dyn GenerationObserver) -> bool {
+        let before = self.domains[cell];
+        let after = before & keep;
+        if after == before {
+            return true;
+        }
+        if let Some(decision) = self.trail.back_mut() {
+            decision.narrowed.push((cell, before));
+            self.trail_bytes += TRAIL_ENTRY;
+        }
+        let xy = self.cell_xy(cell);
+        for tile in tiles_in(before & !after) {
+            observer.on_eliminate(xy, tile);
+        }
+        self.domains[cell] = after;
+        self.entropies[cell] = entropy(after, &self.weights);
+        match after.count_ones() {
+            0 => {
+                observer.on_contradiction(xy);
+                false
+            }
+            1 => {
+                observer.on_collapse(xy, after.trailing_zeros() as usize);
+                true
+            }
+            _ => true,
+        }
+    }
+
+    // narrows each dirty cell's neighbours to tiles some remaining tile of it allows
+    // on that side, and marks the narrowed ones dirty in turn until nothing changes;
+    // false when a cell is left with nothing
+    fn propagate(
+        &mut self,
+        start: impl IntoIterator<Item = usize>,
+        observer: &mut dyn GenerationObserver,
+    ) -> bool {
+        let mut dirty: VecDeque<usize> = start.into_iter().collect();
+        while let Some(cell) = dirty.pop_front() {
+            for (dir, dx, dy) in DIRECTIONS {
+                let Some(neighbour) = self.neighbour(cell, dx, dy) else {
+                    continue;
+                };
+                let support = tiles_in(self.domains[cell])
+                    .fold(0, |mask, tile| mask | self.allowed[tile][dir_index(dir)]);
+                if self.domains[neighbour] & !support == 0 {
+                    continue;
+                }
+                if !self.narrow(neighbour, support, observer) {
+                    return false;
+                }
+                dirty.push_back(neighbour);
+            }
+        }
+        true
+    }
+
+    // puts back every domain the latest decision narrowed and hands it back
+    fn undo(&mut self) -> Option<Decision> {
+        let decision = self.trail.pop_back()?;
+        self.trail_bytes -= size_of::<Decision>() + decision.narrowed.len() * TRAIL_ENTRY;
+        for &(cell, before) in decision.narrowed.iter().rev() {
+            self.domains[cell] = before;
+            self.entropies[cell] = entropy(before, &self.weights);
+        }
+        Some(decision)
+    }
+
+    // undoes observations, latest first, until one can go on without the tile it
+    // chose; false once the trail or the backtrack budget runs out. ruling the tile
+    // out is a consequence of the decisions before it, so it lands on theirs
+    fn backtrack(&mut self, observer: &mut dyn GenerationObserver) -> bool {
+        while self.backtracks < self.options.max_backtracks {
+            let Some(Decision { cell, tile, .. }) = self.undo() else {
+                return false;
+            };
+            self.backtracks += 1;
+            observer.on_backtrack(self.cell_xy(cell));
+            if self.narrow(cell, !(1 << tile), observer) && self.propagate([cell], observer) {
+                return true;
+            }
+        }
+        false
+    }
+
+    // drops the oldest observations while the trail is over the memory budget
+    fn forget_over_budget(&mut self, observer: &mut dyn GenerationObserver) {
+        if self.trail_bytes > self.peak_memory {
+            self.peak_memory = self.trail_bytes;
+            observer.on_memory(self.trail_bytes);
+        }
+        while self.trail_bytes > self.memory_budget
+            && let Some(oldest) = self.trail.pop_front()
+        {
+            self.trail_bytes -= size_of::<Decision>() + oldest.narrowed.len() * TRAIL_ENTRY;
+        }
+    }
+
+    // runs observe/propagate to the end, backtracking out of contradictions; None
+    // when that doesn't get past one
+    fn attempt(&mut self, observer: &mut dyn GenerationObserver) -> Option<Vec<Vec<usize>>> {
+        // fixed cells and left-out tiles narrow the rest before anything is observed
+        let cell_count = self.domains.len();
+        if self.domains.contains(&0) || !self.propagate(0..cell_count, observer) {
+            return None;
+        }
+        self.observe_all(observer)
+    }
+
+    // the observation loop, from wherever the domains and trail are
+    fn observe_all(&mut self, observer: &mut dyn GenerationObserver) -> Option<Vec<Vec<usize>>> {
+        loop {
+            let weights = self.value_weights();
+            observer.on_step(&self.domains, &weights);
+            if self
+                .suspend_requests
+                .as_ref()
+                .is_some_and(|requests| requests.try_iter().count() > 0)
+            {
+                self.suspended = true;
+                return None;
+            }
+            if let Some(model) = self
+                .model_updates
+                .as_ref()
+                .and_then(|updates| updates.try_iter().last())
+            {
+                self.apply_model(model);
+                if !self.reopen(observer) {
+                    say!("Model updated and what's decided no longer fits it");
+                    return None;
+                }
+                say!("Model updated, reopened the undecided cells under it");
+                continue;
+            }
+            let Some(cell) = self.observe() else {
+                break;
+            };
+            self.collapse(cell, &weights, observer);
+            let consistent = self.propagate([cell], observer);
+            self.forget_over_budget(observer);
+            if !consistent && !self.backtrack(observer) {
+                return None;
+            }
+        }
+        Some(
+            self.domains
+                .chunks(self.width)
+                .map(|row| row.iter().map(|d| d.trailing_zeros() as usize).collect())
+                .collect(),
+        )
+    }
+
+    // attempts the run, then again from the start with the next seed each time one
+    // ends in a contradiction, up to `max_retries` times; `seed` is left at the last
+    // one tried
+    pub fn solve(&mut self, observer: &mut dyn GenerationObserver) -> Option<Vec<Vec<usize>>> {
+        self.first_seed = self.seed;
+        self.attempts(false, observer)
+    }
+
+    // goes on with a suspended run where it stopped, as if it never had
+    pub fn resume(&mut self, observer: &mut dyn GenerationObserver) -> Option<Vec<Vec<usize>>> {
+        if !self.suspended {
+            return None;
+        }
+        self.suspended = false;
+        self.attempts(true, observer)
+    }
+
+    // the current attempt and the retries after it; `resumed` goes on with the current
+    // one where it stopped rather than starting it
+    fn attempts(
+        &mut self,
+        mut resumed: bool,
+        observer: &mut dyn GenerationObserver,
+    ) -> Option<Vec<Vec<usize>>> {
+        let first = self.first_seed;
+        let mut result = None;
+        for retry in self.seed.wrapping_sub(first)..=self.options.max_retries as u64 {
+            if resumed {
+                resumed = false;
+                result = self.observe_all(observer);
+            } else {
+                if retry > 0 {
+                    observer.on_restart();
+                    self.reset(first.wrapping_add(retry));
+                }
+                result = self.attempt(observer);
+            }
+            if self.suspended {
+                return None;
+            }
+            if result.is_some() {
+                say!(
+                    "Solved after {retries} retries and {backtracks} backtracks",
+                    retries = retry,
+                    backtracks = self.backtracks
+                );
+                break;
+            }
+            say!(
+                "Seed {seed} hit a contradiction after {backtracks} backtracks",
+                seed = self.seed,
+                backtracks = self.backtracks
+            );
+        }
+        observer.on_complete(result.is_some());
+        result
+    }
+}
+
+impl TileSystem {
+    // a WFC solver for this map in the state ids of `states`: the model's rules,
+    // targets and costs, tile weights from the sample, painted bias and locked cells
+    pub fn wfc_solver(
+        &self,
+        name: &str,
+        states: &StateTable,
+        model: SolverModel,
+        seed: u64,
+        options: WfcOptions,
+        memory_budget: usize,
+    ) -> WfcSolver {
+        let mut solver = WfcSolver::new(
+            &model.rules,
+            states.len(),
+            self.grid_width,
+            self.grid_height,
+            seed,
+        )
+        .with_options(options)
+        .with_memory_budget(memory_budget);
+        solver.set_weights(&states.expand_weights(&self.learned_weights(name)));
+        solver.set_weighted_preferences(&states.expand_preferences(&self.bias_preferences()));
+        solver.set_targets(model.targets);
+        solver.set_transition_costs(model.transition_costs);
+        solver.fix(&self.locked_state_ids(states));
+        solver
+    }
+
+    // fills the map with the WFC solver, trained on a saved config (or the enabled
+    // samples when name is blank), keeping locked cells as they are; a run that ends
+    // in a contradiction starts over with the next seed, up to `max_retries` times
+    pub fn generate_from_config(
+        &mut self,
+        name: &str,
+        seed: u64,
+        options: WfcOptions,
+        memory_budget: usize,
+        observer: &mut dyn GenerationObserver,
+    ) -> Result<(), String> {
+        let rules = self.learn_rules(name)?;
+        let states = self.state_table();
+        let model = solver_model(self, &states, &rules);
+        let mut solver = self.wfc_solver(name, &states, model, seed, options, memory_budget);
+        let result = solver.solve(observer);
+        if result.is_none() {
+            return Err(t!(
+                "No solution for '{name}' in {attempts} attempts",
+                name = name,
+                attempts = options.max_retries as u64 + 1
+            ));
+        }
+        self.apply_generated_states(name, solver.seed, result, &states, Backend::Wfc);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TileType;
+    use crate::regions::Quiet;
+
+    // three tiles that may sit beside anything but themselves: a grid colouring always
+    // exists, but on a big enough grid propagation alone doesn't see every dead end
+    // coming
+    fn colouring() -> HashMap<usize, HashSet<(Direction, usize)>> {
+        (0..3)
+            .map(|tile| {
+                let neighbours = DIRECTIONS
+                    .iter()
+                    .flat_map(|&(dir, _, _)| {
+                        (0..3).filter(move |n| *n != tile).map(move |n| (dir, n))
+                    })
+                    .collect();
+                (tile, neighbours)
+            })
+            .collect()
+    }
+
+    fn is_coloured(rows: &[Vec<usize>]) -> bool {
+        rows.iter().enumerate().all(|(y, row)| {
+            row.iter().enumerate().all(|(x, &tile)| {
+                row.get(x + 1).is_none_or(|&right| right != tile)
+                    && rows.get(y + 1).is_none_or(|below| below[x] != tile)
+            })
+        })
+    }
+
+    fn solver(seed: u64, max_backtracks: usize) -> WfcSolver {
+        WfcSolver::new(&colouring(), 3, 20, 20, seed).with_options(WfcOptions {
+            max_retries: 0,
+            max_backtracks,
+        })
+    }
+
+    // every tile left next to a cell is one some tile left in it allows on that side
+    fn agrees_with_rules(solver: &WfcSolver) -> bool {
+        (0..solver.domains.len()).all(|cell| {
+            DIRECTIONS.iter().all(|&(dir, dx, dy)| {
+                solver.neighbour(cell, dx, dy).is_none_or(|neighbour| {
+                    let support = tiles_in(solver.domains[cell])
+                        .fold(0, |mask, tile| mask | solver.allowed[tile][dir_index(dir)]);
+                    solver.domains[neighbour] & !support == 0
+                })
+            })
+        })
+    }
+
+    fn pinned() -> HashMap<(usize, usize), usize> {
+        HashMap::from([((0, 0), 0), ((5, 5), 1), ((6, 5), 2), ((19, 19), 0)])
+    }
+
+    #[test]
+    fn propagation_leaves_open_cells_agreeing_with_the_rules() {
+        let mut solver = solver(3, WfcOptions::default().max_backtracks);
+        solver.fix(&pinned());
+        let cell_count = solver.domains.len();
+        assert!(solver.propagate(0..cell_count, &mut Quiet));
+        assert!(agrees_with_rules(&solver));
+        for _ in 0..20 {
+            let Some(cell) = solver.observe() else {
+                break;
+            };
+            let weights = solver.value_weights();
+            solver.collapse(cell, &weights, &mut Quiet);
+            if !solver.propagate([cell], &mut Quiet) {
+                break;
+            }
+            assert!(agrees_with_rules(&solver));
+        }
+    }
+
+    #[test]
+    fn fixed_cells_keep_their_tiles() {
+        let mut solved = 0;
+        for seed in 0..10 {
+            let mut solver = solver(seed, WfcOptions::default().max_backtracks);
+            solver.fix(&pinned());
+            if let Some(rows) = solver.solve(&mut Quiet) {
+                assert!(is_coloured(&rows));
+                for (&(x, y), &tile) in &pinned() {
+                    assert_eq!(rows[y][x], tile, "seed {} moved ({}, {})", seed, x, y);
+                }
+                solved += 1;
+            }
+        }
+        assert!(solved > 0, "no seed solved around the fixed cells");
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_map() {
+        for seed in 0..5 {
+            let first = solver(seed, WfcOptions::default().max_backtracks).solve(&mut Quiet);
+            let second = solver(seed, WfcOptions::default().max_backtracks).solve(&mut Quiet);
+            assert_eq!(first, second, "seed {} differed between runs", seed);
+        }
+    }
+
+    #[test]
+    fn entropy_is_zero_for_one_tile_and_ln_n_for_even_weights() {
+        assert_eq!(entropy(0b100, &[0.2, 0.3, 0.5]), 0.0);
+        let even = entropy(0b1111, &[0.25; 4]);
+        assert!((even - 4f64.ln()).abs() < 1e-12, "{}", even);
+        // no weight at all counts as even too
+        assert!((entropy(0b111, &[]) - 3f64.ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn picks_follow_the_weight_shares() {
+        // anything may sit beside anything, so only the weights decide
+        let anything: HashMap<usize, HashSet<(Direction, usize)>> = (0..3)
+            .map(|tile| {
+                let neighbours = DIRECTIONS
+                    .iter()
+                    .flat_map(|&(dir, _, _)| (0..3).map(move |n| (dir, n)))
+                    .collect();
+                (tile, neighbours)
+            })
+            .collect();
+        let shares = [0.6, 0.3, 0.1];
+        let mut solver = WfcSolver::new(&anything, 3, 50, 50, 11);
+        solver.set_weights(&shares);
+        let rows = solver.solve(&mut Quiet).unwrap();
+        let mut counts = [0.0; 3];
+        for &tile in rows.iter().flatten() {
+            counts[tile] += 1.0 / 2500.0;
+        }
+        for (count, share) in counts.iter().zip(shares) {
+            assert!(
+                (count - share).abs() < 0.03,
+                "{:?} against {:?}",
+                counts,
+                shares
+            );
+        }
+    }
+
+    #[test]
+    fn backtracking_undoes_a_contradiction() {
+        let mut backtracked = 0;
+        for seed in 0..50 {
+            let mut solver = solver(seed, WfcOptions::default().max_backtracks);
+            if let Some(rows) = solver.solve(&mut Quiet) {
+                assert!(is_coloured(&rows), "seed {} left neighbours alike", seed);
+                backtracked += (solver.backtracks > 0) as usize;
+            }
+        }
+        assert!(
+            backtracked > 0,
+            "no seed backtracked its way to a colouring"
+        );
+    }
+
+    #[test]
+    fn run_fails_once_backtracks_are_spent() {
+        // a seed that only gets through by backtracking
+        let seed = (0..50)
+            .find(|&seed| {
+                let mut solver = solver(seed, WfcOptions::default().max_backtracks);
+                solver.solve(&mut Quiet).is_some() && solver.backtracks > 0
+            })
+            .expect("some seed backtracks to a colouring");
+        let mut solver = solver(seed, 0);
+        assert!(solver.solve(&mut Quiet).is_none());
+        assert_eq!(solver.backtracks, 0);
+    }
+
+    #[test]
+    fn trail_stays_within_the_memory_budget() {
+        for seed in 0..10 {
+            let mut solver =
+                solver(seed, WfcOptions::default().max_backtracks).with_memory_budget(256);
+            if let Some(rows) = solver.solve(&mut Quiet) {
+                assert!(is_coloured(&rows));
+            }
+            assert!(solver.trail_bytes <= 256);
+        }
+    }
+
+    // counts the attempts `solve` started over
+    #[derive(Default)]
+    struct Restarts(usize);
+
+    impl GenerationObserver for Restarts {
+        fn on_restart(&mut self) {
+            self.0 += 1;
+        }
+    }
+
+    fn tiles(rows: &[Vec<usize>]) -> Vec<Vec<TileType>> {
+        rows.iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&tile| TileType::ALL[tile].clone())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unsolvable_model_fails_after_every_retry() {
+        // the sample is a single row, so nothing may sit above or below anything
+        let mut map = TileSystem::new(160.0, 120.0, 8.0);
+        map.store_config("row".to_string(), tiles(&[vec![2, 4, 2, 4]]));
+        let options = WfcOptions {
+            max_retries: 3,
+            max_backtracks: 100,
+        };
+        let mut restarts = Restarts::default();
+        let generated =
+            map.generate_from_config("row", 5, options, DEFAULT_MEMORY_BUDGET, &mut restarts);
+        assert!(generated.is_err());
+        assert_eq!(restarts.0, 3);
+        assert!(map.last_generation.is_none());
+    }
+
+    #[test]
+    fn map_is_stamped_with_the_seed_that_worked() {
+        // a three-colouring of Mountain, Land and Coast with each as often as the
+        // others, so the model is the colouring rules under even weights
+        let steps = [0, 1, 0, 2, 1, 2];
+        let sample: Vec<Vec<usize>> = steps
+            .iter()
+            .map(|y| steps.iter().map(|x| (x + y) % 3 + 1).collect())
+            .collect();
+        let no_backtracking = |max_retries| WfcOptions {
+            max_retries,
+            max_backtracks: 0,
+        };
+        let coloured_map = || {
+            let mut map = TileSystem::new(160.0, 120.0, 8.0);
+            map.store_config("colours".to_string(), tiles(&sample));
+            map
+        };
+        let solves = |seed| {
+            coloured_map()
+                .generate_from_config(
+                    "colours",
+                    seed,
+                    no_backtracking(0),
+                    DEFAULT_MEMORY_BUDGET,
+                    &mut Quiet,
+                )
+                .is_ok()
+        };
+        let seed = (0..200)
+            .find(|&seed| !solves(seed) && solves(seed + 1))
+            .expect("some seed fails where the next one solves");
+
+        let mut map = coloured_map();
+        map.generate_from_config(
+            "colours",
+            seed,
+            no_backtracking(1),
+            DEFAULT_MEMORY_BUDGET,
+            &mut Quiet,
+        )
+        .unwrap();
+        let last = map.last_generation.as_ref().unwrap();
+        assert_eq!(last.seed, seed + 1);
+        assert_eq!(last.backend, Backend::Wfc);
+    }
+}
@@ -0,0 +1,726 @@
+use piston_window::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crate::config::AppConfig;
+use crate::csp::{CspSolver, SolverModel, SolverSettings};
+use crate::grid::TileGrid;
+use crate::i18n::{say, t};
+use crate::observer::{GenerationObserver, Observers, StatsObserver};
+use crate::resume::{self, SavedRun};
+use crate::rotations::StateTable;
+use crate::trace::TraceObserver;
+use crate::wfc::WfcSolver;
+use crate::{Backend, Direction, Tile, TileSystem};
+
+// fixed rate of the window's update events, which drive polling and playback
+// whatever the redraw rate is
+pub const UPDATES_PER_SECOND: u64 = 60;
+
+pub enum GenerationEvent {
+    Observe((usize, usize), usize),
+    Collapse((usize, usize), usize),
+    Eliminate((usize, usize), usize),
+    Contradiction((usize, usize)),
+    Backtrack((usize, usize)),
+    Restart,
+    Memory(usize),
+    // the solver is waiting while paused: remaining tiles per cell and value weights
+    Paused(Vec<u32>, Vec<f64>),
+    // the WFC solver finished on a later seed than it started with
+    Reseeded(u64),
+    // the solver stopped on a suspend request, handed back to be saved
+    Suspended(Box<Solver>),
+    Complete(Option<Vec<Vec<usize>>>),
+}
+
+// where a job's solver starts
+enum Origin {
+    // the beginning, with locked cells pinned and a trace written to the path if given
+    Fresh(HashMap<(usize, usize), usize>, Option<PathBuf>),
+    // where it was suspended
+    Suspension,
+}
+
+// the solver a job runs, which is also what a suspended run saves
+#[derive(Serialize, Deserialize)]
+pub enum Solver {
+    Wfc(WfcSolver),
+    Sat(CspSolver),
+}
+
+impl Solver {
+    // runs from the start with `fixed` pinned, or goes on from a suspension with None
+    fn run(
+        &mut self,
+        fixed: Option<&HashMap<(usize, usize), usize>>,
+        observer: &mut dyn GenerationObserver,
+    ) -> Option<Vec<Vec<usize>>> {
+        match (self, fixed) {
+            (Solver::Wfc(solver), Some(_)) => solver.solve(observer),
+            (Solver::Wfc(solver), None) => solver.resume(observer),
+            (Solver::Sat(solver), Some(fixed)) => solver.solve(fixed, observer),
+            (Solver::Sat(solver), None) => solver.resume(observer),
+        }
+    }
+
+    fn is_suspended(&self) -> bool {
+        match self {
+            Solver::Wfc(solver) => solver.is_suspended(),
+            Solver::Sat(solver) => solver.is_suspended(),
+        }
+    }
+
+    // cells down to one tile, what the preview shows of a suspended run
+    fn decided(&self, width: usize) -> HashMap<(usize, usize), usize> {
+        let domains = match self {
+            Solver::Wfc(solver) => solver.domains(),
+            Solver::Sat(solver) => solver.suspended_domains().unwrap_or_default(),
+        };
+        domains
+            .iter()
+            .enumerate()
+            .filter(|(_, domain)| domain.count_ones() == 1)
+            .map(|(cell, domain)| {
+                (
+                    (cell % width, cell / width),
+                    domain.trailing_zeros() as usize,
+                )
+            })
+            .collect()
+    }
+}
+
+// a decision's cell and the cells that collapsed from it on
+type Decision = ((usize, usize), Vec<(usize, usize)>);
+
+// runs on the worker, turning solver callbacks into messages for the UI thread
+struct ChannelObserver {
+    sender: Sender<GenerationEvent>,
+    paused: Arc<AtomicBool>,
+}
+
+impl GenerationObserver for ChannelObserver {
+    fn on_observe(&mut self, cell: (usize, usize), tile: usize) {
+        self.sender.send(GenerationEvent::Observe(cell, tile)).ok();
+    }
+
+    fn on_collapse(&mut self, cell: (usize, usize), tile: usize) {
+        self.sender.send(GenerationEvent::Collapse(cell, tile)).ok();
+    }
+
+    fn on_eliminate(&mut self, cell: (usize, usize), tile: usize) {
+        self.sender
+            .send(GenerationEvent::Eliminate(cell, tile))
+            .ok();
+    }
+
+    fn on_contradiction(&mut self, cell: (usize, usize)) {
+        self.sender.send(GenerationEvent::Contradiction(cell)).ok();
+    }
+
+    fn on_backtrack(&mut self, cell: (usize, usize)) {
+        self.sender.send(GenerationEvent::Backtrack(cell)).ok();
+    }
+
+    fn on_restart(&mut self) {
+        self.sender.send(GenerationEvent::Restart).ok();
+    }
+
+    fn on_memory(&mut self, bytes: usize) {
+        self.sender.send(GenerationEvent::Memory(bytes)).ok();
+    }
+
+    // holds the solver between decisions until the window resumes it
+    fn on_step(&mut self, domains: &[u32], weights: &[f64]) {
+        if !self.paused.load(Ordering::Relaxed) {
+            return;
+        }
+        self.sender
+            .send(GenerationEvent::Paused(domains.to_vec(), weights.to_vec()))
+            .ok();
+        while self.paused.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
+
+// the map's rules, targets and costs in the job's state ids
+pub fn solver_model(
+    tile_system: &TileSystem,
+    states: &StateTable,
+    rules: &HashMap<usize, HashSet<(Direction, usize)>>,
+) -> SolverModel {
+    SolverModel {
+        rules: states.expand_rules(rules),
+        targets: states.expand_targets(tile_system.target_shares()),
+        transition_costs: states.expand_costs(tile_system.transition_cost_matrix()),
+    }
+}
+
+// a generation running on a background thread; the window polls it every frame
+pub struct GenerationJob {
+    pub map_index: usize,
+    pub label: String,
+    pub seed: u64,
+    pub backend: Backend,
+    // what the solver's ids stand for, tiles or turned tiles
+    pub states: StateTable,
+    // the map as the solver was given it, to tell which cells were edited meanwhile
+    start: TileGrid,
+    receiver: Receiver<GenerationEvent>,
+    // latest collapsed tile per cell, drawn over the map while the job runs
+    preview: HashMap<(usize, usize), usize>,
+    // latest last, so a backtrack can take their cells off the preview again
+    decisions: Vec<Decision>,
+    // tiles eliminated per cell since the latest observation, and where that was
+    wave: HashMap<(usize, usize), usize>,
+    observed: Option<(usize, usize)>,
+    paused: Arc<AtomicBool>,
+    // the model the solver is working from, and where to send a changed one
+    model: SolverModel,
+    model_sender: Sender<SolverModel>,
+    suspend_sender: Sender<()>,
+    // the file a suspend request saves the run to once the solver stops
+    saving: Option<(String, File)>,
+    // stopped and saved rather than finished, so there's nothing to apply
+    suspended: bool,
+    // what the solver had left when it stopped, for the candidate popup
+    snapshot: Option<(Vec<u32>, Vec<f64>)>,
+    preferences: HashMap<(usize, usize), (usize, f64)>,
+    width: usize,
+    cell_count: usize,
+    stats: StatsObserver,
+    // collapses the next poll may still show when playback is paced
+    budget: f64,
+    // seconds run since the start or the latest restart, paused time left out
+    elapsed: f64,
+}
+
+impl GenerationJob {
+    // learns the model's rules and starts solving with `backend`; Err when the rules
+    // can't be learned
+    pub fn spawn(
+        tile_system: &TileSystem,
+        map_index: usize,
+        label: String,
+        seed: u64,
+        backend: Backend,
+        app_config: &AppConfig,
+        // directory for a JSONL solver trace, one file per seed
+        trace_dir: Option<&str>,
+    ) -> Result<Self, String> {
+        let rules = tile_system.learn_rules(&label)?;
+        let memory_budget = app_config.solver_memory_budget();
+        let states = tile_system.state_table();
+        let model = solver_model(tile_system, &states, &rules);
+        let fixed = tile_system.locked_state_ids(&states);
+        let (width, height) = (tile_system.grid_width, tile_system.grid_height);
+        // built here since it reads the map, then handed to the worker
+        let solver = match backend {
+            Backend::Wfc => Solver::Wfc(tile_system.wfc_solver(
+                &label,
+                &states,
+                model.clone(),
+                seed,
+                app_config.wfc,
+                memory_budget,
+            )),
+            Backend::Sat => {
+                let mut solver = CspSolver::new(&model.rules, states.len(), width, height, seed)
+                    .with_settings(SolverSettings {
+                        memory_budget,
+                        ..SolverSettings::default()
+                    });
+                solver.set_weighted_preferences(
+                    &states.expand_preferences(&tile_system.bias_preferences()),
+                );
+                solver.set_targets(model.targets.clone());
+                solver.set_transition_costs(model.transition_costs.clone());
+                Solver::Sat(solver)
+            }
+        };
+        let trace_path = trace_dir.map(|dir| Path::new(dir).join(format!("trace-{}.jsonl", seed)));
+        say!(
+            "Generating from '{label}' with seed {seed} in the background",
+            label = label,
+            seed = seed
+        );
+        Ok(Self::start(
+            tile_system,
+            map_index,
+            (label, seed, backend),
+            states,
+            model,
+            solver,
+            Origin::Fresh(fixed, trace_path),
+        ))
+    }
+
+    // takes up a saved run where it was suspended; Err when the map isn't the size or
+    // doesn't have the tiles it was generating
+    pub fn resume(
+        tile_system: &TileSystem,
+        map_index: usize,
+        saved: SavedRun,
+    ) -> Result<Self, String> {
+        if (saved.width, saved.height) != (tile_system.grid_width, tile_system.grid_height) {
+            return Err(t!(
+                "The run was saved at {width}x{height} but the map is {grid_width}x{grid_height}",
+                width = saved.width,
+                height = saved.height,
+                grid_width = tile_system.grid_width,
+                grid_height = tile_system.grid_height
+            ));
+        }
+        let states = tile_system.state_table();
+        if states.labels() != saved.states {
+            return Err(t!(
+                "The run was saved with the tiles {saved} but the map has {tiles}",
+                saved = saved.states.join(", "),
+                tiles = states.labels().join(", ")
+            ));
+        }
+        // the solver carries its own model; this is only to tell later edits from it,
+        // and a model that can't be learned here is never sent
+        let model = tile_system
+            .learn_rules(&saved.label)
+            .map(|rules| solver_model(tile_system, &states, &rules))
+            .unwrap_or_else(|_| SolverModel {
+                rules: HashMap::new(),
+                targets: None,
+                transition_costs: None,
+            });
+        say!(
+            "Resuming '{label}' with seed {seed} where it was saved",
+            label = saved.label,
+            seed = saved.seed
+        );
+        Ok(Self::start(
+            tile_system,
+            map_index,
+            (saved.label, saved.seed, saved.backend),
+            states,
+            model,
+            saved.solver,
+            Origin::Suspension,
+        ))
+    }
+
+    // runs `solver` on a worker thread from `origin`
+    fn start(
+        tile_system: &TileSystem,
+        map_index: usize,
+        (label, seed, backend): (String, u64, Backend),
+        states: StateTable,
+        model: SolverModel,
+        mut solver: Solver,
+        origin: Origin,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let (model_sender, model_receiver) = mpsc::channel();
+        let (suspend_sender, suspend_receiver) = mpsc::channel();
+        let preferences = states.expand_preferences(&tile_system.bias_preferences());
+        let (width, height) = (tile_system.grid_width, tile_system.grid_height);
+        let paused = Arc::new(AtomicBool::new(false));
+        let worker_paused = paused.clone();
+        let labels = states.labels();
+        let (fixed, trace_path) = match origin {
+            Origin::Fresh(fixed, trace_path) => (Some(fixed), trace_path),
+            Origin::Suspension => (None, None),
+        };
+        let preview = if fixed.is_some() {
+            HashMap::new()
+        } else {
+            solver.decided(width)
+        };
+
+        thread::spawn(move || {
+            let mut observer = ChannelObserver {
+                sender: sender.clone(),
+                paused: worker_paused,
+            };
+            let mut trace = trace_path.and_then(|path| {
+                TraceObserver::create(&path.display().to_string(), width, height, seed, labels)
+                    .map_err(|e| eprintln!("{}", e))
+                    .ok()
+            });
+            let mut observers = Observers(vec![&mut observer]);
+            if let Some(trace) = trace.as_mut() {
+                observers.0.push(trace);
+            }
+            match &mut solver {
+                Solver::Wfc(solver) => {
+                    solver.set_model_updates(model_receiver);
+                    solver.set_suspend_requests(suspend_receiver);
+                }
+                Solver::Sat(solver) => {
+                    solver.set_model_updates(model_receiver);
+                    solver.set_suspend_requests(suspend_receiver);
+                }
+            }
+            let result = solver.run(fixed.as_ref(), &mut observers);
+            drop(observers);
+            if solver.is_suspended() {
+                sender
+                    .send(GenerationEvent::Suspended(Box::new(solver)))
+                    .ok();
+                return;
+            }
+            if let Solver::Wfc(solver) = &solver
+                && solver.seed != seed
+            {
+                sender.send(GenerationEvent::Reseeded(solver.seed)).ok();
+            }
+            sender.send(GenerationEvent::Complete(result)).ok();
+        });
+
+        GenerationJob {
+            map_index,
+            label,
+            seed,
+            backend,
+            states,
+            start: tile_system.tiles.clone(),
+            receiver,
+            preview,
+            decisions: Vec::new(),
+            wave: HashMap::new(),
+            observed: None,
+            paused,
+            model,
+            model_sender,
+            suspend_sender,
+            saving: None,
+            suspended: false,
+            snapshot: None,
+            preferences,
+            width,
+            cell_count: width * height,
+            stats: StatsObserver::default(),
+            budget: 0.0,
+            elapsed: 0.0,
+        }
+    }
+
+    // writes the result into `map` as one undo step, keeping the cells that were edited
+    // while the solver ran; a map resized meanwhile no longer fits the result
+    pub fn finish(&self, map: &mut TileSystem, result: Option<Vec<Vec<usize>>>) {
+        if self.suspended {
+            return;
+        }
+        if (map.tiles.width(), map.tiles.height()) != (self.start.width(), self.start.height()) {
+            say!("The map was resized while generating, discarded the result");
+            return;
+        }
+        let edited = self.start.edited_in(&map.tiles);
+        if !map.apply_generated_states(&self.label, self.seed, result, &self.states, self.backend)
+            || edited.is_empty()
+        {
+            return;
+        }
+        for (x, y, tile) in &edited {
+            map.set_tile(*x, *y, tile.clone());
+        }
+        say!(
+            "Kept {count} cell(s) edited while generating",
+            count = edited.len()
+        );
+    }
+
+    pub fn progress(&self) -> f64 {
+        if self.cell_count == 0 {
+            1.0
+        } else {
+            self.preview.len() as f64 / self.cell_count as f64
+        }
+    }
+
+    pub fn peak_memory(&self) -> usize {
+        self.stats.peak_memory
+    }
+
+    pub fn contradictions(&self) -> usize {
+        self.stats.contradictions
+    }
+
+    // seconds left at the rate cells have stayed collapsed so far; backtracking takes
+    // cells off the preview, so it slows the rate rather than being counted as progress
+    pub fn eta(&self) -> Option<f64> {
+        let done = self.preview.len();
+        if done == 0 || self.elapsed <= 0.0 {
+            return None;
+        }
+        let rate = done as f64 / self.elapsed;
+        Some(self.cell_count.saturating_sub(done) as f64 / rate)
+    }
+
+    // called once per update of `dt` seconds: shows up to `speed` collapses a second
+    // (all pending ones when None), forwarding messages to `observer`; Some(result)
+    // once the last one has been shown
+    pub fn poll(
+        &mut self,
+        dt: f64,
+        speed: Option<f64>,
+        observer: &mut dyn GenerationObserver,
+    ) -> Option<Option<Vec<Vec<usize>>>> {
+        if !self.is_paused() {
+            self.elapsed += dt;
+        }
+        self.budget = match speed {
+            // a whole collapse left over means the solver fell behind, don't bank it
+            Some(speed) if speed > 0.0 && self.budget.is_finite() => {
+                self.budget.fract() + speed * dt
+            }
+            Some(speed) if speed > 0.0 => speed * dt,
+            // a speed of zero or less would never finish
+            _ => f64::INFINITY,
+        };
+        let mut observers = Observers(vec![&mut self.stats, observer]);
+        while self.budget >= 1.0
+            && let Ok(event) = self.receiver.try_recv()
+        {
+            match event {
+                GenerationEvent::Collapse(cell, tile) => {
+                    self.budget -= 1.0;
+                    self.preview.insert(cell, tile);
+                    if let Some((_, collapsed)) = self.decisions.last_mut() {
+                        collapsed.push(cell);
+                    }
+                    observers.on_collapse(cell, tile);
+                }
+                GenerationEvent::Observe(cell, tile) => {
+                    self.decisions.push((cell, Vec::new()));
+                    self.wave.clear();
+                    self.observed = Some(cell);
+                    observers.on_observe(cell, tile);
+                }
+                GenerationEvent::Eliminate(cell, tile) => {
+                    *self.wave.entry(cell).or_default() += 1;
+                    observers.on_eliminate(cell, tile);
+                }
+                GenerationEvent::Contradiction(cell) => observers.on_contradiction(cell),
+                // the decisions are undone latest first, down to the one at `cell`
+                GenerationEvent::Backtrack(cell) => {
+                    while let Some((decided, collapsed)) = self.decisions.pop() {
+                        for undone in collapsed {
+                            self.preview.remove(&undone);
+                        }
+                        if decided == cell {
+                            break;
+                        }
+                    }
+                    observers.on_backtrack(cell);
+                }
+                GenerationEvent::Restart => {
+                    self.preview.clear();
+                    self.decisions.clear();
+                    self.wave.clear();
+                    self.observed = None;
+                    self.elapsed = 0.0;
+                    observers.on_restart();
+                }
+                GenerationEvent::Memory(bytes) => observers.on_memory(bytes),
+                GenerationEvent::Paused(domains, weights) => {
+                    self.snapshot = Some((domains, weights));
+                }
+                GenerationEvent::Reseeded(seed) => self.seed = seed,
+                GenerationEvent::Suspended(solver) => {
+                    self.save(*solver);
+                    return Some(None);
+                }
+                GenerationEvent::Complete(result) => {
+                    if self.saving.take().is_some() {
+                        say!("The generation finished before it could be saved");
+                    }
+                    observers.on_complete(result.is_some());
+                    return Some(result);
+                }
+            }
+        }
+        None
+    }
+
+    // asks the solver to stop before its next decision and save itself to `path`;
+    // the file is created now, so a path that can't be written fails here
+    pub fn suspend(&mut self, path: String) -> Result<(), String> {
+        if self.saving.is_some() {
+            return Err(t!("The generation is already being saved"));
+        }
+        let file = File::create(&path)
+            .map_err(|e| t!("Failed to write {path}: {e}", path = path, e = e))?;
+        self.saving = Some((path, file));
+        self.suspend_sender.send(()).ok();
+        // a paused solver has to be let go to see the request
+        self.paused.store(false, Ordering::Relaxed);
+        self.snapshot = None;
+        Ok(())
+    }
+
+    fn save(&mut self, solver: Solver) {
+        self.suspended = true;
+        let Some((path, mut file)) = self.saving.take() else {
+            return;
+        };
+        let run = SavedRun {
+            label: self.label.clone(),
+            seed: self.seed,
+            backend: self.backend,
+            width: self.width,
+            height: self.cell_count / self.width.max(1),
+            states: self.states.labels(),
+            solver,
+        };
+        match resume::write(&mut file, &path, &run) {
+            Ok(()) => say!(
+                "Saved the generation to {path}, Ctrl+Shift+S resumes it",
+                path = path
+            ),
+            Err(e) => println!("{}", e),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn toggle_pause(&mut self) {
+        let paused = !self.paused.load(Ordering::Relaxed);
+        self.paused.store(paused, Ordering::Relaxed);
+        if !paused {
+            self.snapshot = None;
+        }
+        say!(
+            "Generation of '{label}' {paused}",
+            label = self.label,
+            paused = if paused { t!("paused") } else { t!("resumed") }
+        );
+    }
+
+    // sends the map's current rules, targets and costs to the solver, which re-checks
+    // what it has left against them before its next decision; nothing is sent when
+    // they haven't changed
+    pub fn reload_model(&mut self, tile_system: &TileSystem) -> Result<(), String> {
+        let rules = tile_system.learn_rules(&self.label)?;
+        let model = solver_model(tile_system, &self.states, &rules);
+        if model != self.model {
+            say!(
+                "Model for '{label}' changed, continuing with it",
+                label = self.label
+            );
+            self.model_sender.send(model.clone()).ok();
+            self.model = model;
+        }
+        Ok(())
+    }
+
+    // the tiles an undecided cell could still become and the chance of each being
+    // tried first if the solver picked it next; None unless paused on an open cell.
+    // a preferred tile is tried first as often as its strength, otherwise it's the value weights
+    pub fn candidates(&self, (x, y): (usize, usize)) -> Option<Vec<(String, f64)>> {
+        let (domains, weights) = self.snapshot.as_ref()?;
+        if x >= self.width {
+            return None;
+        }
+        let domain = *domains.get(y * self.width + x)?;
+        if domain.count_ones() < 2 {
+            return None;
+        }
+        let open: Vec<usize> = (0..self.states.len())
+            .filter(|id| domain & (1 << id) != 0)
+            .collect();
+        let weight = |id: usize| weights.get(id).copied().unwrap_or(1.0);
+        let total: f64 = open.iter().map(|&id| weight(id)).sum();
+        let preferred = self
+            .preferences
+            .get(&(x, y))
+            .filter(|(tile, _)| domain & (1 << tile) != 0);
+        Some(
+            open.iter()
+                .map(|&id| {
+                    let label = self.states.label(id);
+                    let mut share = if total > 0.0 {
+                        weight(id) / total
+                    } else {
+                        1.0 / open.len() as f64
+                    };
+                    if let Some(&(tile, strength)) = preferred {
+                        share *= 1.0 - strength;
+                        if id == tile {
+                            share += strength;
+                        }
+                    }
+                    (label, share)
+                })
+                .collect(),
+        )
+    }
+
+    pub fn render_preview(&self, tile_system: &TileSystem, c: Context, g: &mut G2d) {
+        for (&(x, y), &tile) in &self.preview {
+            let mut colour = Tile::from_type(&self.states.state(tile).0).colour;
+            colour[3] *= 0.6;
+            let (world_x, world_y) = tile_system.grid_to_world(x, y);
+            rectangle(
+                colour,
+                [
+                    world_x,
+                    world_y,
+                    tile_system.tile_size,
+                    tile_system.tile_size,
+                ],
+                c.transform,
+                g,
+            );
+        }
+    }
+
+    // cells whose options shrank since the latest observation, yellow for one tile
+    // gone through to red for all of them, with the observed cell outlined
+    pub fn render_wave(&self, tile_system: &TileSystem, c: Context, g: &mut G2d) {
+        let most = (self.states.len() - 1) as f32;
+        for (&(x, y), &removed) in &self.wave {
+            let share = (removed as f32 / most).min(1.0);
+            let (world_x, world_y) = tile_system.grid_to_world(x, y);
+            rectangle(
+                [1.0, 1.0 - 0.8 * share, 0.1, 0.35 + 0.4 * share],
+                [
+                    world_x,
+                    world_y,
+                    tile_system.tile_size,
+                    tile_system.tile_size,
+                ],
+                c.transform,
+                g,
+            );
+        }
+        if let Some((x, y)) = self.observed {
+            let (world_x, world_y) = tile_system.grid_to_world(x, y);
+            Rectangle::new_border([1.0, 1.0, 1.0, 1.0], 1.5).draw(
+                [
+                    world_x,
+                    world_y,
+                    tile_system.tile_size,
+                    tile_system.tile_size,
+                ],
+                &c.draw_state,
+                c.transform,
+                g,
+            );
+        }
+    }
+}
+
+// a job dropped while paused lets its thread run on to the end rather than wait forever
+impl Drop for GenerationJob {
+    fn drop(&mut self) {
+        self.paused.store(false, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,224 @@
+use std::fs;
+use std::path::Path;
+
+use crate::cli::MapSize;
+use crate::grid::TileGrid;
+use crate::i18n::{say, t};
+use crate::templates::{self, MapTemplate};
+use crate::{TileSystem, library};
+
+// several maps open side by side, e.g. a training sample and a generation target
+pub struct Workspace {
+    pub maps: Vec<TileSystem>,
+    pub active: usize,
+    clipboard: Option<TileGrid>,
+    templates: Vec<MapTemplate>,
+    // map indices, most recently visited first
+    pub recent_maps: Vec<usize>,
+    // another editor holds the save lock, so nothing is written
+    pub read_only: bool,
+    // how many map files the last load or save left on disk; only those past the
+    // current map count are stale
+    saved_maps: usize,
+}
+
+impl Workspace {
+    const DEFAULT_TEMPLATE: &'static str = "bordered_arena";
+
+    // `size` is only used when there is no save to load
+    pub fn load(size: &MapSize) -> Self {
+        let templates = templates::load_templates();
+
+        let fresh = !Path::new(TileSystem::SAVE_FILE).exists();
+        let mut maps = vec![TileSystem::load_or_recover(size)];
+        if fresh
+            && let Some(template) = templates::find_template(&templates, Self::DEFAULT_TEMPLATE)
+        {
+            template.apply(&mut maps[0]);
+        }
+
+        // extra maps live next to the main save as tile_system_2.json, tile_system_3.json, ...
+        // and a damaged one gets the same recovery choices as the main save
+        while let Some(map) = TileSystem::open_or_recover(&Self::map_path(maps.len()), size) {
+            maps.push(map);
+        }
+        if maps.len() > 1 {
+            say!("Opened {count} maps", count = maps.len());
+        }
+
+        let saved_maps = maps.len();
+        Workspace {
+            maps,
+            active: 0,
+            clipboard: None,
+            templates,
+            recent_maps: vec![0],
+            read_only: false,
+            saved_maps,
+        }
+    }
+
+    fn map_path(index: usize) -> String {
+        format!("tile_system_{}.json", index + 1)
+    }
+
+    pub fn current(&mut self) -> &mut TileSystem {
+        &mut self.maps[self.active]
+    }
+
+    pub fn set_tile_size(&mut self, tile_size: f64) {
+        for map in &mut self.maps {
+            map.set_tile_size(tile_size);
+        }
+    }
+
+    // after the palette changes
+    pub fn recolour(&mut self) {
+        for map in &mut self.maps {
+            map.recolour();
+        }
+    }
+
+    // reads the library directory into every open map
+    pub fn scan_library(&mut self, dir: &str) {
+        let (configs, tags) = library::scan(dir);
+        say!(
+            "Library {dir}: {count} configuration(s)",
+            dir = dir,
+            count = configs.len()
+        );
+        for map in &mut self.maps {
+            map.set_library(configs.clone(), &tags);
+        }
+    }
+
+    pub fn reload_templates(&mut self) {
+        self.templates = templates::load_templates();
+        say!("Reloaded {count} template(s)", count = self.templates.len());
+    }
+
+    pub fn list_templates(&self) {
+        templates::list_templates(&self.templates);
+    }
+
+    // blank template name gives an empty map
+    pub fn open_new_map(&mut self, template_name: &str) {
+        let current = &self.maps[self.active];
+        let mut map = TileSystem::new(
+            current.window_width,
+            current.window_height,
+            current.tile_size,
+        );
+        if !template_name.is_empty() {
+            match templates::find_template(&self.templates, template_name) {
+                Some(template) => template.apply(&mut map),
+                None => say!(
+                    "Template '{template_name}' not found, using empty map",
+                    template_name = template_name
+                ),
+            }
+        }
+        map.library = current.library.clone();
+        map.dirty = true;
+        self.maps.push(map);
+        self.active = self.maps.len() - 1;
+        self.visit(self.active);
+        say!("Opened map {active}", active = self.active + 1);
+    }
+
+    // loads a saved map from anywhere as a new map; it is saved alongside the others
+    pub fn open_file(&mut self, path: &str) -> bool {
+        let Some(mut map) = TileSystem::load_from_path(path) else {
+            say!("Couldn't open {path}", path = path);
+            return false;
+        };
+        map.library = self.maps[self.active].library.clone();
+        map.recolour();
+        map.dirty = true;
+        self.maps.push(map);
+        self.active = self.maps.len() - 1;
+        self.visit(self.active);
+        say!(
+            "Opened {path} as map {active}",
+            path = path,
+            active = self.active + 1
+        );
+        true
+    }
+
+    pub fn switch_to(&mut self, index: usize) -> bool {
+        if index < self.maps.len() {
+            self.active = index;
+            self.visit(index);
+            say!(
+                "Switched to map {active}/{count}",
+                active = self.active + 1,
+                count = self.maps.len()
+            );
+            true
+        } else {
+            say!("Map {index} is not open", index = index + 1);
+            false
+        }
+    }
+
+    fn visit(&mut self, index: usize) {
+        self.recent_maps.retain(|&recent| recent != index);
+        self.recent_maps.insert(0, index);
+    }
+
+    pub fn next_map(&mut self) {
+        self.switch_to((self.active + 1) % self.maps.len());
+    }
+
+    pub fn copy(&mut self) {
+        self.clipboard = Some(self.maps[self.active].tiles.clone());
+        say!("Copied map {active}", active = self.active + 1);
+    }
+
+    // pastes from the top-left corner, cropping to the target and skipping locked cells
+    pub fn paste(&mut self) {
+        let Some(clipboard) = &self.clipboard else {
+            say!("Nothing to paste");
+            return;
+        };
+        let target = &mut self.maps[self.active];
+        target.push_undo();
+        for (x, y, tile) in clipboard.all_cells() {
+            target.set_tile(x, y, tile.clone());
+        }
+        say!("Pasted into map {active}", active = self.active + 1);
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.maps.iter().any(|map| map.dirty)
+    }
+
+    pub fn save_all(&mut self) {
+        if self.read_only {
+            say!("Opened read-only, not saving over the other editor's work");
+            return;
+        }
+        for (index, map) in self.maps.iter_mut().enumerate() {
+            if index == 0 {
+                map.save_to_file();
+            } else {
+                map.save_to_path(&Self::map_path(index));
+            }
+        }
+        // drop files written for maps that are no longer open, never ones this session
+        // didn't load
+        for index in self.maps.len()..self.saved_maps {
+            let path = Self::map_path(index);
+            if Path::new(&path).exists()
+                && let Err(e) = fs::remove_file(&path)
+            {
+                eprintln!(
+                    "{}",
+                    t!("Failed to remove {index}: {e}", index = path, e = e)
+                );
+            }
+        }
+        self.saved_maps = self.maps.len();
+    }
+}